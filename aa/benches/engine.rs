@@ -0,0 +1,127 @@
+//! Compares processing a large patch on the audio thread alone with
+//! processing it in parallel on a pool of workers.
+//!
+//! Run with `cargo bench -p aa --bench engine`, or set `AA_WORKERS` to the
+//! number of workers to compare with.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use aa::engine::{self, Binding, Connection, Engine, NodeSetup, Plan, Pool, Setup, MAX_BLOCK};
+use aa::nodes;
+use aa::sync::SyncState;
+
+use vst::plugin::HostCallback;
+
+const SAMPLE_RATE: f32 = 44100.0;
+const BRANCHES: usize = 64;
+const GAINS: usize = 8;
+const BLOCKS: usize = 2000;
+
+fn node(id: usize, kind: &str, parameters: Vec<Binding>) -> NodeSetup {
+    NodeSetup {
+        id,
+        kind: kind.to_string(),
+        parameters,
+        meters: Vec::new(),
+        mode: None,
+    }
+}
+
+fn connection(from: usize, output: usize, to: usize, input: usize) -> Connection {
+    Connection { from, output, to, input, feedback: false }
+}
+
+/// Branches of an oscillator through a chain of gains, mixed down four at a
+/// time to the host's outputs.
+fn setup() -> Setup {
+    let mut nodes = Vec::new();
+    let mut connections = Vec::new();
+    let mut next = 0;
+    let mut id = || {
+        next += 1;
+        next
+    };
+
+    let mut mix = Vec::new();
+    for branch in 0..BRANCHES {
+        let oscillator = id();
+        nodes.push(node(oscillator, "Oscillator", vec![
+            Binding::Value(55.0 * (branch + 1) as f32),
+            Binding::Value(0.0),
+            Binding::Value(-24.0),
+        ]));
+        let mut last = oscillator;
+        for _ in 0..GAINS {
+            let gain = id();
+            nodes.push(node(gain, "Gain", vec![Binding::Value(-0.5)]));
+            connections.push(connection(last, 0, gain, 0));
+            last = gain;
+        }
+        mix.push(last);
+    }
+
+    while mix.len() > 1 {
+        mix = mix
+            .chunks(4)
+            .map(|inputs| {
+                let mixer = id();
+                nodes.push(node(mixer, "Mixer", vec![Binding::Value(0.0); 4]));
+                for (input, from) in inputs.iter().enumerate() {
+                    connections.push(connection(*from, 0, mixer, input));
+                }
+                mixer
+            })
+            .collect();
+    }
+
+    let output = id();
+    nodes.push(node(output, "Audio Output", Vec::new()));
+    connections.push(connection(mix[0], 0, output, 0));
+    connections.push(connection(mix[0], 0, output, 1));
+
+    Setup { nodes, connections }
+}
+
+/// Processes `BLOCKS` blocks, returning how long it took and the first
+/// channel of output.
+fn run(engine: Engine, sync: &SyncState, setup: &Setup) -> (Duration, Vec<f32>) {
+    let mut engine = engine;
+    sync.plans.send(Plan::new(setup, SAMPLE_RATE, nodes::create));
+    // picks up the plan
+    engine.process(0, MAX_BLOCK, &[]);
+
+    let mut samples = Vec::with_capacity(BLOCKS * MAX_BLOCK);
+    let start = Instant::now();
+    for _ in 0..BLOCKS {
+        engine.process(0, MAX_BLOCK, &[]);
+        samples.extend_from_slice(engine.output(0).unwrap_or(&[]));
+    }
+    (start.elapsed(), samples)
+}
+
+fn main() {
+    let workers = engine::workers();
+    let setup = setup();
+    let audio = BLOCKS as f64 * MAX_BLOCK as f64 / f64::from(SAMPLE_RATE);
+
+    println!(
+        "{} nodes, {} blocks of {} samples ({:.1}s of audio)",
+        setup.nodes.len(), BLOCKS, MAX_BLOCK, audio,
+    );
+
+    let sync = Arc::new(SyncState::new(HostCallback::default()));
+    let mut in_turn = Engine::new(sync.clone());
+    in_turn.set_parallel(false);
+    let (in_turn, expected) = run(in_turn, &sync, &setup);
+    println!("in turn:          {:>10.3?} ({:.1}x real time)", in_turn, audio / in_turn.as_secs_f64());
+
+    let sync = Arc::new(SyncState::new(HostCallback::default()));
+    let (parallel, samples) = run(Engine::new(sync.clone()).pool(Arc::new(Pool::new(workers))), &sync, &setup);
+    println!(
+        "{} workers:        {:>10.3?} ({:.1}x real time, {:.2}x in turn)",
+        workers, parallel, audio / parallel.as_secs_f64(), in_turn.as_secs_f64() / parallel.as_secs_f64(),
+    );
+
+    assert_eq!(samples, expected, "parallel output differs from in turn");
+}
@@ -8,9 +8,36 @@ pub const PLUGIN_VENDOR: &str = "cuberoo_";
 pub const PLUGIN_NUMBER_INPUTS: i32 = 2;
 pub const PLUGIN_NUMBER_OUTPUTS: i32 = 2;
 
+// The range of the master output level, in dB
+pub const MASTER_DB_MIN: f32 = -12.0;
+pub const MASTER_DB_MAX: f32 = 12.0;
+
 // GUI constants
 
+// The initial size of the editor, and the limits it can be resized to
 pub const GUI_WIDTH: usize = 1000;
 pub const GUI_HEIGHT: usize = 750;
+pub const GUI_MIN_WIDTH: usize = 600;
+pub const GUI_MIN_HEIGHT: usize = 400;
+pub const GUI_MAX_WIDTH: usize = 3840;
+pub const GUI_MAX_HEIGHT: usize = 2160;
 
 // Paths to configs, etc
+
+// Environment variable listing the directories scanned for modules, separated
+// as for PATH
+pub const MODULE_PATH_ENV: &str = "AA_MODULE_PATH";
+
+// Environment variable naming a JSON or TOML theme file for the audio graph,
+// otherwise the built in light theme is used
+pub const THEME_PATH_ENV: &str = "AA_THEME_PATH";
+
+// Environment variable naming the directory patch diagrams are exported to,
+// otherwise they go in the home directory
+pub const EXPORT_DIR_ENV: &str = "AA_EXPORT_PATH";
+
+// Environment variable setting how many worker threads process the graph
+// alongside the audio thread, 0 to process it all on the audio thread,
+// otherwise one fewer than the cores available, at most MAX_WORKERS
+pub const WORKERS_ENV: &str = "AA_WORKERS";
+pub const MAX_WORKERS: usize = 7;
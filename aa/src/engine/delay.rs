@@ -0,0 +1,80 @@
+//! Fixed delays inserted by the engine to keep parallel paths in time.
+
+/// A delay of a fixed number of samples, allocated when created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delay {
+    buffer: Vec<f32>,
+    // where the next sample is read from, and then written to
+    position: usize,
+}
+
+impl Delay {
+    pub fn new(samples: usize) -> Self {
+        Self {
+            buffer: vec![0.0; samples],
+            position: 0,
+        }
+    }
+
+    /// Returns the delay in samples.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Adds `input`, delayed, to `output`.
+    pub fn add_to(&mut self, input: &[f32], output: &mut [f32]) {
+        if self.buffer.is_empty() {
+            for (output, input) in output.iter_mut().zip(input) {
+                *output += input;
+            }
+            return;
+        }
+
+        for (output, input) in output.iter_mut().zip(input) {
+            *output += std::mem::replace(&mut self.buffer[self.position], *input);
+            self.position = (self.position + 1) % self.buffer.len();
+        }
+    }
+
+    /// Delays `block` in place.
+    pub fn apply(&mut self, block: &mut [f32]) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        for sample in block.iter_mut() {
+            *sample = std::mem::replace(&mut self.buffer[self.position], *sample);
+            self.position = (self.position + 1) % self.buffer.len();
+        }
+    }
+
+    /// Empties the delay, as if it had only been fed silence.
+    pub fn clear(&mut self) {
+        self.buffer.iter_mut().for_each(|sample| *sample = 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let mut delay = Delay::new(3);
+        let mut output = [1.0; 5];
+        delay.add_to(&[1.0, 2.0, 3.0, 4.0, 5.0], &mut output);
+        assert_eq!(output, [1.0, 1.0, 1.0, 2.0, 3.0]);
+
+        let mut block = [6.0, 7.0];
+        delay.apply(&mut block);
+        assert_eq!(block, [3.0, 4.0]);
+
+        let mut block = [0.0; 2];
+        Delay::new(0).apply(&mut block);
+        assert_eq!(block, [0.0; 2]);
+    }
+}
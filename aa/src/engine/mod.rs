@@ -0,0 +1,775 @@
+//! Runs the graph set up in the editor on the audio thread.
+//!
+//! The editor builds a [`Plan`] whenever the graph changes and sends it
+//! through the [`PlanQueue`]. The audio thread picks it up between blocks,
+//! handing back the plan it replaces for the editor to drop, so that the audio
+//! thread never allocates, frees or waits.
+//!
+//! Values of parameters without a host parameter slot go through a
+//! [`ValueQueue`] instead, so that turning a knob does not rebuild the plan.
+//!
+//! Nodes that do not depend on each other can be processed in parallel, on a
+//! [`Pool`] of workers shared with the audio thread.
+
+pub mod delay;
+pub mod plan;
+pub mod pool;
+
+pub use plan::{Binding, Connection, Host, NodeSetup, Plan, Setup};
+pub use pool::Pool;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::constants::{MAX_WORKERS, WORKERS_ENV};
+use crate::nodes::io::HOST_CHANNELS;
+use crate::nodes::MidiEvent;
+use crate::sync::{AtomicF32, SyncState};
+
+/// The longest block processed at once. Longer host blocks are processed in
+/// several blocks.
+pub const MAX_BLOCK: usize = 256;
+
+/// The most input or output buffers of a node that are processed, one for
+/// each channel of its ports.
+pub const MAX_PORTS: usize = 16;
+
+/// The most values waiting in a [`ValueQueue`].
+const MAX_VALUES: usize = 256;
+
+/// Returns the number of workers to process the graph on alongside the audio
+/// thread: as many as the workers environment variable sets, or else one
+/// fewer than the cores available, and at most [`MAX_WORKERS`]. Setting the
+/// variable to 0 processes every node in turn on the audio thread, e.g. to
+/// debug a graph.
+pub fn workers() -> usize {
+    std::env::var(WORKERS_ENV)
+        .ok()
+        .and_then(|workers| workers.trim().parse().ok())
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|cores| cores.get() - 1)
+                .unwrap_or(0)
+        })
+        .min(MAX_WORKERS)
+}
+
+/// Passes plans from the editor to the audio thread, and replaced plans back.
+#[derive(Default)]
+pub struct PlanQueue {
+    next: Mutex<Option<Plan>>,
+    retired: Mutex<Option<Plan>>,
+    // a plan the audio thread received for another sample rate than it runs
+    // at, to be prepared again off the audio thread
+    stale: Mutex<Option<Plan>>,
+}
+
+impl PlanQueue {
+    /// Sends a plan to the audio thread, replacing any not yet picked up,
+    /// and drops the plan it last replaced. Called from the editor.
+    pub fn send(&self, plan: Plan) {
+        if let Ok(mut retired) = self.retired.lock() {
+            retired.take();
+        }
+        if let Ok(mut stale) = self.stale.lock() {
+            stale.take();
+        }
+        if let Ok(mut next) = self.next.lock() {
+            *next = Some(plan);
+        }
+    }
+
+    /// Prepares the plan sent but not yet picked up, or sends again the plan
+    /// the audio thread handed back, for `sample_rate`. Called from the
+    /// editor, and by the host while not processing.
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        let stale = match self.stale.lock() {
+            Ok(mut stale) => stale.take(),
+            Err(_) => None,
+        };
+        if let Ok(mut next) = self.next.lock() {
+            if next.is_none() {
+                *next = stale;
+            }
+            if let Some(plan) = next.as_mut().filter(|plan| plan.sample_rate() != sample_rate) {
+                plan.set_sample_rate(sample_rate);
+            }
+        }
+    }
+
+    /// Passes the plan sent, if there is one for `sample_rate` and the plan
+    /// last replaced has been dropped, to `swap`, which returns the plan it
+    /// replaces for the editor to drop. A plan for another sample rate is
+    /// handed back for the editor to prepare again. Called from the audio
+    /// thread; never waits.
+    ///
+    /// Returns false while a plan sent is still to be swapped in.
+    fn receive(&self, sample_rate: f32, mut swap: impl FnMut(Plan) -> Option<Plan>) -> bool {
+        let mut next = match self.next.try_lock() {
+            Ok(next) => next,
+            Err(_) => return false,
+        };
+        let plan = match next.take() {
+            Some(plan) => plan,
+            None => return matches!(self.stale.try_lock().as_deref(), Ok(None)),
+        };
+
+        if plan.sample_rate() != sample_rate {
+            match self.stale.try_lock() {
+                Ok(mut stale) if stale.is_none() => *stale = Some(plan),
+                // picked up again on a later block
+                _ => *next = Some(plan),
+            }
+            return false;
+        }
+
+        match self.retired.try_lock() {
+            Ok(mut retired) if retired.is_none() => {
+                *retired = swap(plan);
+                true
+            }
+            _ => {
+                *next = Some(plan);
+                false
+            }
+        }
+    }
+}
+
+/// The value of a parameter without a slot, sent through a [`ValueQueue`].
+struct Value {
+    node: AtomicUsize,
+    parameter: AtomicUsize,
+    value: AtomicF32,
+}
+
+/// Passes the values of parameters without a host parameter slot from the
+/// editor to the audio thread, which sets them in its plan between blocks.
+/// Neither side waits or allocates; when the queue is full, the editor sends
+/// a new plan with the values instead.
+pub struct ValueQueue {
+    values: Vec<Value>,
+    // counts of the values written by the editor and read by the audio
+    // thread, wrapping around
+    written: AtomicUsize,
+    read: AtomicUsize,
+}
+
+impl Default for ValueQueue {
+    fn default() -> Self {
+        Self {
+            values: (0..MAX_VALUES)
+                .map(|_| Value {
+                    node: AtomicUsize::new(0),
+                    parameter: AtomicUsize::new(0),
+                    value: AtomicF32::new(0.0),
+                })
+                .collect(),
+            written: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl ValueQueue {
+    /// Sends the value, in its unit, of parameter `parameter` of the node
+    /// with id `node`. Returns false if the queue is full. Called from the
+    /// editor.
+    pub fn push(&self, node: usize, parameter: usize, value: f32) -> bool {
+        let written = self.written.load(Ordering::Relaxed);
+        if written.wrapping_sub(self.read.load(Ordering::Acquire)) >= self.values.len() {
+            return false;
+        }
+
+        let entry = &self.values[written % self.values.len()];
+        entry.node.store(node, Ordering::Relaxed);
+        entry.parameter.store(parameter, Ordering::Relaxed);
+        entry.value.set(value);
+        self.written.store(written.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Returns the count of values sent so far, for a plan built with every
+    /// one of them to skip them. Called from the editor.
+    pub fn written(&self) -> usize {
+        self.written.load(Ordering::Relaxed)
+    }
+
+    /// Passes each value sent since the last call to `f`, with the id of its
+    /// node and the index of its parameter. Called from the audio thread.
+    fn drain(&self, mut f: impl FnMut(usize, usize, f32)) {
+        let written = self.written.load(Ordering::Acquire);
+        let mut read = self.read.load(Ordering::Relaxed);
+        while read != written {
+            let entry = &self.values[read % self.values.len()];
+            f(entry.node.load(Ordering::Relaxed), entry.parameter.load(Ordering::Relaxed), entry.value.get());
+            read = read.wrapping_add(1);
+        }
+        self.read.store(read, Ordering::Release);
+    }
+
+    /// Drops the values sent before the count `written`, unless they have
+    /// been read already. Called from the audio thread.
+    fn skip(&self, written: usize) {
+        let read = self.read.load(Ordering::Relaxed);
+        if written.wrapping_sub(read) <= self.written.load(Ordering::Acquire).wrapping_sub(read) {
+            self.read.store(written, Ordering::Release);
+        }
+    }
+}
+
+/// Runs the latest plan, a block at a time.
+pub struct Engine {
+    sync: Arc<SyncState>,
+    plan: Option<Plan>,
+    sample_rate: f32,
+    inputs: Vec<Vec<f32>>,
+    outputs: Vec<Vec<f32>>,
+    pool: Option<Arc<Pool>>,
+    parallel: bool,
+}
+
+impl Engine {
+    pub fn new(sync: Arc<SyncState>) -> Self {
+        Self {
+            sample_rate: sync.sample_rate(),
+            sync,
+            plan: None,
+            inputs: vec![vec![0.0; MAX_BLOCK]; HOST_CHANNELS],
+            outputs: vec![vec![0.0; MAX_BLOCK]; HOST_CHANNELS],
+            pool: None,
+            parallel: true,
+        }
+    }
+
+    /// Processes nodes in parallel with the audio thread on the workers of
+    /// `pool`. Without a pool, or workers, every node is processed on the
+    /// audio thread.
+    pub fn pool(self, pool: Arc<Pool>) -> Self {
+        Self {
+            pool: Some(pool).filter(|pool| !pool.is_empty()),
+            ..self
+        }
+    }
+
+    /// Processes nodes in parallel, if there are workers, or else every node
+    /// in turn on the audio thread, e.g. while the host renders offline. The
+    /// output is the same either way.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    /// Prepares the graph for a new sample rate. Called by the host while
+    /// not processing.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        if let Some(plan) = &mut self.plan {
+            plan.set_sample_rate(sample_rate);
+        }
+        self.sync.plans.set_sample_rate(sample_rate);
+    }
+
+    /// Clears what is left of earlier blocks, e.g. when the host restarts
+    /// playback.
+    pub fn reset(&mut self) {
+        if let Some(plan) = &mut self.plan {
+            plan.reset();
+        }
+    }
+
+    /// Returns how long, in samples, the output may go on sounding after
+    /// the input falls silent.
+    pub fn tail(&self) -> usize {
+        self.plan.as_ref().map_or(0, Plan::tail)
+    }
+
+    /// Sets the parameter bound to a host parameter slot.
+    pub fn set_slot(&mut self, slot: usize, normal: f32) {
+        if let Some(plan) = &mut self.plan {
+            plan.set_slot(slot, normal);
+        }
+    }
+
+    /// Returns the buffer to copy a host input channel to before
+    /// processing, if the channel is used.
+    pub fn input(&mut self, channel: usize) -> Option<&mut [f32]> {
+        self.inputs.get_mut(channel).map(|input| input.as_mut_slice())
+    }
+
+    /// Returns a host output channel, as processed.
+    pub fn output(&self, channel: usize) -> Option<&[f32]> {
+        self.outputs.get(channel).map(|output| output.as_slice())
+    }
+
+    /// Processes `len` samples, at most [`MAX_BLOCK`], from `start` in the
+    /// host's block, with `events` the MIDI of the host's whole block.
+    pub fn process(&mut self, start: usize, len: usize, events: &[MidiEvent]) {
+        let Engine { sync, plan: current, sample_rate, inputs, outputs, pool, parallel } = self;
+
+        let current_plan = sync.plans.receive(*sample_rate, |mut plan| {
+            if let Some(previous) = current.as_mut() {
+                plan.take_over(previous);
+            }
+            plan.read_slots(sync);
+            sync.unbound.skip(plan.values_sent());
+            sync.set_latency(plan.latency());
+            current.replace(plan)
+        });
+
+        // values sent after a plan still to be swapped in wait for it
+        if current_plan {
+            sync.unbound.drain(|node, parameter, value| {
+                if let Some(plan) = current.as_mut() {
+                    plan.set_value(node, parameter, value);
+                }
+            });
+        }
+
+        let mut host = Host {
+            inputs,
+            outputs,
+            events,
+            start,
+            len: len.min(MAX_BLOCK),
+        };
+
+        match current {
+            Some(plan) => plan.run(&mut host, sync, pool.as_deref().filter(|_| *parallel)),
+            None => {
+                for output in host.outputs.iter_mut() {
+                    output.iter_mut().for_each(|sample| *sample = 0.0);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::delay::Delay;
+    use crate::library::{Manifest, PortKind, PortSpec};
+    use crate::modes::Mode;
+    use crate::nodes::utility::Gain;
+    use crate::nodes::{self, Events, Processor};
+    use vst::plugin::HostCallback;
+
+    /// A gain that takes two samples to come out, as a lookahead would.
+    struct Lookahead(Delay);
+
+    impl Processor for Lookahead {
+        fn describe(&self) -> Manifest {
+            Gain::manifest()
+        }
+
+        fn latency(&self) -> usize {
+            self.0.len()
+        }
+
+        fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, _: &[f32]) {
+            outputs[0].copy_from_slice(&inputs[0][..outputs[0].len()]);
+            self.0.apply(outputs[0]);
+        }
+    }
+
+    /// Passes a stereo signal through.
+    struct Stereo;
+
+    impl Processor for Stereo {
+        fn describe(&self) -> Manifest {
+            let port = |name: &str| PortSpec {
+                name: name.to_string(),
+                kind: PortKind::Stereo,
+                description: None,
+                unit: None,
+            };
+            Manifest {
+                name: "Stereo".to_string(),
+                category: "Utilities".to_string(),
+                description: None,
+                inputs: vec![port("In")],
+                outputs: vec![port("Out")],
+                parameters: Vec::new(),
+                gui: Default::default(),
+            }
+        }
+
+        fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, _: &[f32]) {
+            for (output, input) in outputs.iter_mut().zip(inputs) {
+                output.copy_from_slice(&input[..output.len()]);
+            }
+        }
+    }
+
+    fn create(kind: &str) -> Option<Box<dyn Processor>> {
+        match kind {
+            "Lookahead" => Some(Box::new(Lookahead(Delay::new(2)))),
+            "Stereo" => Some(Box::new(Stereo)),
+            _ => nodes::create(kind),
+        }
+    }
+
+    fn node(id: usize, kind: &str, parameters: Vec<Binding>) -> NodeSetup {
+        NodeSetup {
+            id,
+            kind: kind.to_string(),
+            parameters,
+            meters: Vec::new(),
+            mode: Some(id),
+        }
+    }
+
+    fn connection(from: usize, output: usize, to: usize, input: usize) -> Connection {
+        Connection { from, output, to, input, feedback: false }
+    }
+
+    /// A constant, bound to slot 3, through a gain to both host channels,
+    /// listed out of order.
+    fn setup() -> Setup {
+        Setup {
+            nodes: vec![
+                node(2, "Audio Output", Vec::new()),
+                node(1, "Gain", vec![Binding::Value(0.0)]),
+                node(0, "Constant", vec![Binding::Slot(3)]),
+            ],
+            connections: vec![
+                connection(0, 0, 1, 0),
+                connection(1, 0, 2, 0),
+                connection(1, 0, 2, 1),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_engine_runs_plan() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        // silent until a plan is sent
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.0; 4]);
+
+        sync.set_parameter(3, 0.75);
+        sync.plans.send(Plan::new(&setup(), 44100.0, nodes::create));
+        engine.process(0, 4, &[]);
+        for channel in 0..HOST_CHANNELS {
+            assert_eq!(&engine.output(channel).unwrap()[..4], &[0.5; 4]);
+        }
+
+        // the constant is at control rate, and ramps to its new value over
+        // a block
+        engine.set_slot(3, 0.25);
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(1).unwrap()[..4], &[0.25, 0.0, -0.25, -0.5]);
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(1).unwrap()[..4], &[-0.5; 4]);
+    }
+
+    #[test]
+    fn test_value_queue_passes_values_in_order() {
+        let queue = ValueQueue::default();
+        for value in 0..MAX_VALUES {
+            assert!(queue.push(1, value, value as f32));
+        }
+        assert!(!queue.push(1, 0, 0.0));
+
+        let mut values = Vec::new();
+        queue.drain(|node, parameter, value| values.push((node, parameter, value)));
+        assert_eq!(values.len(), MAX_VALUES);
+        assert_eq!(values[3], (1, 3, 3.0));
+
+        // values a plan was built with are skipped, later ones are not
+        queue.push(2, 0, 1.0);
+        let written = queue.written();
+        queue.push(2, 0, 2.0);
+        queue.skip(written);
+        values.clear();
+        queue.drain(|node, parameter, value| values.push((node, parameter, value)));
+        assert_eq!(values, vec![(2, 0, 2.0)]);
+    }
+
+    #[test]
+    fn test_engine_sets_unbound_values_without_a_new_plan() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        let setup = Setup {
+            nodes: vec![
+                node(0, "Constant", vec![Binding::Value(0.5)]),
+                node(1, "Audio Output", Vec::new()),
+            ],
+            connections: vec![connection(0, 0, 1, 0)],
+        };
+        let mut plan = Plan::new(&setup, 44100.0, nodes::create);
+        // sent before the plan was built, and so already in it
+        sync.unbound.push(0, 0, 0.125);
+        plan.set_values_sent(sync.unbound.written());
+        sync.plans.send(plan);
+        sync.unbound.push(0, 0, 0.25);
+
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.25; 4]);
+    }
+
+    #[test]
+    fn test_engine_hands_back_plan_for_other_sample_rate() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        sync.set_parameter(3, 0.75);
+        sync.plans.send(Plan::new(&setup(), 48000.0, nodes::create));
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.0; 4]);
+
+        // prepared again off the audio thread, and picked up
+        sync.plans.set_sample_rate(44100.0);
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.5; 4]);
+    }
+
+    #[test]
+    fn test_engine_fades_silenced_node() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+        // a fade of 2 samples
+        let sample_rate = 2.0 / crate::modes::CROSSFADE_TIME;
+        engine.set_sample_rate(sample_rate);
+
+        sync.set_parameter(3, 1.0);
+        sync.plans.send(Plan::new(&setup(), sample_rate, nodes::create));
+        sync.modes.set(1, Mode { bypass: false, silenced: true });
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.5, 0.0, 0.0, 0.0]);
+
+        // bypassing passes the constant straight through
+        sync.modes.set(1, Mode { bypass: true, silenced: false });
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.5, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_engine_runs_feedback_loop() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        // the gain feeds the mixer back what it heard the block before
+        let setup = Setup {
+            nodes: vec![
+                node(2, "Gain", vec![Binding::Value(0.0)]),
+                node(1, "Mixer", vec![Binding::Value(0.0); 4]),
+                node(0, "Constant", vec![Binding::Value(0.5)]),
+                node(3, "Audio Output", Vec::new()),
+            ],
+            connections: vec![
+                connection(0, 0, 1, 0),
+                connection(1, 0, 2, 0),
+                Connection { feedback: true, ..connection(2, 0, 1, 1) },
+                connection(2, 0, 3, 0),
+            ],
+        };
+        sync.plans.send(Plan::new(&setup, 44100.0, nodes::create));
+
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.5; 4]);
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[1.0; 4]);
+    }
+
+    #[test]
+    fn test_engine_carries_feedback_and_delays_into_new_plan() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        // a feedback loop, and a direct path delayed to match a lookahead
+        let setup = Setup {
+            nodes: vec![
+                node(0, "Constant", vec![Binding::Value(0.5)]),
+                node(1, "Mixer", vec![Binding::Value(0.0); 4]),
+                node(2, "Gain", vec![Binding::Value(0.0)]),
+                node(3, "Lookahead", vec![Binding::Value(0.0)]),
+                node(4, "Mixer", vec![Binding::Value(0.0); 4]),
+                node(5, "Audio Output", Vec::new()),
+            ],
+            connections: vec![
+                connection(0, 0, 1, 0),
+                connection(1, 0, 2, 0),
+                Connection { feedback: true, ..connection(2, 0, 1, 1) },
+                connection(0, 0, 3, 0),
+                connection(3, 0, 4, 0),
+                connection(0, 0, 4, 2),
+                connection(2, 0, 5, 0),
+                connection(4, 0, 5, 1),
+            ],
+        };
+        sync.plans.send(Plan::new(&setup, 44100.0, create));
+        // the loop reaches the host as late as the lookahead
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.0, 0.0, 0.5, 0.5]);
+        assert_eq!(&engine.output(1).unwrap()[..4], &[0.0, 0.0, 1.0, 1.0]);
+
+        // the same graph again, which picks up where the last block left off
+        sync.plans.send(Plan::new(&setup, 44100.0, create));
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.5, 0.5, 1.0, 1.0]);
+        assert_eq!(&engine.output(1).unwrap()[..4], &[1.0; 4]);
+    }
+
+    #[test]
+    fn test_engine_runs_in_parallel_as_in_turn() {
+        // four branches of different lengths, mixed
+        let mut nodes = vec![
+            node(0, "Mixer", vec![Binding::Value(0.0); 4]),
+            node(1, "Audio Output", Vec::new()),
+        ];
+        let mut connections = vec![connection(0, 0, 1, 0), connection(0, 0, 1, 1)];
+        for branch in 0..4 {
+            let id = 10 * (branch + 1);
+            nodes.push(node(id, "Oscillator", vec![Binding::Value(110.0 * (branch + 1) as f32), Binding::Value(2.0), Binding::Value(-6.0)]));
+            for gain in 1..=branch {
+                nodes.push(node(id + gain, "Gain", vec![Binding::Value(-1.0)]));
+                connections.push(connection(id + gain - 1, 0, id + gain, 0));
+            }
+            connections.push(connection(id + branch, 0, 0, branch));
+        }
+        let setup = Setup { nodes, connections };
+
+        let run = |engine: &mut Engine| {
+            let mut samples = Vec::new();
+            for _ in 0..8 {
+                engine.process(0, MAX_BLOCK, &[]);
+                samples.extend_from_slice(engine.output(0).unwrap());
+            }
+            samples
+        };
+
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut in_turn = Engine::new(sync.clone());
+        sync.plans.send(Plan::new(&setup, 44100.0, nodes::create));
+        let expected = run(&mut in_turn);
+
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut parallel = Engine::new(sync.clone()).pool(Arc::new(Pool::new(3)));
+        sync.plans.send(Plan::new(&setup, 44100.0, nodes::create));
+        assert_eq!(run(&mut parallel), expected);
+        assert!(expected.iter().any(|sample| *sample != 0.0));
+    }
+
+    #[test]
+    fn test_engine_compensates_latency() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        // the constant reaches the mixer both directly and through the
+        // lookahead, and the direct path is delayed to match
+        let setup = Setup {
+            nodes: vec![
+                node(0, "Constant", vec![Binding::Value(0.75)]),
+                node(1, "Lookahead", vec![Binding::Value(0.0)]),
+                node(2, "Mixer", vec![Binding::Value(0.0); 4]),
+                node(3, "Audio Output", Vec::new()),
+            ],
+            connections: vec![
+                connection(0, 0, 1, 0),
+                connection(1, 0, 2, 0),
+                connection(0, 0, 2, 2),
+                connection(2, 0, 3, 0),
+            ],
+        };
+        let plan = Plan::new(&setup, 44100.0, create);
+        assert_eq!(plan.latency(), 2);
+
+        // the latency is published once the plan is swapped in
+        sync.plans.send(plan);
+        assert_eq!(sync.latency(), 0);
+        engine.process(0, 4, &[]);
+        assert_eq!(sync.latency(), 2);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.0, 0.0, 1.5, 1.5]);
+    }
+
+    #[test]
+    fn test_engine_delays_bypass_by_latency() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+        // a fade of a single sample
+        let sample_rate = 1.0 / crate::modes::CROSSFADE_TIME;
+        engine.set_sample_rate(sample_rate);
+
+        let setup = Setup {
+            nodes: vec![
+                node(0, "Constant", vec![Binding::Value(0.75)]),
+                node(1, "Lookahead", vec![Binding::Value(0.0)]),
+                node(2, "Mixer", vec![Binding::Value(0.0); 4]),
+                node(3, "Audio Output", Vec::new()),
+            ],
+            connections: vec![
+                connection(0, 0, 1, 0),
+                connection(1, 0, 2, 0),
+                connection(0, 0, 2, 2),
+                connection(2, 0, 3, 0),
+            ],
+        };
+        sync.plans.send(Plan::new(&setup, sample_rate, create));
+
+        // bypassed, the lookahead still takes two samples, as the direct
+        // path was delayed to match it
+        sync.modes.set(1, Mode { bypass: true, silenced: false });
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[0.0, 0.0, 1.5, 1.5]);
+    }
+
+    #[test]
+    fn test_engine_converts_between_port_kinds() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        // the constant reaches the stereo node only through the gain, as
+        // control cannot feed stereo, and the stereo node's channels are
+        // summed into the left output
+        let setup = Setup {
+            nodes: vec![
+                node(0, "Constant", vec![Binding::Value(0.5)]),
+                node(1, "Gain", vec![Binding::Value(0.0)]),
+                node(2, "Stereo", Vec::new()),
+                node(3, "Audio Output", Vec::new()),
+            ],
+            connections: vec![
+                connection(0, 0, 1, 0),
+                connection(0, 0, 2, 0),
+                connection(1, 0, 2, 0),
+                connection(2, 0, 3, 0),
+                connection(0, 0, 3, 1),
+            ],
+        };
+        sync.plans.send(Plan::new(&setup, 44100.0, create));
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[1.0; 4]);
+        assert_eq!(&engine.output(1).unwrap()[..4], &[0.5; 4]);
+    }
+
+    #[test]
+    fn test_engine_downsamples_audio_to_control() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+
+        // the host's input scales the constant by its mean over the block
+        let setup = Setup {
+            nodes: vec![
+                node(0, "Audio Input", Vec::new()),
+                node(1, "Constant", vec![Binding::Value(1.0)]),
+                node(2, "Gain", vec![Binding::Value(0.0)]),
+                node(3, "Audio Output", Vec::new()),
+            ],
+            connections: vec![
+                connection(1, 0, 2, 0),
+                connection(0, 0, 2, 1),
+                connection(2, 0, 3, 0),
+                connection(0, 0, 3, 1),
+            ],
+        };
+        sync.plans.send(Plan::new(&setup, 44100.0, create));
+        engine.input(0).unwrap()[..4].copy_from_slice(&[0.0, 1.0, 2.0, 3.0]);
+        engine.process(0, 4, &[]);
+        assert_eq!(&engine.output(0).unwrap()[..4], &[1.5; 4]);
+        assert_eq!(&engine.output(1).unwrap()[..4], &[0.0, 1.0, 2.0, 3.0]);
+    }
+}
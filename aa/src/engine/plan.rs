@@ -0,0 +1,1010 @@
+//! What the engine runs: the graph's nodes in the order they are processed,
+//! each with its processor and the buffers it reads and writes.
+//!
+//! A [`Plan`] is built by the editor from a [`Setup`], so that everything it
+//! needs is allocated off the audio thread, and then only run by the audio
+//! thread.
+
+use std::collections::HashMap;
+use std::ops::Range as Channels;
+
+use ag::{Conversion, PortRegistry};
+
+use crate::library::{Manifest, PortKind, Range};
+use crate::modes::{Crossfade, Mode};
+use crate::nodes::io::{External, HOST_CHANNELS};
+use crate::nodes::{self, Events, MidiEvent, Processor, MAX_EVENTS};
+use crate::sync::{SyncState, MAX_PARAMETERS};
+
+use super::delay::Delay;
+use super::pool::Pool;
+use super::{MAX_BLOCK, MAX_PORTS};
+
+/// Where a parameter's value comes from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    /// The host parameter slot the parameter is bound to.
+    Slot(usize),
+    /// A value, in the parameter's unit, for a parameter without a slot.
+    Value(f32),
+}
+
+/// A node as set up in the editor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSetup {
+    pub id: usize,
+    pub kind: String,
+    /// One for each parameter, in the order of the node's manifest.
+    pub parameters: Vec<Binding>,
+    /// The meter for each output, if it is metered.
+    pub meters: Vec<Option<usize>>,
+    /// The slot the node's mode is published in, if it has one.
+    pub mode: Option<usize>,
+}
+
+/// A connection from output `output` of the node with id `from` to input
+/// `input` of the node with id `to`.
+///
+/// A feedback connection may close a loop, where it carries the block `from`
+/// output before. That adds a block to the time around the loop, on top of
+/// any delay on it, and blocks are as long as the host makes them, so the
+/// loop's timing changes with the host's block size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Connection {
+    pub from: usize,
+    pub output: usize,
+    pub to: usize,
+    pub input: usize,
+    pub feedback: bool,
+}
+
+/// The graph as set up in the editor, from which a [`Plan`] is built.
+///
+/// The editor only sends a new plan when its graph changes, which parameter
+/// values do not: those bound to a slot are read from the slot, and others
+/// are sent through the [`ValueQueue`](super::ValueQueue).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Setup {
+    pub nodes: Vec<NodeSetup>,
+    pub connections: Vec<Connection>,
+}
+
+impl Setup {
+    /// Returns true if `other` has the same nodes, bindings, meters, mode
+    /// slots and connections, whatever the values of parameters without a slot, so
+    /// that a plan built for one runs the other once the values are sent.
+    pub fn same_graph(&self, other: &Setup) -> bool {
+        let same_binding = |a: &Binding, b: &Binding| match (a, b) {
+            (Binding::Slot(a), Binding::Slot(b)) => a == b,
+            (Binding::Value(_), Binding::Value(_)) => true,
+            _ => false,
+        };
+
+        self.connections == other.connections
+            && self.nodes.len() == other.nodes.len()
+            && self.nodes.iter().zip(&other.nodes).all(|(a, b)| {
+                a.id == b.id
+                    && a.kind == b.kind
+                    && a.meters == b.meters
+                    && a.mode == b.mode
+                    && a.parameters.len() == b.parameters.len()
+                    && a.parameters.iter().zip(&b.parameters).all(|(a, b)| same_binding(a, b))
+            })
+    }
+}
+
+/// The host's buffers for one block, of at most [`MAX_BLOCK`] samples.
+#[derive(Debug)]
+pub struct Host<'a> {
+    pub inputs: &'a [Vec<f32>],
+    pub outputs: &'a mut [Vec<f32>],
+    /// The MIDI events of the host's whole block.
+    pub events: &'a [MidiEvent],
+    /// Where this block starts in the host's block.
+    pub start: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Parameter {
+    range: Range,
+    slot: Option<usize>,
+}
+
+/// A port of a node, and the buffers its channels are processed in.
+#[derive(Debug, Clone, PartialEq)]
+struct Port {
+    kind: PortKind,
+    channels: Channels<usize>,
+}
+
+/// How a signal changes rate between an output and an input of another
+/// kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rate {
+    Same,
+    /// From control rate, the last sample of each block, ramped to from the
+    /// block before, or held on the first block.
+    Up { last: Option<f32> },
+    /// To control rate, the mean of each block.
+    Down,
+}
+
+impl Rate {
+    fn of(conversion: Conversion) -> Self {
+        match conversion {
+            Conversion::UpSample => Rate::Up { last: None },
+            Conversion::DownSample => Rate::Down,
+            _ => Rate::Same,
+        }
+    }
+
+    /// Converts a block in place.
+    fn convert(&mut self, block: &mut [f32]) {
+        let len = block.len();
+        match self {
+            Rate::Same => {}
+            Rate::Up { last } => {
+                let to = match block.last() {
+                    Some(to) => *to,
+                    None => return,
+                };
+                let from = last.unwrap_or(to);
+                for (index, sample) in block.iter_mut().enumerate() {
+                    *sample = from + (to - from) * (index + 1) as f32 / len as f32;
+                }
+                *last = Some(to);
+            }
+            Rate::Down => {
+                let mean = block.iter().sum::<f32>() / len.max(1) as f32;
+                block.iter_mut().for_each(|sample| *sample = mean);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        if let Rate::Up { last } = self {
+            *last = None;
+        }
+    }
+}
+
+/// An output channel connected to an input channel.
+#[derive(Debug)]
+struct Source {
+    // the node, by its index in the plan, and by its id
+    node: usize,
+    id: usize,
+    output: usize,
+    rate: Rate,
+    // makes up for the latency of other paths to the same node
+    delay: Delay,
+}
+
+/// A channel of an input port.
+#[derive(Debug)]
+struct Input {
+    sources: Vec<Source>,
+    midi: bool,
+    // filled from the host's input of the same channel
+    host: bool,
+    buffer: Vec<f32>,
+    // where sources that change rate are converted, empty if none do
+    scratch: Vec<f32>,
+}
+
+impl Input {
+    fn new(midi: bool, host: bool) -> Self {
+        Self {
+            sources: Vec::new(),
+            midi,
+            host,
+            buffer: vec![0.0; MAX_BLOCK],
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Returns the block arriving at the input, or an empty slice if
+    /// nothing is connected.
+    fn block(&self, len: usize) -> &[f32] {
+        if self.sources.is_empty() && !self.host { &[] } else { &self.buffer[..len] }
+    }
+}
+
+/// A node as the engine runs it.
+struct Step {
+    id: usize,
+    kind: String,
+    // `None` for nodes that cannot be run yet, whose outputs stay silent
+    processor: Option<Box<dyn Processor>>,
+    external: Option<External>,
+    input_ports: Vec<Port>,
+    output_ports: Vec<Port>,
+    // a channel of each input and output port
+    inputs: Vec<Input>,
+    outputs: Vec<Vec<f32>>,
+    // the input channel passed through to each output channel when bypassed
+    through: Vec<Option<usize>>,
+    // delays what is passed through by the node's latency, so that
+    // bypassing it keeps the paths after it in time
+    through_delays: Vec<Delay>,
+    // the delayed input of one output channel
+    dry: Vec<f32>,
+    // the meter of each output port, if it is metered
+    meters: Vec<Option<usize>>,
+    // the slot of the node's mode, if it has one
+    mode: Option<usize>,
+    fades: Vec<Crossfade>,
+    // the MIDI arriving at the node's MIDI inputs, and that it sent
+    events: Vec<MidiEvent>,
+    sent: Vec<MidiEvent>,
+    parameters: Vec<Parameter>,
+    values: Vec<f32>,
+    // the latency of everything before the node, where its inputs arrive
+    arrival: usize,
+    // for audio outputs, makes up for the latency of other audio outputs
+    host_delays: Vec<Delay>,
+}
+
+/// The nodes of a graph, in the order they are processed, ready to run.
+pub struct Plan {
+    steps: Vec<Step>,
+    // where each level of steps that can run in parallel starts, and then
+    // where the last one ends
+    levels: Vec<usize>,
+    // the step and parameter bound to each host parameter slot
+    slots: Vec<Option<(usize, usize)>>,
+    // the step of each node, by the node's id
+    ids: HashMap<usize, usize>,
+    // the count of values sent through the value queue that the plan was
+    // built with
+    values_sent: usize,
+    sample_rate: f32,
+    latency: usize,
+}
+
+impl Plan {
+    /// Builds a plan for `setup`, with processors made by `create` for each
+    /// node's kind and prepared for `sample_rate`. Nodes without a processor
+    /// have no ports.
+    ///
+    /// Nodes are ordered so that each comes after the nodes it takes input
+    /// from, except through feedback connections. A loop is broken at a
+    /// delay on it if there is one, so that the delay reads the loop's
+    /// previous block; nodes read any later node's previous block. Nodes are
+    /// then grouped into levels, of nodes that do not read each other's
+    /// outputs and so can be processed in parallel.
+    ///
+    /// Where paths of different latency meet, the shorter ones are delayed
+    /// to match, so that they stay in time, and so are audio outputs.
+    ///
+    /// Connections between ports of different kinds convert the signal as
+    /// the editor's [`PortRegistry`] says, and are left out if it has no
+    /// conversion for them. A mono output feeds each channel of a stereo
+    /// input, and the channels of a stereo output are summed into a mono
+    /// input.
+    pub fn new(
+        setup: &Setup,
+        sample_rate: f32,
+        mut create: impl FnMut(&str) -> Option<Box<dyn Processor>>) -> Self {
+        let (order, levels) = levels(setup, &order(setup));
+        let ids: HashMap<usize, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(step, node)| (setup.nodes[*node].id, step))
+            .collect();
+
+        let mut steps: Vec<Step> = order
+            .iter()
+            .map(|node| {
+                let node = &setup.nodes[*node];
+                step(node, create(&node.kind), sample_rate)
+            })
+            .collect();
+
+        let registry = PortRegistry::default();
+        for connection in &setup.connections {
+            let (from, to) = match (ids.get(&connection.from), ids.get(&connection.to)) {
+                (Some(from), Some(to)) => (*from, *to),
+                _ => continue,
+            };
+            let (output, input) = match (
+                steps[from].output_ports.get(connection.output),
+                steps[to].input_ports.get(connection.input)) {
+                (Some(output), Some(input)) => (output.clone(), input.clone()),
+                _ => continue,
+            };
+            let rate = match registry.conversion(output.kind.port_type(), input.kind.port_type()) {
+                Some(conversion) => Rate::of(conversion),
+                None => continue,
+            };
+
+            // channel to channel, or else every channel to every channel
+            let paired = output.channels.len() == input.channels.len();
+            for (index, channel) in input.channels.enumerate() {
+                let input = &mut steps[to].inputs[channel];
+                if rate != Rate::Same {
+                    input.scratch = vec![0.0; MAX_BLOCK];
+                }
+                let outputs = match paired {
+                    true => output.channels.start + index..output.channels.start + index + 1,
+                    false => output.channels.clone(),
+                };
+                for output in outputs {
+                    input.sources.push(Source {
+                        node: from,
+                        id: connection.from,
+                        output,
+                        rate,
+                        delay: Delay::new(0),
+                    });
+                }
+            }
+        }
+        let latency = compensate(&mut steps);
+
+        let mut slots = vec![None; MAX_PARAMETERS];
+        for (index, step) in steps.iter().enumerate() {
+            for (parameter, spec) in step.parameters.iter().enumerate() {
+                if let Some(slot) = spec.slot.and_then(|slot| slots.get_mut(slot)) {
+                    *slot = Some((index, parameter));
+                }
+            }
+        }
+
+        Self {
+            steps,
+            levels,
+            slots,
+            ids,
+            values_sent: 0,
+            sample_rate,
+            latency,
+        }
+    }
+
+    /// Takes over the processors and fades of nodes that are in both plans,
+    /// so that they carry on where they were. Where a node's ports are laid
+    /// out the same in both, so does what is left of its last block: the
+    /// outputs that feedback connections read, and the delay lines making up
+    /// for latency. Called from the audio thread.
+    pub fn take_over(&mut self, previous: &mut Plan) {
+        for step in &mut self.steps {
+            let same = match previous.ids.get(&step.id) {
+                Some(old) => Some(&mut previous.steps[*old]).filter(|old| old.kind == step.kind),
+                None => None,
+            };
+
+            if let Some(old) = same {
+                std::mem::swap(&mut step.processor, &mut old.processor);
+                for (fade, old) in step.fades.iter_mut().zip(&old.fades) {
+                    *fade = *old;
+                }
+                for (delay, old) in step.through_delays.iter_mut().zip(&mut old.through_delays) {
+                    if delay.len() == old.len() {
+                        std::mem::swap(delay, old);
+                    }
+                }
+
+                if step.input_ports == old.input_ports && step.output_ports == old.output_ports {
+                    step.take_over_block(old);
+                }
+            }
+        }
+    }
+
+    /// Sets every parameter bound to a slot from the slot's current value.
+    pub fn read_slots(&mut self, sync: &SyncState) {
+        for slot in 0..self.slots.len() {
+            self.set_slot(slot, sync.value(slot));
+        }
+    }
+
+    /// Returns the latency of the graph, from the host's inputs to its
+    /// outputs, in samples.
+    pub fn latency(&self) -> usize {
+        self.latency
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Prepares every processor for a new sample rate.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for processor in self.processors() {
+            processor.prepare(sample_rate, MAX_BLOCK);
+        }
+    }
+
+    /// Clears what is left of earlier blocks, in every processor and in the
+    /// buffers read on the next block.
+    pub fn reset(&mut self) {
+        for processor in self.processors() {
+            processor.reset();
+        }
+        for step in &mut self.steps {
+            for output in &mut step.outputs {
+                output.iter_mut().for_each(|sample| *sample = 0.0);
+            }
+            for source in step.inputs.iter_mut().flat_map(|input| input.sources.iter_mut()) {
+                source.delay.clear();
+                source.rate.clear();
+            }
+            step.host_delays.iter_mut().for_each(Delay::clear);
+            step.through_delays.iter_mut().for_each(Delay::clear);
+            step.sent.clear();
+        }
+    }
+
+    /// Returns the longest time, in samples, that any node goes on sounding
+    /// after its inputs fall silent.
+    pub fn tail(&self) -> usize {
+        self.steps
+            .iter()
+            .filter_map(|step| step.processor.as_ref())
+            .map(|processor| processor.tail())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn processors(&mut self) -> impl Iterator<Item = &mut Box<dyn Processor>> {
+        self.steps.iter_mut().filter_map(|step| step.processor.as_mut())
+    }
+
+    /// Sets the parameter bound to `slot` from its normal.
+    pub fn set_slot(&mut self, slot: usize, normal: f32) {
+        if let Some(Some((step, parameter))) = self.slots.get(slot) {
+            let step = &mut self.steps[*step];
+            step.values[*parameter] = step.parameters[*parameter].range.unmap(normal.into()) as f32;
+        }
+    }
+
+    /// Sets parameter `parameter` of the node with id `node`, if it is not
+    /// bound to a slot, to a value in its unit.
+    pub fn set_value(&mut self, node: usize, parameter: usize, value: f32) {
+        if let Some(&step) = self.ids.get(&node) {
+            let step = &mut self.steps[step];
+            if matches!(step.parameters.get(parameter), Some(spec) if spec.slot.is_none()) {
+                step.values[parameter] = value;
+            }
+        }
+    }
+
+    /// Returns the count of values sent through the value queue that the
+    /// plan was built with, which the engine skips when it swaps the plan in.
+    pub fn values_sent(&self) -> usize {
+        self.values_sent
+    }
+
+    /// Records that the plan was built with the values sent through the
+    /// value queue up to the count `sent`. Called from the editor.
+    pub fn set_values_sent(&mut self, sent: usize) {
+        self.values_sent = sent;
+    }
+
+    /// Processes one block, mixing the outputs of every audio output node
+    /// into the host's outputs. Called from the audio thread.
+    ///
+    /// With a pool, the steps of each level are processed in parallel on its
+    /// workers and the calling thread, otherwise one by one. The output is
+    /// the same either way.
+    pub fn run(&mut self, host: &mut Host<'_>, sync: &SyncState, pool: Option<&Pool>) {
+        let len = host.len;
+        for output in host.outputs.iter_mut() {
+            output[..len].iter_mut().for_each(|sample| *sample = 0.0);
+        }
+
+        for level in 0..self.levels.len().saturating_sub(1) {
+            let steps = self.levels[level]..self.levels[level + 1];
+            match pool {
+                Some(pool) if steps.len() > 1 => self.run_parallel(steps.clone(), host, sync, pool),
+                _ => {
+                    for index in steps.clone() {
+                        self.run_step(index, host, sync);
+                    }
+                }
+            }
+
+            // in the order of the steps, so that the mix is the same however
+            // they were run
+            for step in &mut self.steps[steps] {
+                if step.external == Some(External::AudioOutput) {
+                    let channels = host.outputs.iter_mut().zip(&step.outputs).zip(&mut step.host_delays);
+                    for ((host, output), delay) in channels {
+                        delay.add_to(&output[..len], &mut host[..len]);
+                    }
+                }
+
+                step.measure(len, sync);
+            }
+        }
+    }
+
+    /// Gathers the inputs of a step and processes it.
+    fn run_step(&mut self, index: usize, host: &Host<'_>, sync: &SyncState) {
+        let (done, rest) = self.steps.split_at_mut(index);
+        let (step, later) = match rest.split_first_mut() {
+            Some(split) => split,
+            None => return,
+        };
+        let source = |node: usize| -> Option<&Step> {
+            match node {
+                node if node < index => done.get(node),
+                node if node > index => later.get(node - index - 1),
+                _ => None,
+            }
+        };
+
+        step.gather(host, host.len, source);
+        step.process(host.len, sync, self.sample_rate);
+    }
+
+    /// Gathers the inputs of the steps in `level` and processes them, on the
+    /// workers of `pool` as well as the calling thread.
+    fn run_parallel(&mut self, level: std::ops::Range<usize>, host: &Host<'_>, sync: &SyncState, pool: &Pool) {
+        let steps = Steps(self.steps.as_mut_ptr(), self.steps.len());
+        let sample_rate = self.sample_rate;
+
+        pool.run(level.len(), &|task| {
+            let index = level.start + task;
+            // a step only reads the steps of other levels, none of which run
+            // until this one is done, and each step of the level runs once
+            let step = unsafe { &mut *steps.0.add(index) };
+            let source = |node: usize| match node {
+                node if node != index && node < steps.1 => Some(unsafe { &*steps.0.add(node) }),
+                _ => None,
+            };
+
+            step.gather(host, host.len, source);
+            step.process(host.len, sync, sample_rate);
+        });
+    }
+}
+
+/// The steps of a plan, shared by the threads running a level.
+struct Steps(*mut Step, usize);
+
+unsafe impl Sync for Steps {}
+
+impl Step {
+    /// Takes what is left of the last block from the same node in another
+    /// plan, with its ports laid out the same.
+    fn take_over_block(&mut self, old: &mut Step) {
+        std::mem::swap(&mut self.outputs, &mut old.outputs);
+        for (delay, old) in self.host_delays.iter_mut().zip(&mut old.host_delays) {
+            if delay.len() == old.len() {
+                std::mem::swap(delay, old);
+            }
+        }
+
+        for (input, old) in self.inputs.iter_mut().zip(&mut old.inputs) {
+            for source in &mut input.sources {
+                let old = old
+                    .sources
+                    .iter_mut()
+                    .find(|old| old.id == source.id && old.output == source.output);
+                if let Some(old) = old {
+                    if source.delay.len() == old.delay.len() {
+                        std::mem::swap(&mut source.delay, &mut old.delay);
+                    }
+                    if let (Rate::Up { last }, Rate::Up { last: old }) = (&mut source.rate, old.rate) {
+                        *last = old;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fills the inputs with what arrives at them, from other nodes or from
+    /// the host.
+    fn gather<'a>(&mut self, host: &Host<'_>, len: usize, source: impl Fn(usize) -> Option<&'a Step>) {
+        let Step { external, inputs, outputs, events, .. } = self;
+
+        events.clear();
+        if *external == Some(External::MidiInput) {
+            let (start, end) = (host.start, host.start + len);
+            let arriving = host.events.iter().filter(|event| event.offset >= start && event.offset < end);
+            for event in arriving.take(MAX_EVENTS) {
+                events.push(MidiEvent::new(event.offset - start, event.data));
+            }
+        }
+
+        for (channel, input) in inputs.iter_mut().enumerate() {
+            if *external == Some(External::AudioInput) {
+                if let Some(host) = host.inputs.get(channel) {
+                    input.buffer[..len].copy_from_slice(&host[..len]);
+                }
+                continue;
+            }
+
+            if input.midi {
+                for Source { node, .. } in &input.sources {
+                    if let Some(node) = source(*node) {
+                        let room = events.capacity() - events.len();
+                        events.extend(node.sent.iter().take(room));
+                    }
+                }
+                continue;
+            }
+
+            let Input { sources, buffer, scratch, .. } = input;
+            let buffer = &mut buffer[..len];
+            buffer.iter_mut().for_each(|sample| *sample = 0.0);
+            for Source { node, output, rate, delay, .. } in sources {
+                // a node connected to itself hears its previous block
+                let output = match source(*node) {
+                    Some(node) => &node.outputs[*output],
+                    None => &outputs[*output],
+                };
+                if *rate == Rate::Same {
+                    delay.add_to(&output[..len], buffer);
+                    continue;
+                }
+
+                // converted as sent, and then delayed
+                let converted = &mut scratch[..len];
+                converted.copy_from_slice(&output[..len]);
+                rate.convert(converted);
+                delay.add_to(converted, buffer);
+            }
+        }
+
+        sort_by_offset(events);
+    }
+
+    /// Runs the processor, unless the mode of the node means it would not be
+    /// heard, and fades its outputs as the mode changes.
+    fn process(&mut self, len: usize, sync: &SyncState, sample_rate: f32) {
+        let mode = self.mode.map_or_else(Mode::default, |slot| sync.modes.get(slot));
+        for fade in &mut self.fades {
+            fade.set(mode, sample_rate);
+        }
+        let idle = !self.fades.is_empty() && self.fades.iter().all(Crossfade::is_idle);
+
+        let Step { processor, inputs, outputs, through, through_delays, dry, fades, events, sent, values, .. } = self;
+
+        match processor {
+            Some(processor) if !idle => {
+                let mut ins: [&[f32]; MAX_PORTS] = Default::default();
+                for (block, input) in ins.iter_mut().zip(inputs.iter()) {
+                    *block = input.block(len);
+                }
+                let count = outputs.len();
+                let mut outs: [&mut [f32]; MAX_PORTS] = Default::default();
+                for (block, output) in outs.iter_mut().zip(outputs.iter_mut()) {
+                    *block = &mut output[..len];
+                }
+
+                processor.process(
+                    &ins[..inputs.len()],
+                    &mut outs[..count],
+                    &mut Events::new(events, sent),
+                    values);
+            }
+            _ => {
+                sent.clear();
+                for output in outputs.iter_mut() {
+                    output[..len].iter_mut().for_each(|sample| *sample = 0.0);
+                }
+            }
+        }
+
+        let channels = outputs.iter_mut().zip(fades.iter_mut()).zip(through.iter().zip(through_delays.iter_mut()));
+        for ((output, fade), (through, delay)) in channels {
+            let input = through
+                .and_then(|input| inputs.get(input))
+                .map(|input| input.block(len))
+                .filter(|block| !block.is_empty());
+            // fed every block, so that it is full when the node is bypassed
+            let input = match input {
+                Some(input) if !delay.is_empty() => {
+                    dry[..len].copy_from_slice(input);
+                    delay.apply(&mut dry[..len]);
+                    Some(&dry[..len])
+                }
+                input => input,
+            };
+            fade.apply(&mut output[..len], input);
+        }
+
+        if mode.silenced {
+            sent.clear();
+        } else if mode.bypass {
+            sent.clear();
+            sent.extend(events.iter().take(MAX_EVENTS));
+        }
+    }
+
+    /// Meters the outputs and records those that are probed.
+    fn measure(&self, len: usize, sync: &SyncState) {
+        if sync.meters.is_enabled() {
+            for (port, meter) in self.output_ports.iter().zip(&self.meters) {
+                if let Some(meter) = meter.and_then(|meter| sync.meters.output(meter)) {
+                    meter.measure_channels(port.channels.clone().map(|channel| &self.outputs[channel][..len]));
+                }
+            }
+        }
+
+        // the first channel of a probed port
+        for (probe, (node, output)) in sync.probes.attached() {
+            if node == self.id {
+                if let Some(port) = self.output_ports.get(output) {
+                    probe.record(&self.outputs[port.channels.start][..len]);
+                }
+            }
+        }
+    }
+}
+
+/// Creates the step for a node, with buffers for the channels of the ports
+/// its processor describes.
+fn step(node: &NodeSetup, mut processor: Option<Box<dyn Processor>>, sample_rate: f32) -> Step {
+    let external = External::of(&node.kind);
+    if let Some(processor) = &mut processor {
+        processor.prepare(sample_rate, MAX_BLOCK);
+    }
+    let manifest: Option<Manifest> = processor.as_ref().map(|processor| processor.describe());
+    let manifest = manifest.as_ref();
+
+    let (input_kinds, mut output_kinds): (Vec<PortKind>, Vec<PortKind>) = match manifest {
+        Some(manifest) => (
+            manifest.inputs.iter().map(|port| port.kind).collect(),
+            manifest.outputs.iter().map(|port| port.kind).collect(),
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+    // the host's outputs, as the audio output node passes them on
+    if external == Some(External::AudioOutput) {
+        output_kinds = vec![PortKind::Audio; HOST_CHANNELS];
+    }
+    let input_ports = ports(&input_kinds);
+    let output_ports = ports(&output_kinds);
+
+    let inputs = if external == Some(External::AudioInput) {
+        (0..HOST_CHANNELS).map(|_| Input::new(false, true)).collect()
+    } else {
+        input_ports
+            .iter()
+            .flat_map(|port| port.channels.clone().map(move |_| Input::new(port.kind == PortKind::Midi, false)))
+            .collect()
+    };
+    let channels = output_ports.last().map_or(0, |port| port.channels.end);
+
+    // the n-th output of a kind passes through the n-th input of that kind,
+    // channel by channel
+    let mut through = vec![None; channels];
+    for (index, output) in output_ports.iter().enumerate() {
+        let nth = output_ports[..index].iter().filter(|port| port.kind == output.kind).count();
+        let input = input_ports.iter().filter(|port| port.kind == output.kind).nth(nth);
+        if let Some(input) = input {
+            for (output, input) in output.channels.clone().zip(input.channels.clone()) {
+                through[output] = Some(input);
+            }
+        }
+    }
+
+    let latency = processor.as_ref().map_or(0, |processor| processor.latency());
+    let through_delays = through.iter().map(|_| Delay::new(latency)).collect();
+
+    let specs = manifest.map_or(&[][..], |manifest| &manifest.parameters[..]);
+    let parameters: Vec<Parameter> = specs
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| Parameter {
+            range: Range::new(spec),
+            slot: match node.parameters.get(index) {
+                Some(Binding::Slot(slot)) => Some(*slot),
+                _ => None,
+            },
+        })
+        .collect();
+    let values = specs
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| match node.parameters.get(index) {
+            Some(Binding::Value(value)) => *value,
+            _ => spec.default as f32,
+        })
+        .collect();
+
+    Step {
+        id: node.id,
+        kind: node.kind.clone(),
+        processor,
+        external,
+        meters: (0..output_ports.len()).map(|index| node.meters.get(index).copied().flatten()).collect(),
+        mode: node.mode,
+        input_ports,
+        output_ports,
+        inputs,
+        outputs: (0..channels).map(|_| vec![0.0; MAX_BLOCK]).collect(),
+        through,
+        through_delays,
+        dry: vec![0.0; if latency > 0 { MAX_BLOCK } else { 0 }],
+        fades: (0..channels).map(|_| Crossfade::default()).collect(),
+        events: Vec::with_capacity(MAX_EVENTS),
+        sent: Vec::with_capacity(MAX_EVENTS),
+        parameters,
+        values,
+        arrival: 0,
+        host_delays: Vec::new(),
+    }
+}
+
+/// Lays out ports of the given kinds, each channel in a buffer of its own,
+/// up to [`MAX_PORTS`] buffers.
+fn ports(kinds: &[PortKind]) -> Vec<Port> {
+    let mut start = 0;
+    kinds
+        .iter()
+        .map(|kind| {
+            let channels = start..start + kind.channels();
+            start = channels.end;
+            Port { kind: *kind, channels }
+        })
+        .take_while(|port| port.channels.end <= MAX_PORTS)
+        .collect()
+}
+
+/// Works out where each node's inputs arrive, delaying the connections on
+/// shorter paths to match the longest, and then the audio outputs to match
+/// each other. Returns the latency of the graph.
+///
+/// Connections back to earlier nodes, on a cycle, are not delayed.
+fn compensate(steps: &mut [Step]) -> usize {
+    let latency = |step: &Step| step.processor.as_ref().map_or(0, |processor| processor.latency());
+
+    for index in 0..steps.len() {
+        let (done, rest) = steps.split_at_mut(index);
+        let step = &mut rest[0];
+        // when the output of an earlier node is ready
+        let ready = |node: usize| done.get(node).map(|node| node.arrival + latency(node));
+
+        step.arrival = step
+            .inputs
+            .iter()
+            .filter(|input| !input.midi)
+            .flat_map(|input| input.sources.iter())
+            .filter_map(|source| ready(source.node))
+            .max()
+            .unwrap_or(0);
+
+        for input in step.inputs.iter_mut().filter(|input| !input.midi) {
+            for source in &mut input.sources {
+                if let Some(ready) = ready(source.node) {
+                    source.delay = Delay::new(step.arrival - ready);
+                }
+            }
+        }
+    }
+
+    let outputs = || steps.iter().filter(|step| step.external == Some(External::AudioOutput));
+    let total = outputs().map(|step| step.arrival).max().unwrap_or(0);
+    for step in steps.iter_mut().filter(|step| step.external == Some(External::AudioOutput)) {
+        step.host_delays = (0..HOST_CHANNELS).map(|_| Delay::new(total - step.arrival)).collect();
+    }
+
+    total
+}
+
+/// Returns the indices of the nodes in `setup` in the order they are to be
+/// processed, keeping the order of the setup where it does not matter.
+fn order(setup: &Setup) -> Vec<usize> {
+    let mut placed = vec![false; setup.nodes.len()];
+    let mut order = Vec::with_capacity(setup.nodes.len());
+    let position: HashMap<usize, usize> = setup
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| (node.id, index))
+        .collect();
+    // the connections that order nodes, as pairs of indices
+    let edges: Vec<(usize, usize)> = setup
+        .connections
+        .iter()
+        .filter(|connection| !connection.feedback)
+        .filter_map(|connection| Some((*position.get(&connection.from)?, *position.get(&connection.to)?)))
+        .collect();
+
+    // the nodes `node` takes input from, other than through feedback
+    let sources = |node: usize| edges.iter().filter(move |(_, to)| *to == node).map(|(from, _)| *from);
+    // the nodes that take input from `node`, directly or not
+    let reached = |node: usize| {
+        let mut reached = vec![false; setup.nodes.len()];
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            for (_, to) in edges.iter().filter(|(from, _)| *from == node) {
+                if !reached[*to] {
+                    reached[*to] = true;
+                    stack.push(*to);
+                }
+            }
+        }
+        reached
+    };
+
+    // a node is ready once every node it takes input from is placed
+    let ready = |node: usize, placed: &[bool]| sources(node).all(|from| placed[from]);
+    // a delay can break a loop if every node it waits for is on a loop
+    // through it
+    let breaks_loop = |node: usize, placed: &[bool]| {
+        nodes::is_delay(&setup.nodes[node].kind) && {
+            let reached = reached(node);
+            sources(node).all(|from| placed[from] || reached[from])
+        }
+    };
+
+    while order.len() < setup.nodes.len() {
+        let next = (0..setup.nodes.len())
+            .find(|node| !placed[*node] && ready(*node, &placed))
+            .or_else(|| (0..setup.nodes.len()).find(|node| !placed[*node] && breaks_loop(*node, &placed)))
+            // on a loop without a delay, place the first node left
+            .or_else(|| (0..setup.nodes.len()).find(|node| !placed[*node]));
+
+        match next {
+            Some(node) => {
+                placed[node] = true;
+                order.push(node);
+            }
+            None => break,
+        }
+    }
+
+    order
+}
+
+/// Groups the nodes in `order` into levels, each of which can be processed in
+/// parallel: a node comes in a later level than the earlier nodes it takes
+/// input from, and than the earlier nodes that read its previous block.
+///
+/// Returns the nodes ordered by level, keeping `order` within each, and where
+/// each level starts followed by where the last one ends.
+fn levels(setup: &Setup, order: &[usize]) -> (Vec<usize>, Vec<usize>) {
+    let position: HashMap<usize, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(position, node)| (setup.nodes[*node].id, position))
+        .collect();
+
+    let mut levels = vec![0; order.len()];
+    for at in 0..order.len() {
+        let level = setup
+            .connections
+            .iter()
+            .filter_map(|connection| {
+                let (from, to) = (*position.get(&connection.from)?, *position.get(&connection.to)?);
+                match (from, to) {
+                    (from, to) if to == at && from < at => Some(levels[from] + 1),
+                    (from, to) if from == at && to < at => Some(levels[to] + 1),
+                    _ => None,
+                }
+            })
+            .max()
+            .unwrap_or(0);
+        levels[at] = level;
+    }
+
+    let mut positions: Vec<usize> = (0..order.len()).collect();
+    positions.sort_by_key(|position| levels[*position]);
+
+    let mut starts: Vec<usize> = (0..positions.len())
+        .filter(|index| *index == 0 || levels[positions[*index]] != levels[positions[*index - 1]])
+        .collect();
+    starts.push(positions.len());
+
+    (positions.iter().map(|position| order[*position]).collect(), starts)
+}
+
+/// Sorts events by offset, keeping the order of events at the same offset,
+/// without allocating.
+fn sort_by_offset(events: &mut [MidiEvent]) {
+    for index in 1..events.len() {
+        let mut at = index;
+        while at > 0 && events[at - 1].offset > events[at].offset {
+            events.swap(at - 1, at);
+            at -= 1;
+        }
+    }
+}
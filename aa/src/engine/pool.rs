@@ -0,0 +1,304 @@
+//! Worker threads that the audio thread shares the nodes of a block with.
+//!
+//! The workers are started off the audio thread and then wait for work, first
+//! spinning and then parked. Handing them work and waiting for it to finish
+//! neither allocates nor locks: the audio thread publishes the tasks through
+//! atomics, runs tasks itself until none are left, and then waits only for
+//! the tasks that workers took and have not finished. A worker that wakes
+//! too late to take a task is never waited for.
+//!
+//! A worker descheduled in the middle of a task holds up the block it belongs
+//! to until it runs again, so workers ask for the real time priority of an
+//! audio thread. Where the system refuses, e.g. without RealtimeKit on Linux,
+//! they keep the normal priority.
+
+use audio_thread_priority::promote_current_thread_to_real_time;
+
+use std::cell::UnsafeCell;
+use std::hint::spin_loop;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+
+use super::MAX_BLOCK;
+
+/// A task, run for each index of a run.
+pub type Task<'a> = dyn Fn(usize) + Sync + 'a;
+
+/// How many times an idle worker looks for work before it parks.
+const SPINS: usize = 2000;
+
+/// The sample rate workers ask for real time priority at, as they serve every
+/// engine in the process whatever its rate.
+const PRIORITY_RATE: u32 = 48000;
+
+/// The most indices in a run, so that the next index and the count fit in
+/// `Shared::claims`.
+pub const MAX_TASKS: usize = 0xFFFF;
+
+/// The bits of `Shared::claims` holding the next index to run, the number of
+/// indices in the run, and the number of runs started.
+const INDEX_BITS: u64 = 16;
+const INDEX_MASK: u64 = (1 << INDEX_BITS) - 1;
+const COUNT_SHIFT: u64 = INDEX_BITS;
+const RUN_SHIFT: u64 = 2 * INDEX_BITS;
+
+struct Shared {
+    // the task of the latest run, only replaced once every index of the run
+    // before has finished
+    task: UnsafeCell<Option<*const Task<'static>>>,
+    // the next index to run, the number of indices and the run they belong
+    // to, changed together so that an index is only ever taken from the run
+    // that it belongs to
+    claims: AtomicU64,
+    // the number of indices of the latest run that have finished
+    finished: AtomicUsize,
+    // set while a thread is running tasks on the pool
+    busy: AtomicBool,
+    quit: AtomicBool,
+}
+
+// the task is only read by threads that took an index of the run, while the
+// thread that set it waits for that index to finish
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    /// Takes the next index of the latest run, if there are any left.
+    fn take(&self) -> Option<usize> {
+        let mut claims = self.claims.load(Ordering::Acquire);
+        loop {
+            let (next, count) = (claims & INDEX_MASK, (claims >> COUNT_SHIFT) & INDEX_MASK);
+            if next >= count {
+                return None;
+            }
+            match self.claims.compare_exchange_weak(claims, claims + 1, Ordering::Acquire, Ordering::Acquire) {
+                Ok(_) => return Some(next as usize),
+                Err(current) => claims = current,
+            }
+        }
+    }
+
+    /// Runs the tasks of the latest run until none are left, returning the
+    /// payload of the first that panicked.
+    fn claim(&self) -> Option<Box<dyn std::any::Any + Send>> {
+        let mut panicked = None;
+        while let Some(index) = self.take() {
+            // set before the run was published, and kept until this index
+            // is finished
+            if let Some(task) = unsafe { *self.task.get() } {
+                let task = unsafe { &*task };
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| task(index))) {
+                    panicked.get_or_insert(payload);
+                }
+            }
+            self.finished.fetch_add(1, Ordering::Release);
+        }
+        panicked
+    }
+}
+
+/// The pool shared by every engine in the process, while any use it.
+static SHARED: Mutex<Weak<Pool>> = Mutex::new(Weak::new());
+
+/// A pool of worker threads.
+pub struct Pool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Pool {
+    /// Starts up to `count` workers, fewer if threads cannot be started.
+    pub fn new(count: usize) -> Self {
+        let shared = Arc::new(Shared {
+            task: UnsafeCell::new(None),
+            claims: AtomicU64::new(0),
+            finished: AtomicUsize::new(0),
+            busy: AtomicBool::new(false),
+            quit: AtomicBool::new(false),
+        });
+
+        let mut workers = Vec::with_capacity(count);
+        for _ in 0..count {
+            let index = workers.len();
+            let shared = shared.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("aa worker {}", index))
+                .spawn(move || {
+                    // kept for the life of the thread
+                    let _priority = promote_current_thread_to_real_time(MAX_BLOCK as u32, PRIORITY_RATE);
+                    work(&shared)
+                });
+            match spawned {
+                Ok(worker) => workers.push(worker),
+                Err(_) => break,
+            }
+        }
+
+        Self { shared, workers }
+    }
+
+    /// Returns the pool shared by every engine in the process, starting it
+    /// with `count` workers if no engine is using it. Called off the audio
+    /// thread.
+    ///
+    /// Sharing one pool keeps the number of workers, spinning or not, the
+    /// same however many instances of the plugin are loaded; an engine
+    /// whose block comes while another's is using the pool processes its
+    /// nodes itself.
+    pub fn shared(count: usize) -> Arc<Pool> {
+        let mut shared = SHARED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match shared.upgrade() {
+            Some(pool) => pool,
+            None => {
+                let pool = Arc::new(Pool::new(count));
+                *shared = Arc::downgrade(&pool);
+                pool
+            }
+        }
+    }
+
+    /// Returns the number of workers.
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns true if there are no workers, so that every task runs on the
+    /// calling thread.
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// Runs `task` once for each index in `0..count`, on the calling thread
+    /// and as many workers as there are indices left, returning once every
+    /// index has run. Called from the audio thread; waits only for tasks
+    /// that workers have started.
+    ///
+    /// While another thread is running tasks on the pool, or for more than
+    /// [`MAX_TASKS`] indices, every index runs on the calling thread.
+    ///
+    /// If a task panics, the panic is passed on once every index has run.
+    pub fn run(&self, count: usize, task: &Task<'_>) {
+        let helpers = self.workers.len().min(count.saturating_sub(1));
+        let shared = &*self.shared;
+        if helpers == 0 || count > MAX_TASKS || shared.busy.swap(true, Ordering::Acquire) {
+            (0..count).for_each(task);
+            return;
+        }
+
+        // no index of the last run is left running, and every index of this
+        // run will have finished with `task` by the time it returns
+        unsafe {
+            let task = std::mem::transmute::<&Task<'_>, &'static Task<'static>>(task);
+            *shared.task.get() = Some(task as *const Task<'static>);
+        }
+        shared.finished.store(0, Ordering::Relaxed);
+        let runs = (shared.claims.load(Ordering::Relaxed) >> RUN_SHIFT).wrapping_add(1);
+        shared.claims.store((runs << RUN_SHIFT) | ((count as u64) << COUNT_SHIFT), Ordering::Release);
+
+        for worker in &self.workers[..helpers] {
+            worker.thread().unpark();
+        }
+
+        let panicked = shared.claim();
+        while shared.finished.load(Ordering::Acquire) < count {
+            spin_loop();
+        }
+        shared.busy.store(false, Ordering::Release);
+
+        if let Some(payload) = panicked {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.shared.quit.store(true, Ordering::Release);
+        for worker in self.workers.drain(..) {
+            worker.thread().unpark();
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Waits for runs, helping with each one that still has indices left.
+fn work(shared: &Shared) {
+    let mut seen = 0;
+    loop {
+        let mut spins = 0;
+        loop {
+            if shared.quit.load(Ordering::Acquire) {
+                return;
+            }
+            let run = shared.claims.load(Ordering::Acquire) >> RUN_SHIFT;
+            if run != seen {
+                seen = run;
+                break;
+            }
+            if spins < SPINS {
+                spins += 1;
+                spin_loop();
+            } else {
+                thread::park();
+            }
+        }
+
+        // a task that panics is passed on by the thread that started the run
+        // if it panics there too, otherwise it is abandoned
+        let _ = shared.claim();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_runs_every_index_once() {
+        let pool = Pool::new(3);
+        let counts: Vec<AtomicUsize> = (0..50).map(|_| AtomicUsize::new(0)).collect();
+
+        for count in 0..counts.len() {
+            pool.run(count, &|index| {
+                counts[index].fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        for (index, count) in counts.iter().enumerate() {
+            assert_eq!(count.load(Ordering::Relaxed), counts.len() - 1 - index);
+        }
+    }
+
+    #[test]
+    fn test_pool_is_shared_while_in_use() {
+        let pool = Pool::shared(2);
+        assert!(Arc::ptr_eq(&pool, &Pool::shared(5)));
+        assert_eq!(Pool::shared(5).len(), 2);
+    }
+
+    #[test]
+    fn test_pool_passes_on_panic_after_every_index() {
+        let pool = Pool::new(2);
+        let ran = AtomicUsize::new(0);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.run(8, &|index| {
+                ran.fetch_add(1, Ordering::Relaxed);
+                if index == 0 {
+                    panic!("task 0");
+                }
+            });
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(ran.load(Ordering::Relaxed), 8);
+
+        // and the pool is still usable
+        pool.run(4, &|_| {
+            ran.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(ran.load(Ordering::Relaxed), 12);
+    }
+}
@@ -0,0 +1,527 @@
+//! Vector drawings of a patch, for documentation and printing.
+//!
+//! A [`Diagram`] is a plain description of the graph, built from the editor's
+//! state but independent of how it is drawn on screen, so that it can be
+//! written as SVG or PDF without a window or GPU.
+
+use ag::core::edge;
+use ag::core::geometry::Point;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Object, Stream};
+use svg::node::element::{self, path::Data};
+use thiserror::Error;
+
+use std::path::{Path, PathBuf};
+
+/// The height of a node's title bar in a diagram.
+const TITLE_HEIGHT: f32 = 28.0;
+
+/// The vertical distance between two ports.
+const PORT_SPACING: f32 = 24.0;
+
+const PORT_RADIUS: f32 = 6.0;
+
+/// The space left around the graph.
+const MARGIN: f32 = 20.0;
+
+const FONT_SIZE: f32 = 12.0;
+
+const NODE_FILL: (u8, u8, u8) = (0xF2, 0xF3, 0xF5);
+const NODE_STROKE: (u8, u8, u8) = (0x42, 0x42, 0x42);
+const TITLE_FILL: (u8, u8, u8) = (0xE0, 0xD6, 0x44);
+const TEXT: (u8, u8, u8) = (0x20, 0x20, 0x20);
+
+/// A problem writing a diagram.
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("could not write {}: {source}", .path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+
+    #[error("could not encode PDF: {0}")]
+    Pdf(String),
+}
+
+/// A node in a [`Diagram`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramNode {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub title: String,
+    /// The type id of each input port.
+    pub inputs: Vec<usize>,
+    /// The type id of each output port.
+    pub outputs: Vec<usize>,
+    /// The name and formatted value of each parameter.
+    pub parameters: Vec<(String, String)>,
+}
+
+impl DiagramNode {
+    fn input(&self, index: usize) -> (f32, f32) {
+        (self.x, self.port_y(index))
+    }
+
+    fn output(&self, index: usize) -> (f32, f32) {
+        (self.x + self.width, self.port_y(index))
+    }
+
+    /// Returns the centre and type id of each input, then each output.
+    fn ports(&self) -> impl Iterator<Item = ((f32, f32), usize)> + '_ {
+        let inputs = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(move |(index, port_type)| (self.input(index), *port_type));
+        let outputs = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(move |(index, port_type)| (self.output(index), *port_type));
+        inputs.chain(outputs)
+    }
+
+    fn port_y(&self, index: usize) -> f32 {
+        self.y + TITLE_HEIGHT + PORT_SPACING * (index as f32 + 0.5)
+    }
+}
+
+/// An edge in a [`Diagram`], between output `output` of node `from` and input
+/// `input` of node `to`, given as indices into [`Diagram::nodes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramEdge {
+    pub from: usize,
+    pub output: usize,
+    pub to: usize,
+    pub input: usize,
+    /// The points the edge is rerouted through, in order from its output.
+    pub route: Vec<(f32, f32)>,
+}
+
+/// A drawing of a graph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagram {
+    pub nodes: Vec<DiagramNode>,
+    pub edges: Vec<DiagramEdge>,
+    /// The colour of each port type, in order of their ids, as the editor's
+    /// theme draws them. Types without one are drawn like node outlines.
+    pub port_colours: Vec<[u8; 3]>,
+}
+
+/// A line in a diagram: cubic curves with the same shape as edges drawn in
+/// the editor, each given as its two control points and end, and the colour
+/// of its port type.
+struct Curve {
+    from: (f32, f32),
+    segments: Vec<[(f32, f32); 3]>,
+    colour: (u8, u8, u8),
+}
+
+impl Diagram {
+    /// Returns the area covered by the nodes and reroute points, including
+    /// the margin, as `(x, y, width, height)`.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        if self.nodes.is_empty() {
+            return (0.0, 0.0, 2.0 * MARGIN, 2.0 * MARGIN);
+        }
+
+        let reroutes = self
+            .edges
+            .iter()
+            .flat_map(|edge| edge.route.iter())
+            .map(|(x, y)| (*x, *y, 0.0, 0.0));
+
+        let (min_x, min_y, max_x, max_y) = self
+            .nodes
+            .iter()
+            .map(|node| (node.x, node.y, node.width, node.height))
+            .chain(reroutes)
+            .fold(
+                (f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+                |(min_x, min_y, max_x, max_y), (x, y, width, height)| {
+                    (
+                        min_x.min(x),
+                        min_y.min(y),
+                        max_x.max(x + width),
+                        max_y.max(y + height),
+                    )
+                });
+
+        (
+            min_x - MARGIN,
+            min_y - MARGIN,
+            max_x - min_x + 2.0 * MARGIN,
+            max_y - min_y + 2.0 * MARGIN,
+        )
+    }
+
+    fn port_colour(&self, port_type: usize) -> (u8, u8, u8) {
+        match self.port_colours.get(port_type) {
+            Some([r, g, b]) => (*r, *g, *b),
+            None => NODE_STROKE,
+        }
+    }
+
+    fn curves(&self) -> Vec<Curve> {
+        self.edges
+            .iter()
+            .filter_map(|edge| {
+                let from = self.nodes.get(edge.from)?;
+                let to = self.nodes.get(edge.to)?;
+                let port_type = *from.outputs.get(edge.output)?;
+
+                let point = |(x, y): (f32, f32)| Point::new(x, y);
+                let pair = |p: Point| (p.x, p.y);
+
+                let start = from.output(edge.output);
+                let reroutes: Vec<_> = edge.route.iter().copied().map(point).collect();
+                let segments = edge::route(point(start), &reroutes, point(to.input(edge.input)))
+                    .into_iter()
+                    .map(|(a, b)| {
+                        let (c1, c2) = edge::controls(a, b);
+                        [pair(c1), pair(c2), pair(b)]
+                    })
+                    .collect();
+
+                Some(Curve {
+                    from: start,
+                    segments,
+                    colour: self.port_colour(port_type),
+                })
+            })
+            .collect()
+    }
+
+    /// Draws the diagram as an SVG document.
+    pub fn to_svg(&self) -> svg::Document {
+        let (x, y, width, height) = self.bounds();
+        let mut document = svg::Document::new()
+            .set("viewBox", (x, y, width, height))
+            .set("width", width)
+            .set("height", height)
+            .set("font-family", "Helvetica, Arial, sans-serif")
+            .set("font-size", FONT_SIZE);
+
+        for curve in self.curves() {
+            let data = curve.segments.iter().fold(
+                Data::new().move_to(curve.from),
+                |data, [c1, c2, to]| data.cubic_curve_to((c1.0, c1.1, c2.0, c2.1, to.0, to.1)));
+            document = document.add(
+                element::Path::new()
+                    .set("d", data)
+                    .set("fill", "none")
+                    .set("stroke", svg_colour(curve.colour))
+                    .set("stroke-width", 3));
+        }
+
+        for node in &self.nodes {
+            let mut group = element::Group::new()
+                .add(
+                    element::Rectangle::new()
+                        .set("x", node.x)
+                        .set("y", node.y)
+                        .set("width", node.width)
+                        .set("height", node.height)
+                        .set("rx", 4)
+                        .set("fill", svg_colour(NODE_FILL))
+                        .set("stroke", svg_colour(NODE_STROKE)))
+                .add(
+                    element::Rectangle::new()
+                        .set("x", node.x)
+                        .set("y", node.y)
+                        .set("width", node.width)
+                        .set("height", TITLE_HEIGHT)
+                        .set("fill", svg_colour(TITLE_FILL))
+                        .set("stroke", svg_colour(NODE_STROKE)))
+                .add(svg_text(node.x + 8.0, node.y + TITLE_HEIGHT / 2.0 + FONT_SIZE / 3.0, &node.title));
+
+            for ((cx, cy), port_type) in node.ports() {
+                group = group.add(
+                    element::Circle::new()
+                        .set("cx", cx)
+                        .set("cy", cy)
+                        .set("r", PORT_RADIUS)
+                        .set("fill", svg_colour(self.port_colour(port_type)))
+                        .set("stroke", svg_colour(NODE_STROKE)));
+            }
+
+            for (line, y) in parameter_lines(node) {
+                group = group.add(svg_text(node.x + 2.0 * PORT_RADIUS + 8.0, y, &line));
+            }
+
+            document = document.add(group);
+        }
+
+        document
+    }
+
+    /// Draws the diagram as a single page PDF document.
+    pub fn to_pdf(&self) -> Result<lopdf::Document, ExportError> {
+        let (x, y, width, height) = self.bounds();
+
+        // PDF puts the origin at the bottom left
+        let to_page = |(px, py): (f32, f32)| (px - x, height - (py - y));
+
+        let mut operations = Vec::new();
+
+        for curve in self.curves() {
+            let from = to_page(curve.from);
+
+            operations.push(pdf_colour("RG", curve.colour));
+            operations.push(Operation::new("w", vec![real(3.0)]));
+            operations.push(Operation::new("m", vec![real(from.0), real(from.1)]));
+            for [c1, c2, to] in &curve.segments {
+                let (c1, c2, to) = (to_page(*c1), to_page(*c2), to_page(*to));
+                operations.push(Operation::new(
+                    "c",
+                    vec![real(c1.0), real(c1.1), real(c2.0), real(c2.1), real(to.0), real(to.1)]));
+            }
+            operations.push(Operation::new("S", vec![]));
+        }
+
+        operations.push(Operation::new("w", vec![real(1.0)]));
+        operations.push(pdf_colour("RG", NODE_STROKE));
+
+        for node in &self.nodes {
+            let (left, top) = to_page((node.x, node.y));
+            let rectangle = |fill, bottom: f32, height: f32| {
+                vec![
+                    pdf_colour("rg", fill),
+                    Operation::new("re", vec![real(left), real(bottom), real(node.width), real(height)]),
+                    Operation::new("B", vec![]),
+                ]
+            };
+            operations.extend(rectangle(NODE_FILL, top - node.height, node.height));
+            operations.extend(rectangle(TITLE_FILL, top - TITLE_HEIGHT, TITLE_HEIGHT));
+
+            for (centre, port_type) in node.ports() {
+                let (cx, cy) = to_page(centre);
+                operations.push(pdf_colour("rg", self.port_colour(port_type)));
+                operations.extend(pdf_circle(cx, cy, PORT_RADIUS));
+            }
+
+            operations.push(pdf_colour("rg", TEXT));
+            operations.extend(pdf_text(
+                left + 8.0,
+                top - TITLE_HEIGHT / 2.0 - FONT_SIZE / 3.0,
+                &node.title));
+            for (line, line_y) in parameter_lines(node) {
+                let (_, baseline) = to_page((0.0, line_y));
+                operations.extend(pdf_text(left + 2.0 * PORT_RADIUS + 8.0, baseline, &line));
+            }
+        }
+
+        let content = Content { operations }
+            .encode()
+            .map_err(|e| ExportError::Pdf(format!("{:?}", e)))?;
+
+        let mut document = lopdf::Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! {
+                "F1" => font_id,
+            },
+        });
+        let content_id = document.add_object(Stream::new(dictionary! {}, content));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), real(width), real(height)],
+        }));
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+
+        Ok(document)
+    }
+
+    /// Writes the diagram to `<stem>.svg` and `<stem>.pdf`, returning their
+    /// paths.
+    pub fn save(&self, stem: &Path) -> Result<(PathBuf, PathBuf), ExportError> {
+        let svg_path = stem.with_extension("svg");
+        svg::save(&svg_path, &self.to_svg()).map_err(|source| ExportError::Io {
+            path: svg_path.clone(),
+            source,
+        })?;
+
+        let pdf_path = stem.with_extension("pdf");
+        self.to_pdf()?.save(&pdf_path).map_err(|source| ExportError::Io {
+            path: pdf_path.clone(),
+            source,
+        })?;
+
+        Ok((svg_path, pdf_path))
+    }
+}
+
+/// Returns each parameter of `node` as a line of text, with the y position
+/// of its baseline.
+fn parameter_lines(node: &DiagramNode) -> impl Iterator<Item = (String, f32)> + '_ {
+    let ports = node.inputs.len().max(node.outputs.len()) as f32;
+    let top = node.y + TITLE_HEIGHT + PORT_SPACING * ports + FONT_SIZE;
+
+    node.parameters
+        .iter()
+        .enumerate()
+        .map(move |(index, (name, value))| {
+            (format!("{}: {}", name, value), top + index as f32 * FONT_SIZE * 1.5)
+        })
+}
+
+fn svg_colour((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn svg_text(x: f32, y: f32, text: &str) -> element::Text {
+    element::Text::new()
+        .set("x", x)
+        .set("y", y)
+        .set("fill", svg_colour(TEXT))
+        .add(svg::node::Text::new(text))
+}
+
+fn real(value: f32) -> Object {
+    Object::Real(value.into())
+}
+
+fn pdf_colour(operator: &str, (r, g, b): (u8, u8, u8)) -> Operation {
+    let channel = |c: u8| real(f32::from(c) / 255.0);
+    Operation::new(operator, vec![channel(r), channel(g), channel(b)])
+}
+
+/// A filled and stroked circle, as four cubic curves.
+fn pdf_circle(cx: f32, cy: f32, r: f32) -> Vec<Operation> {
+    // the distance of the control points that best approximates a circle
+    let k = 0.552_284_8 * r;
+    let curve = |points: [f32; 6]| Operation::new("c", points.iter().map(|p| real(*p)).collect());
+
+    vec![
+        Operation::new("m", vec![real(cx + r), real(cy)]),
+        curve([cx + r, cy + k, cx + k, cy + r, cx, cy + r]),
+        curve([cx - k, cy + r, cx - r, cy + k, cx - r, cy]),
+        curve([cx - r, cy - k, cx - k, cy - r, cx, cy - r]),
+        curve([cx + k, cy - r, cx + r, cy - k, cx + r, cy]),
+        Operation::new("B", vec![]),
+    ]
+}
+
+fn pdf_text(x: f32, y: f32, text: &str) -> Vec<Operation> {
+    vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F1".into(), real(FONT_SIZE)]),
+        Operation::new("Td", vec![real(x), real(y)]),
+        Operation::new("Tj", vec![Object::string_literal(win_ansi(text))]),
+        Operation::new("ET", vec![]),
+    ]
+}
+
+/// Encodes text for the standard PDF fonts, which use WinAnsiEncoding.
+/// Characters it cannot encode are replaced with `?`.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+            // units are often written with the Greek letter rather than the
+            // micro sign
+            'μ' => 0xB5,
+            '€' => 0x80,
+            '‚' => 0x82,
+            'ƒ' => 0x83,
+            '„' => 0x84,
+            '…' => 0x85,
+            '†' => 0x86,
+            '‡' => 0x87,
+            'ˆ' => 0x88,
+            '‰' => 0x89,
+            'Š' => 0x8A,
+            '‹' => 0x8B,
+            'Œ' => 0x8C,
+            'Ž' => 0x8E,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '˜' => 0x98,
+            '™' => 0x99,
+            'š' => 0x9A,
+            '›' => 0x9B,
+            'œ' => 0x9C,
+            'ž' => 0x9E,
+            'Ÿ' => 0x9F,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagram() -> Diagram {
+        let node = |x, title: &str| DiagramNode {
+            x,
+            y: 0.0,
+            width: 300.0,
+            height: 300.0,
+            title: title.to_string(),
+            inputs: vec![0, 1],
+            outputs: vec![0, 1],
+            parameters: vec![("gain".to_string(), "-6.0 dB".to_string())],
+        };
+
+        Diagram {
+            nodes: vec![node(0.0, "Oscillator 0"), node(400.0, "Gain 1")],
+            edges: vec![DiagramEdge { from: 0, output: 1, to: 1, input: 1, route: vec![(350.0, 150.0)] }],
+            port_colours: vec![[0x72, 0x89, 0xDA], [0xE0, 0x6C, 0x4C]],
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(diagram().bounds(), (-MARGIN, -MARGIN, 700.0 + 2.0 * MARGIN, 300.0 + 2.0 * MARGIN));
+    }
+
+    #[test]
+    fn test_svg() {
+        let svg = diagram().to_svg().to_string();
+        assert!(svg.contains("Oscillator 0"));
+        assert!(svg.contains("gain: -6.0 dB"));
+        assert_eq!(svg.matches("<circle").count(), 8);
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert_eq!(svg.matches("#e06c4c").count(), 5);
+    }
+
+    #[test]
+    fn test_pdf() {
+        let mut document = diagram().to_pdf().unwrap();
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"%PDF-1.5"));
+    }
+
+    #[test]
+    fn test_win_ansi() {
+        assert_eq!(win_ansi("Gain 1"), b"Gain 1".to_vec());
+        assert_eq!(win_ansi("time: 20 µs – 2 μs"), b"time: 20 \xB5s \x96 2 \xB5s".to_vec());
+        assert_eq!(win_ansi("Filtre passe-bas à 3 dB"), b"Filtre passe-bas \xE0 3 dB".to_vec());
+        assert_eq!(win_ansi("振幅 →"), b"?? ?".to_vec());
+    }
+}
@@ -0,0 +1,210 @@
+use iced_native::{
+    event, layout, mouse, overlay, Clipboard, Element, Event, Hasher, Layout, Length, Point,
+    Rectangle, Size, Vector, Widget,
+};
+
+/// How far a floating panel is kept from the corner it is anchored to.
+const MARGIN: f32 = 20.0;
+
+/// The state of a [`Floating`] panel, kept between frames.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct State {
+    // how far the panel has been dragged from its anchor
+    offset: Vector,
+    // where the cursor would have been with no offset, while dragging
+    origin: Option<Point>,
+}
+
+/// Draws a panel over the top right of its underlay, as an overlay which can
+/// be dragged by any part that does not handle the mouse itself.
+///
+/// iced shows a single overlay at a time, so while the underlay has one of
+/// its own, e.g. an open pick list, the panel is hidden.
+pub struct Floating<'a, Message, Renderer> {
+    state: &'a mut State,
+    underlay: Element<'a, Message, Renderer>,
+    panel: Option<Element<'a, Message, Renderer>>,
+}
+
+impl<'a, Message, Renderer> Floating<'a, Message, Renderer> {
+    /// Creates a [`Floating`] panel over `underlay`, or just the underlay if
+    /// there is no panel.
+    pub fn new(
+        state: &'a mut State,
+        underlay: impl Into<Element<'a, Message, Renderer>>,
+        panel: Option<Element<'a, Message, Renderer>>) -> Self {
+        Self {
+            state,
+            underlay: underlay.into(),
+            panel,
+        }
+    }
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for Floating<'a, Message, Renderer>
+where
+    Renderer: iced_native::Renderer,
+{
+    fn width(&self) -> Length {
+        self.underlay.width()
+    }
+
+    fn height(&self) -> Length {
+        self.underlay.height()
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.underlay.layout(renderer, limits)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+        renderer: &Renderer,
+        clipboard: Option<&dyn Clipboard>,
+    ) -> event::Status {
+        self.underlay.on_event(event, layout, cursor_position, messages, renderer, clipboard)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> Renderer::Output {
+        self.underlay.draw(renderer, defaults, layout, cursor_position, viewport)
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        use std::hash::Hash;
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+
+        self.underlay.hash_layout(state);
+    }
+
+    fn overlay(
+        &mut self,
+        layout: Layout<'_>,
+    ) -> Option<overlay::Element<'_, Message, Renderer>> {
+        let Self { state, underlay, panel } = self;
+        let bounds = layout.bounds();
+        let anchor = Point::new(bounds.x + bounds.width - MARGIN, bounds.y + MARGIN);
+
+        underlay.overlay(layout).or_else(move || {
+            let panel = Panel {
+                state: &mut **state,
+                content: panel.as_mut()?,
+            };
+            Some(overlay::Element::new(anchor, Box::new(panel)))
+        })
+    }
+}
+
+impl<'a, Message, Renderer> From<Floating<'a, Message, Renderer>> for Element<'a, Message, Renderer>
+where
+    Message: 'a,
+    Renderer: 'a + iced_native::Renderer,
+{
+    fn from(floating: Floating<'a, Message, Renderer>) -> Self {
+        Element::new(floating)
+    }
+}
+
+/// The overlay a [`Floating`] panel is drawn in, whose position is the top
+/// right corner it is anchored to.
+struct Panel<'a, 'b, Message, Renderer> {
+    state: &'a mut State,
+    content: &'a mut Element<'b, Message, Renderer>,
+}
+
+impl<'a, 'b, Message, Renderer> overlay::Overlay<Message, Renderer> for Panel<'a, 'b, Message, Renderer>
+where
+    Renderer: iced_native::Renderer,
+{
+    fn layout(&self, renderer: &Renderer, bounds: Size, position: Point) -> layout::Node {
+        let mut node = self.content.layout(renderer, &layout::Limits::new(Size::ZERO, bounds));
+        let size = node.size();
+
+        // kept inside the window however far it is dragged
+        let x = (position.x - size.width + self.state.offset.x)
+            .min(bounds.width - size.width)
+            .max(0.0);
+        let y = (position.y + self.state.offset.y)
+            .min(bounds.height - size.height)
+            .max(0.0);
+        node.move_to(Point::new(x, y));
+        node
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+    ) -> Renderer::Output {
+        self.content.draw(renderer, defaults, layout, cursor_position, &layout.bounds())
+    }
+
+    fn hash_layout(&self, state: &mut Hasher, position: Point) {
+        use std::hash::Hash;
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+
+        (position.x as u32).hash(state);
+        (position.y as u32).hash(state);
+        (self.state.offset.x as i32).hash(state);
+        (self.state.offset.y as i32).hash(state);
+        self.content.hash_layout(state);
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+        renderer: &Renderer,
+        clipboard: Option<&dyn Clipboard>,
+    ) -> event::Status {
+        let status = self.content.on_event(
+            event.clone(),
+            layout,
+            cursor_position,
+            messages,
+            renderer,
+            clipboard);
+        if status == event::Status::Captured {
+            return status;
+        }
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if layout.bounds().contains(cursor_position) =>
+            {
+                self.state.origin = Some(cursor_position - self.state.offset);
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => match self.state.origin {
+                Some(origin) => {
+                    self.state.offset = position - origin;
+                    event::Status::Captured
+                }
+                None => event::Status::Ignored,
+            },
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if self.state.origin.is_some() =>
+            {
+                self.state.origin = None;
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use iced_baseview::{Element, Length};
+use iced_graphics::canvas::{Canvas, Cursor, Event, Frame, Geometry, Path, Program, Stroke};
+use iced_native::{event, mouse, Color, Point, Rectangle};
+
+use super::Message;
+
+/// The size of the resize grip.
+const GRIP_SIZE: u16 = 16;
+
+const GRIP_COLOR: Color = Color::from_rgb(0.5, 0.5, 0.5);
+
+/// The state of a [`Grip`], kept between frames.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct State {
+    // where the current drag started
+    origin: Option<Point>,
+}
+
+/// A handle in the corner of the editor which resizes the window when
+/// dragged.
+pub struct Grip<'a> {
+    state: &'a mut State,
+    size: (u32, u32),
+}
+
+impl<'a> Grip<'a> {
+    /// Creates a [`Grip`] for a window of the given logical size.
+    pub fn new(state: &'a mut State, size: (u32, u32)) -> Self {
+        Self { state, size }
+    }
+
+    pub fn view(self) -> Element<'a, Message> {
+        Canvas::new(self)
+            .width(Length::Units(GRIP_SIZE))
+            .height(Length::Units(GRIP_SIZE))
+            .into()
+    }
+}
+
+impl<'a> Program<Message> for Grip<'a> {
+    fn update(
+        &mut self,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let position = match cursor.position() {
+            Some(position) => position,
+            None => return (event::Status::Ignored, None),
+        };
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if bounds.contains(position) =>
+            {
+                self.state.origin = Some(position);
+                (event::Status::Captured, None)
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                match self.state.origin.take() {
+                    Some(origin) => {
+                        let (width, height) = self.size;
+                        let width = (width as f32 + position.x - origin.x).max(0.0);
+                        let height = (height as f32 + position.y - origin.y).max(0.0);
+                        (
+                            event::Status::Captured,
+                            Some(Message::Resize(width as u32, height as u32)),
+                        )
+                    }
+                    None => (event::Status::Ignored, None),
+                }
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let size = bounds.width.min(bounds.height);
+
+        // three diagonal strokes in the bottom right corner
+        for step in 1..=3 {
+            let offset = size * step as f32 / 4.0;
+            let line = Path::line(
+                Point::new(size - offset, size),
+                Point::new(size, size - offset));
+            frame.stroke(&line, Stroke::default().with_width(1.5).with_color(GRIP_COLOR));
+        }
+
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(&self, bounds: Rectangle, cursor: Cursor) -> mouse::Interaction {
+        if self.state.origin.is_some() {
+            mouse::Interaction::Grabbing
+        } else if cursor.is_over(&bounds) {
+            mouse::Interaction::Grab
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
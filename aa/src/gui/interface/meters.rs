@@ -0,0 +1,261 @@
+use iced_baseview::{Element, Length};
+use iced_graphics::canvas::{Canvas, Cursor, Event, Frame, Geometry, Path, Program};
+use iced_native::{event, mouse, Color, Point, Rectangle, Size};
+
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use super::Message;
+use crate::meters::{MeterBank, MASTER_CHANNELS, MAX_METERS};
+
+/// The lowest level shown on a meter.
+const FLOOR_DB: f32 = -60.0;
+
+/// How fast a meter falls back once the signal drops.
+const FALLOFF_DB_PER_SECOND: f32 = 24.0;
+
+/// How long the clip indicator stays lit after the signal clips.
+const CLIP_HOLD: Duration = Duration::from_secs(2);
+
+const MASTER_WIDTH: u16 = 24;
+
+/// A meter as shown, falling back smoothly rather than following every block.
+#[derive(Debug, Clone, Copy)]
+struct Ballistics {
+    peak_db: f32,
+    rms_db: f32,
+    clipped: Option<Instant>,
+}
+
+impl Default for Ballistics {
+    fn default() -> Self {
+        Self {
+            peak_db: FLOOR_DB,
+            rms_db: FLOOR_DB,
+            clipped: None,
+        }
+    }
+}
+
+impl Ballistics {
+    /// Moves the meter towards the measured `(peak, rms)` amplitudes,
+    /// `elapsed` after the last update.
+    fn update(&mut self, (peak, rms): (f32, f32), now: Instant, elapsed: Duration) {
+        let fall = FALLOFF_DB_PER_SECOND * elapsed.as_secs_f32();
+        self.peak_db = to_db(peak).max(self.peak_db - fall);
+        self.rms_db = to_db(rms).max(self.rms_db - fall);
+
+        if peak >= 1.0 {
+            self.clipped = Some(now);
+        }
+    }
+
+    fn level(&self, now: Instant) -> ag::Level {
+        ag::Level {
+            peak: position(self.peak_db),
+            rms: position(self.rms_db),
+            clip: self
+                .clipped
+                .map_or(false, |clipped| now.duration_since(clipped) < CLIP_HOLD),
+        }
+    }
+}
+
+fn to_db(amplitude: f32) -> f32 {
+    if amplitude > 0.0 {
+        (20.0 * amplitude.log10()).max(FLOOR_DB)
+    } else {
+        FLOOR_DB
+    }
+}
+
+/// Maps a level in dB to its position on a meter.
+fn position(db: f32) -> f32 {
+    ((db - FLOOR_DB) / -FLOOR_DB).max(0.0).min(1.0)
+}
+
+/// The editor side of metering: which meter each node output reads from, and
+/// how each meter is currently shown.
+#[derive(Debug, Clone)]
+pub struct Meters {
+    enabled: bool,
+    updated: Instant,
+    master: [Ballistics; MASTER_CHANNELS],
+    outputs: HashMap<(ag::Node, usize), (usize, Ballistics)>,
+}
+
+impl Default for Meters {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            updated: Instant::now(),
+            master: [Ballistics::default(); MASTER_CHANNELS],
+            outputs: HashMap::new(),
+        }
+    }
+}
+
+impl Meters {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns metering on or off, on both the audio thread and the editor.
+    pub fn set_enabled(&mut self, enabled: bool, bank: &MeterBank) {
+        self.enabled = enabled;
+        bank.set_enabled(enabled);
+
+        if !enabled {
+            self.master = [Ballistics::default(); MASTER_CHANNELS];
+            for (_, ballistics) in self.outputs.values_mut() {
+                *ballistics = Ballistics::default();
+            }
+        }
+    }
+
+    /// Returns the meter index for output `index` of `node`, if it is
+    /// metered.
+    pub fn meter(&self, node: ag::Node, index: usize) -> Option<usize> {
+        self.outputs.get(&(node, index)).map(|(meter, _)| *meter)
+    }
+
+    /// Meters exactly the outputs in `outputs`, keeping the meter index of
+    /// any output already metered. Returns true if any output was metered or
+    /// stopped being metered.
+    pub fn assign(&mut self, outputs: &[(ag::Node, usize)]) -> bool {
+        let metered = self.outputs.len();
+        self.outputs.retain(|output, _| outputs.contains(output));
+        let mut changed = self.outputs.len() != metered;
+
+        let mut used: BTreeSet<usize> = self.outputs.values().map(|(meter, _)| *meter).collect();
+        for output in outputs {
+            if self.outputs.contains_key(output) {
+                continue;
+            }
+
+            match (0..MAX_METERS).find(|meter| !used.contains(meter)) {
+                Some(meter) => {
+                    used.insert(meter);
+                    self.outputs.insert(*output, (meter, Ballistics::default()));
+                    changed = true;
+                }
+                None => break,
+            }
+        }
+        changed
+    }
+
+    /// Reads the levels measured since the last frame.
+    pub fn update(&mut self, bank: &MeterBank) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated);
+        self.updated = now;
+
+        if !self.enabled {
+            return;
+        }
+
+        for (channel, ballistics) in self.master.iter_mut().enumerate() {
+            if let Some(meter) = bank.master(channel) {
+                ballistics.update(meter.take(), now, elapsed);
+            }
+        }
+
+        for (meter, ballistics) in self.outputs.values_mut() {
+            if let Some(meter) = bank.output(*meter) {
+                ballistics.update(meter.take(), now, elapsed);
+            }
+        }
+    }
+
+    /// Returns the level shown for output `index` of `node`.
+    pub fn level(&self, node: ag::Node, index: usize) -> Option<ag::Level> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.outputs
+            .get(&(node, index))
+            .map(|(_, ballistics)| ballistics.level(self.updated))
+    }
+
+    /// Turns off the clip indicators.
+    pub fn reset_clips(&mut self) {
+        for ballistics in self.master.iter_mut() {
+            ballistics.clipped = None;
+        }
+        for (_, ballistics) in self.outputs.values_mut() {
+            ballistics.clipped = None;
+        }
+    }
+
+    /// The meter for the master bus, one bar per channel. Clicking it turns
+    /// off the clip indicators.
+    pub fn master_view(&self) -> Element<'static, Message> {
+        let levels = self.master.iter().map(|b| b.level(self.updated)).collect();
+
+        Canvas::new(MasterMeter { levels })
+            .width(Length::Units(MASTER_WIDTH))
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+struct MasterMeter {
+    levels: Vec<ag::Level>,
+}
+
+impl Program<Message> for MasterMeter {
+    fn update(
+        &mut self,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (event::Status, Option<Message>) {
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if cursor.is_over(&bounds) =>
+            {
+                (event::Status::Captured, Some(Message::ResetClips))
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+
+        let channels = self.levels.len().max(1) as f32;
+        let width = bounds.width / channels;
+        // the top of each bar is kept for its clip indicator
+        let clip_height = width.min(8.0);
+        let height = bounds.height - clip_height - 2.0;
+
+        for (channel, level) in self.levels.iter().enumerate() {
+            let x = channel as f32 * width + 1.0;
+            let bar = width - 2.0;
+            let y = |position: f32| clip_height + 2.0 + height * (1.0 - position);
+
+            frame.fill(
+                &Path::rectangle(Point::new(x, clip_height + 2.0), Size::new(bar, height)),
+                Color::from_rgb(0.15, 0.15, 0.15));
+            frame.fill(
+                &Path::rectangle(
+                    Point::new(x, y(level.rms)),
+                    Size::new(bar, height * level.rms)),
+                Color::from_rgb(0.3, 0.8, 0.4));
+            frame.fill(
+                &Path::rectangle(Point::new(x, y(level.peak)), Size::new(bar, 1.0)),
+                Color::from_rgb(0.9, 0.9, 0.3));
+            frame.fill(
+                &Path::rectangle(Point::new(x, 0.0), Size::new(bar, clip_height)),
+                if level.clip {
+                    Color::from_rgb(0.9, 0.2, 0.2)
+                } else {
+                    Color::from_rgb(0.3, 0.1, 0.1)
+                });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -1,6 +1,33 @@
 use ag::{audio_graph::AudioGraph};
 
-use ag::{audio_graph, DragEvent, Connectors, PortType, Ports};
+mod floating;
+mod grip;
+mod meters;
+mod modules;
+mod notes;
+mod palette;
+mod parameters;
+mod probe;
+
+use floating::Floating;
+use grip::Grip;
+use meters::Meters;
+use modules::ModuleType;
+use notes::{NoteEditor, Tag, Target};
+use palette::Palette;
+use parameters::{Panel, Slots};
+use probe::Probe;
+
+use crate::constants::{EXPORT_DIR_ENV, MASTER_DB_MIN, MASTER_DB_MAX, THEME_PATH_ENV};
+use crate::engine::{Binding, Connection, NodeSetup, Plan, Setup};
+use crate::export::{Diagram, DiagramEdge, DiagramNode};
+use crate::gui::window::{Scale, WindowState};
+use crate::library::Library;
+use crate::modes::{Mode, ModeSlots};
+use crate::nodes::{self, wasm::Runtime};
+use crate::sync::{PatchLoader, SyncState, MASTER_PARAMETER};
+
+use ag::{audio_graph, DragEvent, ClipboardEvent, Shortcut, Connectors, PortRegistry, PortType, Ports, NodeData, Persist};
 
 use iced_baseview::{executor, Align, renderer, Application, Command, Subscription, WindowSubs};
 use iced_baseview::{
@@ -14,19 +41,58 @@ use iced_audio::{
     HSlider, IntRange, Knob, LogDBRange, Normal, VSlider, XYPad,
 };
 
-use iced_native::{ button, Button, Color, Point };
+use iced_native::{ button, pick_list, window, Button, Checkbox, Color, Event, PickList, Point };
 use iced_graphics::{Antialiasing};
+
+use chrono::Local;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::mem::ManuallyDrop;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
 #[derive(Debug, Clone)]
 pub enum Message {
     Frame,
     VSliderDB(Normal),
     ParameterChange(usize, f64),
+    Parameter(ag::Node, usize, Normal),
+    XYParameter(ag::Node, usize, Normal, Normal),
     Close(ag::Node),
+    Mode(ag::Node, ag::NodeMode),
     Dragged(ag::DragEvent),
+    Clipboard(ag::ClipboardEvent),
+    Shortcut(ag::Shortcut),
+    Focus(ag::Node),
+    Connect(ag::Edge),
+    ConnectFeedback(ag::Edge),
+    OpenPalette(ag::PaletteRequest),
+    PaletteQuery(String),
+    PaletteSelect(usize),
+    PaletteClose,
+    WindowResized(u32, u32),
+    Resize(u32, u32),
+    Scale(Scale),
+    Metering(bool),
+    ResetClips,
+    ProbeEdge(ag::Edge),
+    Probe(usize, probe::Event),
+    Export,
+    DarkTheme(bool),
+    Flow(bool),
+    Minimap(bool),
+    Note(String),
+    Tag(Tag),
     //Clicked(audio_graph::Node),
 }
 pub struct AAIcedApplication {
-    sync_handle: (),
+    sync_handle: Arc<SyncState>,
+
+    // handed back through `shared_session` when the window closes, and not
+    // used after
+    session: ManuallyDrop<Session>,
+    shared_session: SharedSession,
 
     db_range: LogDBRange,
     v_slider_state: v_slider::State,
@@ -34,43 +100,368 @@ pub struct AAIcedApplication {
     // A group of tick marks with their size and position.
     center_tick_mark: tick_marks::Group,
 
+    bindings: ag::Bindings,
+
+    window: Arc<WindowState>,
+    grip: grip::State,
+    // where the probes float over the editor
+    floating: floating::State,
+    scales: pick_list::State<Scale>,
+    export: button::State,
+    notes: NoteEditor,
+
+    port_types: PortRegistry,
+    palette: Option<Palette>,
+    // an edit that was refused, shown in the footer, and when it was
+    status: Option<(String, Instant)>,
+}
+
+/// The editor state that outlives its window, so that closing and reopening
+/// the editor leaves the graph, its selection and the undo history as they
+/// were.
+pub struct Session {
     nodes: ag::State<Content>,
     nodes_created: usize,
     focus: Option<ag::Node>,
+    history: ag::History<Content>,
+    // the note or frame title being typed into, recorded in the history
+    // once for the whole run of typing
+    noting: Option<Target>,
+
+    // the host parameter slot of each node parameter shown
+    slots: Slots,
+    // the meter of each node output
+    meters: Meters,
+    // the slot each node's mode is published in
+    modes: ModeSlots,
+    probes: Vec<Probe>,
+    // the graph as last sent to the audio thread
+    setup: Setup,
+    // set when the graph, or the values of its parameters, may have changed
+    // since it was last sent
+    changed: bool,
+    // the modules found in the library directories, and those built in
+    library: Library,
+    modules: Vec<ModuleType>,
+    // compiles the library modules that nodes run
+    runtime: Runtime,
+
+    theme: Arc<ag::Theme>,
+    // whether `theme` is the built in dark theme rather than that loaded
+    dark_theme: bool,
+    // when the flow along edges started being animated, if it is
+    flow: Option<Instant>,
+    minimap: bool,
+}
+
+impl Session {
+    /// Creates a session with a simple synth: the MIDI input drives an
+    /// oscillator through an envelope to the audio output.
+    fn new() -> Self {
+        let mut library = Library::new(Library::default_dirs());
+        library.scan();
+        for error in library.errors() {
+            info!("Module library: {}", error);
+        }
+        let modules = modules::available(&library);
+
+        let (mut nodes, midi) = ag::State::new(
+            Point::new(0.0, 0.0),
+            Content::new(0, "MIDI Input"));
+
+        let mut insert = |id, kind, x, y| nodes.insert(Point::new(x, y), Content::new(id, kind));
+        let freq = insert(1, "MIDI to Freq", 300.0, 0.0);
+        let oscillator = insert(2, "Oscillator", 600.0, -150.0);
+        let envelope = insert(3, "ADSR", 600.0, 150.0);
+        let gain = insert(4, "Gain", 900.0, 0.0);
+        let output = insert(5, "Audio Output", 1200.0, 0.0);
+
+        if let (Some(freq), Some(oscillator), Some(envelope), Some(gain), Some(output)) =
+            (freq, oscillator, envelope, gain, output) {
+            let edges = [
+                (midi, 0, freq, 0),
+                (freq, 0, oscillator, 0),
+                (freq, 1, envelope, 0),
+                (oscillator, 0, gain, 0),
+                (envelope, 0, gain, 1),
+                (gain, 0, output, 0),
+                (gain, 0, output, 1),
+            ];
+            for (from, output, to, input) in edges.iter() {
+                let _ = nodes.connect(*from, *output, *to, *input);
+            }
+        }
+
+        Self {
+            nodes,
+            nodes_created: 6,
+            focus: None,
+            noting: None,
+            history: ag::History::default(),
+            slots: Slots::default(),
+            meters: Meters::default(),
+            modes: ModeSlots::default(),
+            probes: Vec::new(),
+            setup: Setup::default(),
+            changed: true,
+            library,
+            modules,
+            runtime: Runtime::default(),
+            theme: Arc::new(load_theme()),
+            dark_theme: false,
+            flow: None,
+            minimap: true,
+        }
+    }
+
+    /// Describes the graph to the audio thread if it may have changed since
+    /// the last call. Values of parameters without a slot are sent on their
+    /// own; a new plan is only built when the graph itself changed, or there
+    /// is no room for the values. A plan the audio thread handed back
+    /// because the sample rate changed while it was built is sent again.
+    /// The patch is kept for the host to save along with it.
+    fn publish(&mut self, sync: &SyncState) {
+        sync.plans.set_sample_rate(sync.sample_rate());
+        if !self.changed {
+            return;
+        }
+        self.changed = false;
+        sync.save_patch(self.nodes.to_patch());
+
+        let slots = &self.slots;
+        let meters = &self.meters;
+        let modes = &self.modes;
+        let modules = &self.modules;
+
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(node, content)| {
+                let module = modules.iter().find(|m| m.name == content.kind);
+                let specs = module.map_or(&[][..], |module| &module.parameters[..]);
+                let outputs = module.map_or(0, |module| module.outputs.len());
+
+                NodeSetup {
+                    id: node.id(),
+                    kind: content.kind.clone(),
+                    parameters: specs
+                        .iter()
+                        .enumerate()
+                        .map(|(index, spec)| match slots.slot(*node, index) {
+                            Some(slot) => Binding::Slot(slot),
+                            None => Binding::Value(
+                                *content.parameters.get(&spec.name).unwrap_or(&spec.default) as f32),
+                        })
+                        .collect(),
+                    meters: (0..outputs).map(|index| meters.meter(*node, index)).collect(),
+                    mode: modes.slot(node.id()),
+                }
+            })
+            .collect();
+
+        let connections = self
+            .nodes
+            .edges()
+            .iter()
+            .map(|edge| Connection {
+                from: edge.from.id(),
+                output: edge.output,
+                to: edge.to.id(),
+                input: edge.input,
+                feedback: self.nodes.is_feedback(edge),
+            })
+            .collect();
+
+        let setup = Setup { nodes, connections };
+        if !setup.same_graph(&self.setup) || !self.send_values(&setup, sync) {
+            let runtime = &mut self.runtime;
+            let library = &self.library;
+            let mut plan = Plan::new(&setup, sync.sample_rate(), |kind| runtime.create(library, kind));
+            plan.set_values_sent(sync.unbound.written());
+            sync.plans.send(plan);
+        }
+        self.setup = setup;
+    }
+
+    /// Sends the values of parameters without a slot that differ from the
+    /// graph last sent. Returns false if there was no room for them all.
+    fn send_values(&self, setup: &Setup, sync: &SyncState) -> bool {
+        for (node, sent) in setup.nodes.iter().zip(&self.setup.nodes) {
+            for (index, binding) in node.parameters.iter().enumerate() {
+                if let (Binding::Value(value), Some(Binding::Value(sent))) = (binding, sent.parameters.get(index)) {
+                    if value != sent && !sync.unbound.push(node.id, index, *value) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Scans the library again, only reading the files that changed.
+    fn rescan(&mut self) {
+        self.library.scan();
+        self.modules = modules::available(&self.library);
+    }
+
+    /// Replaces the graph with a patch the host restored, keeping the old
+    /// graph in the history.
+    fn load(&mut self, patch: &ag::Patch) {
+        self.history.record(&self.nodes);
+        self.focus = None;
+        let nodes = self.nodes.load_patch(patch);
+        self.renumber(&nodes);
+        self.changed = true;
+    }
+
+    /// Gives freshly pasted or loaded nodes their own display ids.
+    fn renumber(&mut self, nodes: &[ag::Node]) {
+        for node in nodes {
+            if let Some(content) = self.nodes.get_mut(node) {
+                content.id = self.nodes_created;
+                self.nodes_created += 1;
+            }
+        }
+    }
+
+    /// Sets the next display id past the highest in the graph, after undo or
+    /// redo has replaced the nodes.
+    fn recount(&mut self) {
+        self.nodes_created = self
+            .nodes
+            .iter()
+            .map(|(_, content)| content.id + 1)
+            .max()
+            .unwrap_or(0);
+    }
+}
+
+/// Creates the session for a new plugin instance, with its graph already
+/// running, so that it plays before the editor is first opened.
+pub fn new_session(sync: &SyncState) -> SharedSession {
+    let mut session = Session::new();
+    session.publish(sync);
+    Arc::new(SessionSlot {
+        session: Mutex::new(Some(session)),
+    })
+}
+
+/// The theme named by [`THEME_PATH_ENV`], or the light theme.
+fn load_theme() -> ag::Theme {
+    match std::env::var_os(THEME_PATH_ENV) {
+        Some(path) => ag::Theme::load(&path).unwrap_or_else(|error| {
+            info!("Theme {:?}: {}", path, error);
+            ag::Theme::light()
+        }),
+        None => ag::Theme::light(),
+    }
+}
+
+/// The nodes last copied or cut in any editor in the process, used when the
+/// system clipboard cannot be written, or no longer contains a patch.
+static CLIPBOARD: Mutex<Option<String>> = Mutex::new(None);
+
+fn clipboard() -> Option<String> {
+    CLIPBOARD.lock().ok()?.clone()
+}
+
+/// Puts copied nodes on the system clipboard, which iced can only read, so
+/// that they can be pasted in another instance or application.
+fn set_clipboard(text: String) {
+    let written = arboard::Clipboard::new().and_then(|mut system| system.set_text(text.clone()));
+    if let Err(e) = written {
+        info!("Cannot write to the clipboard: {}", e);
+    }
+    if let Ok(mut clipboard) = CLIPBOARD.lock() {
+        *clipboard = Some(text);
+    }
+}
+
+/// Where a [`Session`] is kept while the editor window is closed.
+pub type SharedSession = Arc<SessionSlot>;
+
+/// Holds the session while no window has it.
+pub struct SessionSlot {
+    session: Mutex<Option<Session>>,
+}
+
+impl SessionSlot {
+    /// Takes the session for a window to open with, or `None` while a window
+    /// that is closing, possibly on its own thread, still holds it.
+    pub fn take(&self) -> Option<Session> {
+        self.session.lock().ok()?.take()
+    }
+
+    fn put(&self, session: Session) {
+        if let Ok(mut shared) = self.session.lock() {
+            *shared = Some(session);
+        }
+    }
+}
+
+impl PatchLoader for SessionSlot {
+    /// Loads the patch into the session while no window has it.
+    fn load(&self, patch: &ag::Patch, sync: &SyncState) -> bool {
+        let mut shared = match self.session.lock() {
+            Ok(shared) => shared,
+            Err(_) => return false,
+        };
+        match shared.as_mut() {
+            Some(session) => {
+                session.load(patch);
+                session.publish(sync);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// What the editor is opened with.
+pub struct Flags {
+    pub sync_handle: Arc<SyncState>,
+    /// Taken from `shared_session`, which it is handed back to when the
+    /// window closes.
+    pub session: Session,
+    pub shared_session: SharedSession,
+    pub window: Arc<WindowState>,
 }
 
 impl  Application for AAIcedApplication {
     type Executor = executor::Default;
     type Message = Message;
-    type Flags = ();
-
-    fn new(sync_handle: Self::Flags) -> (Self, Command<Self::Message>) {
-        let db_range = LogDBRange::new(-12.0, 12.0, 0.5.into());
-
-        let (mut nodes, _) = 
-            ag::State::new(
-        Point::new(0.0, 0.0), 
-Content::new(0));
-            nodes.insert(
-                Point::new(400.0,0.0), 
-                Content::new(1));
-            
-        let app = Self {
+    type Flags = Flags;
+
+    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let Flags { sync_handle, session, shared_session, window } = flags;
+
+        let db_range = LogDBRange::new(MASTER_DB_MIN, MASTER_DB_MAX, 0.5.into());
+        let mut master = db_range.default_normal_param();
+        master.value = sync_handle.value(MASTER_PARAMETER).into();
+
+        let mut app = Self {
             db_range,
             
-            v_slider_state: v_slider::State::new(
-                db_range.default_normal_param(),
-            ),
+            v_slider_state: v_slider::State::new(master),
             
             // Add a tick mark at the center position with the tier 2 size
             center_tick_mark: tick_marks::Group::center(tick_marks::Tier::Two),
 
             sync_handle,
-            nodes,
-            nodes_created: 2,
-            focus: None,
+            session: ManuallyDrop::new(session),
+            shared_session,
+            bindings: ag::Bindings::default(),
+            window,
+            grip: grip::State::default(),
+            floating: floating::State::default(),
+            scales: pick_list::State::default(),
+            export: button::State::new(),
+            notes: NoteEditor::default(),
+            port_types: PortRegistry::default(),
+            palette: None,
+            status: None,
         };
-
+        app.report_library();
 
         (app, Command::none())
     }
@@ -88,41 +479,283 @@ Content::new(0));
         window_subs: &mut WindowSubs<Self::Message>) -> Subscription<Self::Message> {
         window_subs.on_frame = Some(Message::Frame);
 
-        Subscription::none()
+        // the host, or the user in standalone, may resize the window
+        iced_native::subscription::events_with(|event, _| match event {
+            Event::Window(window::Event::Resized { width, height }) => {
+                Some(Message::WindowResized(width, height))
+            }
+            _ => None,
+        })
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        // any message but a frame may change the graph or a parameter
+        if !matches!(message, Message::Frame) {
+            self.session.changed = true;
+        }
+        // and any but typing ends a run of typing
+        if !matches!(message, Message::Frame | Message::Note(_)) {
+            self.session.noting = None;
+        }
+
         match message {
             Message::Frame => {
-                //self.update_widgets_from_parameters();
+                if matches!(&self.status, Some((_, since)) if since.elapsed() > STATUS_TIME) {
+                    self.status = None;
+                }
+                self.restore_patch();
+                if self.bind_parameters() {
+                    self.session.changed = true;
+                }
+                self.update_widgets_from_parameters();
+                if self.update_meters() {
+                    self.session.changed = true;
+                }
+                self.update_probes();
+                self.publish_modes();
+                self.session.publish(&self.sync_handle);
+                self.sync_handle.report_latency();
             },
             Message::ParameterChange(index, value) => {
-                //self.sync_handle.set_parameter(index, value);
-
-                match index {
-                    _ => ()
-                }
-
-                //self.sync_handle.update_host_display();
+                self.sync_handle.set_parameter(index, value as f32);
+                self.sync_handle.update_host_display();
             },
+            Message::Parameter(node, index, normal) => {
+                if let Some(content) = self.session.nodes.get_mut(&node) {
+                    content.set_parameter(index, normal);
+                }
+                self.send_parameter(node, index, normal);
+            }
+            Message::XYParameter(node, index, x, y) => {
+                if let Some(content) = self.session.nodes.get_mut(&node) {
+                    content.set_parameter(index, x);
+                    content.set_parameter(index + 1, y);
+                }
+                self.send_parameter(node, index, x);
+                self.send_parameter(node, index + 1, y);
+            }
             Message::VSliderDB(normal) => {
                 let value = self.db_range.unmap_to_value(normal);
-                // self.output_text = format!("VSliderDB: {:.3}", value);
                 info!("VSliderDB: {:.3}", value);
+                self.sync_handle.set_parameter(MASTER_PARAMETER, normal.as_f32());
             }
             Message::Close(node) => {
                 info!("Close {:?}", node);
             }
+            Message::Mode(node, mode) => {
+                info!("Mode {:?} {:?}", node, mode);
+                self.session.history.record(&self.session.nodes);
+                self.session.nodes.set_mode(node, mode);
+                self.publish_modes();
+            }
             Message::Dragged(e) => {
                 match e {
-                    ag::DragEvent::Dropped{node, diff} => {
-                        self.nodes.translate(node, diff);
+                    ag::DragEvent::FrameDropped { frame, diff } => {
+                        self.session.history.record(&self.session.nodes);
+                        self.session.nodes.move_frame(frame, diff);
+                    }
+                    ag::DragEvent::Rerouted { edge, route } => {
+                        self.session.history.record(&self.session.nodes);
+                        self.session.nodes.set_route(&edge, &route);
+                    }
+                    ag::DragEvent::Dropped { node, diff, edit } => {
+                        self.session.history.record(&self.session.nodes);
+                        self.session.nodes.translate(node, diff);
+                        match edit {
+                            Some(ag::DropEdit::Splice { edge, input, output }) => {
+                                self.session.nodes.splice(node, &edge, input, output);
+                            }
+                            Some(ag::DropEdit::Extract { through, heal }) => {
+                                let refused = self.session.nodes.extract(node, &through, &heal).refused;
+                                for (edge, error) in refused {
+                                    self.refuse(edge, error);
+                                }
+                            }
+                            None => {}
+                        }
                     }
                     _ => {}
                 }
 
                 info!("Dragged {:?}", e);
             }
+            Message::Clipboard(e) => {
+                match e {
+                    ClipboardEvent::Copy => {
+                        if let Some(text) = self.session.nodes.copy() {
+                            set_clipboard(text);
+                        }
+                    }
+                    ClipboardEvent::Cut => {
+                        self.session.history.record(&self.session.nodes);
+                        if let Some(text) = self.session.nodes.cut() {
+                            set_clipboard(text);
+                        }
+                    }
+                    ClipboardEvent::Paste { contents, position } => {
+                        let text = contents
+                            .filter(|text| ag::Patch::from_text(text).is_ok())
+                            .or_else(clipboard);
+
+                        if let Some(text) = text {
+                            self.session.history.record(&self.session.nodes);
+                            match self.session.nodes.paste(&text, position) {
+                                Ok(pasted) => self.session.renumber(&pasted),
+                                Err(e) => info!("Paste failed: {}", e),
+                            }
+                        }
+                    }
+                    ClipboardEvent::Duplicate => {
+                        self.session.history.record(&self.session.nodes);
+                        let duplicated = self.session.nodes.duplicate();
+                        self.session.renumber(&duplicated);
+                    }
+                }
+            }
+            Message::Shortcut(shortcut) => {
+                match shortcut {
+                    Shortcut::Delete => {
+                        let nodes = &self.session.nodes;
+                        if !nodes.selection().is_empty()
+                            || !nodes.selected_edges().is_empty()
+                            || nodes.selected_frame().is_some() {
+                            self.session.history.record(&self.session.nodes);
+                            self.session.nodes.remove_selection();
+                            self.session.focus = self.session.nodes.focus();
+                        }
+                    }
+                    Shortcut::Nudge(offset) => {
+                        if !self.session.nodes.selection().is_empty() {
+                            self.session.history.record(&self.session.nodes);
+                            self.session.nodes.translate_selection(offset);
+                        }
+                    }
+                    Shortcut::Undo => {
+                        if self.session.history.undo(&mut self.session.nodes) {
+                            self.session.recount();
+                            self.send_parameters();
+                        }
+                        self.session.focus = self.session.nodes.focus();
+                    }
+                    Shortcut::Redo => {
+                        if self.session.history.redo(&mut self.session.nodes) {
+                            self.session.recount();
+                            self.send_parameters();
+                        }
+                        self.session.focus = self.session.nodes.focus();
+                    }
+                    Shortcut::Frame => {
+                        if !self.session.nodes.selection().is_empty() {
+                            self.session.history.record(&self.session.nodes);
+                            self.session.nodes.frame_selection("Comment");
+                        }
+                    }
+                    Shortcut::Palette => {}
+                    _ => {
+                        info!("Shortcut {:?}", shortcut);
+                    }
+                }
+            }
+            Message::Focus(node) => {
+                self.session.focus = Some(node);
+            }
+            Message::Connect(edge) => {
+                self.connect(edge, false);
+            }
+            Message::ConnectFeedback(edge) => {
+                self.connect(edge, true);
+            }
+            Message::OpenPalette(request) => {
+                // so that modules added or fixed since are offered
+                self.session.rescan();
+                self.report_library();
+                self.palette = Some(Palette::new(request, &self.session.modules));
+            }
+            Message::PaletteQuery(query) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.set_query(query);
+                }
+            }
+            Message::PaletteSelect(index) => {
+                if let Some(palette) = self.palette.take() {
+                    self.add_module(index, palette.request());
+                }
+            }
+            Message::PaletteClose => {
+                self.palette = None;
+            }
+            Message::WindowResized(width, height) => {
+                self.window.resized(width, height);
+            }
+            Message::Resize(width, height) => {
+                self.window.request_size(width, height);
+            }
+            Message::Scale(scale) => {
+                self.window.request_scale(scale);
+            }
+            Message::Metering(enabled) => {
+                self.session.meters.set_enabled(enabled, &self.sync_handle.meters);
+            }
+            Message::ResetClips => {
+                self.session.meters.reset_clips();
+            }
+            Message::ProbeEdge(edge) => {
+                if !self.session.probes.iter().any(|probe| probe.edge() == edge) {
+                    match self.sync_handle.probes.attach(edge.from.id(), edge.output) {
+                        Some(index) => self.session.probes.push(Probe::new(edge, index)),
+                        None => info!("No free probes for {:?}", edge),
+                    }
+                }
+            }
+            Message::Probe(id, event) => {
+                if let Some(probe) = self.session.probes.get_mut(id) {
+                    if !probe.apply(event) {
+                        self.sync_handle.probes.detach(probe.index());
+                        self.session.probes.remove(id);
+                    }
+                }
+            }
+            Message::DarkTheme(dark) => {
+                self.session.theme = Arc::new(if dark { ag::Theme::dark() } else { load_theme() });
+                self.session.dark_theme = dark;
+            }
+            Message::Flow(enabled) => {
+                self.session.flow = if enabled { Some(Instant::now()) } else { None };
+            }
+            Message::Minimap(shown) => {
+                self.session.minimap = shown;
+            }
+            Message::Note(text) => {
+                // a run of typing is undone as one edit
+                if let Some(target) = self.note_target() {
+                    if self.session.noting != Some(target) {
+                        self.session.history.record(&self.session.nodes);
+                        self.session.noting = Some(target);
+                    }
+                    match target {
+                        Target::Node(node) => self.session.nodes.set_note(node, &text),
+                        Target::Frame(frame) => self.session.nodes.set_frame_title(frame, &text),
+                    };
+                }
+            }
+            Message::Tag(tag) => {
+                if let Some(target) = self.note_target() {
+                    self.session.history.record(&self.session.nodes);
+                    match target {
+                        Target::Node(node) => self.session.nodes.set_color(node, tag.color()),
+                        Target::Frame(frame) => self.session.nodes.set_frame_color(frame, tag.color()),
+                    };
+                }
+            }
+            Message::Export => {
+                match self.diagram().save(&export_path()) {
+                    Ok((svg, pdf)) => info!("Exported patch to {} and {}", svg.display(), pdf.display()),
+                    Err(error) => {
+                        error!("Export failed: {}", error);
+                        self.report(format!("Export failed: {}", error));
+                    }
+                }
+            }
         }
 
         Command::none()
@@ -139,17 +772,75 @@ Content::new(0));
             VSlider::new(&mut self.v_slider_state, Message::VSliderDB)
                 .tick_marks(&self.center_tick_mark);
 
-        let focus = self.focus;
-        let total_nodes = self.nodes.len();
+        let probe_titles: Vec<String> = self
+            .session
+            .probes
+            .iter()
+            .map(|probe| {
+                let edge = probe.edge();
+                let name = |node| {
+                    self.session
+                        .nodes
+                        .get(&node)
+                        .map_or_else(String::new, |c: &Content| format!("{} {}", c.kind, c.id))
+                };
+                format!("{}:{} > {}:{}", name(edge.from), edge.output, name(edge.to), edge.input)
+            })
+            .collect();
+
+        let focus = self.session.focus;
+        let theme = self.session.theme.clone();
+        let dark_theme = self.session.dark_theme;
+        let flow = self
+            .session
+            .flow
+            .map(|start| start.elapsed().as_secs_f32() * FLOW_SPEED);
+        let minimap = self.session.minimap;
+        let status = self.status.as_ref().map_or_else(String::new, |(status, _)| status.clone());
+        let note_target = self.note_target();
+        let (note, tag) = match note_target {
+            Some(Target::Node(node)) => {
+                let annotation = self.session.nodes.annotation(&node).cloned().unwrap_or_default();
+                (annotation.note, Tag::of(annotation.color))
+            }
+            Some(Target::Frame(frame)) => match self.session.nodes.frame(frame) {
+                Some(frame) => (frame.title.clone(), Tag::of(frame.color)),
+                None => (String::new(), Tag::of(None)),
+            },
+            None => (String::new(), Tag::of(None)),
+        };
+        let annotations: HashMap<_, _> = self
+            .session
+            .nodes
+            .iter()
+            .filter_map(|(node, _)| Some((*node, self.session.nodes.annotation(node)?.clone())))
+            .collect();
+        let modes: HashMap<_, _> = self
+            .session
+            .nodes
+            .iter()
+            .map(|(node, _)| (*node, self.session.nodes.mode(node)))
+            .collect();
+        let total_nodes = self.session.nodes.len();
+        let modules = &self.session.modules;
+        let port_types = &self.port_types;
+        let meters = &self.session.meters;
+
+        // while the palette is open its search box owns the keyboard
+        let bindings = if self.palette.is_some() {
+            &NO_BINDINGS
+        } else {
+            &self.bindings
+        };
 
         let audio_graph = AudioGraph::new(
-            &mut self.nodes, 
+            &mut self.session.nodes, 
             |node, content| {
            
             let is_focused = focus == Some(node);
 
             let title = Row::with_children(vec![
-                Text::new("Node").into(),
+                Text::new(content.kind.clone()).into(),
                 Text::new(content.id.to_string())
                     .color(if is_focused {
                         NODE_ID_COLOR_FOCUSED
@@ -163,29 +854,58 @@ Content::new(0));
 
            // println!("id = {}", content.id);
 
-            let title_bar = ag::TitleBar::new(title)
+            let mut title_bar = ag::TitleBar::new(title)
                 .padding(10);
                 //.style(style::TitleBar { is_focused });
+            if let Some(annotation) = annotations.get(&node) {
+                title_bar = title_bar.note(annotation.note.clone());
+                if let Some(color) = annotation.color {
+                    title_bar = title_bar.tag(ag::tag_color(color));
+                }
+            }
+
+            let module = modules.iter().find(|m| m.name == content.kind);
 
-            let ports = 
-                InputOutputs::new()
-                .inputs(vec![InputOutputs::PMidi, InputOutputs::PAudio])
-                .outputs(vec![InputOutputs::PMidi, InputOutputs::PAudio]);
+            let ports = InputOutputs::of(module);
+            let levels = (0..ports.outputs.len())
+                .map(|index| meters.level(node, index))
+                .collect();
+            let ports = ports.levels(levels);
 
-            ag::Content::new(
-                content.view(node, total_nodes))
-                .title_bar(title_bar)
+            let mode = modes.get(&node).copied().unwrap_or_default();
+            let (body, controls) = content.view(node, mode, total_nodes, module);
+
+            ag::Content::new(body)
+                .title_bar(title_bar.controls(controls).always_show_controls())
                 .ports(Ports::new(Box::new(ports)))
                 //.style(style::Pane { is_focused })
         })
 
         // let audio_graph = AudioGraph::new(
-        //     &mut self.nodes,
+        //     &mut self.session.nodes,
         //     |node, content| {
         //         ag::Content::new(content.view(node, total_nodes))
         //     })
             .on_drag(Message::Dragged)
-            .set_style_sheet(Box::new(audio_graph_style::AudioGraphStyle::new()));
+            .on_clipboard(Message::Clipboard)
+            .on_shortcut(Message::Shortcut)
+            .on_focus(Message::Focus)
+            .on_connect(Message::Connect)
+            .on_feedback(Message::ConnectFeedback)
+            .on_palette(Message::OpenPalette)
+            .on_edge_click(Message::ProbeEdge)
+            .bindings(bindings)
+            .port_types(port_types)
+            .set_style_sheet(theme);
+        let audio_graph = match flow {
+            Some(phase) => audio_graph.flow(phase),
+            None => audio_graph,
+        };
+        let audio_graph = if minimap {
+            audio_graph.minimap(ag::MINIMAP_SIZE)
+        } else {
+            audio_graph
+        };
 
         let all = Column::new()
             .height(Length::Fill)
@@ -194,15 +914,395 @@ Content::new(0));
             .spacing(20)
             .padding(20)
             .align_items(Align::Center)
-            .push(v_slider_widget)
+            .push(
+                Row::new()
+                    .height(Length::Units(160))
+                    .spacing(10)
+                    .push(v_slider_widget)
+                    .push(self.session.meters.master_view()));
+
+        let sample_rate = self.sync_handle.sample_rate();
+        let has_probes = !self.session.probes.is_empty();
+        let probes = self
+            .session
+            .probes
+            .iter_mut()
+            .zip(probe_titles)
+            .enumerate()
+            .fold(Column::new().spacing(10), |column, (id, (probe, title))| {
+                column.push(probe.view(id, title, sample_rate))
+            });
+
+        let probes: Option<Element<'_, Message>> = if has_probes { Some(probes.into()) } else { None };
+
+        let mut graph = Row::new()
+            .spacing(10)
             .push(audio_graph);
+        if let Some(palette) = &mut self.palette {
+            graph = graph.push(palette.view(modules, port_types));
+        }
 
-        Container::new(all)
-            .padding(16)
-            .into()
+        let all = all.push(graph);
+
+        let scale = PickList::new(
+            &mut self.scales,
+            &Scale::ALL[..],
+            Some(self.window.scale()),
+            Message::Scale)
+            .text_size(12);
+
+        let footer = Row::new()
+            .width(Length::Fill)
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(self.notes.view(note_target, &note, tag))
+            .push(Text::new(status).size(12))
+            .push(Space::with_width(Length::Fill))
+            .push(
+                Button::new(&mut self.export, Text::new("Export").size(12))
+                    .padding(4)
+                    .on_press(Message::Export)
+                    .style(style::Button::Primary))
+            .push(
+                Checkbox::new(self.session.meters.is_enabled(), "Meters", Message::Metering)
+                    .text_size(12))
+            .push(
+                Checkbox::new(dark_theme, "Dark", Message::DarkTheme)
+                    .text_size(12))
+            .push(
+                Checkbox::new(flow.is_some(), "Flow", Message::Flow)
+                    .text_size(12))
+            .push(
+                Checkbox::new(minimap, "Minimap", Message::Minimap)
+                    .text_size(12))
+            .push(Text::new("Scale").size(12))
+            .push(scale)
+            .push(Grip::new(&mut self.grip, self.window.size()).view());
+
+        let all = all.push(footer);
+
+        Floating::new(&mut self.floating, Container::new(all).padding(16), probes).into()
     }
 }
 
+impl AAIcedApplication {
+    /// Returns what the note editor changes: the selected frame, or else the
+    /// node with focus.
+    fn note_target(&self) -> Option<Target> {
+        match self.session.nodes.selected_frame() {
+            Some(frame) => Some(Target::Frame(frame)),
+            None => self.session.focus.map(Target::Node),
+        }
+    }
+
+    /// Connects the nodes of `edge`, with a feedback edge if `feedback`,
+    /// recording the edit unless the connection is refused.
+    fn connect(&mut self, edge: ag::Edge, feedback: bool) {
+        let nodes = &mut self.session.nodes;
+        let before = nodes.clone();
+        let connected = match feedback {
+            true => nodes.connect_feedback(edge.from, edge.output, edge.to, edge.input),
+            false => nodes.connect(edge.from, edge.output, edge.to, edge.input),
+        };
+
+        match connected {
+            Ok(_) => self.session.history.record(&before),
+            Err(error) => self.refuse(edge, error),
+        }
+    }
+
+    /// Tells the user why `edge` could not be connected.
+    fn refuse(&mut self, edge: ag::Edge, error: ag::ConnectError) {
+        info!("Could not connect {:?}: {}", edge, error);
+        self.report(format!("Could not connect: {}", error));
+    }
+
+    /// Shows `status` in the footer for a few seconds.
+    fn report(&mut self, status: String) {
+        self.status = Some((status, Instant::now()));
+    }
+
+    /// Shows the problems the last scan of the library found, if any.
+    fn report_library(&mut self) {
+        let errors = self.session.library.errors();
+        let status = match errors {
+            [] => return,
+            [error] => format!("Module library: {}", error),
+            [error, rest @ ..] => format!("Module library: {}, and {} more problems", error, rest.len()),
+        };
+        self.report(status);
+    }
+
+    /// Inserts the module at `index` where the palette was requested,
+    /// connecting it to the cable the palette was opened from, if any.
+    fn add_module(&mut self, index: usize, request: ag::PaletteRequest) {
+        let module = match self.session.modules.get(index) {
+            Some(module) => module.clone(),
+            None => return,
+        };
+
+        self.session.history.record(&self.session.nodes);
+
+        let content = Content::new(self.session.nodes_created, &module.name);
+        let node = match self.session.nodes.insert(request.position, content) {
+            Some(node) => node,
+            None => return,
+        };
+        self.session.nodes_created += 1;
+        if nodes::is_delay(&module.name) {
+            self.session.nodes.set_delay(node, true);
+        }
+
+        if let Some((from, socket)) = request.cable {
+            if let Some(index) = module.port(socket.direction.opposite(), socket.port_type, &self.port_types) {
+                let connected = match socket.direction {
+                    ag::Direction::Output => self.session.nodes.connect(from, socket.index, node, index),
+                    ag::Direction::Input => self.session.nodes.connect(node, index, from, socket.index),
+                };
+                if let Err(error) = connected {
+                    info!("Could not connect {}: {}", module.name, error);
+                    self.report(format!("Could not connect {}: {}", module.name, error));
+                }
+            }
+        }
+
+        self.session.nodes.select(&[node]);
+    }
+
+    /// Binds host parameter slots to the parameters of nodes that have been
+    /// shown, and frees those of nodes that have gone. Returns true if any
+    /// slot was bound or freed.
+    fn bind_parameters(&mut self) -> bool {
+        let nodes = &self.session.nodes;
+        let released = self.session.slots.retain(|node, index| {
+            nodes
+                .get(&node)
+                .and_then(|content| content.panel.as_ref())
+                .map_or(false, |panel| index < panel.len())
+        });
+        if !released.is_empty() {
+            self.sync_handle.release(&released);
+        }
+
+        let mut bound = false;
+        for (node, content) in self.session.nodes.iter() {
+            if let Some(panel) = &content.panel {
+                bound |= panel.bind(
+                    *node,
+                    (&content.kind, content.id),
+                    &mut self.session.slots,
+                    &self.sync_handle);
+            }
+        }
+        bound || !released.is_empty()
+    }
+
+    /// Tells the audio thread which nodes are bypassed and which are not
+    /// heard. Soloing depends on the edges as well as the modes, so this is
+    /// done every frame rather than only when a mode is toggled.
+    fn publish_modes(&mut self) {
+        let nodes = &self.session.nodes;
+        let silenced = nodes.silenced();
+
+        let ids: Vec<usize> = nodes.iter().map(|(node, _)| node.id()).collect();
+        self.session.modes.assign(&ids);
+
+        for (node, _) in nodes.iter() {
+            if let Some(slot) = self.session.modes.slot(node.id()) {
+                self.sync_handle.modes.set(slot, Mode {
+                    bypass: nodes.mode(node).bypass,
+                    silenced: silenced.contains(node),
+                });
+            }
+        }
+    }
+
+    /// Moves the widgets for parameters changed by the host since the last
+    /// frame, leaving all others alone.
+    fn update_widgets_from_parameters(&mut self) {
+        let nodes = &mut self.session.nodes;
+        let slots = &self.session.slots;
+        let master = &mut self.v_slider_state;
+
+        self.sync_handle.gui_changes(|slot, value| {
+            if slot == MASTER_PARAMETER {
+                master.normal_param.value = value.into();
+            } else if let Some((node, index)) = slots.owner(slot) {
+                if let Some(content) = nodes.get_mut(&node) {
+                    content.move_parameter(index, value.into());
+                }
+            }
+        });
+    }
+
+    /// Meters the audio outputs of every node and reads the levels measured
+    /// since the last frame. Returns true if the outputs metered changed.
+    fn update_meters(&mut self) -> bool {
+        if !self.session.meters.is_enabled() {
+            return false;
+        }
+
+        let modules = &self.session.modules;
+        let port_types = &self.port_types;
+        let outputs: Vec<(ag::Node, usize)> = self
+            .session
+            .nodes
+            .iter()
+            .flat_map(|(node, content)| {
+                let module = modules.iter().find(|m| m.name == content.kind);
+                InputOutputs::of(module)
+                    .outputs
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, port_type)| matches!(
+                        port_types.get(*port_type),
+                        Some(info) if info.signal == ag::Signal::Audio))
+                    .map(move |(index, _)| (*node, index))
+            })
+            .collect();
+
+        let changed = self.session.meters.assign(&outputs);
+        self.session.meters.update(&self.sync_handle.meters);
+        changed
+    }
+
+    /// Closes the probes of edges that have gone, and reads the latest
+    /// samples for the others.
+    fn update_probes(&mut self) {
+        let edges = self.session.nodes.edges();
+        let bank = &self.sync_handle.probes;
+
+        self.session.probes.retain(|probe| {
+            let attached = edges.contains(&probe.edge());
+            if !attached {
+                bank.detach(probe.index());
+            }
+            attached
+        });
+
+        for probe in &mut self.session.probes {
+            if let Some(source) = bank.get(probe.index()) {
+                probe.update(source);
+            }
+        }
+    }
+
+    /// Passes a parameter change made in the editor on to the audio thread
+    /// and the host.
+    fn send_parameter(&self, node: ag::Node, index: usize, normal: Normal) {
+        if let Some(slot) = self.session.slots.slot(node, index) {
+            self.sync_handle.set_parameter(slot, normal.as_f32());
+        }
+    }
+
+    /// Passes the value of every bound parameter on, after the graph has been
+    /// replaced by undo or redo.
+    fn send_parameters(&self) {
+        for (slot, node, index) in self.session.slots.iter() {
+            let normal = self
+                .session
+                .nodes
+                .get(&node)
+                .and_then(|content| content.panel.as_ref())
+                .and_then(|panel| panel.normal(index));
+
+            if let Some(normal) = normal {
+                self.sync_handle.set_parameter(slot, normal.as_f32());
+            }
+        }
+    }
+
+    /// Describes the graph as it stands, for export.
+    fn diagram(&self) -> Diagram {
+        let nodes = &self.session.nodes;
+        let mut indices = HashMap::new();
+        let mut diagram = Diagram {
+            port_colours: self.session.theme.ports.rgb8(),
+            ..Diagram::default()
+        };
+
+        for (node, content) in nodes.iter() {
+            let position = match nodes.position(node) {
+                Some(position) => position,
+                None => continue,
+            };
+            let module = self.session.modules.iter().find(|m| m.name == content.kind);
+            let ports = InputOutputs::of(module);
+
+            indices.insert(*node, diagram.nodes.len());
+            diagram.nodes.push(DiagramNode {
+                x: position.x,
+                y: position.y,
+                width: ag::NODE_WIDTH,
+                height: ag::NODE_HEIGHT,
+                title: format!("{} {}", content.kind, content.id),
+                inputs: ports.inputs.iter().map(|port| port.id()).collect(),
+                outputs: ports.outputs.iter().map(|port| port.id()).collect(),
+                parameters: content
+                    .panel
+                    .as_ref()
+                    .map(|panel| panel.values())
+                    .unwrap_or_default(),
+            });
+        }
+
+        diagram.edges = nodes
+            .edges()
+            .iter()
+            .filter_map(|edge| {
+                Some(DiagramEdge {
+                    from: *indices.get(&edge.from)?,
+                    output: edge.output,
+                    to: *indices.get(&edge.to)?,
+                    input: edge.input,
+                    route: nodes.route(edge).iter().map(|p| (p.x, p.y)).collect(),
+                })
+            })
+            .collect();
+
+        diagram
+    }
+
+    /// Replaces the graph with a patch the host restored while the editor
+    /// was open, if any.
+    fn restore_patch(&mut self) {
+        if let Some(patch) = self.sync_handle.take_restored() {
+            self.session.load(&patch);
+        }
+    }
+}
+
+impl Drop for AAIcedApplication {
+    /// Keeps the session for the next time the editor is opened, with any
+    /// patch the host restored since the last frame loaded and running.
+    fn drop(&mut self) {
+        self.restore_patch();
+        self.session.publish(&self.sync_handle);
+        // the session is not used again
+        let session = unsafe { ManuallyDrop::take(&mut self.session) };
+        self.shared_session.put(session);
+    }
+}
+
+/// Where to export the next diagram, without its extension.
+fn export_path() -> PathBuf {
+    let dir = std::env::var_os(EXPORT_DIR_ENV)
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(PathBuf::from))
+        .or_else(|| std::env::var_os("USERPROFILE").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir);
+
+    dir.join(format!("aa-patch-{}", Local::now().format("%Y%m%d-%H%M%S")))
+}
+
+static NO_BINDINGS: ag::Bindings = ag::Bindings::empty();
+
+/// How long a refused edit is shown in the footer.
+const STATUS_TIME: Duration = Duration::from_secs(4);
+
+/// How many flow marks pass a point on an edge each second.
+const FLOW_SPEED: f32 = 2.0;
+
 const NODE_ID_COLOR_UNFOCUSED: Color = Color::from_rgb(
     0xFF as f32 / 255.0,
     0xC7 as f32 / 255.0,
@@ -214,28 +1314,87 @@ const NODE_ID_COLOR_FOCUSED: Color = Color::from_rgb(
     0x47 as f32 / 255.0,
 );
 
+#[derive(Debug, Clone)]
 struct Content {
     id: usize,
+    kind: String,
+    parameters: BTreeMap<String, f64>,
+    // built from the module's parameters the first time the node is shown
+    panel: Option<Panel>,
     close: button::State,
+    bypass: button::State,
+    mute: button::State,
+    solo: button::State,
 }
 
 impl Content {
-    fn new(id: usize) -> Self {
+    fn new(id: usize, kind: &str) -> Self {
         Content {
             id,
+            kind: kind.to_string(),
+            parameters: BTreeMap::new(),
+            panel: None,
             close: button::State::new(),
+            bypass: button::State::new(),
+            mute: button::State::new(),
+            solo: button::State::new(),
+        }
+    }
+
+    fn set_parameter(&mut self, index: usize, normal: Normal) {
+        if let Some(panel) = &mut self.panel {
+            if let Some((name, value)) = panel.set(index, normal) {
+                self.parameters.insert(name, value);
+            }
+        }
+    }
+
+    /// Moves the widget for a parameter changed outside the editor.
+    fn move_parameter(&mut self, index: usize, normal: Normal) {
+        if let Some(panel) = &mut self.panel {
+            if let Some((name, value)) = panel.move_to(index, normal) {
+                self.parameters.insert(name, value);
+            }
         }
     }
+
+    /// Returns the body of the node, and the bypass, mute and solo toggles
+    /// for its title bar.
     fn view(
         &mut self,
         node: ag::Node,
+        mode: ag::NodeMode,
         total_panes: usize,
-    ) -> Element<Message> {
+        module: Option<&ModuleType>,
+    ) -> (Element<Message>, Element<Message>) {
+        if self.panel.is_none() {
+            if let Some(module) = module {
+                self.panel = Some(Panel::new(&module.parameters, &mut self.parameters));
+            }
+        }
+
         let Content {
             close,
+            bypass,
+            mute,
+            solo,
+            panel,
             ..
         } = self;
 
+        let toggle = |state, label, on: bool, toggled: ag::NodeMode| {
+            Button::new(state, Text::new(label).size(12))
+                .padding(2)
+                .on_press(Message::Mode(node, toggled))
+                .style(style::Button::Toggle(on))
+        };
+
+        let toggles = Row::new()
+            .spacing(2)
+            .push(toggle(bypass, "B", mode.bypass, mode.bypass(!mode.bypass)))
+            .push(toggle(mute, "M", mode.mute, mode.mute(!mode.mute)))
+            .push(toggle(solo, "S", mode.solo, mode.solo(!mode.solo)));
+
         let button = |state, label, message, style| {
             Button::new(
                 state,
@@ -250,8 +1409,16 @@ impl Content {
             .style(style)
         };
 
-        let mut controls = Column::new()
-            .max_width(150)
+        let controls = Column::new()
+            .spacing(10)
+            .align_items(Align::Center);
+
+        let controls = match panel {
+            Some(panel) if !panel.is_empty() => controls.push(panel.view(node)),
+            _ => controls,
+        };
+
+        let controls = controls
             .push(button(
                 close,
                 "Close",
@@ -259,42 +1426,88 @@ impl Content {
                 style::Button::Destructive,
             ));
 
-        Container::new(controls)
+        let body = Container::new(controls)
             .width(Length::Units(200))
             .height(Length::Units(200))
             .padding(5)
             .center_y()
-            .into()
+            .into();
+
+        (body, toggles.into())
+    }
+}
+
+impl Persist for Content {
+    fn save(&self) -> NodeData {
+        NodeData {
+            kind: self.kind.clone(),
+            parameters: self.parameters.clone(),
+        }
+    }
+
+    fn load(data: &NodeData) -> Option<Self> {
+        Some(Content {
+            kind: data.kind.clone(),
+            parameters: data.parameters.clone(),
+            ..Content::new(0, &data.kind)
+        })
     }
 }
 
 struct InputOutputs {
     inputs: Vec<PortType>,
     outputs: Vec<PortType>,
+    input_labels: Vec<ag::PortLabel>,
+    output_labels: Vec<ag::PortLabel>,
+    levels: Vec<Option<ag::Level>>,
 }
 
 impl InputOutputs {
-    pub const PMidi : PortType = PortType::new();
-    pub const PAudio : PortType = PortType::fresh(Self::PMidi);
-
     pub fn new() -> Self {
         Self {
             inputs: Vec::new(),
             outputs: Vec::new(),
+            input_labels: Vec::new(),
+            output_labels: Vec::new(),
+            levels: Vec::new(),
+        }
+    }
+
+    /// The ports of a node of the given module type, or MIDI and audio in
+    /// and out for nodes of an unknown type.
+    pub fn of(module: Option<&ModuleType>) -> Self {
+        match module {
+            Some(module) => InputOutputs {
+                input_labels: module.input_labels.clone(),
+                output_labels: module.output_labels.clone(),
+                ..InputOutputs::new()
+                    .inputs(module.inputs.clone())
+                    .outputs(module.outputs.clone())
+            },
+            None => InputOutputs::new()
+                .inputs(vec![PortType::MIDI, PortType::AUDIO])
+                .outputs(vec![PortType::MIDI, PortType::AUDIO]),
         }
     }
 
     pub fn inputs(self, is: Vec<PortType>) -> Self {
         Self {
             inputs: is,
-            outputs: self.outputs,
+            ..self
         }
     }
 
     pub fn outputs(self, outs: Vec<PortType>) -> Self {
         Self {
-            inputs: self.inputs,
             outputs: outs,
+            ..self
+        }
+    }
+
+    pub fn levels(self, levels: Vec<Option<ag::Level>>) -> Self {
+        Self {
+            levels,
+            ..self
         }
     }
 }
@@ -326,64 +1539,19 @@ impl Connectors for InputOutputs {
     fn outputs(&self) -> std::slice::Iter<'_, PortType> {
         self.outputs.iter()
     }
-}
-mod audio_graph_style {
-    use ag::style::style::*;
-    use iced_native::{ Color };
-
-    pub struct AudioGraphStyle;
-    impl AudioGraphStyle {
-        const ACTIVE_STYLE: MusesStyle = MusesStyle {
-            fill: Color {
-                r: 0.26,
-                g: 0.26,
-                b: 0.26,
-                a: 0.75,
-            },
-            background: Color::from_rgb(
-                0xe0 as f32 / 255.0,
-                0xd6 as f32 / 255.0,
-                0x44 as f32 / 255.0,
-            ),
-            border_radius: 0.5,
-            border_width: 1.0,
-            border_color: Color::from_rgb(
-                0xda as f32 / 255.0,
-                0x70 as f32 / 255.0,
-                0xd6 as f32 / 255.0,
-            ),
-        };
 
-        pub fn new() -> Self {
-            AudioGraphStyle
-        }
+    fn input_label(&self, index: usize) -> Option<&ag::PortLabel> {
+        self.input_labels.get(index)
     }
 
-    impl StyleSheet for AudioGraphStyle {
-        fn active(&self) -> Style {
-            Style::Muses(Self::ACTIVE_STYLE)
-        }
-
-        fn hovered(&self) -> Style {
-            Style::Muses(MusesStyle {
-                ..Self::ACTIVE_STYLE
-            })
-        }
-
-        fn dragging(&self) -> Style {
-            Style::Muses(MusesStyle {
-                ..Self::ACTIVE_STYLE
-            })
-        }
+    fn output_label(&self, index: usize) -> Option<&ag::PortLabel> {
+        self.output_labels.get(index)
+    }
 
-        fn style(&self) -> Style {
-            Style::Muses(MusesStyle {
-                ..Self::ACTIVE_STYLE
-            })
-        }
+    fn output_level(&self, index: usize) -> Option<ag::Level> {
+        self.levels.get(index).copied().flatten()
     }
 }
-
 mod style {
     use iced_graphics::{button, container, Background, Color, Vector};
 
@@ -405,9 +1573,26 @@ mod style {
         0xC4 as f32 / 255.0,
     );
 
+    /// A panel floating over the editor.
+    pub struct Floating;
+
+    impl container::StyleSheet for Floating {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(Background::Color(SURFACE)),
+                border_radius: 5.0,
+                border_width: 1.0,
+                border_color: Color::from_rgb8(0x42, 0x42, 0x42),
+                ..container::Style::default()
+            }
+        }
+    }
+
     pub enum Button {
         Primary,
         Destructive,
+        /// A toggle in a node's title bar, and whether it is on.
+        Toggle(bool),
     }
 
     impl button::StyleSheet for Button {
@@ -417,6 +1602,8 @@ mod style {
                 Button::Destructive => {
                     (None, Color::from_rgb8(0xFF, 0x47, 0x47))
                 }
+                Button::Toggle(true) => (Some(ACTIVE), Color::WHITE),
+                Button::Toggle(false) => (Some(SURFACE), Color::from_rgb8(0x42, 0x42, 0x42)),
             };
 
             button::Style {
@@ -437,6 +1624,11 @@ mod style {
                     a: 0.2,
                     ..active.text_color
                 }),
+                Button::Toggle(true) => Some(HOVERED),
+                Button::Toggle(false) => Some(Color {
+                    a: 0.2,
+                    ..ACTIVE
+                }),
             };
 
             button::Style {
@@ -449,4 +1641,55 @@ mod style {
 
 mod connectors {
     
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::Preset;
+    use vst::plugin::HostCallback;
+
+    fn patch_node(id: usize, kind: &str) -> ag::PatchNode {
+        ag::PatchNode {
+            id,
+            x: 300.0 * id as f32,
+            y: 0.0,
+            data: NodeData { kind: kind.to_string(), ..Default::default() },
+            note: String::new(),
+            color: None,
+            bypass: false,
+            mute: false,
+            solo: false,
+            delay: false,
+        }
+    }
+
+    #[test]
+    fn test_restore_without_editor() {
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let session = new_session(&sync);
+        sync.set_loader(session.clone());
+
+        let mut patch = ag::Patch::new();
+        patch.nodes.push(patch_node(0, "Oscillator"));
+        patch.nodes.push(patch_node(1, "Audio Output"));
+        patch.edges.push(ag::PatchEdge {
+            from: 0,
+            output: 0,
+            to: 1,
+            input: 0,
+            feedback: false,
+            route: Vec::new(),
+        });
+        sync.restore(Preset { patch: Some(patch), master: None, window: None });
+
+        // nothing is left for an editor to load
+        assert_eq!(sync.take_restored(), None);
+
+        let shared = session.take().unwrap();
+        let mut kinds: Vec<&str> = shared.setup.nodes.iter().map(|node| node.kind.as_str()).collect();
+        kinds.sort_unstable();
+        assert_eq!(kinds, vec!["Audio Output", "Oscillator"]);
+        assert_eq!(shared.setup.connections.len(), 1);
+        assert_eq!(sync.preset().patch, Some(shared.nodes.to_patch()));
+    }
+}
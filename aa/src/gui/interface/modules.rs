@@ -0,0 +1,134 @@
+use ag::{Direction, PortLabel, PortRegistry, PortType};
+
+use crate::library::{Library, Manifest, Module, ParameterSpec};
+use crate::nodes;
+
+/// A type of module that can be added to the graph.
+#[derive(Debug, Clone)]
+pub struct ModuleType {
+    pub name: String,
+    pub category: String,
+    pub inputs: Vec<PortType>,
+    pub outputs: Vec<PortType>,
+    pub input_labels: Vec<PortLabel>,
+    pub output_labels: Vec<PortLabel>,
+    pub parameters: Vec<ParameterSpec>,
+}
+
+impl ModuleType {
+    pub fn new(name: &str, category: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            category: category.to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            input_labels: Vec::new(),
+            output_labels: Vec::new(),
+            parameters: Vec::new(),
+        }
+    }
+
+    pub fn inputs(self, is: Vec<PortType>) -> Self {
+        Self {
+            inputs: is,
+            ..self
+        }
+    }
+
+    pub fn outputs(self, outs: Vec<PortType>) -> Self {
+        Self {
+            outputs: outs,
+            ..self
+        }
+    }
+
+    /// Names the inputs, in order.
+    pub fn input_labels(self, labels: &[&str]) -> Self {
+        Self {
+            input_labels: labels.iter().map(|name| PortLabel::new(name)).collect(),
+            ..self
+        }
+    }
+
+    /// Names the outputs, in order.
+    pub fn output_labels(self, labels: &[&str]) -> Self {
+        Self {
+            output_labels: labels.iter().map(|name| PortLabel::new(name)).collect(),
+            ..self
+        }
+    }
+
+    pub fn parameters(self, parameters: Vec<ParameterSpec>) -> Self {
+        Self {
+            parameters,
+            ..self
+        }
+    }
+
+    /// Returns the index of the port in `direction` that can be connected to
+    /// a port of type `port_type`, preferring one of the same type to one
+    /// needing a conversion.
+    pub fn port(
+        &self,
+        direction: Direction,
+        port_type: PortType,
+        registry: &PortRegistry) -> Option<usize> {
+        let ports = match direction {
+            Direction::Input => &self.inputs,
+            Direction::Output => &self.outputs,
+        };
+        let compatible = |p: PortType| match direction {
+            Direction::Input => registry.can_connect(port_type, p),
+            Direction::Output => registry.can_connect(p, port_type),
+        };
+
+        ports
+            .iter()
+            .position(|p| *p == port_type)
+            .or_else(|| ports.iter().position(|p| compatible(*p)))
+    }
+
+    /// Returns true if `query` appears in the name or category, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self.category.to_lowercase().contains(&query)
+    }
+}
+
+impl From<&Manifest> for ModuleType {
+    fn from(manifest: &Manifest) -> Self {
+        ModuleType {
+            input_labels: manifest.inputs.iter().map(|p| p.label()).collect(),
+            output_labels: manifest.outputs.iter().map(|p| p.label()).collect(),
+            ..ModuleType::new(&manifest.name, &manifest.category)
+                .inputs(manifest.inputs.iter().map(|p| p.kind.port_type()).collect())
+                .outputs(manifest.outputs.iter().map(|p| p.kind.port_type()).collect())
+                .parameters(manifest.parameters.clone())
+        }
+    }
+}
+
+impl From<&Module> for ModuleType {
+    fn from(module: &Module) -> Self {
+        ModuleType::from(&module.manifest)
+    }
+}
+
+/// Returns the built-in module types followed by those found in `library`.
+pub fn available(library: &Library) -> Vec<ModuleType> {
+    let mut modules = builtin();
+    modules.extend(
+        library
+            .modules()
+            .iter()
+            .filter(|module| !modules.iter().any(|m| m.name == module.manifest.name))
+            .map(ModuleType::from)
+            .collect::<Vec<_>>());
+    modules
+}
+
+/// Module types that are always available: the native nodes.
+pub fn builtin() -> Vec<ModuleType> {
+    nodes::manifests().iter().map(ModuleType::from).collect()
+}
@@ -0,0 +1,86 @@
+use iced_baseview::{Align, Element, Row, Text, TextInput};
+use iced_native::{pick_list, text_input, PickList};
+
+use std::fmt;
+
+use super::Message;
+
+/// One of the colour tags of [`ag::TAGS`], or none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tag(Option<usize>);
+
+impl Tag {
+    /// The tags offered in the editor.
+    pub const ALL: [Tag; 7] = [
+        Tag(None),
+        Tag(Some(0)),
+        Tag(Some(1)),
+        Tag(Some(2)),
+        Tag(Some(3)),
+        Tag(Some(4)),
+        Tag(Some(5)),
+    ];
+
+    /// Returns the tag with the given colour, or no tag if the colour is not
+    /// one of [`ag::TAGS`].
+    pub fn of(color: Option<[u8; 3]>) -> Self {
+        Tag(color.and_then(|color| ag::TAGS.iter().position(|(_, tag)| *tag == color)))
+    }
+
+    pub fn color(self) -> Option<[u8; 3]> {
+        self.0
+            .and_then(|index| ag::TAGS.get(index))
+            .map(|(_, color)| *color)
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.and_then(|index| ag::TAGS.get(index)) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "No tag"),
+        }
+    }
+}
+
+/// What the note editor changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// The note and tag of a node.
+    Node(ag::Node),
+    /// The title and tag of a comment frame.
+    Frame(ag::FrameId),
+}
+
+/// Edits the note and colour tag of the focused node, or the title and
+/// colour tag of the selected comment frame.
+#[derive(Default)]
+pub struct NoteEditor {
+    input: text_input::State,
+    tags: pick_list::State<Tag>,
+}
+
+impl NoteEditor {
+    pub fn view<'a>(&'a mut self, target: Option<Target>, text: &str, tag: Tag) -> Element<'a, Message> {
+        let (label, placeholder) = match target {
+            Some(Target::Node(_)) => ("Note", "Add a note"),
+            Some(Target::Frame(_)) => ("Frame", "Title"),
+            None => return Row::new().into(),
+        };
+
+        let input = TextInput::new(&mut self.input, placeholder, text, Message::Note)
+            .padding(4)
+            .size(12)
+            .width(iced_baseview::Length::Units(180));
+        let tags = PickList::new(&mut self.tags, &Tag::ALL[..], Some(tag), Message::Tag)
+            .text_size(12);
+
+        Row::new()
+            .spacing(6)
+            .align_items(Align::Center)
+            .push(Text::new(label).size(12))
+            .push(input)
+            .push(tags)
+            .into()
+    }
+}
@@ -0,0 +1,126 @@
+use iced_baseview::{
+    Column, Element, Row, Container, Length, Text, Scrollable, TextInput,
+    HorizontalAlignment,
+};
+use iced_native::{button, scrollable, text_input, Button};
+
+use ag::PortRegistry;
+
+use super::modules::ModuleType;
+use super::{style, Message};
+
+/// The searchable list of module types shown when adding a node.
+pub struct Palette {
+    request: ag::PaletteRequest,
+    query: String,
+    search: text_input::State,
+    scroll: scrollable::State,
+    entries: Vec<button::State>,
+    cancel: button::State,
+}
+
+impl Palette {
+    pub fn new(request: ag::PaletteRequest, modules: &[ModuleType]) -> Self {
+        Self {
+            request,
+            query: String::new(),
+            search: text_input::State::focused(),
+            scroll: scrollable::State::new(),
+            entries: modules.iter().map(|_| button::State::new()).collect(),
+            cancel: button::State::new(),
+        }
+    }
+
+    pub fn request(&self) -> ag::PaletteRequest {
+        self.request
+    }
+
+    pub fn set_query(&mut self, query: String) {
+        self.query = query;
+    }
+
+    pub fn view<'a>(
+        &'a mut self,
+        modules: &'a [ModuleType],
+        port_types: &PortRegistry) -> Element<'a, Message> {
+        let search = TextInput::new(
+            &mut self.search,
+            "Search modules",
+            &self.query,
+            Message::PaletteQuery)
+            .padding(5)
+            .size(14);
+
+        let request = self.request;
+        let query = &self.query;
+
+        let mut entries: Vec<_> = self
+            .entries
+            .iter_mut()
+            .zip(modules.iter().enumerate())
+            .filter(|(_, (_, module))| accepts(&request, query, module, port_types))
+            .collect();
+
+        entries.sort_by(|(_, (_, a)), (_, (_, b))| {
+            (&a.category, &a.name).cmp(&(&b.category, &b.name))
+        });
+
+        let mut category: Option<&str> = None;
+        let list = entries
+            .into_iter()
+            .fold(Scrollable::new(&mut self.scroll).spacing(2), |list, (state, (index, module))| {
+                let list = if category != Some(module.category.as_str()) {
+                    category = Some(module.category.as_str());
+                    list.push(Text::new(&module.category).size(12))
+                } else {
+                    list
+                };
+
+                list.push(
+                    Button::new(state, Text::new(&module.name).size(14))
+                        .width(Length::Fill)
+                        .padding(4)
+                        .on_press(Message::PaletteSelect(index))
+                        .style(style::Button::Primary))
+            });
+
+        let cancel = Button::new(
+            &mut self.cancel,
+            Text::new("Cancel")
+                .width(Length::Fill)
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .size(14))
+            .width(Length::Fill)
+            .padding(4)
+            .on_press(Message::PaletteClose)
+            .style(style::Button::Destructive);
+
+        Container::new(
+            Column::new()
+                .spacing(8)
+                .push(search)
+                .push(list.height(Length::Fill))
+                .push(Row::new().push(cancel)))
+            .width(Length::Units(220))
+            .height(Length::Fill)
+            .padding(5)
+            .into()
+    }
+}
+
+/// Returns true if `module` should be listed for `query`, and can be connected
+/// to the cable the palette was opened from, if any.
+fn accepts(
+    request: &ag::PaletteRequest,
+    query: &str,
+    module: &ModuleType,
+    port_types: &PortRegistry) -> bool {
+    let compatible = match request.cable {
+        Some((_, socket)) => module
+            .port(socket.direction.opposite(), socket.port_type, port_types)
+            .is_some(),
+        None => true,
+    };
+
+    compatible && module.matches(query)
+}
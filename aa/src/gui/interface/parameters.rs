@@ -0,0 +1,296 @@
+use iced_baseview::{Align, Column, Element, Length, Row, Text};
+// Import iced_audio modules.
+use iced_audio::{
+    h_slider, knob, v_slider, xy_pad, HSlider, Knob, Normal, VSlider, XYPad,
+};
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::Message;
+use crate::library::{ParameterSpec, Range, Unit, WidgetHint};
+use crate::sync::SyncState;
+
+/// The number of controls placed side by side in a panel.
+const CONTROLS_PER_ROW: usize = 3;
+
+/// The widget state for a parameter.
+#[derive(Debug, Clone)]
+enum Control {
+    Knob(knob::State),
+    HSlider(h_slider::State),
+    VSlider(v_slider::State),
+    XYPad(xy_pad::State),
+    /// The y axis of the preceding [`Control::XYPad`].
+    Paired,
+}
+
+#[derive(Debug, Clone)]
+struct Parameter {
+    spec: ParameterSpec,
+    range: Range,
+    control: Control,
+    value: f64,
+}
+
+/// The controls for all parameters of a node, generated from the parameters
+/// declared by its module.
+///
+/// Widgets reset to the parameter's default when double clicked.
+#[derive(Debug, Clone)]
+pub struct Panel {
+    parameters: Vec<Parameter>,
+}
+
+impl Panel {
+    /// Creates a [`Panel`] for `specs`, starting from the given values and
+    /// adding the default for any parameter without one.
+    pub fn new(specs: &[ParameterSpec], values: &mut BTreeMap<String, f64>) -> Self {
+        let mut parameters: Vec<Parameter> = specs
+            .iter()
+            .map(|spec| {
+                let range = Range::new(spec);
+                let value = *values.entry(spec.name.clone()).or_insert(spec.default);
+                Parameter {
+                    spec: spec.clone(),
+                    range,
+                    control: Control::Paired,
+                    value,
+                }
+            })
+            .collect();
+
+        let mut index = 0;
+        while index < parameters.len() {
+            let normal = |p: &Parameter| p.range.normal_param(p.value, p.spec.default);
+            let x = normal(&parameters[index]);
+
+            let control = match parameters[index].spec.hint {
+                Some(WidgetHint::XYPad) if index + 1 < parameters.len() => {
+                    let y = normal(&parameters[index + 1]);
+                    index += 1;
+                    Control::XYPad(xy_pad::State::new(x, y))
+                }
+                Some(WidgetHint::HSlider) => Control::HSlider(h_slider::State::new(x)),
+                Some(WidgetHint::VSlider) => Control::VSlider(v_slider::State::new(x)),
+                _ => Control::Knob(knob::State::new(x)),
+            };
+
+            // an XY pad moved index on to its y axis, which stays Paired
+            let owner = if let Control::XYPad(_) = control { index - 1 } else { index };
+            parameters[owner].control = control;
+            index += 1;
+        }
+
+        Self { parameters }
+    }
+
+    /// Returns true if there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    /// Records a new normal for parameter `index`, returning its name and
+    /// value.
+    pub fn set(&mut self, index: usize, normal: Normal) -> Option<(String, f64)> {
+        let parameter = self.parameters.get_mut(index)?;
+        parameter.value = parameter.range.unmap(normal);
+        Some((parameter.spec.name.clone(), parameter.value))
+    }
+
+    /// Returns the number of parameters.
+    pub fn len(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// Returns the name and formatted value of every parameter.
+    pub fn values(&self) -> Vec<(String, String)> {
+        self.parameters
+            .iter()
+            .map(|p| (p.spec.name.clone(), format_value(p.value, p.spec.unit)))
+            .collect()
+    }
+
+    /// Returns the current normal of parameter `index`.
+    pub fn normal(&self, index: usize) -> Option<Normal> {
+        let parameter = self.parameters.get(index)?;
+        Some(parameter.range.normal_param(parameter.value, parameter.spec.default).value)
+    }
+
+    /// Moves the widget for parameter `index` to `normal`, for changes that
+    /// did not come from the widget itself, such as host automation.
+    /// Returns the parameter's name and new value.
+    pub fn move_to(&mut self, index: usize, normal: Normal) -> Option<(String, f64)> {
+        match self.parameters.get_mut(index)?.control {
+            Control::Knob(ref mut state) => state.normal_param.value = normal,
+            Control::HSlider(ref mut state) => state.normal_param.value = normal,
+            Control::VSlider(ref mut state) => state.normal_param.value = normal,
+            Control::XYPad(ref mut state) => state.normal_param_x.value = normal,
+            Control::Paired => {
+                if let Some(Control::XYPad(state)) = index
+                    .checked_sub(1)
+                    .and_then(|x| self.parameters.get_mut(x))
+                    .map(|p| &mut p.control)
+                {
+                    state.normal_param_y.value = normal;
+                }
+            }
+        }
+
+        self.set(index, normal)
+    }
+
+    /// Assigns a host parameter slot in `sync` to each parameter of `node`
+    /// that does not have one yet, naming it after the node's kind and id.
+    /// Returns true if any was bound.
+    pub fn bind(
+        &self,
+        node: ag::Node,
+        (kind, id): (&str, usize),
+        slots: &mut Slots,
+        sync: &SyncState) -> bool {
+        let mut bound = false;
+        for index in 0..self.len() {
+            if slots.slot(node, index).is_some() {
+                continue;
+            }
+
+            let normal = self.normal(index).map_or(0.0, |n| n.as_f32());
+            let name = format!("{} {} {}", kind, id, self.parameters[index].spec.name);
+            if let Some(slot) = sync.assign(node.id(), index, &name, normal) {
+                slots.insert(slot, node, index);
+                bound = true;
+            }
+        }
+        bound
+    }
+
+    pub fn view(&mut self, node: ag::Node) -> Element<'_, Message> {
+        let mut controls: Vec<Element<'_, Message>> = Vec::new();
+        let mut y_axis: Option<(String, String)> = None;
+
+        // y axis labels are shown under their XY pad, so visit in reverse
+        for (index, parameter) in self.parameters.iter_mut().enumerate().rev() {
+            let name = parameter.spec.name.clone();
+            let readout = format_value(parameter.value, parameter.spec.unit);
+
+            let widget: Element<'_, Message> = match &mut parameter.control {
+                Control::Paired => {
+                    y_axis = Some((name, readout));
+                    continue;
+                }
+                Control::Knob(state) => Knob::new(
+                    state,
+                    move |normal| Message::Parameter(node, index, normal))
+                    .size(Length::Units(40))
+                    .into(),
+                Control::HSlider(state) => HSlider::new(
+                    state,
+                    move |normal| Message::Parameter(node, index, normal))
+                    .width(Length::Units(80))
+                    .into(),
+                Control::VSlider(state) => VSlider::new(
+                    state,
+                    move |normal| Message::Parameter(node, index, normal))
+                    .height(Length::Units(80))
+                    .into(),
+                Control::XYPad(state) => XYPad::new(
+                    state,
+                    move |x, y| Message::XYParameter(node, index, x, y))
+                    .size(Length::Units(80))
+                    .into(),
+            };
+
+            let (name, readout) = match y_axis.take() {
+                Some((y_name, y_readout)) => (
+                    format!("{} / {}", name, y_name),
+                    format!("{} / {}", readout, y_readout)),
+                None => (name, readout),
+            };
+
+            controls.push(
+                Column::new()
+                    .spacing(2)
+                    .align_items(Align::Center)
+                    .push(Text::new(name).size(12))
+                    .push(widget)
+                    .push(Text::new(readout).size(12))
+                    .into());
+        }
+
+        controls.reverse();
+
+        let mut rows = Column::new().spacing(8);
+        let mut controls = controls.into_iter().peekable();
+        while controls.peek().is_some() {
+            let row = controls
+                .by_ref()
+                .take(CONTROLS_PER_ROW)
+                .fold(Row::new().spacing(8), |row, control| row.push(control));
+            rows = rows.push(row);
+        }
+
+        rows.into()
+    }
+}
+
+/// The host parameter slot bound to each node parameter.
+#[derive(Debug, Clone, Default)]
+pub struct Slots {
+    owners: HashMap<usize, (ag::Node, usize)>,
+    slots: HashMap<(ag::Node, usize), usize>,
+}
+
+impl Slots {
+    pub fn insert(&mut self, slot: usize, node: ag::Node, index: usize) {
+        self.owners.insert(slot, (node, index));
+        self.slots.insert((node, index), slot);
+    }
+
+    /// Returns the slot bound to parameter `index` of `node`.
+    pub fn slot(&self, node: ag::Node, index: usize) -> Option<usize> {
+        self.slots.get(&(node, index)).copied()
+    }
+
+    /// Returns the node parameter bound to `slot`.
+    pub fn owner(&self, slot: usize) -> Option<(ag::Node, usize)> {
+        self.owners.get(&slot).copied()
+    }
+
+    /// Removes the slots of every node parameter for which `keep` returns
+    /// false, returning them.
+    pub fn retain(&mut self, mut keep: impl FnMut(ag::Node, usize) -> bool) -> Vec<usize> {
+        let released: Vec<usize> = self
+            .owners
+            .iter()
+            .filter(|(_, (node, index))| !keep(*node, *index))
+            .map(|(slot, _)| *slot)
+            .collect();
+
+        for slot in &released {
+            if let Some(owner) = self.owners.remove(slot) {
+                self.slots.remove(&owner);
+            }
+        }
+
+        released
+    }
+
+    /// Returns every bound slot with its node parameter.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, ag::Node, usize)> + '_ {
+        self.owners.iter().map(|(slot, (node, index))| (*slot, *node, *index))
+    }
+}
+
+/// Formats a parameter value for display with its unit.
+fn format_value(value: f64, unit: Unit) -> String {
+    match unit {
+        Unit::None => format!("{:.2}", value),
+        Unit::Db => format!("{:.1} dB", value),
+        Unit::Hz if value >= 1000.0 => format!("{:.2} kHz", value / 1000.0),
+        Unit::Hz => format!("{:.1} Hz", value),
+        Unit::Percent => format!("{:.0} %", value),
+        Unit::Seconds => format!("{:.2} s", value),
+        Unit::Milliseconds => format!("{:.0} ms", value),
+        Unit::Integer => format!("{:.0}", value),
+    }
+}
@@ -0,0 +1,311 @@
+use iced_baseview::{Align, Column, Container, Element, Length, Row, Text};
+use iced_audio::{h_slider, FloatRange, HSlider, Normal};
+use iced_graphics::canvas::{Canvas, Cursor, Frame, Geometry, Path, Program, Stroke};
+use iced_native::{button, Button, Color, Point, Rectangle};
+
+use std::f32::consts::PI;
+
+use super::{style, Message};
+use crate::probes;
+
+/// The number of samples shown by the scope.
+const SCOPE_LENGTH: usize = 1024;
+
+/// The number of samples analysed for the spectrum. A power of two.
+const FFT_LENGTH: usize = 2048;
+
+/// The range of the spectrum's axes.
+const SPECTRUM_FLOOR_DB: f32 = -100.0;
+const SPECTRUM_MIN_HZ: f32 = 20.0;
+
+const PLOT_HEIGHT: u16 = 120;
+const PANEL_WIDTH: u16 = 300;
+
+/// What a probe shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Scope,
+    Spectrum,
+}
+
+/// A change made in a probe's panel.
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    Show(Mode),
+    Freeze,
+    LogAxis,
+    Trigger(Normal),
+    Close,
+}
+
+/// A panel showing the signal on an edge, recorded by a probe on the audio
+/// thread. Panels float over the editor, see [`Floating`].
+///
+/// [`Floating`]: super::floating::Floating
+pub struct Probe {
+    edge: ag::Edge,
+    index: usize,
+    mode: Mode,
+    frozen: bool,
+    log_axis: bool,
+    trigger_range: FloatRange,
+    trigger: h_slider::State,
+    // the latest samples read, oldest first
+    samples: Vec<f32>,
+    scope: button::State,
+    spectrum: button::State,
+    freeze: button::State,
+    axis: button::State,
+    close: button::State,
+}
+
+impl Probe {
+    /// Creates the panel for probe `index` of the bank, attached to `edge`.
+    pub fn new(edge: ag::Edge, index: usize) -> Self {
+        let trigger_range = FloatRange::new(-1.0, 1.0);
+
+        Self {
+            edge,
+            index,
+            mode: Mode::Scope,
+            frozen: false,
+            log_axis: true,
+            trigger_range,
+            trigger: h_slider::State::new(trigger_range.normal_param(0.0, 0.0)),
+            samples: vec![0.0; FFT_LENGTH],
+            scope: button::State::new(),
+            spectrum: button::State::new(),
+            freeze: button::State::new(),
+            axis: button::State::new(),
+            close: button::State::new(),
+        }
+    }
+
+    pub fn edge(&self) -> ag::Edge {
+        self.edge
+    }
+
+    /// Returns the index of the probe in the bank.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Applies a change made in the panel. Returns false if the panel was
+    /// closed.
+    pub fn apply(&mut self, event: Event) -> bool {
+        match event {
+            Event::Show(mode) => self.mode = mode,
+            Event::Freeze => self.frozen = !self.frozen,
+            Event::LogAxis => self.log_axis = !self.log_axis,
+            // the slider keeps its own position
+            Event::Trigger(_) => {}
+            Event::Close => return false,
+        }
+        true
+    }
+
+    /// Reads the latest samples, unless frozen.
+    pub fn update(&mut self, probe: &probes::Probe) {
+        if !self.frozen {
+            probe.read(&mut self.samples);
+        }
+    }
+
+    pub fn view(&mut self, id: usize, title: String, sample_rate: f32) -> Element<'_, Message> {
+        let level = self.trigger_range.unmap_to_value(self.trigger.normal_param.value);
+
+        let plot = match self.mode {
+            Mode::Scope => scope(&self.samples, level),
+            Mode::Spectrum => spectrum(&self.samples, sample_rate, self.log_axis),
+        };
+
+        let button = |state, label: &str, event, selected: bool| {
+            Button::new(state, Text::new(label).size(12))
+                .padding(3)
+                .on_press(Message::Probe(id, event))
+                .style(if selected {
+                    style::Button::Primary
+                } else {
+                    style::Button::Destructive
+                })
+        };
+
+        let mode = self.mode;
+        let buttons = Row::new()
+            .spacing(4)
+            .align_items(Align::Center)
+            .push(Text::new(title).size(12).width(Length::Fill))
+            .push(button(&mut self.scope, "Scope", Event::Show(Mode::Scope), mode == Mode::Scope))
+            .push(button(&mut self.spectrum, "FFT", Event::Show(Mode::Spectrum), mode == Mode::Spectrum))
+            .push(button(&mut self.freeze, "Freeze", Event::Freeze, self.frozen))
+            .push(button(&mut self.axis, "Log", Event::LogAxis, self.log_axis))
+            .push(button(&mut self.close, "X", Event::Close, false));
+
+        let controls = match mode {
+            Mode::Scope => Row::new()
+                .spacing(8)
+                .align_items(Align::Center)
+                .push(Text::new(format!("Trigger {:+.2}", level)).size(12))
+                .push(HSlider::new(&mut self.trigger, move |normal| {
+                    Message::Probe(id, Event::Trigger(normal))
+                })),
+            Mode::Spectrum => Row::new(),
+        };
+
+        Container::new(
+            Column::new()
+                .spacing(4)
+                .push(buttons)
+                .push(
+                    Canvas::new(plot)
+                        .width(Length::Fill)
+                        .height(Length::Units(PLOT_HEIGHT)))
+                .push(controls))
+            .width(Length::Units(PANEL_WIDTH))
+            .padding(5)
+            .style(style::Floating)
+            .into()
+    }
+}
+
+/// A line through points given as positions within the plot, `(0, 0)` being
+/// the bottom left, with an optional horizontal marker.
+struct Plot {
+    points: Vec<Point>,
+    marker: Option<f32>,
+}
+
+impl Program<Message> for Plot {
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let to_frame = |p: &Point| Point::new(p.x * bounds.width, (1.0 - p.y) * bounds.height);
+
+        frame.fill(&Path::rectangle(Point::ORIGIN, bounds.size()), Color::from_rgb(0.1, 0.1, 0.1));
+
+        if let Some(marker) = self.marker {
+            let y = (1.0 - marker) * bounds.height;
+            frame.stroke(
+                &Path::line(Point::new(0.0, y), Point::new(bounds.width, y)),
+                Stroke::default().with_width(1.0).with_color(Color::from_rgb(0.5, 0.3, 0.3)));
+        }
+
+        let line = Path::new(|path| {
+            let mut points = self.points.iter().map(to_frame);
+            if let Some(first) = points.next() {
+                path.move_to(first);
+                for point in points {
+                    path.line_to(point);
+                }
+            }
+        });
+        frame.stroke(&line, Stroke::default().with_width(1.0).with_color(Color::from_rgb(0.3, 0.9, 0.5)));
+
+        vec![frame.into_geometry()]
+    }
+}
+
+/// The waveform, starting at the first rising crossing of the trigger `level`
+/// if there is one, otherwise free running.
+fn scope(samples: &[f32], level: f32) -> Plot {
+    let length = SCOPE_LENGTH.min(samples.len());
+    let latest = samples.len() - length;
+
+    let start = (1..=latest)
+        .find(|&i| samples[i - 1] < level && samples[i] >= level)
+        .unwrap_or(latest);
+
+    let points = samples[start..start + length]
+        .iter()
+        .enumerate()
+        .map(|(i, s)| Point::new(i as f32 / length as f32, (s.max(-1.0).min(1.0) + 1.0) / 2.0))
+        .collect();
+
+    Plot {
+        points,
+        marker: Some((level + 1.0) / 2.0),
+    }
+}
+
+/// The magnitude spectrum in dB of the latest samples, with a logarithmic or
+/// linear frequency axis.
+fn spectrum(samples: &[f32], sample_rate: f32, log_axis: bool) -> Plot {
+    let n = FFT_LENGTH;
+    if samples.len() < n {
+        return Plot { points: Vec::new(), marker: None };
+    }
+    let samples = &samples[samples.len() - n..];
+
+    // Hann window
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, s)| s * (0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; n];
+    fft(&mut re, &mut im);
+
+    let nyquist = sample_rate / 2.0;
+    let bin_hz = sample_rate / n as f32;
+    // the window halves the amplitude of a sinusoid, on top of the FFT's n/2
+    let scale = 4.0 / n as f32;
+
+    let points = (1..n / 2)
+        .filter_map(|bin| {
+            let hz = bin as f32 * bin_hz;
+            let x = if log_axis {
+                if hz < SPECTRUM_MIN_HZ {
+                    return None;
+                }
+                (hz / SPECTRUM_MIN_HZ).ln() / (nyquist / SPECTRUM_MIN_HZ).ln()
+            } else {
+                hz / nyquist
+            };
+
+            let magnitude = (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() * scale;
+            let db = if magnitude > 0.0 { 20.0 * magnitude.log10() } else { SPECTRUM_FLOOR_DB };
+            let y = ((db - SPECTRUM_FLOOR_DB) / -SPECTRUM_FLOOR_DB).max(0.0).min(1.0);
+
+            Some(Point::new(x, y))
+        })
+        .collect();
+
+    Plot { points, marker: None }
+}
+
+/// An in place, iterative radix-2 FFT. The length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    // bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f32;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + length / 2);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        length <<= 1;
+    }
+}
@@ -1,39 +1,110 @@
-use baseview::{Parent, Size, WindowOpenOptions, WindowScalePolicy};
+use baseview::{Parent, Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
 use iced_baseview::{settings, Runner, Settings };
 
 use vst::editor::Editor;
 use raw_window_handle::RawWindowHandle;
 
-use crate::constants::{PLUGIN_NAME, GUI_WIDTH, GUI_HEIGHT};
+use crate::constants::PLUGIN_NAME;
+use crate::sync::SyncState;
+
+use std::sync::Arc;
 
 pub mod interface;
+pub mod window;
+
+use interface::{new_session, AAIcedApplication, Flags, Session, SharedSession};
+use window::WindowState;
+
+/// The window the host opened the editor in.
+#[derive(Clone, Copy)]
+struct ParentWindow(*mut ::core::ffi::c_void);
 
-use interface::AAIcedApplication;
+// only used to reopen the editor in the same window, from the host's thread
+unsafe impl Send for ParentWindow {}
 
 pub struct Gui {
-    opened: bool,
+    // the open window, if any, closed when dropped
+    handle: Option<WindowHandle>,
+    parent: Option<ParentWindow>,
+    sync_handle: Arc<SyncState>,
+    session: SharedSession,
+    window: Arc<WindowState>,
 }
 
 impl Gui {
-    pub fn new() -> Self {
+    pub fn new(sync_handle: Arc<SyncState>) -> Self {
+        // patches the host restores are loaded while the editor is closed
+        let session = new_session(&sync_handle);
+        sync_handle.set_loader(session.clone());
         Self {
-            opened: false,
+            handle: None,
+            parent: None,
+            session,
+            window: sync_handle.window.clone(),
+            sync_handle,
         }
     }
 
-    #[cfg(feature = "standalone")]
-    pub fn app_run(&mut self) {
-        let parent = Parent::None;
+    /// Returns a closed editor for the same plugin instance, which opens
+    /// with the same graph, size and scale as this one.
+    pub fn share(&self) -> Self {
+        Self {
+            handle: None,
+            parent: None,
+            sync_handle: self.sync_handle.clone(),
+            session: self.session.clone(),
+            window: self.window.clone(),
+        }
+    }
+
+    fn settings(&self, parent: Parent, session: Session) -> Settings<Flags> {
+        let (width, height) = self.window.size();
+        let scale = match self.window.scale().factor() {
+            Some(factor) => WindowScalePolicy::ScaleFactor(factor),
+            None => WindowScalePolicy::SystemScaleFactor,
+        };
 
-        let settings = Settings {
+        Settings {
             window: WindowOpenOptions {
                 parent,
-                size: Size::new(GUI_WIDTH as f64, GUI_HEIGHT as f64),
-                scale: WindowScalePolicy::SystemScaleFactor,
+                size: Size::new(f64::from(width), f64::from(height)),
+                scale,
                 title: PLUGIN_NAME.to_string(),
             },
-            flags: (),
+            flags: Flags {
+                sync_handle: self.sync_handle.clone(),
+                session,
+                shared_session: self.session.clone(),
+                window: self.window.clone(),
+            },
+        }
+    }
+
+    /// Opens the window in the parent the host gave, if the session is free.
+    fn open_window(&mut self) {
+        let parent = match self.parent {
+            Some(ParentWindow(parent)) => parent,
+            None => return,
+        };
+        let session = match self.session.take() {
+            Some(session) => session,
+            None => return,
+        };
+
+        let settings = self.settings(
+            Parent::WithParent(raw_window_handle_from_parent(parent)),
+            session);
+        let (handle, _) = Runner::<AAIcedApplication>::open(settings);
+        self.handle = Some(handle);
+    }
+
+    #[cfg(feature = "standalone")]
+    pub fn app_run(&mut self) {
+        let session = match self.session.take() {
+            Some(session) => session,
+            None => return,
         };
+        let settings = self.settings(Parent::None, session);
 
         let (_, opt_runner) = Runner::<AAIcedApplication>::open(settings);
 
@@ -43,43 +114,61 @@ impl Gui {
 
 impl Editor for Gui {
     fn size(&self) -> (i32, i32) {
-        (GUI_WIDTH as i32, GUI_HEIGHT as i32)
+        let (width, height) = self.window.physical_size();
+        (width as i32, height as i32)
     }
 
     fn position(&self) -> (i32, i32) {
         (0, 0)
     }
 
+    /// Opens the editor in the parent window, or defers it to
+    /// [`Editor::idle`] until a window that is closing hands back the
+    /// session, so as never to block the host.
     fn open(&mut self, parent: *mut ::core::ffi::c_void) -> bool {
-        if self.opened {
-            return false;
-        }
+        // some hosts open the editor again, possibly in a new parent, without
+        // closing it first
+        self.close();
+        self.parent = Some(ParentWindow(parent));
+        self.open_window();
 
-        let parent = Parent::WithParent(
-            raw_window_handle_from_parent(parent)
-        );
+        true
+    }
 
-        let settings = Settings {
-            window: WindowOpenOptions {
-                parent,
-                size: Size::new(GUI_WIDTH as f64, GUI_HEIGHT as f64),
-                scale: WindowScalePolicy::SystemScaleFactor,
-                title: PLUGIN_NAME.to_string(),
-            },
-            flags: (),
-        };
+    fn close(&mut self) {
+        self.parent = None;
+        if let Some(mut handle) = self.handle.take() {
+            handle.close();
+        }
+    }
 
-        Runner::<AAIcedApplication>::open(settings);
+    /// Opens a window that had to wait for its session. Then applies a size
+    /// or scale chosen in the editor by asking the host to resize its window
+    /// and reopening the editor in it, as baseview can neither resize nor
+    /// rescale a window once it is open.
+    fn idle(&mut self) {
+        if self.handle.is_none() {
+            self.open_window();
+        }
+        if !self.window.take_pending() {
+            return;
+        }
 
-        false
+        if let Some(ParentWindow(parent)) = self.parent {
+            let (width, height) = self.window.physical_size();
+            self.sync_handle.size_window(width, height);
+            self.open(parent);
+        }
     }
 
-    fn close(&mut self) {
-        self.opened = false;
+    fn is_open(&mut self) -> bool {
+        self.handle.is_some()
     }
+}
 
-    fn is_open(&mut self) -> bool {
-        self.opened
+impl Drop for Gui {
+    fn drop(&mut self) {
+        self.close();
     }
 }
 
@@ -0,0 +1,179 @@
+//! The size and scale of the editor window, shared between the editor and the
+//! host facing [`Gui`](super::Gui) so that both survive the window closing.
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::constants::{
+    GUI_HEIGHT, GUI_MAX_HEIGHT, GUI_MAX_WIDTH, GUI_MIN_HEIGHT, GUI_MIN_WIDTH, GUI_WIDTH,
+};
+
+/// A UI scale chosen by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    /// Follow the system scale factor.
+    System,
+    /// A fixed percentage, ignoring the system scale factor.
+    Percent(u32),
+}
+
+impl Scale {
+    /// The scales offered in the editor.
+    pub const ALL: [Scale; 7] = [
+        Scale::System,
+        Scale::Percent(75),
+        Scale::Percent(100),
+        Scale::Percent(125),
+        Scale::Percent(150),
+        Scale::Percent(175),
+        Scale::Percent(200),
+    ];
+
+    /// Returns the scale factor, or `None` to follow the system.
+    pub fn factor(self) -> Option<f64> {
+        match self {
+            Scale::System => None,
+            Scale::Percent(percent) => Some(f64::from(percent) / 100.0),
+        }
+    }
+
+    fn to_bits(self) -> u32 {
+        match self {
+            Scale::System => 0,
+            Scale::Percent(percent) => percent,
+        }
+    }
+
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0 => Scale::System,
+            percent => Scale::Percent(percent),
+        }
+    }
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Scale::System
+    }
+}
+
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scale::System => write!(f, "System"),
+            Scale::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+/// The size and scale of the editor window, as the host saves them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowPreset {
+    pub width: u32,
+    pub height: u32,
+    /// The scale in percent, or 0 to follow the system.
+    pub scale: u32,
+}
+
+/// The logical size and scale of the editor window.
+///
+/// The editor records resizes made by the user or the host here. Changes
+/// that need the window to be reopened, such as a new scale, are marked as
+/// pending and picked up by the [`Gui`](super::Gui) on the host's next idle
+/// call.
+#[derive(Debug)]
+pub struct WindowState {
+    width: AtomicU32,
+    height: AtomicU32,
+    scale: AtomicU32,
+    pending: AtomicBool,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            width: AtomicU32::new(GUI_WIDTH as u32),
+            height: AtomicU32::new(GUI_HEIGHT as u32),
+            scale: AtomicU32::new(Scale::default().to_bits()),
+            pending: AtomicBool::new(false),
+        }
+    }
+}
+
+impl WindowState {
+    /// Returns the logical size of the window.
+    pub fn size(&self) -> (u32, u32) {
+        (
+            self.width.load(Ordering::Relaxed),
+            self.height.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn scale(&self) -> Scale {
+        Scale::from_bits(self.scale.load(Ordering::Relaxed))
+    }
+
+    /// Returns the size of the window in pixels, as reported to the host.
+    /// Unknown for [`Scale::System`], which reports the logical size.
+    pub fn physical_size(&self) -> (u32, u32) {
+        let (width, height) = self.size();
+        let factor = self.scale().factor().unwrap_or(1.0);
+        (
+            (f64::from(width) * factor).round() as u32,
+            (f64::from(height) * factor).round() as u32,
+        )
+    }
+
+    /// Records a size the window already has, e.g. after the host resized
+    /// it.
+    pub fn resized(&self, width: u32, height: u32) {
+        let (width, height) = clamp(width, height);
+        self.width.store(width, Ordering::Relaxed);
+        self.height.store(height, Ordering::Relaxed);
+    }
+
+    /// Asks for the window to be resized to a new logical size.
+    pub fn request_size(&self, width: u32, height: u32) {
+        self.resized(width, height);
+        self.pending.store(true, Ordering::Release);
+    }
+
+    /// Asks for the window to be reopened at a new scale.
+    pub fn request_scale(&self, scale: Scale) {
+        self.scale.store(scale.to_bits(), Ordering::Relaxed);
+        self.pending.store(true, Ordering::Release);
+    }
+
+    /// Returns true, once, after a resize or new scale has been requested.
+    pub fn take_pending(&self) -> bool {
+        self.pending.swap(false, Ordering::Acquire)
+    }
+
+    pub fn preset(&self) -> WindowPreset {
+        let (width, height) = self.size();
+        WindowPreset {
+            width,
+            height,
+            scale: self.scale().to_bits(),
+        }
+    }
+
+    /// Restores a size and scale saved by the host, reopening the window if
+    /// it is open.
+    pub fn restore(&self, preset: WindowPreset) {
+        if preset != self.preset() {
+            self.scale.store(preset.scale, Ordering::Relaxed);
+            self.request_size(preset.width, preset.height);
+        }
+    }
+}
+
+fn clamp(width: u32, height: u32) -> (u32, u32) {
+    (
+        width.max(GUI_MIN_WIDTH as u32).min(GUI_MAX_WIDTH as u32),
+        height.max(GUI_MIN_HEIGHT as u32).min(GUI_MAX_HEIGHT as u32),
+    )
+}
@@ -3,7 +3,15 @@
 extern crate log;
 
 pub mod constants;
+pub mod engine;
+pub mod export;
 pub mod gui;
+pub mod library;
+pub mod meters;
+pub mod modes;
+pub mod nodes;
+pub mod probes;
+pub mod sync;
 
 use vst::api::{Supported, Events};
 use vst::editor::Editor;
@@ -12,27 +20,27 @@ use vst::plugin::{Category, Plugin, Info, CanDo, HostCallback, PluginParameters}
 use vst::host::Host;
 
 use constants::*;
+use engine::{Engine, Pool, MAX_BLOCK};
 use gui::Gui;
+use nodes::{MidiEvent, MAX_EVENTS};
+use sync::{SyncState, MASTER_PARAMETER, MAX_PARAMETERS};
 
-use std::f64::consts::PI;
-pub const TAU: f64 = PI * 2.0;
-
-fn midi_pitch_to_freq(pitch: u8) -> f64 {
-    const A4_PITCH: i8 = 69;
-    const A4_FREQ: f64 = 440.0;
-
-    // Midi notes can be 0-127
-    ((f64::from(pitch as i8 - A4_PITCH)) / 12.).exp2() * A4_FREQ
-}
+use iced_audio::LogDBRange;
 
+use std::sync::Arc;
 
 pub struct AA {
-    sample_rate: f64,
-    time: f64,
-    note_duration: f64,
-    note: Option<u8>,
+    // runs the graph set up in the editor
+    engine: Engine,
+    // MIDI received from the host for the next block
+    events: Vec<MidiEvent>,
 
-    editor: Option<Gui>,
+    // parameters shared with the editor and the host
+    sync: Arc<SyncState>,
+    master_range: LogDBRange,
+    master_gain: f32,
+
+    editor: Gui,
 }
 
 impl Default for AA {
@@ -42,38 +50,27 @@ impl Default for AA {
 }
 
 impl AA {
-    fn time_per_sample(&self) -> f64 {
-        1.0 / self.sample_rate
-    }
-
-    /// MIDI keyboard support
-
-    pub fn process_midi_event(&mut self, data: [u8; 3]) {
-        match data[0] {
-            128 => self.note_off(data[1]),
-            144 => self.note_on(data[1], data[2]),
-            _   => ()
-        }
-    }
-
-    fn note_on(&mut self, note: u8, velocity: u8) {
-        self.note_duration = 0.0;
-        self.note = Some(note)
-    }
-
-    fn note_off(&mut self, note: u8) {
-        if self.note == Some(note) {
-            self.note = None
-        }
+    /// Picks up parameters changed by the editor or the host since the last
+    /// block.
+    fn update_parameters(&mut self) {
+        let sync = &self.sync;
+        let range = &self.master_range;
+        let master_gain = &mut self.master_gain;
+        let engine = &mut self.engine;
+
+        sync.dsp_changes(|index, value| {
+            if index == MASTER_PARAMETER {
+                let db = range.unmap_to_value(value.into());
+                *master_gain = 10f32.powf(db / 20.0);
+            } else {
+                engine.set_slot(index, value);
+            }
+        });
     }
 
     #[cfg(feature = "standalone")]
     pub fn get_gui(&mut self) -> Option<Box<Gui>> {
-        if let Some(editor) = self.editor.take(){
-            Some(Box::new(editor) as Box<Gui>)
-        } else {
-            None
-        }
+        Some(Box::new(self.editor.share()))
     }
 }
 
@@ -82,58 +79,79 @@ impl Plugin for AA {
     fn new(host: HostCallback) -> Self {
         info!("Plugin::new()");
         
-        let editor = Gui::new();
+        let sync = Arc::new(SyncState::new(host));
+        let editor = Gui::new(sync.clone());
         
         Self {
-            sample_rate: 44100.0,
-            note_duration: 0.0,
-            time: 0.0,
-            note: None,
+            engine: Engine::new(sync.clone()).pool(Pool::shared(engine::workers())),
+            events: Vec::with_capacity(MAX_EVENTS),
+
+            sync,
+            master_range: LogDBRange::new(MASTER_DB_MIN, MASTER_DB_MAX, 0.5.into()),
+            master_gain: 1.0,
 
-            editor: Some(editor),    
+            editor,
         }
     }        
 
+    /// Keeps the MIDI for the next block, for the graph's MIDI inputs.
     fn process_events(&mut self, events: &Events) {
         for event in events.events() {
             if let Event::Midi(ev) = event {
-                self.process_midi_event(ev.data);
-            } 
+                if self.events.len() < self.events.capacity() {
+                    self.events.push(MidiEvent::new(ev.delta_frames.max(0) as usize, ev.data));
+                }
+            }
         }
     }
 
     fn process(&mut self, buffer: &mut vst::buffer::AudioBuffer<f32>) {
         info!("Plugin::process()");
 
+        self.update_parameters();
+        // an offline render runs as fast as the host can take it, usually
+        // alongside other plugins, so the workers would only compete with it
+        // for the cores
+        self.engine.set_parallel(!self.sync.is_offline());
+
         let samples = buffer.samples();
-        let (_, mut outputs) = buffer.split();
+        let (inputs, mut outputs) = buffer.split();
         let output_count = outputs.len();
-        let per_sample = self.time_per_sample();
-        let mut output_sample;
-        for sample_idx in 0..samples {
-            let time = self.time;
-            let note_duration = self.note_duration;
-            if let Some(current_note) = self.note {
-                let signal = (time * midi_pitch_to_freq(current_note) * TAU).sin();
-
-                // Apply a quick envelope to the attack of the signal to avoid popping.
-                let attack = 0.5;
-                let alpha = if note_duration < attack {
-                    note_duration / attack
-                } else {
-                    1.0
-                };
-
-                output_sample = (signal * alpha) as f32;
-
-                self.time += per_sample;
-                self.note_duration += per_sample;
-            } else {
-                output_sample = 0.0;
+
+        let mut start = 0;
+        while start < samples {
+            let len = (samples - start).min(MAX_BLOCK);
+
+            for channel in 0..inputs.len() {
+                if let Some(input) = self.engine.input(channel) {
+                    input[..len].copy_from_slice(&inputs.get(channel)[start..start + len]);
+                }
+            }
+
+            self.engine.process(start, len, &self.events);
+
+            for channel in 0..output_count {
+                let output = &mut outputs.get_mut(channel)[start..start + len];
+                match self.engine.output(channel) {
+                    Some(processed) => {
+                        for (sample, processed) in output.iter_mut().zip(processed) {
+                            *sample = processed * self.master_gain;
+                        }
+                    }
+                    None => output.iter_mut().for_each(|sample| *sample = 0.0),
+                }
             }
-            for buf_idx in 0..output_count {
-                let buff = outputs.get_mut(buf_idx);
-                buff[sample_idx] = output_sample;
+
+            start += len;
+        }
+        self.events.clear();
+
+        let meters = &self.sync.meters;
+        if meters.is_enabled() {
+            for channel in 0..output_count {
+                if let Some(meter) = meters.master(channel) {
+                    meter.measure(outputs.get(channel));
+                }
             }
         }
     }
@@ -148,9 +166,9 @@ impl Plugin for AA {
             inputs: PLUGIN_NUMBER_INPUTS,  
             outputs: PLUGIN_NUMBER_OUTPUTS, 
             presets: 0 as i32, // TODO: add support
-            parameters: 0 as i32,
-            initial_delay: 0,
-            preset_chunks: false,
+            parameters: MAX_PARAMETERS as i32,
+            initial_delay: self.sync.latency() as i32,
+            preset_chunks: true,
             f64_precision: false,
             ..Info::default()
         }
@@ -181,6 +199,16 @@ impl Plugin for AA {
     }
 
     fn set_sample_rate(&mut self, rate: f32) {
+        self.sync.set_sample_rate(rate);
+        self.engine.set_sample_rate(rate);
+    }
+
+    fn resume(&mut self) {
+        self.engine.reset();
+    }
+
+    fn get_tail_size(&self) -> isize {
+        self.engine.tail() as isize
     }
 
     fn can_do(&self, can_do: CanDo) -> Supported {
@@ -191,12 +219,14 @@ impl Plugin for AA {
         }
     }
     
+    fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+        self.sync.clone()
+    }
+
+    /// Each editor returned shares its state with the others, so hosts may
+    /// ask for one as often as they like.
     fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
-        if let Some(editor) = self.editor.take(){
-            Some(Box::new(editor) as Box<dyn Editor>)
-        } else {
-            None
-        }
+        Some(Box::new(self.editor.share()) as Box<dyn Editor>)
     }
 }
 
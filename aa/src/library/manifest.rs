@@ -0,0 +1,206 @@
+use serde_derive::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+
+/// The kind of signal carried by a module port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortKind {
+    Audio,
+    Stereo,
+    Midi,
+    Control,
+    Events,
+}
+
+impl PortKind {
+    /// Returns the type of port the graph editor shows for this kind.
+    pub fn port_type(self) -> ag::PortType {
+        match self {
+            PortKind::Audio => ag::PortType::AUDIO,
+            PortKind::Stereo => ag::PortType::AUDIO_STEREO,
+            PortKind::Midi => ag::PortType::MIDI,
+            PortKind::Control => ag::PortType::CONTROL,
+            PortKind::Events => ag::PortType::EVENTS,
+        }
+    }
+
+    /// Returns the number of buffers a port of this kind is processed as.
+    pub fn channels(self) -> usize {
+        match self {
+            PortKind::Stereo => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// A single input or output of a module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: PortKind,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The unit of the signal, e.g. "Hz" for a frequency CV.
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+impl PortSpec {
+    /// Returns the label shown for the port in the graph.
+    pub fn label(&self) -> ag::PortLabel {
+        let label = ag::PortLabel::new(&self.name);
+        let label = match &self.description {
+            Some(description) => label.description(description),
+            None => label,
+        };
+        match &self.unit {
+            Some(unit) => label.unit(unit),
+            None => label,
+        }
+    }
+}
+
+/// The unit a parameter is measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    None,
+    Db,
+    Hz,
+    Percent,
+    Seconds,
+    Milliseconds,
+    /// A whole number, such as a count or an index.
+    Integer,
+}
+
+impl Default for Unit {
+    fn default() -> Self {
+        Unit::None
+    }
+}
+
+/// The widget a parameter would like to be shown with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WidgetHint {
+    Knob,
+    HSlider,
+    VSlider,
+    /// The first of two parameters shown together on an XY pad, the next
+    /// parameter in the manifest provides the y axis.
+    XYPad,
+}
+
+/// A parameter of a module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterSpec {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    #[serde(default)]
+    pub unit: Unit,
+    #[serde(default)]
+    pub hint: Option<WidgetHint>,
+}
+
+/// Hints for how the module's node should be displayed.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct GuiHints {
+    #[serde(default)]
+    pub width: Option<u16>,
+    #[serde(default)]
+    pub height: Option<u16>,
+}
+
+/// The JSON sidecar describing an Audio Anywhere module.
+///
+/// A module `foo.wasm` is described by `foo.json` in the same directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub category: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<PortSpec>,
+    #[serde(default)]
+    pub outputs: Vec<PortSpec>,
+    #[serde(default)]
+    pub parameters: Vec<ParameterSpec>,
+    #[serde(default)]
+    pub gui: GuiHints,
+}
+
+impl Manifest {
+    /// Checks the manifest is self consistent, returning a description of the
+    /// first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("module name is empty".to_string());
+        }
+
+        if self.category.trim().is_empty() {
+            return Err("module category is empty".to_string());
+        }
+
+        let mut names = HashSet::new();
+        for parameter in &self.parameters {
+            if !names.insert(parameter.name.as_str()) {
+                return Err(format!("parameter '{}' is declared twice", parameter.name));
+            }
+
+            if parameter.min >= parameter.max {
+                return Err(format!(
+                    "parameter '{}' has min {} not below max {}",
+                    parameter.name, parameter.min, parameter.max));
+            }
+
+            if parameter.default < parameter.min || parameter.default > parameter.max {
+                return Err(format!(
+                    "parameter '{}' default {} is outside {}..{}",
+                    parameter.name, parameter.default, parameter.min, parameter.max));
+            }
+
+            if parameter.unit == Unit::Hz && parameter.min <= 0.0 {
+                return Err(format!(
+                    "frequency parameter '{}' must have a positive min", parameter.name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(description: Option<&str>, unit: Option<&str>) -> PortSpec {
+        PortSpec {
+            name: "Cutoff".to_string(),
+            kind: PortKind::Control,
+            description: description.map(str::to_string),
+            unit: unit.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_port_label() {
+        assert_eq!(port(None, None).label(), ag::PortLabel::new("Cutoff"));
+
+        let label = port(Some("Where the filter starts"), Some("Hz")).label();
+        assert_eq!(label.name, "Cutoff");
+        assert_eq!(label.description.as_deref(), Some("Where the filter starts"));
+        assert_eq!(label.unit.as_deref(), Some("Hz"));
+        assert_eq!(label.short(), "Cuto");
+        assert_eq!(label.title(), "Cutoff (Hz)");
+
+        let label = port(None, Some("V")).label();
+        assert_eq!(label.description, None);
+        assert_eq!(label.title(), "Cutoff (V)");
+    }
+}
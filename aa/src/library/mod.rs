@@ -0,0 +1,363 @@
+//! Discovery of the Audio Anywhere modules available to the graph.
+//!
+//! Each module is a wasm binary with a JSON sidecar manifest of the same name,
+//! see [`Manifest`]. Directories are scanned with [`Library::scan`], which only
+//! re-reads files that changed since the previous scan. A module that does not
+//! compile, or lacks the exports and ports its manifest describes, is reported
+//! rather than listed.
+
+pub mod manifest;
+pub mod range;
+
+pub use manifest::*;
+pub use range::Range;
+
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::constants::MODULE_PATH_ENV;
+use crate::nodes;
+
+/// The first four bytes of every wasm binary.
+const WASM_MAGIC: &[u8; 4] = b"\0asm";
+
+/// A problem found while scanning for modules.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum LibraryError {
+    #[error("could not read {}: {message}", .path.display())]
+    Io { path: PathBuf, message: String },
+
+    #[error("invalid manifest {}: {message}", .path.display())]
+    Manifest { path: PathBuf, message: String },
+
+    #[error("module {} has no manifest, expected {}", .wasm.display(), .manifest.display())]
+    MissingManifest { wasm: PathBuf, manifest: PathBuf },
+
+    #[error("manifest {} has no module, expected {}", .manifest.display(), .wasm.display())]
+    MissingWasm { manifest: PathBuf, wasm: PathBuf },
+
+    #[error("{} is not a wasm module", .path.display())]
+    InvalidWasm { path: PathBuf },
+
+    #[error("module {} does not match its manifest: {message}", .path.display())]
+    Mismatch { path: PathBuf, message: String },
+
+    #[error("module '{name}' is defined by both {} and {}", .first.display(), .second.display())]
+    Duplicate { name: String, first: PathBuf, second: PathBuf },
+}
+
+impl LibraryError {
+    fn io(path: &Path, error: std::io::Error) -> Self {
+        LibraryError::Io {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A module found in the library.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub manifest: Manifest,
+    pub wasm: PathBuf,
+}
+
+/// A cached scan result for a single wasm file.
+#[derive(Debug, Clone)]
+struct Entry {
+    modified: (Option<SystemTime>, Option<SystemTime>),
+    result: Result<Module, LibraryError>,
+}
+
+/// The set of modules found in a list of directories.
+#[derive(Debug, Clone, Default)]
+pub struct Library {
+    dirs: Vec<PathBuf>,
+    cache: HashMap<PathBuf, Entry>,
+    modules: Vec<Module>,
+    errors: Vec<LibraryError>,
+}
+
+impl Library {
+    /// Creates a [`Library`] for the given directories. Nothing is read until
+    /// [`Library::scan`] is called.
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the directories listed in the module path environment variable.
+    pub fn default_dirs() -> Vec<PathBuf> {
+        std::env::var_os(MODULE_PATH_ENV)
+            .map(|paths| std::env::split_paths(&paths).collect())
+            .unwrap_or_default()
+    }
+
+    /// Scans all directories, returning the number of valid modules found.
+    ///
+    /// Results for files that have not changed since the last scan are reused.
+    pub fn scan(&mut self) -> usize {
+        let mut cache = HashMap::new();
+        let mut errors = Vec::new();
+
+        for dir in &self.dirs {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    errors.push(LibraryError::io(dir, e));
+                    continue;
+                }
+            };
+
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                match path.extension().and_then(|e| e.to_str()) {
+                    Some("wasm") => {
+                        let modified = (modified(&path), modified(&path.with_extension("json")));
+                        let entry = match self.cache.remove(&path) {
+                            Some(entry) if entry.modified == modified => entry,
+                            _ => Entry {
+                                modified,
+                                result: load_module(&path),
+                            },
+                        };
+                        cache.insert(path, entry);
+                    }
+                    Some("json") => {
+                        let wasm = path.with_extension("wasm");
+                        if !wasm.exists() {
+                            errors.push(LibraryError::MissingWasm {
+                                manifest: path,
+                                wasm,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.cache = cache;
+
+        let mut paths: Vec<_> = self.cache.keys().cloned().collect();
+        paths.sort();
+
+        let mut modules: Vec<Module> = Vec::new();
+        for path in paths {
+            match &self.cache[&path].result {
+                Ok(module) => {
+                    let name = &module.manifest.name;
+                    match modules.iter().find(|m| &m.manifest.name == name) {
+                        Some(first) => errors.push(LibraryError::Duplicate {
+                            name: name.clone(),
+                            first: first.wasm.clone(),
+                            second: module.wasm.clone(),
+                        }),
+                        None => modules.push(module.clone()),
+                    }
+                }
+                Err(e) => errors.push(e.clone()),
+            }
+        }
+
+        self.modules = modules;
+        self.errors = errors;
+        self.modules.len()
+    }
+
+    /// Returns the valid modules found by the last scan.
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    /// Returns the module with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&Module> {
+        self.modules.iter().find(|m| m.manifest.name == name)
+    }
+
+    /// Returns the problems found by the last scan.
+    pub fn errors(&self) -> &[LibraryError] {
+        &self.errors
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Reads a wasm module and its manifest, and checks that the module compiles
+/// and has the exports and ports the manifest describes.
+fn load_module(wasm: &Path) -> Result<Module, LibraryError> {
+    let manifest_path = wasm.with_extension("json");
+    if !manifest_path.exists() {
+        return Err(LibraryError::MissingManifest {
+            wasm: wasm.to_path_buf(),
+            manifest: manifest_path,
+        });
+    }
+
+    let text = fs::read_to_string(&manifest_path)
+        .map_err(|e| LibraryError::io(&manifest_path, e))?;
+    let manifest = parse_manifest(&text).map_err(|message| LibraryError::Manifest {
+        path: manifest_path.clone(),
+        message,
+    })?;
+
+    let bytes = fs::read(wasm).map_err(|e| LibraryError::io(wasm, e))?;
+    if !bytes.starts_with(WASM_MAGIC) {
+        return Err(LibraryError::InvalidWasm {
+            path: wasm.to_path_buf(),
+        });
+    }
+    nodes::wasm::validate(&bytes, &manifest).map_err(|e| LibraryError::Mismatch {
+        path: wasm.to_path_buf(),
+        message: format!("{:#}", e),
+    })?;
+
+    Ok(Module {
+        manifest,
+        wasm: wasm.to_path_buf(),
+    })
+}
+
+/// Parses and validates a manifest.
+pub fn parse_manifest(text: &str) -> Result<Manifest, String> {
+    let manifest: Manifest = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    const GAIN: &str = r#"{
+        "name": "Gain",
+        "category": "Utilities",
+        "inputs": [{ "name": "in", "type": "audio" }],
+        "outputs": [{ "name": "out", "type": "audio" }],
+        "parameters": [
+            { "name": "gain", "min": -60.0, "max": 12.0, "default": 0.0, "unit": "db", "hint": "knob" }
+        ]
+    }"#;
+
+    /// A module with the buffers for [`GAIN`], that leaves its output alone.
+    const GAIN_MODULE: &str = r#"(module
+        (memory (export "memory") 1)
+        (func (export "init") (param f32))
+        (func (export "get_input") (param i32) (result i32) (i32.const 1024))
+        (func (export "get_output") (param i32) (result i32) (i32.const 2048))
+        (func (export "set_param_float") (param i32 f32))
+        (func (export "compute") (param i32)))"#;
+
+    fn gain_module() -> Vec<u8> {
+        wat::parse_str(GAIN_MODULE).unwrap()
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = parse_manifest(GAIN).unwrap();
+        assert_eq!(manifest.name, "Gain");
+        assert_eq!(manifest.inputs[0].kind, PortKind::Audio);
+        assert_eq!(manifest.parameters[0].unit, Unit::Db);
+        assert_eq!(manifest.parameters[0].hint, Some(WidgetHint::Knob));
+    }
+
+    #[test]
+    fn test_manifest_default_out_of_range() {
+        let text = GAIN.replace(r#""default": 0.0"#, r#""default": 20.0"#);
+        assert!(parse_manifest(&text).is_err());
+    }
+
+    #[test]
+    fn test_scan() {
+        let dir = std::env::temp_dir().join(format!("aa_library_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        fs::write(dir.join("gain.wasm"), gain_module()).unwrap();
+        fs::write(dir.join("gain.json"), GAIN).unwrap();
+        fs::write(dir.join("broken.wasm"), b"not wasm").unwrap();
+        fs::write(dir.join("broken.json"), GAIN.replace("Gain", "Broken")).unwrap();
+        fs::write(dir.join("orphan.json"), GAIN).unwrap();
+
+        let mut library = Library::new(vec![dir.clone()]);
+        assert_eq!(library.scan(), 1);
+        assert!(library.get("Gain").is_some());
+        assert_eq!(library.errors().len(), 2);
+
+        // unchanged files come from the cache, even if their contents differ
+        let manifest = dir.join("gain.json");
+        let modified = fs::metadata(&manifest).unwrap().modified().unwrap();
+        let touch = |path: &Path, time| {
+            fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+        };
+        fs::write(&manifest, GAIN.replace("Gain", "Louder")).unwrap();
+        touch(&manifest, modified);
+        assert_eq!(library.scan(), 1);
+        assert!(library.get("Gain").is_some());
+        assert!(library.get("Louder").is_none());
+
+        // a changed manifest or module invalidates its entry
+        touch(&manifest, modified + Duration::from_secs(10));
+        fs::write(dir.join("broken.wasm"), gain_module()).unwrap();
+        touch(&dir.join("broken.wasm"), modified + Duration::from_secs(10));
+        assert_eq!(library.scan(), 2);
+        assert!(library.get("Gain").is_none());
+        assert!(library.get("Louder").is_some());
+        assert!(library.get("Broken").is_some());
+        assert_eq!(library.errors().len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scan_checks_modules() {
+        let dir = std::env::temp_dir().join(format!("aa_library_check_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let write = |name: &str, wasm: &[u8], manifest: String| {
+            fs::write(dir.join(name).with_extension("wasm"), wasm).unwrap();
+            fs::write(dir.join(name).with_extension("json"), manifest).unwrap();
+        };
+        // no exports at all
+        write("empty", b"\0asm\x01\0\0\0", GAIN.replace("Gain", "Empty"));
+        // a buffer past the end of memory
+        let outside = GAIN_MODULE.replace("(i32.const 2048)", "(i32.const 65536)");
+        write("outside", &wat::parse_str(outside).unwrap(), GAIN.replace("Gain", "Outside"));
+        // a MIDI input without notes
+        let synth = GAIN
+            .replace("Gain", "Synth")
+            .replace(r#"{ "name": "in", "type": "audio" }"#, r#"{ "name": "notes", "type": "midi" }"#);
+        write("synth", &gain_module(), synth);
+        // an optional export of the wrong type
+        let latency = GAIN_MODULE.replace(
+            "(func (export \"compute\")",
+            "(func (export \"get_latency\") (param i32)) (func (export \"compute\")");
+        write("latency", &wat::parse_str(latency).unwrap(), GAIN.replace("Gain", "Latency"));
+
+        let mut library = Library::new(vec![dir.clone()]);
+        assert_eq!(library.scan(), 0);
+        let mut mismatched: Vec<_> = library
+            .errors()
+            .iter()
+            .map(|error| match error {
+                LibraryError::Mismatch { path, .. } => path.file_stem().unwrap().to_str().unwrap(),
+                error => panic!("unexpected error {}", error),
+            })
+            .collect();
+        mismatched.sort_unstable();
+        assert_eq!(mismatched, vec!["empty", "latency", "outside", "synth"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
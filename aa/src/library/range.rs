@@ -0,0 +1,57 @@
+//! The mapping between a parameter's value, in its unit, and the normal in
+//! `0..=1` that widgets and the host work with.
+//!
+//! The editor uses it to drive its widgets and the audio thread to turn host
+//! automation back into values, so both agree on where a value sits.
+
+use iced_audio::{FloatRange, FreqRange, IntRange, LogDBRange, Normal, NormalParam};
+
+use super::{ParameterSpec, Unit};
+
+/// The mapping between a parameter's value and a widget's normal, chosen from
+/// the parameter's unit.
+#[derive(Debug, Clone, Copy)]
+pub enum Range {
+    Float(FloatRange),
+    Freq(FreqRange),
+    LogDB(LogDBRange),
+    Int(IntRange),
+}
+
+impl Range {
+    pub fn new(spec: &ParameterSpec) -> Self {
+        let (min, max) = (spec.min as f32, spec.max as f32);
+
+        match spec.unit {
+            Unit::Db => {
+                // place 0 dB where a linear range would, or centre it if the
+                // range does not include 0 dB
+                let zero = if min < 0.0 && max > 0.0 { -min / (max - min) } else { 0.5 };
+                Range::LogDB(LogDBRange::new(min, max, zero.into()))
+            }
+            Unit::Hz => Range::Freq(FreqRange::new(min, max)),
+            Unit::Integer => Range::Int(IntRange::new(
+                spec.min.round() as i32,
+                spec.max.round() as i32)),
+            _ => Range::Float(FloatRange::new(min, max)),
+        }
+    }
+
+    pub fn normal_param(&self, value: f64, default: f64) -> NormalParam {
+        match self {
+            Range::Float(range) => range.normal_param(value as f32, default as f32),
+            Range::Freq(range) => range.normal_param(value as f32, default as f32),
+            Range::LogDB(range) => range.normal_param(value as f32, default as f32),
+            Range::Int(range) => range.normal_param(value.round() as i32, default.round() as i32),
+        }
+    }
+
+    pub fn unmap(&self, normal: Normal) -> f64 {
+        match self {
+            Range::Float(range) => f64::from(range.unmap_to_value(normal)),
+            Range::Freq(range) => f64::from(range.unmap_to_value(normal)),
+            Range::LogDB(range) => f64::from(range.unmap_to_value(normal)),
+            Range::Int(range) => f64::from(range.unmap_to_value(normal)),
+        }
+    }
+}
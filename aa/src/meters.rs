@@ -0,0 +1,137 @@
+//! Signal levels measured on the audio thread for the editor's meters.
+//!
+//! Each [`Meter`] is a pair of atomics written once per block, so measuring
+//! never waits on the editor. The editor applies its own falloff and clip hold
+//! to what it reads.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::sync::AtomicF32;
+
+/// The number of node outputs that can be metered at once.
+pub const MAX_METERS: usize = 256;
+
+/// The number of channels metered on the master bus.
+pub const MASTER_CHANNELS: usize = 2;
+
+/// The level of a single signal, written by the audio thread and read by the
+/// editor.
+#[derive(Debug)]
+pub struct Meter {
+    // bits of the largest peak since the editor last looked
+    peak: AtomicU32,
+    // RMS of the latest block
+    rms: AtomicF32,
+}
+
+impl Meter {
+    fn new() -> Self {
+        Self {
+            peak: AtomicU32::new(0),
+            rms: AtomicF32::new(0.0),
+        }
+    }
+
+    /// Measures a block of samples. Called from the audio thread.
+    pub fn measure(&self, samples: &[f32]) {
+        self.measure_channels(std::iter::once(samples));
+    }
+
+    /// Measures a block of each channel of a port as one signal: the largest
+    /// peak of any channel, and the RMS over the samples of all of them.
+    /// Called from the audio thread.
+    pub fn measure_channels<'a>(&self, channels: impl IntoIterator<Item = &'a [f32]>) {
+        let (peak, sum, count) = channels
+            .into_iter()
+            .flatten()
+            .fold((0.0f32, 0.0f32, 0), |(peak, sum, count), s| (peak.max(s.abs()), sum + s * s, count + 1));
+        if count == 0 {
+            return;
+        }
+
+        // non-negative floats order the same way as their bits, so this keeps
+        // the largest peak until the editor takes it
+        self.peak.fetch_max(peak.to_bits(), Ordering::Relaxed);
+        self.rms.set((sum / count as f32).sqrt());
+    }
+
+    /// Returns the peak amplitude since the last call and the latest RMS.
+    /// Called from the editor.
+    pub fn take(&self) -> (f32, f32) {
+        (
+            f32::from_bits(self.peak.swap(0, Ordering::Relaxed)),
+            self.rms.get(),
+        )
+    }
+}
+
+/// The meters for the master bus and for node outputs.
+///
+/// Metering can be turned off, in which case the audio thread skips measuring
+/// altogether.
+#[derive(Debug)]
+pub struct MeterBank {
+    enabled: AtomicBool,
+    master: Vec<Meter>,
+    outputs: Vec<Meter>,
+}
+
+impl Default for MeterBank {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            master: (0..MASTER_CHANNELS).map(|_| Meter::new()).collect(),
+            outputs: (0..MAX_METERS).map(|_| Meter::new()).collect(),
+        }
+    }
+}
+
+impl MeterBank {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns the meter for a channel of the master bus.
+    pub fn master(&self, channel: usize) -> Option<&Meter> {
+        self.master.get(channel)
+    }
+
+    /// Returns the meter for a node output.
+    pub fn output(&self, index: usize) -> Option<&Meter> {
+        self.outputs.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_keeps_largest_peak() {
+        let meter = Meter::new();
+        meter.measure(&[0.5, -0.75, 0.25]);
+        meter.measure(&[0.1, -0.2]);
+
+        let (peak, rms) = meter.take();
+        assert_eq!(peak, 0.75);
+        assert!((rms - (0.05f32 / 2.0).sqrt()).abs() < 1e-6);
+
+        // the peak is reset once taken
+        assert_eq!(meter.take().0, 0.0);
+    }
+
+    #[test]
+    fn test_meter_measures_channels_together() {
+        let meter = Meter::new();
+        meter.measure_channels(vec![&[0.5, -0.5][..], &[0.0, 0.0][..]]);
+
+        // the silent channel neither hides the peak nor replaces the RMS
+        let (peak, rms) = meter.take();
+        assert_eq!(peak, 0.5);
+        assert!((rms - (0.5f32 / 4.0).sqrt()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,273 @@
+//! Bypass, mute and solo, as the audio thread sees them.
+//!
+//! The editor works out which nodes are bypassed and which are silenced,
+//! whether muted or left out of a solo, and publishes one flag byte per node,
+//! in the slot it gave the node with [`ModeSlots`].
+//! The audio thread reads them without waiting, and ramps between states with
+//! a [`Crossfade`] so that toggling a node never clicks.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The number of nodes whose mode can be published at once. Nodes given no
+/// slot are always processed as usual.
+pub const MAX_NODES: usize = 256;
+
+/// How long a change of mode takes to fade in, in seconds.
+pub const CROSSFADE_TIME: f32 = 0.01;
+
+const BYPASS: u8 = 1;
+const SILENCED: u8 = 2;
+
+/// How the audio thread is to process a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mode {
+    /// Inputs are passed straight through to outputs of the same type.
+    pub bypass: bool,
+    /// Outputs are silent, because the node is muted or outside every
+    /// soloed chain.
+    pub silenced: bool,
+}
+
+impl Mode {
+    fn to_bits(self) -> u8 {
+        (if self.bypass { BYPASS } else { 0 }) | (if self.silenced { SILENCED } else { 0 })
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        Self {
+            bypass: bits & BYPASS != 0,
+            silenced: bits & SILENCED != 0,
+        }
+    }
+}
+
+/// The mode of every node, written by the editor and read by the audio
+/// thread.
+#[derive(Debug)]
+pub struct ModeBank {
+    modes: Vec<AtomicU8>,
+}
+
+impl Default for ModeBank {
+    fn default() -> Self {
+        Self {
+            modes: (0..MAX_NODES).map(|_| AtomicU8::new(0)).collect(),
+        }
+    }
+}
+
+impl ModeBank {
+    /// Returns the mode published in `slot`.
+    pub fn get(&self, slot: usize) -> Mode {
+        self.modes
+            .get(slot)
+            .map_or_else(Mode::default, |bits| Mode::from_bits(bits.load(Ordering::Relaxed)))
+    }
+
+    /// Publishes the mode of the node given `slot`. Called from the editor.
+    pub fn set(&self, slot: usize, mode: Mode) {
+        if let Some(bits) = self.modes.get(slot) {
+            bits.store(mode.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// The slot in the [`ModeBank`] of each node, by id, kept by the editor.
+///
+/// Node ids are never reused, so the slots of nodes that are gone are given
+/// to new ones instead.
+#[derive(Debug, Clone, Default)]
+pub struct ModeSlots {
+    slots: HashMap<usize, usize>,
+}
+
+impl ModeSlots {
+    /// Returns the slot of the node with id `node`, if it has one.
+    pub fn slot(&self, node: usize) -> Option<usize> {
+        self.slots.get(&node).copied()
+    }
+
+    /// Gives slots to exactly the nodes in `nodes`, keeping the slot of any
+    /// node that already has one, while free slots last.
+    pub fn assign(&mut self, nodes: &[usize]) {
+        self.slots.retain(|node, _| nodes.contains(node));
+
+        let mut used: BTreeSet<usize> = self.slots.values().copied().collect();
+        for node in nodes {
+            if self.slots.contains_key(node) {
+                continue;
+            }
+
+            match (0..MAX_NODES).find(|slot| !used.contains(slot)) {
+                Some(slot) => {
+                    used.insert(slot);
+                    self.slots.insert(*node, slot);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// A gain moving linearly towards a target over [`CROSSFADE_TIME`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ramp {
+    value: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Ramp {
+    pub fn new(value: f32) -> Self {
+        Self {
+            value,
+            target: value,
+            step: 0.0,
+        }
+    }
+
+    /// Starts moving towards `target`, reaching it after [`CROSSFADE_TIME`]
+    /// at `sample_rate`.
+    pub fn set(&mut self, target: f32, sample_rate: f32) {
+        if target != self.target {
+            self.target = target;
+            self.step = (target - self.value).abs() / (CROSSFADE_TIME * sample_rate).max(1.0);
+        }
+    }
+
+    /// Returns true if the ramp has reached its target.
+    pub fn is_settled(&self) -> bool {
+        self.value == self.target
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Returns the gain for the next sample.
+    pub fn tick(&mut self) -> f32 {
+        if self.value < self.target {
+            self.value = (self.value + self.step).min(self.target);
+        } else if self.value > self.target {
+            self.value = (self.value - self.step).max(self.target);
+        }
+        self.value
+    }
+}
+
+/// Fades one node output between its processed signal, the input passed
+/// through when bypassed, and silence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossfade {
+    // 0 while processed, 1 while bypassed
+    bypass: Ramp,
+    // 1 while heard, 0 while silenced
+    level: Ramp,
+}
+
+impl Default for Crossfade {
+    fn default() -> Self {
+        Self {
+            bypass: Ramp::new(0.0),
+            level: Ramp::new(1.0),
+        }
+    }
+}
+
+impl Crossfade {
+    /// Starts fading towards `mode`.
+    pub fn set(&mut self, mode: Mode, sample_rate: f32) {
+        self.bypass.set(if mode.bypass { 1.0 } else { 0.0 }, sample_rate);
+        self.level.set(if mode.silenced { 0.0 } else { 1.0 }, sample_rate);
+    }
+
+    /// Returns true if the node's processed signal is not heard at all, so
+    /// the node need not be processed.
+    pub fn is_idle(&self) -> bool {
+        self.bypass.is_settled() && self.level.is_settled()
+            && (self.bypass.value() == 1.0 || self.level.value() == 0.0)
+    }
+
+    /// Mixes `output`, the processed block, with `input`, the block
+    /// arriving at the matching input, or silence if there is none.
+    pub fn apply(&mut self, output: &mut [f32], input: Option<&[f32]>) {
+        if self.bypass.is_settled() && self.level.is_settled()
+            && self.bypass.value() == 0.0 && self.level.value() == 1.0 {
+            return;
+        }
+
+        for (index, sample) in output.iter_mut().enumerate() {
+            let dry = input.and_then(|input| input.get(index)).copied().unwrap_or(0.0);
+            let bypass = self.bypass.tick();
+            let level = self.level.tick();
+            *sample = level * (*sample * (1.0 - bypass) + dry * bypass);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_bank() {
+        let bank = ModeBank::default();
+        let mode = Mode { bypass: true, silenced: false };
+        bank.set(3, mode);
+        bank.set(MAX_NODES, mode);
+
+        assert_eq!(bank.get(3), mode);
+        assert_eq!(bank.get(MAX_NODES), Mode::default());
+    }
+
+    #[test]
+    fn test_mode_slots_are_reused() {
+        let mut slots = ModeSlots::default();
+        slots.assign(&[1000, 1001]);
+        assert_eq!(slots.slot(1000), Some(0));
+        assert_eq!(slots.slot(1001), Some(1));
+
+        // a node that is gone gives up its slot, the others keep theirs
+        slots.assign(&[1001, 5000]);
+        assert_eq!(slots.slot(1000), None);
+        assert_eq!(slots.slot(1001), Some(1));
+        assert_eq!(slots.slot(5000), Some(0));
+
+        let nodes: Vec<usize> = (0..MAX_NODES + 1).collect();
+        slots.assign(&nodes);
+        assert_eq!(slots.slot(MAX_NODES - 1), Some(MAX_NODES - 1));
+        assert_eq!(slots.slot(MAX_NODES), None);
+    }
+
+    #[test]
+    fn test_crossfade_to_bypass() {
+        let mut fade = Crossfade::default();
+        // a fade of 4 samples
+        fade.set(Mode { bypass: true, silenced: false }, 4.0 / CROSSFADE_TIME);
+
+        let mut output = [1.0; 6];
+        fade.apply(&mut output, Some(&[0.0; 6]));
+        for (sample, expected) in output.iter().zip(&[0.75, 0.5, 0.25, 0.0, 0.0, 0.0]) {
+            assert!((sample - expected).abs() < 1e-6);
+        }
+        assert!(fade.is_idle());
+    }
+
+    #[test]
+    fn test_crossfade_to_silence_and_back() {
+        let mut fade = Crossfade::default();
+        let sample_rate = 2.0 / CROSSFADE_TIME;
+        fade.set(Mode { bypass: false, silenced: true }, sample_rate);
+
+        let mut output = [1.0; 3];
+        fade.apply(&mut output, None);
+        assert_eq!(output, [0.5, 0.0, 0.0]);
+
+        fade.set(Mode::default(), sample_rate);
+        let mut output = [1.0; 3];
+        fade.apply(&mut output, None);
+        assert_eq!(output, [0.5, 1.0, 1.0]);
+        assert!(!fade.is_idle());
+    }
+}
@@ -0,0 +1,292 @@
+//! Nodes that turn notes into control signals.
+
+use super::{manifest, parameter, pitch_to_freq, port, sample, Events, Processor};
+use crate::library::{Manifest, PortKind, Unit, WidgetHint};
+
+/// The most notes a [`MidiToFreq`] remembers being held, to return to when
+/// the latest is released.
+const HELD_NOTES: usize = 16;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const PITCH_BEND: u8 = 0xE0;
+
+/// Follows the latest note held, sending its frequency, a gate that is high
+/// while any note is held, and its velocity.
+#[derive(Debug, Clone, Copy)]
+pub struct MidiToFreq {
+    held: [u8; HELD_NOTES],
+    len: usize,
+    // the note followed, kept once released for the envelope's release
+    last: u8,
+    velocity: f32,
+    // in semitones, -1..1 of the bend range
+    bend: f32,
+}
+
+impl Default for MidiToFreq {
+    fn default() -> Self {
+        Self {
+            held: [0; HELD_NOTES],
+            len: 0,
+            last: 69,
+            velocity: 0.0,
+            bend: 0.0,
+        }
+    }
+}
+
+impl MidiToFreq {
+    pub const NAME: &'static str = "MIDI to Freq";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("MIDI", PortKind::Midi)],
+            outputs: vec![
+                port("Freq", PortKind::Control),
+                port("Gate", PortKind::Control),
+                port("Velocity", PortKind::Control),
+            ],
+            parameters: vec![
+                parameter("tuning", 400.0, 480.0, 440.0, Unit::Hz, WidgetHint::Knob),
+                parameter("transpose", -24.0, 24.0, 0.0, Unit::Integer, WidgetHint::Knob),
+                parameter("bend range", 0.0, 12.0, 2.0, Unit::Integer, WidgetHint::Knob),
+            ],
+            ..manifest(Self::NAME, "Control")
+        }
+    }
+
+    fn note_on(&mut self, note: u8, velocity: u8) {
+        self.note_off(note);
+        if self.len == HELD_NOTES {
+            self.held.copy_within(1.., 0);
+            self.len -= 1;
+        }
+        self.held[self.len] = note;
+        self.len += 1;
+        self.velocity = f32::from(velocity) / 127.0;
+    }
+
+    fn note_off(&mut self, note: u8) {
+        if let Some(index) = self.held[..self.len].iter().position(|held| *held == note) {
+            self.held.copy_within(index + 1..self.len, index);
+            self.len -= 1;
+        }
+    }
+
+    fn receive(&mut self, data: [u8; 3]) {
+        match data[0] & 0xF0 {
+            NOTE_ON if data[2] > 0 => self.note_on(data[1], data[2]),
+            NOTE_ON | NOTE_OFF => self.note_off(data[1]),
+            PITCH_BEND => {
+                let value = (i32::from(data[2]) << 7 | i32::from(data[1])) - 8192;
+                self.bend = value as f32 / 8192.0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the note followed, if any is held.
+    fn note(&self) -> Option<u8> {
+        self.held[..self.len].last().copied()
+    }
+}
+
+impl Processor for MidiToFreq {
+    describe!();
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn process(&mut self, _: &[&[f32]], outputs: &mut [&mut [f32]], events: &mut Events<'_>, params: &[f32]) {
+        let (tuning, transpose, bend_range) = (params[0], params[1], params[2]);
+        let input = events.input();
+        let mut next = 0;
+
+        for index in 0..outputs[0].len() {
+            while next < input.len() && input[next].offset <= index {
+                self.receive(input[next].data);
+                next += 1;
+            }
+
+            let note = self.note();
+            if let Some(note) = note {
+                self.last = note;
+            }
+            let pitch = f32::from(self.last) + transpose + self.bend * bend_range;
+            outputs[0][index] = pitch_to_freq(pitch, tuning);
+            outputs[1][index] = if note.is_some() { 1.0 } else { 0.0 };
+            outputs[2][index] = self.velocity;
+        }
+    }
+}
+
+/// The stage an [`Adsr`] is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// An attack, decay, sustain and release envelope, started while its gate is
+/// above a half.
+#[derive(Debug, Clone, Copy)]
+pub struct Adsr {
+    stage: Stage,
+    level: f32,
+    // per sample, fixed when the gate falls so that release takes as long
+    // from any level
+    release_step: f32,
+    // the release time last processed with, in samples
+    release: usize,
+    sample_rate: f32,
+}
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self {
+            stage: Stage::Idle,
+            level: 0.0,
+            release_step: 0.0,
+            release: 0,
+            sample_rate: 44100.0,
+        }
+    }
+}
+
+impl Adsr {
+    pub const NAME: &'static str = "ADSR";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("Gate", PortKind::Control)],
+            outputs: vec![port("Env", PortKind::Control)],
+            parameters: vec![
+                parameter("attack", 0.0, 5000.0, 10.0, Unit::Milliseconds, WidgetHint::Knob),
+                parameter("decay", 0.0, 5000.0, 200.0, Unit::Milliseconds, WidgetHint::Knob),
+                parameter("sustain", 0.0, 100.0, 70.0, Unit::Percent, WidgetHint::Knob),
+                parameter("release", 0.0, 5000.0, 300.0, Unit::Milliseconds, WidgetHint::Knob),
+            ],
+            ..manifest(Self::NAME, "Control")
+        }
+    }
+
+    /// Returns how far to move each sample to cover `distance` in `ms`.
+    fn step(&self, distance: f32, ms: f32) -> f32 {
+        let samples = ms / 1000.0 * self.sample_rate;
+        if samples < 1.0 { distance } else { distance / samples }
+    }
+
+    fn tick(&mut self, gate: bool, params: &[f32]) -> f32 {
+        let (attack, decay, sustain, release) = (params[0], params[1], params[2] / 100.0, params[3]);
+
+        match (gate, self.stage) {
+            (true, Stage::Idle) | (true, Stage::Release) => self.stage = Stage::Attack,
+            (false, Stage::Attack) | (false, Stage::Decay) | (false, Stage::Sustain) => {
+                self.stage = Stage::Release;
+                self.release_step = self.step(self.level, release);
+            }
+            _ => {}
+        }
+
+        match self.stage {
+            Stage::Attack => {
+                self.level = (self.level + self.step(1.0, attack)).min(1.0);
+                if self.level >= 1.0 {
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level = (self.level - self.step(1.0 - sustain, decay)).max(sustain);
+                if self.level <= sustain {
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => self.level = sustain,
+            Stage::Release => {
+                self.level = (self.level - self.release_step).max(0.0);
+                if self.level <= 0.0 {
+                    self.stage = Stage::Idle;
+                }
+            }
+            Stage::Idle => self.level = 0.0,
+        }
+        self.level
+    }
+}
+
+impl Processor for Adsr {
+    describe!();
+
+    fn prepare(&mut self, sample_rate: f32, _: usize) {
+        self.sample_rate = sample_rate;
+    }
+
+    fn reset(&mut self) {
+        *self = Self {
+            sample_rate: self.sample_rate,
+            ..Self::default()
+        };
+    }
+
+    fn tail(&self) -> usize {
+        self.release
+    }
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        self.release = (params[3] / 1000.0 * self.sample_rate) as usize;
+        for (index, out) in outputs[0].iter_mut().enumerate() {
+            let gate = sample(inputs.first(), index, 0.0) > 0.5;
+            *out = self.tick(gate, params);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::MidiEvent;
+
+    #[test]
+    fn test_midi_to_freq_follows_latest_note() {
+        let mut node = MidiToFreq::default();
+        let events = [
+            MidiEvent::new(0, [NOTE_ON, 69, 127]),
+            MidiEvent::new(1, [NOTE_ON, 81, 64]),
+            MidiEvent::new(2, [NOTE_OFF, 81, 0]),
+            MidiEvent::new(3, [NOTE_ON, 69, 0]),
+        ];
+        let (mut freq, mut gate, mut velocity) = ([0.0; 4], [0.0; 4], [0.0; 4]);
+        let mut sent = Vec::new();
+        node.process(
+            &[&[]],
+            &mut [&mut freq, &mut gate, &mut velocity],
+            &mut Events::new(&events, &mut sent),
+            &[440.0, 0.0, 2.0]);
+
+        for (freq, expected) in freq.iter().zip(&[440.0, 880.0, 440.0, 440.0]) {
+            assert!((freq - expected).abs() < 1e-2);
+        }
+        assert_eq!(gate, [1.0, 1.0, 1.0, 0.0]);
+        assert!((velocity[1] - 64.0 / 127.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_adsr_stages() {
+        let mut adsr = Adsr::default();
+        adsr.prepare(1000.0, 16);
+        // 2 ms attack, 2 ms decay to a half, 4 ms release
+        let params = [2.0, 2.0, 50.0, 4.0];
+
+        let levels: Vec<f32> = (0..6).map(|_| adsr.tick(true, &params)).collect();
+        assert_eq!(levels, vec![0.5, 1.0, 0.75, 0.5, 0.5, 0.5]);
+
+        let levels: Vec<f32> = (0..5).map(|_| adsr.tick(false, &params)).collect();
+        assert_eq!(levels, vec![0.375, 0.25, 0.125, 0.0, 0.0]);
+        assert_eq!(adsr.stage, Stage::Idle);
+    }
+}
@@ -0,0 +1,87 @@
+//! Nodes that change a signal over time.
+
+use super::{manifest, parameter, port, sample, Events, Processor};
+use crate::library::{Manifest, PortKind, Unit, WidgetHint};
+
+/// The longest time, in ms, a [`Delay`] can hold a signal for.
+const MAX_TIME: f32 = 2000.0;
+
+/// Repeats a signal after a time.
+///
+/// Loops in the graph may run through a delay, see [`super::is_delay`]. The
+/// delay then reads the loop's previous block, so the time around the loop
+/// is its time plus one block, as long as the host makes it.
+#[derive(Debug, Clone, Default)]
+pub struct Delay {
+    buffer: Vec<f32>,
+    // where the next sample is written
+    position: usize,
+    sample_rate: f32,
+}
+
+impl Delay {
+    pub const NAME: &'static str = "Delay";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("In", PortKind::Audio)],
+            outputs: vec![port("Out", PortKind::Audio)],
+            parameters: vec![
+                parameter("time", 1.0, f64::from(MAX_TIME), 250.0, Unit::Milliseconds, WidgetHint::Knob),
+            ],
+            ..manifest(Self::NAME, "Effects")
+        }
+    }
+}
+
+impl Processor for Delay {
+    describe!();
+
+    fn prepare(&mut self, sample_rate: f32, _: usize) {
+        self.sample_rate = sample_rate;
+        self.buffer = vec![0.0; (MAX_TIME / 1000.0 * sample_rate) as usize + 1];
+        self.position = 0;
+    }
+
+    fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|sample| *sample = 0.0);
+    }
+
+    fn tail(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        let len = self.buffer.len();
+        if len == 0 {
+            outputs[0].iter_mut().for_each(|sample| *sample = 0.0);
+            return;
+        }
+
+        let delay = ((params[0] / 1000.0 * self.sample_rate).round() as usize).min(len - 1).max(1);
+        for (index, out) in outputs[0].iter_mut().enumerate() {
+            self.buffer[self.position] = sample(inputs.first(), index, 0.0);
+            *out = self.buffer[(self.position + len - delay) % len];
+            self.position = (self.position + 1) % len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let mut delay = Delay::default();
+        delay.prepare(1000.0, 4);
+
+        let mut out = [0.0; 4];
+        let mut sent = Vec::new();
+        delay.process(&[&[1.0, 2.0, 3.0, 4.0]], &mut [&mut out], &mut Events::new(&[], &mut sent), &[2.0]);
+        assert_eq!(out, [0.0, 0.0, 1.0, 2.0]);
+
+        delay.process(&[&[]], &mut [&mut out], &mut Events::new(&[], &mut sent), &[2.0]);
+        assert_eq!(out, [3.0, 4.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,114 @@
+//! The nodes through which a graph is connected to the host.
+//!
+//! The engine hands the host's input channels and MIDI to [`AudioInput`] and
+//! [`MidiInput`] as their inputs, and mixes the outputs of every
+//! [`AudioOutput`] into the host's output channels.
+
+use super::{manifest, port, Events, Processor};
+use crate::library::{Manifest, PortKind};
+
+/// The number of channels passed to and from the host.
+pub const HOST_CHANNELS: usize = 2;
+
+/// A node through which the graph reaches the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum External {
+    AudioInput,
+    AudioOutput,
+    MidiInput,
+}
+
+impl External {
+    /// Returns how a node of type `kind` reaches the host, if it does.
+    pub fn of(kind: &str) -> Option<Self> {
+        match kind {
+            AudioInput::NAME => Some(External::AudioInput),
+            AudioOutput::NAME => Some(External::AudioOutput),
+            MidiInput::NAME => Some(External::MidiInput),
+            _ => None,
+        }
+    }
+}
+
+/// Copies each input to the output of the same index.
+fn pass_through(inputs: &[&[f32]], outputs: &mut [&mut [f32]]) {
+    for (input, output) in inputs.iter().zip(outputs.iter_mut()) {
+        if input.is_empty() {
+            output.iter_mut().for_each(|sample| *sample = 0.0);
+        } else {
+            output.copy_from_slice(&input[..output.len()]);
+        }
+    }
+}
+
+/// The host's audio inputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioInput;
+
+impl AudioInput {
+    pub const NAME: &'static str = "Audio Input";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            outputs: vec![port("Left", PortKind::Audio), port("Right", PortKind::Audio)],
+            ..manifest(Self::NAME, "Input/Output")
+        }
+    }
+}
+
+impl Processor for AudioInput {
+    describe!();
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, _: &[f32]) {
+        pass_through(inputs, outputs);
+    }
+}
+
+/// The host's audio outputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioOutput;
+
+impl AudioOutput {
+    pub const NAME: &'static str = "Audio Output";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("Left", PortKind::Audio), port("Right", PortKind::Audio)],
+            ..manifest(Self::NAME, "Input/Output")
+        }
+    }
+}
+
+impl Processor for AudioOutput {
+    describe!();
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, _: &[f32]) {
+        pass_through(inputs, outputs);
+    }
+}
+
+/// The MIDI sent by the host.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MidiInput;
+
+impl MidiInput {
+    pub const NAME: &'static str = "MIDI Input";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            outputs: vec![port("MIDI", PortKind::Midi)],
+            ..manifest(Self::NAME, "Input/Output")
+        }
+    }
+}
+
+impl Processor for MidiInput {
+    describe!();
+
+    fn process(&mut self, _: &[&[f32]], _: &mut [&mut [f32]], events: &mut Events<'_>, _: &[f32]) {
+        for index in 0..events.input().len() {
+            let event = events.input()[index];
+            events.send(event);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Nodes implemented in Rust rather than loaded as wasm modules: the graph's
+//! connections to the host and the everyday utilities, sources, effects and
+//! control signals that every patch needs.
+//!
+//! Each node is described by a [`Manifest`], just like a module in the
+//! library, so the editor shows them the same way, and is run by the engine
+//! through the [`Processor`] trait.
+
+/// Implements [`Processor::describe`] for a native node, with the manifest
+/// its type's `manifest()` returns.
+macro_rules! describe {
+    () => {
+        fn describe(&self) -> crate::library::Manifest {
+            Self::manifest()
+        }
+    };
+}
+
+pub mod control;
+pub mod effects;
+pub mod io;
+pub mod sources;
+pub mod utility;
+pub mod wasm;
+
+use crate::library::{Manifest, ParameterSpec, PortKind, PortSpec, Unit, WidgetHint};
+
+use control::{Adsr, MidiToFreq};
+use effects::Delay;
+use io::{AudioInput, AudioOutput, MidiInput};
+use sources::{Lfo, Oscillator};
+use utility::{Constant, Gain, Mixer, Pan, Splitter};
+
+/// The most MIDI events a node can send in one block, further events are
+/// dropped.
+pub const MAX_EVENTS: usize = 512;
+
+/// A MIDI message, `offset` samples into the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MidiEvent {
+    pub offset: usize,
+    pub data: [u8; 3],
+}
+
+impl MidiEvent {
+    pub fn new(offset: usize, data: [u8; 3]) -> Self {
+        Self { offset, data }
+    }
+}
+
+/// The MIDI events arriving at a node's MIDI inputs, merged and in order,
+/// and those it sends from its MIDI outputs.
+#[derive(Debug)]
+pub struct Events<'a> {
+    input: &'a [MidiEvent],
+    output: &'a mut Vec<MidiEvent>,
+}
+
+impl<'a> Events<'a> {
+    /// Wraps `input` and an empty `output`, whose capacity bounds how many
+    /// events can be sent.
+    pub fn new(input: &'a [MidiEvent], output: &'a mut Vec<MidiEvent>) -> Self {
+        output.clear();
+        Self { input, output }
+    }
+
+    pub fn input(&self) -> &[MidiEvent] {
+        self.input
+    }
+
+    /// Sends an event, dropping it rather than allocating if the output is
+    /// full.
+    pub fn send(&mut self, event: MidiEvent) {
+        if self.output.len() < self.output.capacity() {
+            self.output.push(event);
+        }
+    }
+}
+
+/// Something that processes audio, a block at a time, as a node of the graph.
+///
+/// Native nodes and nodes loaded as wasm modules both implement it, so the
+/// engine runs them the same way. Processors are created and prepared off the
+/// audio thread and then only processed on it, so [`Processor::process`] must
+/// not allocate, lock or block.
+pub trait Processor: Send {
+    /// Describes the node's ports and parameters, in the order their buffers
+    /// and values are given to [`Processor::process`].
+    fn describe(&self) -> Manifest;
+
+    /// Called before the first block, and whenever the sample rate or the
+    /// longest block changes, never from the audio thread. May allocate.
+    fn prepare(&mut self, _sample_rate: f32, _max_block: usize) {}
+
+    /// Clears what is left of earlier blocks, such as envelopes and delay
+    /// lines, e.g. when the host restarts playback.
+    fn reset(&mut self) {}
+
+    /// Returns how many samples the outputs lag behind the inputs.
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// Returns how many samples the outputs may go on sounding after the
+    /// inputs fall silent.
+    fn tail(&self) -> usize {
+        0
+    }
+
+    /// Processes one block.
+    ///
+    /// There is a buffer for each channel of each input and output port
+    /// described, MIDI ports included, so two for a stereo port, left then
+    /// right. All are of the block's length except for inputs that nothing
+    /// is connected to, which are empty. Parameters are given as values in
+    /// their units.
+    fn process(
+        &mut self,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        events: &mut Events<'_>,
+        params: &[f32]);
+}
+
+/// Returns the frequency in Hz of a MIDI pitch, which may be fractional, e.g.
+/// when bent, with A4 tuned to `a4` Hz.
+pub fn pitch_to_freq(pitch: f32, a4: f32) -> f32 {
+    const A4_PITCH: f32 = 69.0;
+
+    ((pitch - A4_PITCH) / 12.0).exp2() * a4
+}
+
+/// Returns the manifests of all native nodes.
+pub fn manifests() -> Vec<Manifest> {
+    vec![
+        AudioInput::manifest(),
+        AudioOutput::manifest(),
+        MidiInput::manifest(),
+        Oscillator::manifest(),
+        Lfo::manifest(),
+        MidiToFreq::manifest(),
+        Adsr::manifest(),
+        Gain::manifest(),
+        Pan::manifest(),
+        Mixer::manifest(),
+        Splitter::manifest(),
+        Constant::manifest(),
+        Delay::manifest(),
+    ]
+}
+
+/// Creates the processor for a native node, or `None` if `kind` is not the
+/// name of one.
+pub fn create(kind: &str) -> Option<Box<dyn Processor>> {
+    let processor: Box<dyn Processor> = match kind {
+        AudioInput::NAME => Box::new(AudioInput),
+        AudioOutput::NAME => Box::new(AudioOutput),
+        MidiInput::NAME => Box::new(MidiInput),
+        Oscillator::NAME => Box::new(Oscillator::default()),
+        Lfo::NAME => Box::new(Lfo::default()),
+        MidiToFreq::NAME => Box::new(MidiToFreq::default()),
+        Adsr::NAME => Box::new(Adsr::default()),
+        Gain::NAME => Box::new(Gain),
+        Pan::NAME => Box::new(Pan),
+        Mixer::NAME => Box::new(Mixer),
+        Splitter::NAME => Box::new(Splitter),
+        Constant::NAME => Box::new(Constant),
+        Delay::NAME => Box::new(Delay::default()),
+        _ => return None,
+    };
+    Some(processor)
+}
+
+/// Returns true if nodes of `kind` are delays, which loops in the graph may
+/// run through without a feedback connection.
+pub fn is_delay(kind: &str) -> bool {
+    kind == Delay::NAME
+}
+
+/// Returns a manifest without ports or parameters.
+fn manifest(name: &str, category: &str) -> Manifest {
+    Manifest {
+        name: name.to_string(),
+        category: category.to_string(),
+        description: None,
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        parameters: Vec::new(),
+        gui: Default::default(),
+    }
+}
+
+fn port(name: &str, kind: PortKind) -> PortSpec {
+    PortSpec {
+        name: name.to_string(),
+        kind,
+        description: None,
+        unit: None,
+    }
+}
+
+fn parameter(
+    name: &str,
+    min: f64,
+    max: f64,
+    default: f64,
+    unit: Unit,
+    hint: WidgetHint) -> ParameterSpec {
+    ParameterSpec {
+        name: name.to_string(),
+        min,
+        max,
+        default,
+        unit,
+        hint: Some(hint),
+    }
+}
+
+/// Returns the gain for a level in dB.
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Returns sample `index` of an input, or `default` if it is not connected.
+fn sample(input: Option<&&[f32]>, index: usize, default: f32) -> f32 {
+    input.and_then(|input| input.get(index)).copied().unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pitch_to_freq() {
+        assert!((pitch_to_freq(69.0, 440.0) - 440.0).abs() < 1e-3);
+        assert!((pitch_to_freq(81.0, 440.0) - 880.0).abs() < 1e-3);
+        assert!((pitch_to_freq(60.0, 440.0) - 261.626).abs() < 1e-2);
+        assert!((pitch_to_freq(69.0, 432.0) - 432.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_manifests_are_valid() {
+        for manifest in manifests() {
+            assert_eq!(manifest.validate(), Ok(()), "{}", manifest.name);
+            assert!(create(&manifest.name).is_some(), "{}", manifest.name);
+        }
+        assert!(create("Reverb").is_none());
+    }
+}
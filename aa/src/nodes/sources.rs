@@ -0,0 +1,177 @@
+//! Nodes that produce a signal of their own: audio oscillators and LFOs.
+
+use super::{db_to_gain, manifest, parameter, port, sample, Events, Processor};
+use crate::library::{Manifest, PortKind, Unit, WidgetHint};
+
+use std::f32::consts::PI;
+
+/// The waveforms of an [`Oscillator`] or [`Lfo`], selected by the `shape`
+/// parameter in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 4] = [Shape::Sine, Shape::Triangle, Shape::Saw, Shape::Square];
+
+    /// Returns the shape selected by a parameter value.
+    pub fn of(value: f32) -> Self {
+        Self::ALL[(value.round().max(0.0) as usize).min(Self::ALL.len() - 1)]
+    }
+
+    /// Returns the waveform at `phase`, in `0..1`, between -1 and 1.
+    pub fn at(self, phase: f32) -> f32 {
+        match self {
+            Shape::Sine => (2.0 * PI * phase).sin(),
+            Shape::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Shape::Saw => 2.0 * phase - 1.0,
+            Shape::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+        }
+    }
+}
+
+/// A phase in `0..1` advanced by a frequency each sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Phasor {
+    phase: f32,
+    sample_rate: f32,
+}
+
+impl Default for Phasor {
+    fn default() -> Self {
+        Self {
+            phase: 0.0,
+            sample_rate: 44100.0,
+        }
+    }
+}
+
+impl Phasor {
+    /// Returns the current phase, then advances it by `freq` Hz.
+    fn tick(&mut self, freq: f32) -> f32 {
+        let phase = self.phase;
+        self.phase = (self.phase + freq / self.sample_rate).rem_euclid(1.0);
+        phase
+    }
+}
+
+/// An audio oscillator, following the frequency at its input if connected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Oscillator {
+    phasor: Phasor,
+}
+
+impl Oscillator {
+    pub const NAME: &'static str = "Oscillator";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("Freq", PortKind::Control)],
+            outputs: vec![port("Out", PortKind::Audio)],
+            parameters: vec![
+                parameter("frequency", 20.0, 20000.0, 440.0, Unit::Hz, WidgetHint::Knob),
+                parameter("shape", 0.0, 3.0, 0.0, Unit::Integer, WidgetHint::Knob),
+                parameter("level", -60.0, 0.0, -12.0, Unit::Db, WidgetHint::VSlider),
+            ],
+            ..manifest(Self::NAME, "Sources")
+        }
+    }
+}
+
+impl Processor for Oscillator {
+    describe!();
+
+    fn prepare(&mut self, sample_rate: f32, _: usize) {
+        self.phasor.sample_rate = sample_rate;
+    }
+
+    fn reset(&mut self) {
+        self.phasor.phase = 0.0;
+    }
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        let shape = Shape::of(params[1]);
+        let gain = db_to_gain(params[2]);
+        for (index, out) in outputs[0].iter_mut().enumerate() {
+            let freq = sample(inputs.first(), index, params[0]);
+            *out = shape.at(self.phasor.tick(freq)) * gain;
+        }
+    }
+}
+
+/// A low frequency oscillator for modulation, between -depth and depth.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lfo {
+    phasor: Phasor,
+}
+
+impl Lfo {
+    pub const NAME: &'static str = "LFO";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            outputs: vec![port("Out", PortKind::Control)],
+            parameters: vec![
+                parameter("rate", 0.01, 20.0, 1.0, Unit::Hz, WidgetHint::Knob),
+                parameter("shape", 0.0, 3.0, 0.0, Unit::Integer, WidgetHint::Knob),
+                parameter("depth", 0.0, 100.0, 100.0, Unit::Percent, WidgetHint::Knob),
+            ],
+            ..manifest(Self::NAME, "Control")
+        }
+    }
+}
+
+impl Processor for Lfo {
+    describe!();
+
+    fn prepare(&mut self, sample_rate: f32, _: usize) {
+        self.phasor.sample_rate = sample_rate;
+    }
+
+    fn reset(&mut self) {
+        self.phasor.phase = 0.0;
+    }
+
+    fn process(&mut self, _: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        let shape = Shape::of(params[1]);
+        let depth = params[2] / 100.0;
+        for out in outputs[0].iter_mut() {
+            *out = shape.at(self.phasor.tick(params[0])) * depth;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shapes() {
+        assert_eq!(Shape::of(2.2), Shape::Saw);
+        assert_eq!(Shape::of(7.0), Shape::Square);
+        assert!((Shape::Sine.at(0.25) - 1.0).abs() < 1e-6);
+        assert_eq!(Shape::Triangle.at(0.5), 1.0);
+        assert_eq!(Shape::Saw.at(0.0), -1.0);
+        assert_eq!(Shape::Square.at(0.75), -1.0);
+    }
+
+    #[test]
+    fn test_oscillator_follows_frequency_input() {
+        let mut oscillator = Oscillator::default();
+        oscillator.prepare(8.0, 4);
+
+        let mut out = [0.0; 4];
+        let mut sent = Vec::new();
+        // a square wave at 2 Hz sampled at 8 Hz, ignoring the parameter
+        oscillator.process(
+            &[&[2.0; 4]],
+            &mut [&mut out],
+            &mut Events::new(&[], &mut sent),
+            &[440.0, 3.0, 0.0]);
+        assert_eq!(out, [1.0, 1.0, -1.0, -1.0]);
+    }
+}
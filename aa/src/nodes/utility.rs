@@ -0,0 +1,200 @@
+//! Nodes that scale, place, combine and copy signals.
+
+use super::{db_to_gain, manifest, parameter, port, sample, Events, Processor};
+use crate::library::{Manifest, PortKind, Unit, WidgetHint};
+
+use std::f32::consts::FRAC_PI_4;
+
+/// The number of inputs of a [`Mixer`] and outputs of a [`Splitter`].
+pub const CHANNELS: usize = 4;
+
+const NAMES: [&str; CHANNELS] = ["1", "2", "3", "4"];
+
+/// Scales a signal by a level in dB and, if connected, by a control signal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gain;
+
+impl Gain {
+    pub const NAME: &'static str = "Gain";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("In", PortKind::Audio), port("CV", PortKind::Control)],
+            outputs: vec![port("Out", PortKind::Audio)],
+            parameters: vec![parameter("gain", -60.0, 12.0, 0.0, Unit::Db, WidgetHint::Knob)],
+            ..manifest(Self::NAME, "Utilities")
+        }
+    }
+}
+
+impl Processor for Gain {
+    describe!();
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        let gain = db_to_gain(params[0]);
+        for (index, out) in outputs[0].iter_mut().enumerate() {
+            *out = sample(inputs.first(), index, 0.0) * sample(inputs.get(1), index, 1.0) * gain;
+        }
+    }
+}
+
+/// Places a signal between left and right, keeping its power constant.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pan;
+
+impl Pan {
+    pub const NAME: &'static str = "Pan";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("In", PortKind::Audio), port("Pan", PortKind::Control)],
+            outputs: vec![port("Left", PortKind::Audio), port("Right", PortKind::Audio)],
+            parameters: vec![parameter("pan", -1.0, 1.0, 0.0, Unit::None, WidgetHint::Knob)],
+            ..manifest(Self::NAME, "Utilities")
+        }
+    }
+}
+
+impl Processor for Pan {
+    describe!();
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        let (left, right) = outputs.split_at_mut(1);
+        for index in 0..left[0].len() {
+            let pan = sample(inputs.get(1), index, params[0]).clamp(-1.0, 1.0);
+            let angle = (pan + 1.0) * FRAC_PI_4;
+            let input = sample(inputs.first(), index, 0.0);
+            left[0][index] = input * angle.cos();
+            right[0][index] = input * angle.sin();
+        }
+    }
+}
+
+/// Adds signals together, each at its own level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mixer;
+
+impl Mixer {
+    pub const NAME: &'static str = "Mixer";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: NAMES.iter().map(|name| port(name, PortKind::Audio)).collect(),
+            outputs: vec![port("Out", PortKind::Audio)],
+            parameters: NAMES
+                .iter()
+                .map(|name| parameter(&format!("level {}", name), -60.0, 12.0, 0.0, Unit::Db, WidgetHint::VSlider))
+                .collect(),
+            ..manifest(Self::NAME, "Utilities")
+        }
+    }
+}
+
+impl Processor for Mixer {
+    describe!();
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        let out = &mut outputs[0];
+        out.iter_mut().for_each(|sample| *sample = 0.0);
+
+        for (input, level) in inputs.iter().zip(params) {
+            let gain = db_to_gain(*level);
+            for (out, sample) in out.iter_mut().zip(input.iter()) {
+                *out += sample * gain;
+            }
+        }
+    }
+}
+
+/// Copies a signal to several outputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Splitter;
+
+impl Splitter {
+    pub const NAME: &'static str = "Splitter";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            inputs: vec![port("In", PortKind::Audio)],
+            outputs: NAMES.iter().map(|name| port(name, PortKind::Audio)).collect(),
+            ..manifest(Self::NAME, "Utilities")
+        }
+    }
+}
+
+impl Processor for Splitter {
+    describe!();
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, _: &[f32]) {
+        for output in outputs.iter_mut() {
+            for (index, out) in output.iter_mut().enumerate() {
+                *out = sample(inputs.first(), index, 0.0);
+            }
+        }
+    }
+}
+
+/// A fixed control signal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Constant;
+
+impl Constant {
+    pub const NAME: &'static str = "Constant";
+
+    pub fn manifest() -> Manifest {
+        Manifest {
+            outputs: vec![port("Out", PortKind::Control)],
+            parameters: vec![parameter("value", -1.0, 1.0, 0.0, Unit::None, WidgetHint::Knob)],
+            ..manifest(Self::NAME, "Utilities")
+        }
+    }
+}
+
+impl Processor for Constant {
+    describe!();
+
+    fn process(&mut self, _: &[&[f32]], outputs: &mut [&mut [f32]], _: &mut Events<'_>, params: &[f32]) {
+        outputs[0].iter_mut().for_each(|sample| *sample = params[0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(processor: &mut dyn Processor, inputs: &[&[f32]], outputs: usize, params: &[f32]) -> Vec<Vec<f32>> {
+        let mut buffers = vec![vec![0.0; 4]; outputs];
+        let mut slices: Vec<&mut [f32]> = buffers.iter_mut().map(|b| b.as_mut_slice()).collect();
+        let mut sent = Vec::new();
+        processor.process(inputs, &mut slices, &mut Events::new(&[], &mut sent), params);
+        buffers
+    }
+
+    #[test]
+    fn test_gain() {
+        let input = [1.0, 0.5, -1.0, 0.0];
+        let out = run(&mut Gain, &[&input, &[]], 1, &[-6.0206]);
+        assert!((out[0][0] - 0.5).abs() < 1e-4 && (out[0][2] + 0.5).abs() < 1e-4);
+
+        // the control input scales the signal when connected
+        let out = run(&mut Gain, &[&input, &[0.0, 1.0, 2.0, 3.0]], 1, &[0.0]);
+        assert_eq!(out[0], vec![0.0, 0.5, -2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_pan_keeps_power() {
+        let input = [1.0; 4];
+        let out = run(&mut Pan, &[&input, &[-1.0, 0.0, 0.5, 1.0]], 2, &[0.0]);
+        for (left, right) in out[0].iter().zip(&out[1]) {
+            let power = left.powi(2) + right.powi(2);
+            assert!((power - 1.0).abs() < 1e-5);
+        }
+        assert!(out[1][0].abs() < 1e-6 && out[0][3].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mixer() {
+        let out = run(&mut Mixer, &[&[1.0; 4], &[], &[0.5; 4], &[]], 1, &[0.0, 0.0, -6.0206, 0.0]);
+        assert!((out[0][0] - 1.25).abs() < 1e-4);
+    }
+}
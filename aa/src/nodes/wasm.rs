@@ -0,0 +1,593 @@
+//! Nodes backed by a module from the library, compiled to wasm.
+//!
+//! The wasm runtime gives each instantiated module as an [`Instance`], which
+//! only knows about audio buffers, parameter indices and notes. [`WasmNode`]
+//! turns it into a [`Processor`], described by the module's manifest.
+//!
+//! [`Runtime`] compiles modules with wasmtime, and the functions below are
+//! the whole interface between a module and the plugin: a module needs no
+//! imports, and can be built with any toolchain that targets wasm32. A module
+//! exports its `memory` and these functions:
+//!
+//! - `init(sample_rate: f32)`, called before the first block;
+//! - `get_input(channel: u32) -> u32` and `get_output(channel: u32) -> u32`,
+//!   the address in memory of the buffer of an audio or control input or
+//!   output, which holds [`BUFFER_FRAMES`] samples;
+//! - `compute(frames: u32)`, which reads `frames` samples from each input
+//!   buffer and writes as many to each output buffer;
+//! - `set_param_float(index: u32, value: f32)`, with `index` the parameter's
+//!   index in the manifest;
+//! - for modules that play notes, `handle_note_on(note: i32, velocity: f32)`
+//!   and `handle_note_off(note: i32, velocity: f32)`, with the velocity from
+//!   0 to 1;
+//! - and, optionally, `get_latency() -> u32` and `get_tail() -> u32`, the
+//!   [`Processor::latency`] and [`Processor::tail`] of the module in
+//!   samples, read after each `init`. Without them both are 0.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+use wasmtime::{Engine, Memory, Store, TypedFunc};
+
+use super::{Events, Processor};
+use crate::engine::MAX_PORTS;
+use crate::library::{Library, Manifest, Module, PortKind, PortSpec};
+
+/// The samples each buffer of a module holds, so the most it computes at
+/// once.
+pub const BUFFER_FRAMES: usize = 128;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+
+/// A module instantiated by the wasm runtime.
+pub trait Instance: Send {
+    /// Called before the first block, and whenever the sample rate changes.
+    fn init(&mut self, sample_rate: f32);
+
+    /// Sets the parameter at `index` in the manifest, in its unit.
+    fn set_parameter(&mut self, index: usize, value: f32);
+
+    fn note_on(&mut self, note: u8, velocity: u8);
+
+    fn note_off(&mut self, note: u8);
+
+    /// Returns how many samples the outputs lag behind the inputs.
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// Returns how many samples the outputs may go on sounding after the
+    /// inputs fall silent.
+    fn tail(&self) -> usize {
+        0
+    }
+
+    /// Computes a block from a buffer for each audio and control port, in
+    /// the order of the manifest, all of the same length.
+    fn compute(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]);
+}
+
+/// Compiles the modules of the library, once each until they change, and
+/// creates nodes running them. Used off the audio thread.
+#[derive(Default)]
+pub struct Runtime {
+    engine: Engine,
+    // each module compiled, with when it was modified
+    compiled: HashMap<PathBuf, (Option<SystemTime>, wasmtime::Module)>,
+}
+
+impl Runtime {
+    /// Creates the processor for a node of `kind`: a native node, or else a
+    /// node running the module of that name in `library`. Returns `None`
+    /// for an unknown kind, or a module that cannot be run.
+    pub fn create(&mut self, library: &Library, kind: &str) -> Option<Box<dyn Processor>> {
+        if let Some(processor) = super::create(kind) {
+            return Some(processor);
+        }
+
+        let module = library.get(kind)?;
+        match self.instantiate(module) {
+            Ok(node) => Some(Box::new(node)),
+            Err(e) => {
+                error!("Module {} cannot run: {}", kind, e);
+                None
+            }
+        }
+    }
+
+    /// Creates a node running `module`.
+    pub fn instantiate(&mut self, module: &Module) -> Result<WasmNode> {
+        let modified = std::fs::metadata(&module.wasm).and_then(|m| m.modified()).ok();
+        let compiled = match self.compiled.get(&module.wasm) {
+            Some((when, compiled)) if *when == modified => compiled.clone(),
+            _ => {
+                let compiled = wasmtime::Module::from_file(&self.engine, &module.wasm)?;
+                self.compiled.insert(module.wasm.clone(), (modified, compiled.clone()));
+                compiled
+            }
+        };
+
+        let instance = Wasmtime::new(&self.engine, &compiled, &module.manifest)?;
+        Ok(WasmNode::new(module.manifest.clone(), Box::new(instance)))
+    }
+}
+
+/// Checks that `bytes` compile to a module with the exports above, of the
+/// right types, and a buffer in its memory for each channel of the ports in
+/// `manifest`. A module with a MIDI input must handle notes.
+pub fn validate(bytes: &[u8], manifest: &Manifest) -> Result<()> {
+    let engine = Engine::default();
+    let module = wasmtime::Module::new(&engine, bytes)?;
+    let instance = Wasmtime::new(&engine, &module, manifest)?;
+
+    let midi = manifest.inputs.iter().any(|port| port.kind == PortKind::Midi);
+    if midi && (instance.note_on.is_none() || instance.note_off.is_none()) {
+        return Err(anyhow!("MIDI input without handle_note_on and handle_note_off exported"));
+    }
+    Ok(())
+}
+
+/// Returns the function of `instance` exported as `name`, or `None` if there
+/// is none, failing if it has another type.
+fn optional<P, R>(instance: &wasmtime::Instance, store: &mut Store<()>, name: &str) -> Result<Option<TypedFunc<P, R>>>
+where
+    P: wasmtime::WasmParams,
+    R: wasmtime::WasmResults,
+{
+    match instance.get_func(&mut *store, name) {
+        Some(func) => Ok(Some(func.typed(&*store).map_err(|e| anyhow!("{}: {}", name, e))?)),
+        None => Ok(None),
+    }
+}
+
+/// A module instantiated by wasmtime.
+struct Wasmtime {
+    store: Store<()>,
+    memory: Memory,
+    init: TypedFunc<f32, ()>,
+    compute: TypedFunc<u32, ()>,
+    set_param: TypedFunc<(u32, f32), ()>,
+    note_on: Option<TypedFunc<(i32, f32), ()>>,
+    note_off: Option<TypedFunc<(i32, f32), ()>>,
+    get_latency: Option<TypedFunc<(), u32>>,
+    get_tail: Option<TypedFunc<(), u32>>,
+    // as the module last reported them
+    latency: usize,
+    tail: usize,
+    // the address of the buffer of each input and output
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    // set once a call traps, after which the module is left silent
+    trapped: bool,
+}
+
+impl Wasmtime {
+    fn new(engine: &Engine, module: &wasmtime::Module, manifest: &Manifest) -> Result<Self> {
+        let mut store = Store::new(engine, ());
+        let instance = wasmtime::Instance::new(&mut store, module, &[])?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("no memory exported"))?;
+        let get_input = instance.get_typed_func::<u32, u32, _>(&mut store, "get_input")?;
+        let get_output = instance.get_typed_func::<u32, u32, _>(&mut store, "get_output")?;
+
+        let signals = |ports: &[PortSpec]| {
+            let channels: usize = ports
+                .iter()
+                .filter(|port| port.kind != PortKind::Midi)
+                .map(|port| port.kind.channels())
+                .sum();
+            channels.min(MAX_PORTS) as u32
+        };
+        let mut buffers = |get: TypedFunc<u32, u32>, count: u32| -> Result<Vec<usize>> {
+            (0..count)
+                .map(|channel| {
+                    let address = get.call(&mut store, channel)? as usize;
+                    if address + BUFFER_FRAMES * 4 > memory.data_size(&store) {
+                        return Err(anyhow!("buffer {} is outside memory", channel));
+                    }
+                    Ok(address)
+                })
+                .collect()
+        };
+        let inputs = buffers(get_input, signals(&manifest.inputs))?;
+        let outputs = buffers(get_output, signals(&manifest.outputs))?;
+
+        Ok(Self {
+            init: instance.get_typed_func(&mut store, "init")?,
+            compute: instance.get_typed_func(&mut store, "compute")?,
+            set_param: instance.get_typed_func(&mut store, "set_param_float")?,
+            note_on: optional(&instance, &mut store, "handle_note_on")?,
+            note_off: optional(&instance, &mut store, "handle_note_off")?,
+            get_latency: optional(&instance, &mut store, "get_latency")?,
+            get_tail: optional(&instance, &mut store, "get_tail")?,
+            latency: 0,
+            tail: 0,
+            store,
+            memory,
+            inputs,
+            outputs,
+            trapped: false,
+        })
+    }
+
+    /// Calls a function of the module, unless an earlier call trapped.
+    fn call<P: wasmtime::WasmParams>(&mut self, func: Option<TypedFunc<P, ()>>, params: P) {
+        if let Some(func) = func.filter(|_| !self.trapped) {
+            self.trapped = func.call(&mut self.store, params).is_err();
+        }
+    }
+
+    /// Asks the module for a number of samples, 0 if it does not export
+    /// `func` or an earlier call trapped.
+    fn query(&mut self, func: Option<TypedFunc<(), u32>>) -> usize {
+        match func.filter(|_| !self.trapped) {
+            Some(func) => match func.call(&mut self.store, ()) {
+                Ok(samples) => samples as usize,
+                Err(_) => {
+                    self.trapped = true;
+                    0
+                }
+            },
+            None => 0,
+        }
+    }
+}
+
+impl Instance for Wasmtime {
+    fn init(&mut self, sample_rate: f32) {
+        self.trapped = false;
+        self.call(Some(self.init), sample_rate);
+        self.latency = self.query(self.get_latency);
+        self.tail = self.query(self.get_tail);
+    }
+
+    fn set_parameter(&mut self, index: usize, value: f32) {
+        self.call(Some(self.set_param), (index as u32, value));
+    }
+
+    fn note_on(&mut self, note: u8, velocity: u8) {
+        self.call(self.note_on, (note as i32, velocity as f32 / 127.0));
+    }
+
+    fn note_off(&mut self, note: u8) {
+        self.call(self.note_off, (note as i32, 0.0));
+    }
+
+    fn latency(&self) -> usize {
+        self.latency
+    }
+
+    fn tail(&self) -> usize {
+        self.tail
+    }
+
+    /// Computes the block in pieces of at most [`BUFFER_FRAMES`], copying
+    /// each through the module's buffers.
+    fn compute(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) {
+        let len = outputs.first().map_or(0, |output| output.len());
+        let mut start = 0;
+        while start < len && !self.trapped {
+            let end = (start + BUFFER_FRAMES).min(len);
+
+            let memory = self.memory.data_mut(&mut self.store);
+            for (input, address) in inputs.iter().zip(&self.inputs) {
+                for (index, sample) in input[start..end].iter().enumerate() {
+                    let at = address + index * 4;
+                    memory[at..at + 4].copy_from_slice(&sample.to_le_bytes());
+                }
+            }
+
+            self.call(Some(self.compute), (end - start) as u32);
+            if self.trapped {
+                break;
+            }
+
+            let memory = self.memory.data(&self.store);
+            for (output, address) in outputs.iter_mut().zip(&self.outputs) {
+                for (index, sample) in output[start..end].iter_mut().enumerate() {
+                    let at = address + index * 4;
+                    *sample = f32::from_le_bytes([memory[at], memory[at + 1], memory[at + 2], memory[at + 3]]);
+                }
+            }
+            start = end;
+        }
+
+        if self.trapped {
+            for output in outputs.iter_mut() {
+                output[start..].iter_mut().for_each(|sample| *sample = 0.0);
+            }
+        }
+    }
+}
+
+/// A module from the library run as a node.
+pub struct WasmNode {
+    manifest: Manifest,
+    instance: Box<dyn Instance>,
+    sample_rate: f32,
+    // the value of each parameter as the instance last saw it, NaN before then
+    values: Vec<f32>,
+    // read for inputs that nothing is connected to
+    silence: Vec<f32>,
+}
+
+impl WasmNode {
+    pub fn new(manifest: Manifest, instance: Box<dyn Instance>) -> Self {
+        Self {
+            values: vec![f32::NAN; manifest.parameters.len()],
+            manifest,
+            instance,
+            sample_rate: 44100.0,
+            silence: Vec::new(),
+        }
+    }
+
+    /// Computes samples `start..end` of the block, from the channels of the
+    /// ports carrying signals rather than MIDI.
+    fn compute(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+
+        let mut ins: [&[f32]; MAX_PORTS] = Default::default();
+        let mut count = 0;
+        for (input, kind) in inputs.iter().zip(channel_kinds(&self.manifest.inputs)) {
+            if kind != PortKind::Midi && count < MAX_PORTS {
+                ins[count] = if input.is_empty() { &self.silence[start..end] } else { &input[start..end] };
+                count += 1;
+            }
+        }
+
+        let mut outs: [&mut [f32]; MAX_PORTS] = Default::default();
+        let mut out_count = 0;
+        for (output, kind) in outputs.iter_mut().zip(channel_kinds(&self.manifest.outputs)) {
+            if kind != PortKind::Midi && out_count < MAX_PORTS {
+                outs[out_count] = &mut output[start..end];
+                out_count += 1;
+            }
+        }
+
+        self.instance.compute(&ins[..count], &mut outs[..out_count]);
+    }
+}
+
+/// Returns the kind of port of each channel of `ports`, in order.
+fn channel_kinds(ports: &[PortSpec]) -> impl Iterator<Item = PortKind> + '_ {
+    ports.iter().flat_map(|port| (0..port.kind.channels()).map(move |_| port.kind))
+}
+
+impl Processor for WasmNode {
+    fn describe(&self) -> Manifest {
+        self.manifest.clone()
+    }
+
+    fn prepare(&mut self, sample_rate: f32, max_block: usize) {
+        self.sample_rate = sample_rate;
+        self.silence = vec![0.0; max_block];
+        self.instance.init(sample_rate);
+        self.values.iter_mut().for_each(|value| *value = f32::NAN);
+    }
+
+    fn reset(&mut self) {
+        self.instance.init(self.sample_rate);
+        self.values.iter_mut().for_each(|value| *value = f32::NAN);
+    }
+
+    fn latency(&self) -> usize {
+        self.instance.latency()
+    }
+
+    fn tail(&self) -> usize {
+        self.instance.tail()
+    }
+
+    /// Passes on changed parameters, then computes the block in pieces
+    /// between notes, so that each note starts on its own sample.
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], events: &mut Events<'_>, params: &[f32]) {
+        for (index, (value, param)) in self.values.iter_mut().zip(params).enumerate() {
+            if *value != *param {
+                *value = *param;
+                self.instance.set_parameter(index, *param);
+            }
+        }
+
+        let len = outputs.first().map_or(0, |output| output.len()).min(self.silence.len());
+        let mut start = 0;
+        for event in events.input() {
+            let offset = event.offset.min(len);
+            self.compute(inputs, outputs, start, offset);
+            start = offset;
+
+            match event.data[0] & 0xF0 {
+                NOTE_ON if event.data[2] > 0 => self.instance.note_on(event.data[1], event.data[2]),
+                NOTE_ON | NOTE_OFF => self.instance.note_off(event.data[1]),
+                _ => {}
+            }
+        }
+        self.compute(inputs, outputs, start, len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::{Unit, WidgetHint};
+    use crate::nodes::{manifest, parameter, port, MidiEvent};
+
+    use crate::engine::{Binding, Connection, Engine, NodeSetup, Plan, Setup};
+    use crate::sync::SyncState;
+    use vst::plugin::HostCallback;
+
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the calls made to it, and outputs the number of notes held.
+    struct Recorder {
+        calls: Arc<Mutex<Vec<String>>>,
+        held: usize,
+    }
+
+    impl Instance for Recorder {
+        fn init(&mut self, _: f32) {}
+
+        fn set_parameter(&mut self, index: usize, value: f32) {
+            self.calls.lock().unwrap().push(format!("set {} {}", index, value));
+        }
+
+        fn note_on(&mut self, note: u8, _: u8) {
+            self.held += 1;
+            self.calls.lock().unwrap().push(format!("on {}", note));
+        }
+
+        fn note_off(&mut self, note: u8) {
+            self.held -= 1;
+            self.calls.lock().unwrap().push(format!("off {}", note));
+        }
+
+        fn compute(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) {
+            self.calls.lock().unwrap().push(format!("compute {}", outputs[0].len()));
+            for (out, input) in outputs[0].iter_mut().zip(inputs[0].iter()) {
+                *out = input + self.held as f32;
+            }
+        }
+    }
+
+    fn ports(kinds: &[PortKind]) -> Vec<PortSpec> {
+        kinds.iter().map(|kind| port("port", *kind)).collect()
+    }
+
+    #[test]
+    fn test_wasm_node_splits_block_at_notes() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let manifest = Manifest {
+            inputs: ports(&[PortKind::Midi, PortKind::Audio]),
+            outputs: ports(&[PortKind::Audio]),
+            parameters: vec![parameter("gain", 0.0, 1.0, 0.5, Unit::None, WidgetHint::Knob)],
+            ..manifest("Synth", "Sources")
+        };
+        let instance = Recorder { calls: calls.clone(), held: 0 };
+        let mut node = WasmNode::new(manifest, Box::new(instance));
+        node.prepare(44100.0, 4);
+
+        let events = [MidiEvent::new(1, [NOTE_ON, 60, 100]), MidiEvent::new(3, [NOTE_OFF, 60, 0])];
+        let mut out = [0.0; 4];
+        let mut sent = Vec::new();
+        let params = [0.25];
+        node.process(&[&[], &[]], &mut [&mut out], &mut Events::new(&events, &mut sent), &params);
+        // unchanged parameters are not set again
+        node.process(&[&[], &[]], &mut [&mut out], &mut Events::new(&[], &mut sent), &params);
+
+        assert_eq!(*calls.lock().unwrap(), vec![
+            "set 0 0.25", "compute 1", "on 60", "compute 2", "off 60", "compute 1", "compute 4",
+        ]);
+        assert_eq!(node.describe().inputs.len(), 2);
+    }
+
+    /// A module that adds its one parameter to its input, with its input
+    /// buffer at 1024 and its output buffer at 2048.
+    const OFFSET: &str = r#"(module
+        (memory (export "memory") 1)
+        (global $offset (mut f32) (f32.const 0))
+        (func (export "init") (param f32))
+        (func (export "get_input") (param i32) (result i32) (i32.const 1024))
+        (func (export "get_output") (param i32) (result i32) (i32.const 2048))
+        (func (export "set_param_float") (param i32 f32) (global.set $offset (local.get 1)))
+        (func (export "compute") (param $frames i32) (local $at i32)
+            (block $done
+                (loop $next
+                    (br_if $done (i32.ge_u (local.get $at) (i32.mul (local.get $frames) (i32.const 4))))
+                    (f32.store offset=2048 (local.get $at)
+                        (f32.add (f32.load offset=1024 (local.get $at)) (global.get $offset)))
+                    (local.set $at (i32.add (local.get $at) (i32.const 4)))
+                    (br $next)))))"#;
+
+    const OFFSET_MANIFEST: &str = r#"{
+        "name": "Offset",
+        "category": "Utilities",
+        "inputs": [{ "name": "in", "type": "audio" }],
+        "outputs": [{ "name": "out", "type": "audio" }],
+        "parameters": [
+            { "name": "offset", "min": -1.0, "max": 1.0, "default": 0.0, "unit": "none", "hint": "knob" }
+        ]
+    }"#;
+
+    /// A module that does nothing, but reports a latency and a tail of a
+    /// second at the sample rate it was initialised with.
+    const DELAYED: &str = r#"(module
+        (memory (export "memory") 1)
+        (global $rate (mut f32) (f32.const 0))
+        (func (export "init") (param f32) (global.set $rate (local.get 0)))
+        (func (export "get_input") (param i32) (result i32) (i32.const 1024))
+        (func (export "get_output") (param i32) (result i32) (i32.const 2048))
+        (func (export "set_param_float") (param i32 f32))
+        (func (export "compute") (param i32))
+        (func (export "get_latency") (result i32) (i32.const 64))
+        (func (export "get_tail") (result i32) (i32.trunc_f32_u (global.get $rate))))"#;
+
+    #[test]
+    fn test_wasm_module_reports_latency_and_tail() {
+        let engine = wasmtime::Engine::default();
+        let manifest: Manifest = serde_json::from_str(OFFSET_MANIFEST).unwrap();
+        let node = |wat| {
+            let module = wasmtime::Module::new(&engine, wat::parse_str(wat).unwrap()).unwrap();
+            let instance = Wasmtime::new(&engine, &module, &manifest).unwrap();
+            WasmNode::new(manifest.clone(), Box::new(instance))
+        };
+
+        let mut delayed = node(DELAYED);
+        delayed.prepare(48000.0, 256);
+        assert_eq!((delayed.latency(), delayed.tail()), (64, 48000));
+        delayed.prepare(44100.0, 256);
+        assert_eq!(delayed.tail(), 44100);
+
+        // neither is exported
+        let mut offset = node(OFFSET);
+        offset.prepare(48000.0, 256);
+        assert_eq!((offset.latency(), offset.tail()), (0, 0));
+    }
+
+    #[test]
+    fn test_wasm_module_runs_in_engine() {
+        let dir = std::env::temp_dir().join(format!("aa_wasm_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("offset.wasm"), wat::parse_str(OFFSET).unwrap()).unwrap();
+        fs::write(dir.join("offset.json"), OFFSET_MANIFEST).unwrap();
+
+        let mut library = Library::new(vec![dir.clone()]);
+        assert_eq!(library.scan(), 1);
+
+        let node = |id, kind: &str, parameters| NodeSetup {
+            id,
+            kind: kind.to_string(),
+            parameters,
+            meters: Vec::new(),
+            mode: None,
+        };
+        let connection = |from, to| Connection { from, output: 0, to, input: 0, feedback: false };
+        let setup = Setup {
+            nodes: vec![
+                node(0, "Constant", vec![Binding::Value(0.25)]),
+                node(1, "Offset", vec![Binding::Value(0.5)]),
+                node(2, "Audio Output", Vec::new()),
+            ],
+            connections: vec![connection(0, 1), connection(1, 2)],
+        };
+
+        let sync = Arc::new(SyncState::new(HostCallback::default()));
+        let mut engine = Engine::new(sync.clone());
+        let mut runtime = Runtime::default();
+        sync.plans.send(Plan::new(&setup, 44100.0, |kind| runtime.create(&library, kind)));
+
+        // longer than the module's buffers
+        engine.process(0, 200, &[]);
+        assert!(engine.output(0).unwrap()[..200].iter().all(|sample| *sample == 0.75));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,146 @@
+//! Taps on graph edges, recording the signal leaving a node output for the
+//! editor's scopes and spectrum analysers.
+//!
+//! Each [`Probe`] is a ring buffer written only by the audio thread. The
+//! editor copies out the latest samples whenever it draws, so recording never
+//! waits and never changes the signal being probed.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+/// The number of probes that can be attached at once.
+pub const MAX_PROBES: usize = 8;
+
+/// The number of samples kept by each probe. A power of two.
+pub const PROBE_LENGTH: usize = 4096;
+
+/// A ring buffer recording the signal on one node output.
+#[derive(Debug)]
+pub struct Probe {
+    active: AtomicBool,
+    node: AtomicUsize,
+    output: AtomicUsize,
+    // total samples written, the next write goes to `written % PROBE_LENGTH`
+    written: AtomicUsize,
+    samples: Vec<AtomicU32>,
+}
+
+impl Probe {
+    fn new() -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            node: AtomicUsize::new(0),
+            output: AtomicUsize::new(0),
+            written: AtomicUsize::new(0),
+            samples: (0..PROBE_LENGTH).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    /// Returns the node id and output recorded by this probe, if attached.
+    pub fn source(&self) -> Option<(usize, usize)> {
+        if self.active.load(Ordering::Acquire) {
+            Some((self.node.load(Ordering::Relaxed), self.output.load(Ordering::Relaxed)))
+        } else {
+            None
+        }
+    }
+
+    /// Records a block of samples. Called from the audio thread.
+    pub fn record(&self, block: &[f32]) {
+        let start = self.written.load(Ordering::Relaxed);
+        for (offset, sample) in block.iter().enumerate() {
+            self.samples[(start + offset) % PROBE_LENGTH].store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.written.store(start.wrapping_add(block.len()), Ordering::Release);
+    }
+
+    /// Copies the latest `out.len()` samples, oldest first. Called from the
+    /// editor.
+    pub fn read(&self, out: &mut [f32]) {
+        let len = out.len().min(PROBE_LENGTH);
+        let end = self.written.load(Ordering::Acquire);
+        let start = end.wrapping_sub(len);
+
+        for (offset, sample) in out.iter_mut().take(len).enumerate() {
+            let index = start.wrapping_add(offset) % PROBE_LENGTH;
+            *sample = f32::from_bits(self.samples[index].load(Ordering::Relaxed));
+        }
+    }
+}
+
+/// The probes attached to the graph.
+#[derive(Debug)]
+pub struct ProbeBank {
+    probes: Vec<Probe>,
+}
+
+impl Default for ProbeBank {
+    fn default() -> Self {
+        Self {
+            probes: (0..MAX_PROBES).map(|_| Probe::new()).collect(),
+        }
+    }
+}
+
+impl ProbeBank {
+    /// Attaches a free probe to output `output` of the node with id `node`,
+    /// returning its index, or `None` if all probes are in use.
+    pub fn attach(&self, node: usize, output: usize) -> Option<usize> {
+        let index = self
+            .probes
+            .iter()
+            .position(|probe| !probe.active.load(Ordering::Acquire))?;
+
+        let probe = &self.probes[index];
+        probe.node.store(node, Ordering::Relaxed);
+        probe.output.store(output, Ordering::Relaxed);
+        for sample in &probe.samples {
+            sample.store(0, Ordering::Relaxed);
+        }
+        probe.active.store(true, Ordering::Release);
+
+        Some(index)
+    }
+
+    pub fn detach(&self, index: usize) {
+        if let Some(probe) = self.probes.get(index) {
+            probe.active.store(false, Ordering::Release);
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Probe> {
+        self.probes.get(index)
+    }
+
+    /// Returns the attached probes. Used by the audio thread to find the
+    /// outputs to record.
+    pub fn attached(&self) -> impl Iterator<Item = (&Probe, (usize, usize))> {
+        self.probes
+            .iter()
+            .filter_map(|probe| probe.source().map(|source| (probe, source)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_wraps() {
+        let bank = ProbeBank::default();
+        let index = bank.attach(3, 1).unwrap();
+        let probe = bank.get(index).unwrap();
+        assert_eq!(probe.source(), Some((3, 1)));
+
+        let block: Vec<f32> = (0..PROBE_LENGTH + 10).map(|i| i as f32).collect();
+        probe.record(&block[..PROBE_LENGTH - 5]);
+        probe.record(&block[PROBE_LENGTH - 5..]);
+
+        let mut out = [0.0; 4];
+        probe.read(&mut out);
+        let last = (PROBE_LENGTH + 10) as f32;
+        assert_eq!(out, [last - 4.0, last - 3.0, last - 2.0, last - 1.0]);
+
+        bank.detach(index);
+        assert!(bank.attached().next().is_none());
+    }
+}
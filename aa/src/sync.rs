@@ -0,0 +1,577 @@
+//! State shared between the audio thread and the editor.
+//!
+//! Parameter values live in atomics so that the audio thread never waits on
+//! the editor, or the host, to read or write them. Each side marks the
+//! parameters it changes, so the other only needs to look at what moved.
+//!
+//! The patch in the editor is kept here too, as a [`Preset`], so that the
+//! host can save and restore it whether or not the editor is open. A patch
+//! the host restores is handed to the [`PatchLoader`] of the graph.
+
+use vst::host::{Host, OpCode};
+use vst::plugin::{HostCallback, PluginParameters};
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::engine::{PlanQueue, ValueQueue};
+use crate::gui::window::{WindowPreset, WindowState};
+use crate::meters::MeterBank;
+use crate::modes::ModeBank;
+use crate::probes::ProbeBank;
+
+/// The number of parameters exposed to the host.
+pub const MAX_PARAMETERS: usize = 128;
+
+/// The parameter slot used for the master output level.
+pub const MASTER_PARAMETER: usize = 0;
+
+/// The process level a host reports while it renders offline, e.g. exporting
+/// a mixdown, rather than playing in real time.
+const PROCESS_LEVEL_OFFLINE: isize = 4;
+
+/// An `f32` that can be shared between threads without locking.
+#[derive(Debug)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// A set of flags recording which parameters changed, with a summary flag so
+/// that checking for no changes is a single load.
+#[derive(Debug)]
+struct Changes {
+    any: AtomicBool,
+    flags: Vec<AtomicBool>,
+}
+
+impl Changes {
+    fn new(len: usize) -> Self {
+        Self {
+            any: AtomicBool::new(false),
+            flags: (0..len).map(|_| AtomicBool::new(false)).collect(),
+        }
+    }
+
+    fn mark(&self, index: usize) {
+        self.flags[index].store(true, Ordering::Release);
+        self.any.store(true, Ordering::Release);
+    }
+
+    /// Calls `f` with the index of each changed parameter, clearing its flag.
+    fn drain(&self, mut f: impl FnMut(usize)) {
+        if !self.any.swap(false, Ordering::Acquire) {
+            return;
+        }
+
+        for (index, flag) in self.flags.iter().enumerate() {
+            if flag.swap(false, Ordering::Acquire) {
+                f(index);
+            }
+        }
+    }
+}
+
+/// What the host saves of the plugin, as JSON.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    /// The graph as set up in the editor, if it has been set up.
+    pub patch: Option<ag::Patch>,
+    /// The normal of the master level.
+    pub master: Option<f32>,
+    /// The size and scale of the editor window.
+    pub window: Option<WindowPreset>,
+}
+
+/// Loads a patch the host restored into the graph the editor edits, so that
+/// it is heard whether or not the editor is open.
+pub trait PatchLoader: Send + Sync {
+    /// Loads `patch` and sends the graph to the audio thread. Returns false
+    /// if an open editor holds the graph, which then loads the patch itself.
+    fn load(&self, patch: &ag::Patch, sync: &SyncState) -> bool;
+}
+
+/// What a parameter slot is currently bound to, used to describe it to the
+/// host.
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    name: String,
+    owner: Option<(usize, usize)>,
+}
+
+/// Parameter values, as normals in `0..=1`, shared between the audio thread,
+/// the editor and the host.
+pub struct SyncState {
+    host: Option<HostCallback>,
+    values: Vec<AtomicF32>,
+    // set by the host (automation) for the editor to pick up
+    to_gui: Changes,
+    // set by the editor for the audio thread to pick up
+    to_dsp: Changes,
+    // only touched when nodes are added or removed and when the host asks
+    // for names, never from the audio thread
+    slots: Mutex<Vec<Slot>>,
+    /// Signal levels measured by the audio thread.
+    pub meters: MeterBank,
+    /// Signals recorded from edges by the audio thread.
+    pub probes: ProbeBank,
+    /// Which nodes are bypassed or silenced, as set in the editor.
+    pub modes: ModeBank,
+    /// The graph as the audio thread is to run it, sent by the editor.
+    pub plans: PlanQueue,
+    /// Values of parameters without a slot, as set in the editor.
+    pub unbound: ValueQueue,
+    /// The size and scale of the editor window, kept here so that the host
+    /// saves them with the patch.
+    pub window: Arc<WindowState>,
+    sample_rate: AtomicF32,
+    // of the plan the audio thread runs, in samples
+    latency: AtomicUsize,
+    // as last reported to the host
+    reported: AtomicUsize,
+    // the patch as last set up in the editor, for the host to save
+    patch: Mutex<Option<ag::Patch>>,
+    // a patch restored by the host, for an open editor to pick up
+    restored: Mutex<Option<ag::Patch>>,
+    loader: Mutex<Option<Arc<dyn PatchLoader>>>,
+}
+
+impl SyncState {
+    pub fn new(host: HostCallback) -> Self {
+        let mut slots = vec![Slot::default(); MAX_PARAMETERS];
+        slots[MASTER_PARAMETER].name = "Master".to_string();
+
+        Self {
+            // a default callback means there is no host, e.g. standalone
+            host: host.raw_callback().map(|_| host),
+            values: (0..MAX_PARAMETERS).map(|_| AtomicF32::new(0.5)).collect(),
+            to_gui: Changes::new(MAX_PARAMETERS),
+            to_dsp: Changes::new(MAX_PARAMETERS),
+            slots: Mutex::new(slots),
+            meters: MeterBank::default(),
+            probes: ProbeBank::default(),
+            modes: ModeBank::default(),
+            plans: PlanQueue::default(),
+            unbound: ValueQueue::default(),
+            window: Arc::new(WindowState::default()),
+            sample_rate: AtomicF32::new(44100.0),
+            latency: AtomicUsize::new(0),
+            reported: AtomicUsize::new(0),
+            patch: Mutex::new(None),
+            restored: Mutex::new(None),
+            loader: Mutex::new(None),
+        }
+    }
+
+    /// Returns the current normal of a parameter.
+    pub fn value(&self, index: usize) -> f32 {
+        self.values.get(index).map_or(0.0, |v| v.get())
+    }
+
+    /// Sets a parameter from the editor, passing it on to the audio thread
+    /// and the host.
+    pub fn set_parameter(&self, index: usize, value: f32) {
+        if let Some(v) = self.values.get(index) {
+            v.set(value);
+            self.to_dsp.mark(index);
+
+            if let Some(host) = &self.host {
+                host.automate(index as i32, value);
+            }
+        }
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate.get()
+    }
+
+    pub fn set_sample_rate(&self, rate: f32) {
+        self.sample_rate.set(rate);
+    }
+
+    /// Returns the latency of the plan the audio thread runs, in samples.
+    pub fn latency(&self) -> usize {
+        self.latency.load(Ordering::Relaxed)
+    }
+
+    /// Publishes the latency of the plan just swapped in. Called from the
+    /// audio thread, which leaves telling the host to the editor.
+    pub fn set_latency(&self, samples: usize) {
+        self.latency.store(samples, Ordering::Relaxed);
+    }
+
+    /// Tells the host the latency of the running plan, if it changed since
+    /// it was last told. Called from the editor.
+    ///
+    /// `Info::initial_delay` is only copied into the effect when the plugin
+    /// is loaded, and the host reads the copy, so the copy is updated here,
+    /// before asking the host to read it again.
+    pub fn report_latency(&self) {
+        let samples = self.latency();
+        if self.reported.swap(samples, Ordering::Relaxed) == samples {
+            return;
+        }
+
+        if let Some(host) = &self.host {
+            let effect = host.raw_effect();
+            if !effect.is_null() {
+                // the effect lives as long as the plugin, which owns `self`,
+                // and the host may read the field from any thread, so it is
+                // written atomically, as an `i32` and an `AtomicI32` share
+                // their layout
+                let delay = unsafe { &*(std::ptr::addr_of_mut!((*effect).initialDelay) as *const AtomicI32) };
+                delay.store(samples as i32, Ordering::Relaxed);
+            }
+        }
+        self.call_host(OpCode::IOChanged, 0, 0);
+    }
+
+    /// Asks the host to refresh its view of the parameters.
+    pub fn update_host_display(&self) {
+        if let Some(host) = &self.host {
+            host.update_display();
+        }
+    }
+
+    /// Asks the host to resize the editor window to the given size in
+    /// pixels, returning true if it did.
+    pub fn size_window(&self, width: u32, height: u32) -> bool {
+        self.call_host(OpCode::SizeWindow, width as i32, height as isize) != 0
+    }
+
+    /// Returns true if the host is rendering offline rather than in real
+    /// time. Asked by the audio thread for each buffer.
+    pub fn is_offline(&self) -> bool {
+        self.call_host(OpCode::GetCurrentProcessLevel, 0, 0) == PROCESS_LEVEL_OFFLINE
+    }
+
+    /// Makes a call to the host that `Host` has no method for, returning the
+    /// host's answer, or 0 if there is no host.
+    fn call_host(&self, opcode: OpCode, index: i32, value: isize) -> isize {
+        let host = match &self.host {
+            Some(host) => host,
+            None => return 0,
+        };
+
+        match host.raw_callback() {
+            Some(callback) => callback(
+                host.raw_effect(),
+                opcode as i32,
+                index,
+                value,
+                std::ptr::null_mut(),
+                0.0),
+            None => 0,
+        }
+    }
+
+    /// Calls `f` with each parameter changed by the host since the last call.
+    /// Used by the editor to move its widgets.
+    pub fn gui_changes(&self, mut f: impl FnMut(usize, f32)) {
+        self.to_gui.drain(|index| f(index, self.values[index].get()));
+    }
+
+    /// Calls `f` with each parameter changed by the editor since the last
+    /// call. Used by the audio thread; does not allocate or lock.
+    pub fn dsp_changes(&self, mut f: impl FnMut(usize, f32)) {
+        self.to_dsp.drain(|index| f(index, self.values[index].get()));
+    }
+
+    /// Binds a free slot to parameter `index` of `node`, starting from
+    /// `value`. Returns the slot, or `None` if all slots are in use.
+    pub fn assign(&self, node: usize, index: usize, name: &str, value: f32) -> Option<usize> {
+        let mut slots = self.slots.lock().ok()?;
+
+        let slot = slots
+            .iter()
+            .enumerate()
+            .position(|(i, s)| i != MASTER_PARAMETER && s.owner.is_none())?;
+
+        slots[slot] = Slot {
+            name: name.to_string(),
+            owner: Some((node, index)),
+        };
+        drop(slots);
+
+        self.values[slot].set(value);
+        self.to_dsp.mark(slot);
+        self.update_host_display();
+
+        Some(slot)
+    }
+
+    /// Keeps the patch set up in the editor for the host to save.
+    pub fn save_patch(&self, patch: ag::Patch) {
+        if let Ok(mut saved) = self.patch.lock() {
+            *saved = Some(patch);
+        }
+    }
+
+    /// Sets what loads the patches the host restores.
+    pub fn set_loader(&self, loader: Arc<dyn PatchLoader>) {
+        if let Ok(mut shared) = self.loader.lock() {
+            *shared = Some(loader);
+        }
+    }
+
+    /// Takes the patch the host last restored, if the loader left it for an
+    /// open editor and the editor has not taken it yet.
+    pub fn take_restored(&self) -> Option<ag::Patch> {
+        self.restored.lock().ok()?.take()
+    }
+
+    /// Returns the patch, the master level and the editor window, as the
+    /// host saves them.
+    pub fn preset(&self) -> Preset {
+        Preset {
+            patch: self.patch.lock().ok().and_then(|patch| patch.clone()),
+            master: Some(self.value(MASTER_PARAMETER)),
+            window: Some(self.window.preset()),
+        }
+    }
+
+    /// Restores a preset saved by the host. Its patch is loaded straight
+    /// away, unless an open editor holds the graph, which picks it up the
+    /// next time it is drawn.
+    pub fn restore(&self, preset: Preset) {
+        if let Some(master) = preset.master {
+            PluginParameters::set_parameter(self, MASTER_PARAMETER as i32, master);
+        }
+        if let Some(window) = preset.window {
+            self.window.restore(window);
+        }
+        if let Some(patch) = preset.patch {
+            self.save_patch(patch.clone());
+            let loader = self.loader.lock().ok().and_then(|loader| loader.clone());
+            let loaded = match loader {
+                Some(loader) => loader.load(&patch, self),
+                None => false,
+            };
+            if !loaded {
+                if let Ok(mut restored) = self.restored.lock() {
+                    *restored = Some(patch);
+                }
+            }
+        }
+    }
+
+    /// Frees the given slots.
+    pub fn release(&self, released: &[usize]) {
+        if let Ok(mut slots) = self.slots.lock() {
+            for slot in released {
+                if let Some(s) = slots.get_mut(*slot) {
+                    if *slot != MASTER_PARAMETER {
+                        *s = Slot::default();
+                    }
+                }
+            }
+        }
+        self.update_host_display();
+    }
+}
+
+impl PluginParameters for SyncState {
+    fn get_parameter(&self, index: i32) -> f32 {
+        self.value(index as usize)
+    }
+
+    /// Called by the host, e.g. for automation.
+    fn set_parameter(&self, index: i32, value: f32) {
+        let index = index as usize;
+        if let Some(v) = self.values.get(index) {
+            v.set(value);
+            self.to_gui.mark(index);
+            self.to_dsp.mark(index);
+        }
+    }
+
+    fn get_parameter_name(&self, index: i32) -> String {
+        self.slots
+            .lock()
+            .ok()
+            .and_then(|slots| slots.get(index as usize).map(|s| s.name.clone()))
+            .unwrap_or_default()
+    }
+
+    fn get_parameter_text(&self, index: i32) -> String {
+        format!("{:.3}", self.value(index as usize))
+    }
+
+    fn can_be_automated(&self, index: i32) -> bool {
+        (index as usize) < MAX_PARAMETERS
+    }
+
+    fn get_preset_data(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.preset()).unwrap_or_default()
+    }
+
+    fn get_bank_data(&self) -> Vec<u8> {
+        self.get_preset_data()
+    }
+
+    fn load_preset_data(&self, data: &[u8]) {
+        match serde_json::from_slice(data) {
+            Ok(preset) => self.restore(preset),
+            Err(error) => error!("Could not load preset: {}", error),
+        }
+    }
+
+    fn load_bank_data(&self, data: &[u8]) {
+        self.load_preset_data(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::window::Scale;
+
+    #[test]
+    fn test_changes_reach_the_other_side() {
+        let sync = SyncState::new(HostCallback::default());
+
+        // changes from the host reach both the editor and the audio thread
+        PluginParameters::set_parameter(&sync, 3, 0.25);
+        let mut gui = Vec::new();
+        sync.gui_changes(|index, value| gui.push((index, value)));
+        assert_eq!(gui, vec![(3, 0.25)]);
+
+        // changes from the editor only reach the audio thread
+        sync.set_parameter(5, 0.75);
+        let mut dsp = Vec::new();
+        sync.dsp_changes(|index, value| dsp.push((index, value)));
+        assert_eq!(dsp, vec![(3, 0.25), (5, 0.75)]);
+
+        let mut gui = Vec::new();
+        sync.gui_changes(|index, value| gui.push((index, value)));
+        assert!(gui.is_empty());
+    }
+
+    #[test]
+    fn test_preset_round_trip() {
+        let sync = SyncState::new(HostCallback::default());
+        let mut patch = ag::Patch::new();
+        patch.nodes.push(ag::PatchNode {
+            id: 0,
+            x: 10.0,
+            y: 20.0,
+            data: ag::NodeData { kind: "Gain".to_string(), ..Default::default() },
+            note: "Boost".to_string(),
+            color: Some([0x4C, 0xB0, 0x6C]),
+            bypass: true,
+            mute: false,
+            solo: true,
+            delay: true,
+        });
+        patch.edges.push(ag::PatchEdge { from: 0, output: 0, to: 0, input: 0, feedback: true, route: vec![(5.0, 5.0)] });
+        patch.frames.push(ag::PatchFrame {
+            x: 0.0,
+            y: 0.0,
+            width: 400.0,
+            height: 300.0,
+            title: "Voice".to_string(),
+            color: Some([0xE0, 0x6C, 0x4C]),
+        });
+        sync.save_patch(patch.clone());
+        PluginParameters::set_parameter(&sync, MASTER_PARAMETER as i32, 0.25);
+        sync.window.request_size(800, 600);
+        sync.window.request_scale(Scale::Percent(150));
+
+        let data = sync.get_preset_data();
+        let restored = SyncState::new(HostCallback::default());
+        restored.load_preset_data(&data);
+        assert_eq!(restored.preset(), sync.preset());
+        assert_eq!(restored.take_restored(), Some(patch));
+        assert_eq!(restored.take_restored(), None);
+        // an open editor is reopened at the restored size and scale
+        assert_eq!(restored.window.size(), (800, 600));
+        assert_eq!(restored.window.scale(), Scale::Percent(150));
+        assert!(restored.window.take_pending());
+
+        // anything else is ignored
+        restored.load_preset_data(b"not a preset");
+        assert_eq!(restored.preset(), sync.preset());
+    }
+
+    /// Loads patches while the editor is closed.
+    #[derive(Default)]
+    struct Loader {
+        open: AtomicBool,
+        loaded: Mutex<Vec<ag::Patch>>,
+    }
+
+    impl PatchLoader for Loader {
+        fn load(&self, patch: &ag::Patch, _sync: &SyncState) -> bool {
+            if self.open.load(Ordering::Relaxed) {
+                return false;
+            }
+            self.loaded.lock().unwrap().push(patch.clone());
+            true
+        }
+    }
+
+    #[test]
+    fn test_restore_loads_patch() {
+        let sync = SyncState::new(HostCallback::default());
+        let loader = Arc::new(Loader::default());
+        sync.set_loader(loader.clone());
+
+        let preset = |kind: &str| {
+            let mut patch = ag::Patch::new();
+            patch.nodes.push(ag::PatchNode {
+                id: 0,
+                x: 0.0,
+                y: 0.0,
+                data: ag::NodeData { kind: kind.to_string(), ..Default::default() },
+                note: String::new(),
+                color: None,
+                bypass: false,
+                mute: false,
+                solo: false,
+                delay: false,
+            });
+            Preset { patch: Some(patch), master: None, window: None }
+        };
+
+        // loaded with no editor open
+        let gain = preset("Gain");
+        sync.restore(gain.clone());
+        assert_eq!(*loader.loaded.lock().unwrap(), vec![gain.patch.clone().unwrap()]);
+        assert_eq!(sync.take_restored(), None);
+        assert_eq!(sync.preset().patch, gain.patch);
+
+        // left for an open editor to pick up
+        loader.open.store(true, Ordering::Relaxed);
+        let delay = preset("Delay");
+        sync.restore(delay.clone());
+        assert_eq!(loader.loaded.lock().unwrap().len(), 1);
+        assert_eq!(sync.take_restored(), delay.patch);
+    }
+
+    #[test]
+    fn test_assign_and_release() {
+        let sync = SyncState::new(HostCallback::default());
+
+        let slot = sync.assign(7, 0, "Gain 1 gain", 0.5).unwrap();
+        assert_ne!(slot, MASTER_PARAMETER);
+        assert_eq!(sync.get_parameter_name(slot as i32), "Gain 1 gain");
+
+        sync.release(&[slot]);
+        assert_eq!(sync.get_parameter_name(slot as i32), "");
+        assert_eq!(sync.assign(8, 0, "Gain 2 gain", 0.5), Some(slot));
+    }
+}
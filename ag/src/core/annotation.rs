@@ -0,0 +1,97 @@
+//! Notes, colour tags and comment frames: documentation kept with a graph
+//! that does not change what it does.
+
+use super::geometry::{Point, Rectangle, Vector};
+
+/// The height of the strip along the top of a [`Frame`] holding its title,
+/// by which it is picked up.
+pub const FRAME_HEADER: f32 = 24.0;
+
+/// The space left around nodes when a [`Frame`] is drawn around them.
+pub const FRAME_PADDING: f32 = 20.0;
+
+/// The colour tags offered for nodes and frames, by name, as red, green and
+/// blue.
+pub const TAGS: [(&str, [u8; 3]); 6] = [
+    ("Red", [0xE0, 0x4C, 0x4C]),
+    ("Orange", [0xE8, 0x8C, 0x3C]),
+    ("Yellow", [0xD6, 0xB4, 0x3C]),
+    ("Green", [0x4C, 0xB0, 0x6C]),
+    ("Blue", [0x4C, 0x8C, 0xDA]),
+    ("Purple", [0xA0, 0x5C, 0xC8]),
+];
+
+/// What the user has written about a node, and the colour it is tagged
+/// with.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Annotation {
+    pub note: String,
+    pub color: Option<[u8; 3]>,
+}
+
+impl Annotation {
+    /// Returns true if there is neither a note nor a tag.
+    pub fn is_empty(&self) -> bool {
+        self.note.is_empty() && self.color.is_none()
+    }
+}
+
+/// Identifies a [`Frame`] in a [`Graph`].
+///
+/// [`Graph`]: super::Graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FrameId(pub(crate) usize);
+
+impl FrameId {
+    pub fn id(self) -> usize {
+        self.0
+    }
+}
+
+/// A titled box on the canvas, free of any node, used to comment on the
+/// nodes within it.
+///
+/// Moving a frame moves the nodes it encloses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub bounds: Rectangle,
+    pub title: String,
+    pub color: Option<[u8; 3]>,
+}
+
+impl Frame {
+    pub fn new(bounds: Rectangle, title: &str) -> Self {
+        Self {
+            bounds,
+            title: title.to_string(),
+            color: None,
+        }
+    }
+
+    pub fn color(self, color: [u8; 3]) -> Self {
+        Self {
+            color: Some(color),
+            ..self
+        }
+    }
+
+    /// Returns the strip holding the title, see [`FRAME_HEADER`].
+    pub fn header(&self) -> Rectangle {
+        Rectangle {
+            height: FRAME_HEADER.min(self.bounds.height),
+            ..self.bounds
+        }
+    }
+
+    /// Returns true if `region` lies entirely within the frame.
+    pub fn encloses(&self, region: &Rectangle) -> bool {
+        self.bounds.contains(Point::new(region.x, region.y))
+            && self.bounds.contains(Point::new(region.x + region.width, region.y + region.height))
+    }
+
+    /// Moves the frame by `offset`, keeping it within the positive quadrant.
+    pub(crate) fn translate(&mut self, offset: Vector) {
+        self.bounds.x = (self.bounds.x + offset.x).max(0.0);
+        self.bounds.y = (self.bounds.y + offset.y).max(0.0);
+    }
+}
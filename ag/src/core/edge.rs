@@ -0,0 +1,156 @@
+use super::geometry::{Point, Rectangle};
+use super::node::Node;
+
+/// How close, in pixels, the cursor must be to an edge to pick it.
+pub const EDGE_PICK_DISTANCE: f32 = 6.0;
+
+/// How close, in pixels, the cursor must be to a reroute point to pick it.
+pub const REROUTE_PICK_DISTANCE: f32 = 8.0;
+
+/// The number of straight segments used to approximate an edge's curve when
+/// picking it.
+const EDGE_SEGMENTS: usize = 32;
+
+/// A connection between an output port of one [`Node`] and an input port of
+/// another.
+///
+/// [`Node`]: crate::core::node::Node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    /// The [`Node`] the connection leaves from.
+    pub from: Node,
+    /// The output port index on `from`.
+    pub output: usize,
+    /// The [`Node`] the connection arrives at.
+    pub to: Node,
+    /// The input port index on `to`.
+    pub input: usize,
+}
+
+impl Edge {
+    pub fn new(from: Node, output: usize, to: Node, input: usize) -> Self {
+        Self {
+            from,
+            output,
+            to,
+            input,
+        }
+    }
+
+    /// Returns true if either end of the [`Edge`] is attached to `node`.
+    pub fn touches(&self, node: Node) -> bool {
+        self.from == node || self.to == node
+    }
+}
+
+/// How far, at the least, an edge runs horizontally out of a socket before it
+/// bends.
+const MIN_HANDLE: f32 = 40.0;
+
+/// Returns the two control points of the cubic curve drawn from an output
+/// socket at `from` to an input socket at `to`.
+///
+/// The curve leaves `from` heading right and arrives at `to` heading right,
+/// so it also loops back cleanly when `to` is left of `from`.
+pub fn controls(from: Point, to: Point) -> (Point, Point) {
+    let handle = ((to.x - from.x).abs() / 2.0).max(MIN_HANDLE);
+    (
+        Point::new(from.x + handle, from.y),
+        Point::new(to.x - handle, to.y),
+    )
+}
+
+/// Returns the point at `t`, in `0..=1`, along the curve drawn between `from`
+/// and `to`.
+pub fn point(from: Point, to: Point, t: f32) -> Point {
+    let (a, b) = controls(from, to);
+    let u = 1.0 - t;
+    let (w0, w1, w2, w3) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    Point::new(
+        w0 * from.x + w1 * a.x + w2 * b.x + w3 * to.x,
+        w0 * from.y + w1 * a.y + w2 * b.y + w3 * to.y,
+    )
+}
+
+/// Returns the distance from `position` to the curve drawn between `from` and
+/// `to`.
+pub fn distance(from: Point, to: Point, position: Point) -> f32 {
+    (0..EDGE_SEGMENTS)
+        .map(|i| {
+            let a = point(from, to, i as f32 / EDGE_SEGMENTS as f32);
+            let b = point(from, to, (i + 1) as f32 / EDGE_SEGMENTS as f32);
+            segment_distance(a, b, position)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Returns the curves an edge is drawn as: one from `from` to the first
+/// reroute point, one between each pair of reroute points, and one from the
+/// last to `to`.
+pub fn route(from: Point, reroutes: &[Point], to: Point) -> Vec<(Point, Point)> {
+    let points: Vec<Point> = std::iter::once(from)
+        .chain(reroutes.iter().copied())
+        .chain(std::iter::once(to))
+        .collect();
+
+    points.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// Returns the distance from `position` to an edge routed through `reroutes`.
+pub fn route_distance(from: Point, reroutes: &[Point], to: Point, position: Point) -> f32 {
+    route(from, reroutes, to)
+        .into_iter()
+        .map(|(a, b)| distance(a, b, position))
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Returns true if an edge routed through `reroutes` passes through `area`.
+pub fn route_crosses(from: Point, reroutes: &[Point], to: Point, area: &Rectangle) -> bool {
+    route(from, reroutes, to).into_iter().any(|(a, b)| {
+        (0..=EDGE_SEGMENTS).any(|i| area.contains(point(a, b, i as f32 / EDGE_SEGMENTS as f32)))
+    })
+}
+
+fn segment_distance(a: Point, b: Point, point: Point) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length = dx * dx + dy * dy;
+    if length == 0.0 {
+        return a.distance(point);
+    }
+
+    let t = (((point.x - a.x) * dx + (point.y - a.y) * dy) / length).clamp(0.0, 1.0);
+    Point::new(a.x + t * dx, a.y + t * dy).distance(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve_leaves_and_arrives_horizontally() {
+        let (from, to) = (Point::new(0.0, 0.0), Point::new(200.0, 100.0));
+        let (a, b) = controls(from, to);
+        assert_eq!((a.y, b.y), (from.y, to.y));
+        assert!(a.x > from.x && b.x < to.x);
+
+        assert_eq!(point(from, to, 0.0), from);
+        assert_eq!(point(from, to, 1.0), to);
+        assert_eq!(point(from, to, 0.5), Point::new(100.0, 50.0));
+    }
+
+    #[test]
+    fn test_route_passes_through_reroutes() {
+        let (from, to) = (Point::new(0.0, 0.0), Point::new(400.0, 0.0));
+        let reroute = Point::new(200.0, 300.0);
+
+        assert_eq!(route(from, &[], to), vec![(from, to)]);
+        assert_eq!(route(from, &[reroute], to).len(), 2);
+
+        assert!(route_distance(from, &[reroute], to, reroute) < 0.01);
+        assert!(route_distance(from, &[], to, reroute) > 100.0);
+
+        let area = Rectangle::spanning(Point::new(150.0, 250.0), Point::new(250.0, 350.0));
+        assert!(route_crosses(from, &[reroute], to, &area));
+        assert!(!route_crosses(from, &[], to, &area));
+    }
+}
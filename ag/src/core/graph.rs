@@ -0,0 +1,1357 @@
+use super::annotation::{Annotation, Frame, FrameId, FRAME_HEADER, FRAME_PADDING};
+use super::edge::{self, Edge, EDGE_PICK_DISTANCE, REROUTE_PICK_DISTANCE};
+use super::geometry::{Point, Rectangle, Size, Vector};
+use super::mode::NodeMode;
+use super::node::Node;
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// The size of the region each node is laid out in.
+pub const NODE_WIDTH: f32 = 300.0;
+pub const NODE_HEIGHT: f32 = 300.0;
+
+/// Why two nodes could not be connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectError {
+    /// One of the nodes does not exist.
+    MissingNode,
+    /// The edge does not exist.
+    MissingEdge,
+    /// The same connection is already present.
+    Exists,
+    /// The connection would close a loop with neither a feedback edge nor a
+    /// delay node in it. Holds the nodes of the loop, from the node the
+    /// connection arrives at to the one it leaves.
+    Loop(Vec<Node>),
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectError::MissingNode => write!(f, "the node does not exist"),
+            ConnectError::MissingEdge => write!(f, "the edge does not exist"),
+            ConnectError::Exists => write!(f, "the ports are already connected"),
+            ConnectError::Loop(nodes) => {
+                let ids: Vec<String> = nodes.iter().map(|node| node.id.to_string()).collect();
+                write!(
+                    f,
+                    "connecting would close a loop through nodes {} with no delay in it; \
+                     connect them with a feedback edge or put a delay node in the loop",
+                    ids.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {}
+
+/// What [`Graph::extract`] did.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Extraction {
+    /// The edges of the chain that were attached to the node.
+    pub removed: Vec<Edge>,
+    /// The heals that could not be connected, each with the reason.
+    pub refused: Vec<(Edge, ConnectError)>,
+}
+
+/// The structure of an audio graph: which nodes exist, where they are, how
+/// they are connected, the points edges are rerouted through, which are
+/// bypassed, muted or soloed, the notes and frames documenting them, and what
+/// is selected.
+///
+/// Every loop in a [`Graph`] runs through a feedback edge, which carries what
+/// left its output a block earlier, or through a node marked as a delay.
+/// Connections that would close any other loop are refused.
+///
+/// A [`Graph`] knows nothing about what a node contains or how it is drawn,
+/// so it can be edited and inspected without a window. The [`State`] of an
+/// [`AudioGraph`] pairs one with the contents of each node.
+///
+/// [`State`]: crate::native::state::State
+/// [`AudioGraph`]: crate::AudioGraph
+#[derive(Debug, Clone)]
+pub struct Graph {
+    positions: HashMap<Node, Point>,
+    edges: Vec<Edge>,
+    routes: HashMap<Edge, Vec<Point>>,
+    feedback: HashSet<Edge>,
+    delays: HashSet<Node>,
+    selection: HashSet<Node>,
+    selected_edges: HashSet<Edge>,
+    modes: HashMap<Node, NodeMode>,
+    annotations: HashMap<Node, Annotation>,
+    frames: BTreeMap<FrameId, Frame>,
+    selected_frame: Option<FrameId>,
+    focus: Option<Node>,
+    next_id: usize,
+    next_frame: usize,
+    node_size: Size,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self {
+            positions: HashMap::new(),
+            edges: Vec::new(),
+            routes: HashMap::new(),
+            feedback: HashSet::new(),
+            delays: HashSet::new(),
+            selection: HashSet::new(),
+            selected_edges: HashSet::new(),
+            modes: HashMap::new(),
+            annotations: HashMap::new(),
+            frames: BTreeMap::new(),
+            selected_frame: None,
+            focus: None,
+            next_id: 0,
+            next_frame: 0,
+            node_size: Size::new(NODE_WIDTH, NODE_HEIGHT),
+        }
+    }
+}
+
+impl Graph {
+    /// Creates an empty [`Graph`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size of the region each node is laid out in.
+    pub fn node_size(self, node_size: Size) -> Self {
+        Self { node_size, ..self }
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn contains(&self, node: &Node) -> bool {
+        self.positions.contains_key(node)
+    }
+
+    /// Returns every node, ordered by creation.
+    pub fn nodes(&self) -> Vec<Node> {
+        let mut nodes: Vec<_> = self.positions.keys().copied().collect();
+        nodes.sort_by_key(|node| node.id);
+        nodes
+    }
+
+    /// Adds a node with its top left corner at `position`, returning `None`
+    /// if there are no identifiers left.
+    pub fn insert(&mut self, position: Point) -> Option<Node> {
+        let node = Node::new(self.next_id);
+        self.next_id = self.next_id.checked_add(1)?;
+        self.positions.insert(node, clamp(position));
+        Some(node)
+    }
+
+    /// Removes a node along with any edges attached to it, returning false if
+    /// it did not exist.
+    pub fn remove(&mut self, node: &Node) -> bool {
+        if self.positions.remove(node).is_none() {
+            return false;
+        }
+
+        for edge in self.edges.clone() {
+            if edge.touches(*node) {
+                self.disconnect(&edge);
+            }
+        }
+        self.selection.remove(node);
+        self.delays.remove(node);
+        self.modes.remove(node);
+        self.annotations.remove(node);
+        if self.focus == Some(*node) {
+            self.focus = None;
+        }
+        true
+    }
+
+    /// Returns the position of the top left corner of a node.
+    pub fn position(&self, node: &Node) -> Option<Point> {
+        self.positions.get(node).copied()
+    }
+
+    /// Moves a node to `position`, returning where it was.
+    pub fn move_to(&mut self, node: Node, position: Point) -> Option<Point> {
+        let current = self.positions.get_mut(&node)?;
+        Some(std::mem::replace(current, clamp(position)))
+    }
+
+    /// Moves a node by `offset`, returning where it was. Nodes are kept
+    /// within the positive quadrant.
+    pub fn translate(&mut self, node: Node, offset: Vector) -> Option<Point> {
+        let position = self.position(&node)? + offset;
+        self.move_to(node, position)
+    }
+
+    /// Connects output port `output` of `from` to input port `input` of `to`.
+    ///
+    /// Returns an error, changing nothing, if either node does not exist, the
+    /// connection is already present, or it would close a loop with no delay
+    /// in it.
+    pub fn connect(
+        &mut self,
+        from: Node,
+        output: usize,
+        to: Node,
+        input: usize,
+    ) -> Result<Edge, ConnectError> {
+        let edge = self.check(from, output, to, input)?;
+        if let Some(nodes) = self.find_loop(from, to) {
+            return Err(ConnectError::Loop(nodes));
+        }
+
+        self.edges.push(edge);
+        Ok(edge)
+    }
+
+    /// Connects output port `output` of `from` to input port `input` of `to`
+    /// with a feedback edge, which may close a loop.
+    ///
+    /// Returns an error, changing nothing, if either node does not exist or
+    /// the connection is already present.
+    pub fn connect_feedback(
+        &mut self,
+        from: Node,
+        output: usize,
+        to: Node,
+        input: usize,
+    ) -> Result<Edge, ConnectError> {
+        let edge = self.check(from, output, to, input)?;
+        self.edges.push(edge);
+        self.feedback.insert(edge);
+        Ok(edge)
+    }
+
+    fn check(&self, from: Node, output: usize, to: Node, input: usize) -> Result<Edge, ConnectError> {
+        if !self.contains(&from) || !self.contains(&to) {
+            return Err(ConnectError::MissingNode);
+        }
+
+        let edge = Edge::new(from, output, to, input);
+        if self.edges.contains(&edge) {
+            return Err(ConnectError::Exists);
+        }
+        Ok(edge)
+    }
+
+    /// Returns the loop with no delay in it that connecting `from` to `to`
+    /// would close, as its nodes from `to` to `from`, if there is one.
+    pub fn find_loop(&self, from: Node, to: Node) -> Option<Vec<Node>> {
+        if self.is_delay(&from) || self.is_delay(&to) {
+            return None;
+        }
+
+        // a breadth first search from `to`, remembering how each node was
+        // reached, so that the loop found is the shortest
+        let mut reached_from = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(to);
+
+        while let Some(node) = queue.pop_front() {
+            if node == from {
+                let mut nodes = vec![from];
+                let mut node = from;
+                while let Some(previous) = reached_from.get(&node) {
+                    nodes.push(*previous);
+                    node = *previous;
+                }
+                nodes.reverse();
+                return Some(nodes);
+            }
+
+            for edge in &self.edges {
+                let next = edge.to;
+                if edge.from == node
+                    && !self.feedback.contains(edge)
+                    && !self.is_delay(&next)
+                    && next != to
+                    && !reached_from.contains_key(&next)
+                {
+                    reached_from.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Removes the given [`Edge`], along with its reroute points, returning
+    /// true if it was present.
+    pub fn disconnect(&mut self, edge: &Edge) -> bool {
+        let len = self.edges.len();
+        self.edges.retain(|e| e != edge);
+        self.routes.remove(edge);
+        self.feedback.remove(edge);
+        self.selected_edges.remove(edge);
+        len != self.edges.len()
+    }
+
+    /// Returns true if the [`Edge`] is a feedback edge.
+    pub fn is_feedback(&self, edge: &Edge) -> bool {
+        self.feedback.contains(edge)
+    }
+
+    /// Makes an [`Edge`] a feedback edge or an ordinary one.
+    ///
+    /// Returns an error, changing nothing, if the edge does not exist or, when
+    /// it stops being a feedback edge, it would leave a loop with no delay.
+    pub fn set_feedback(&mut self, edge: &Edge, feedback: bool) -> Result<(), ConnectError> {
+        if !self.edges.contains(edge) {
+            return Err(ConnectError::MissingEdge);
+        }
+
+        if feedback {
+            self.feedback.insert(*edge);
+        } else if self.feedback.remove(edge) {
+            if let Some(nodes) = self.find_loop(edge.from, edge.to) {
+                self.feedback.insert(*edge);
+                return Err(ConnectError::Loop(nodes));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the node is marked as a delay.
+    pub fn is_delay(&self, node: &Node) -> bool {
+        self.delays.contains(node)
+    }
+
+    /// Marks a node as a delay, whose outputs lag its inputs by at least a
+    /// block, so that loops may run through it. Returns false if the node
+    /// does not exist.
+    ///
+    /// Nodes are meant to be marked as they are inserted, as unmarking one
+    /// does not check the loops running through it.
+    pub fn set_delay(&mut self, node: Node, delay: bool) -> bool {
+        if !self.contains(&node) {
+            return false;
+        }
+
+        if delay {
+            self.delays.insert(node);
+        } else {
+            self.delays.remove(&node);
+        }
+        true
+    }
+
+    /// Inserts `node` into `edge`: the edge is replaced by one into input
+    /// `input` of `node` and one out of its output `output`. If the edge was
+    /// a feedback edge, so is the one into `node`.
+    ///
+    /// Returns false, changing nothing, if the edge does not exist, already
+    /// touches `node`, or the new edges would close a loop with no delay.
+    pub fn splice(&mut self, node: Node, edge: &Edge, input: usize, output: usize) -> bool {
+        if !self.contains(&node) || edge.touches(node) || !self.edges.contains(edge) {
+            return false;
+        }
+
+        let feedback = self.is_feedback(edge);
+        let route = self.routes.get(edge).cloned();
+        self.disconnect(edge);
+
+        let into = match feedback {
+            true => self.connect_feedback(edge.from, edge.output, node, input),
+            false => self.connect(edge.from, edge.output, node, input),
+        };
+        let out = self.connect(node, output, edge.to, edge.input);
+
+        let looped = matches!(into, Err(ConnectError::Loop(_))) || matches!(out, Err(ConnectError::Loop(_)));
+        if !looped {
+            return true;
+        }
+
+        for added in into.iter().chain(out.iter()) {
+            self.disconnect(added);
+        }
+        self.edges.push(*edge);
+        if feedback {
+            self.feedback.insert(*edge);
+        }
+        if let Some(route) = route {
+            self.routes.insert(*edge, route);
+        }
+        false
+    }
+
+    /// Takes `node` out of the chain it is in, which passes through it from
+    /// input to output of each pair in `through`: the edges at those ports
+    /// are removed and `heals` connect its former neighbours. Edges at other
+    /// ports, such as those modulating the node, are left alone.
+    ///
+    /// A heal is a feedback edge if either edge it replaces was one. A heal
+    /// that would close a loop with no delay in it, e.g. when `node` was the
+    /// delay, is refused and returned along with the removed edges.
+    pub fn extract(&mut self, node: Node, through: &[(usize, usize)], heals: &[Edge]) -> Extraction {
+        let removed: Vec<Edge> = self
+            .edges
+            .iter()
+            .filter(|edge| {
+                through.iter().any(|(input, output)| {
+                    (edge.to == node && edge.input == *input) || (edge.from == node && edge.output == *output)
+                })
+            })
+            .copied()
+            .collect();
+
+        let heals: Vec<(Edge, bool)> = heals
+            .iter()
+            .filter(|heal| !heal.touches(node))
+            .map(|heal| {
+                let feedback = removed.iter().any(|edge| {
+                    let replaced = (edge.from == heal.from && edge.output == heal.output && edge.to == node)
+                        || (edge.from == node && edge.to == heal.to && edge.input == heal.input);
+                    replaced && self.is_feedback(edge)
+                });
+                (*heal, feedback)
+            })
+            .collect();
+
+        for edge in &removed {
+            self.disconnect(edge);
+        }
+
+        let mut refused = Vec::new();
+        for (heal, feedback) in heals {
+            let connected = match feedback {
+                true => self.connect_feedback(heal.from, heal.output, heal.to, heal.input),
+                false => self.connect(heal.from, heal.output, heal.to, heal.input),
+            };
+            match connected {
+                Ok(_) | Err(ConnectError::Exists) => {}
+                Err(error) => refused.push((heal, error)),
+            }
+        }
+
+        Extraction { removed, refused }
+    }
+
+    /// Returns all edges, in the order they were connected.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Returns the reroute points of an [`Edge`], in order from its output.
+    pub fn route(&self, edge: &Edge) -> &[Point] {
+        self.routes.get(edge).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replaces the reroute points of an [`Edge`], returning false if it does
+    /// not exist.
+    pub fn set_route(&mut self, edge: &Edge, route: Vec<Point>) -> bool {
+        if !self.edges.contains(edge) {
+            return false;
+        }
+
+        if route.is_empty() {
+            self.routes.remove(edge);
+        } else {
+            self.routes.insert(*edge, route.into_iter().map(clamp).collect());
+        }
+        true
+    }
+
+    /// Adds a reroute point at `point` to an [`Edge`] whose sockets are at
+    /// `from` and `to`, placing it in the part of the route nearest `point`.
+    ///
+    /// Returns the index of the new point, or `None` if the edge does not
+    /// exist.
+    pub fn add_reroute(&mut self, edge: &Edge, from: Point, to: Point, point: Point) -> Option<usize> {
+        if !self.edges.contains(edge) {
+            return None;
+        }
+
+        let index = edge::route(from, self.route(edge), to)
+            .into_iter()
+            .map(|(a, b)| edge::distance(a, b, point))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map_or(0, |(index, _)| index);
+
+        self.routes.entry(*edge).or_default().insert(index, clamp(point));
+        Some(index)
+    }
+
+    /// Moves a reroute point, returning where it was.
+    pub fn move_reroute(&mut self, edge: &Edge, index: usize, point: Point) -> Option<Point> {
+        let current = self.routes.get_mut(edge)?.get_mut(index)?;
+        Some(std::mem::replace(current, clamp(point)))
+    }
+
+    /// Removes a reroute point, returning where it was.
+    pub fn remove_reroute(&mut self, edge: &Edge, index: usize) -> Option<Point> {
+        let route = self.routes.get_mut(edge)?;
+        if index >= route.len() {
+            return None;
+        }
+
+        let point = route.remove(index);
+        if route.is_empty() {
+            self.routes.remove(edge);
+        }
+        Some(point)
+    }
+
+    /// Returns the reroute point within [`REROUTE_PICK_DISTANCE`] of `point`,
+    /// as its edge and index.
+    pub fn reroute_at(&self, point: Point) -> Option<(Edge, usize)> {
+        self.edges
+            .iter()
+            .flat_map(|edge| {
+                self.route(edge)
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, reroute)| (*edge, index, reroute.distance(point)))
+            })
+            .filter(|(_, _, distance)| *distance <= REROUTE_PICK_DISTANCE)
+            .min_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(edge, index, _)| (edge, index))
+    }
+
+    /// Returns the selected edges, in the order they were connected.
+    pub fn selected_edges(&self) -> Vec<Edge> {
+        self.edges
+            .iter()
+            .filter(|edge| self.selected_edges.contains(edge))
+            .copied()
+            .collect()
+    }
+
+    pub fn is_edge_selected(&self, edge: &Edge) -> bool {
+        self.selected_edges.contains(edge)
+    }
+
+    /// Updates the selection for a click on `edge`. When `extend` is set the
+    /// edge is toggled, otherwise it becomes the only thing selected.
+    pub fn click_select_edge(&mut self, edge: &Edge, extend: bool) {
+        if !self.edges.contains(edge) {
+            return;
+        }
+
+        if extend {
+            if !self.selected_edges.remove(edge) {
+                self.selected_edges.insert(*edge);
+            }
+        } else {
+            self.selection.clear();
+            self.selected_edges.clear();
+            self.selected_frame = None;
+            self.selected_edges.insert(*edge);
+        }
+    }
+
+    /// Returns the selected nodes, ordered by creation.
+    pub fn selection(&self) -> Vec<Node> {
+        let mut selection: Vec<_> = self.selection.iter().copied().collect();
+        selection.sort_by_key(|node| node.id);
+        selection
+    }
+
+    pub fn is_selected(&self, node: &Node) -> bool {
+        self.selection.contains(node)
+    }
+
+    /// Replaces the selection, ignoring nodes that do not exist. Edges are
+    /// deselected.
+    pub fn select(&mut self, nodes: &[Node]) {
+        self.selected_edges.clear();
+        self.selected_frame = None;
+        self.selection = nodes
+            .iter()
+            .filter(|node| self.contains(node))
+            .copied()
+            .collect();
+    }
+
+    /// Selects every node. Edges and frames are deselected.
+    pub fn select_all(&mut self) {
+        self.selected_edges.clear();
+        self.selected_frame = None;
+        self.selection = self.positions.keys().copied().collect();
+    }
+
+    /// Deselects every node, edge and frame.
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+        self.selected_edges.clear();
+        self.selected_frame = None;
+    }
+
+    /// Updates the selection for a click on `node`. When `extend` is set the
+    /// node is toggled, otherwise it becomes the only selected node unless it
+    /// was already selected.
+    pub fn click_select(&mut self, node: &Node, extend: bool) {
+        if !self.contains(node) {
+            return;
+        }
+
+        if extend {
+            if !self.selection.remove(node) {
+                self.selection.insert(*node);
+            }
+        } else if !self.selection.contains(node) {
+            self.selection.clear();
+            self.selected_edges.clear();
+            self.selected_frame = None;
+            self.selection.insert(*node);
+        }
+    }
+
+    /// Moves each selected node by `offset`, along with the reroute points
+    /// of edges between selected nodes.
+    pub fn translate_selection(&mut self, offset: Vector) {
+        for node in self.selection() {
+            let _ = self.translate(node, offset);
+        }
+
+        let selection = &self.selection;
+        for (edge, route) in self.routes.iter_mut() {
+            if selection.contains(&edge.from) && selection.contains(&edge.to) {
+                for point in route.iter_mut() {
+                    *point = clamp(*point + offset);
+                }
+            }
+        }
+    }
+
+    /// Returns whether a node is bypassed, muted or soloed.
+    pub fn mode(&self, node: &Node) -> NodeMode {
+        self.modes.get(node).copied().unwrap_or_default()
+    }
+
+    /// Sets whether a node is bypassed, muted or soloed, returning false if
+    /// it does not exist.
+    pub fn set_mode(&mut self, node: Node, mode: NodeMode) -> bool {
+        if !self.contains(&node) {
+            return false;
+        }
+
+        if mode.is_normal() {
+            self.modes.remove(&node);
+        } else {
+            self.modes.insert(node, mode);
+        }
+        true
+    }
+
+    /// Returns true if any node is soloed.
+    pub fn has_solo(&self) -> bool {
+        self.modes.values().any(|mode| mode.solo)
+    }
+
+    /// Returns the nodes silenced by muting or soloing: those muted, and,
+    /// while any node is soloed, those neither feeding nor fed by a soloed
+    /// node.
+    pub fn silenced(&self) -> HashSet<Node> {
+        let mut silenced: HashSet<Node> = self
+            .modes
+            .iter()
+            .filter(|(_, mode)| mode.mute)
+            .map(|(node, _)| *node)
+            .collect();
+
+        if self.has_solo() {
+            let soloed: Vec<Node> = self
+                .modes
+                .iter()
+                .filter(|(_, mode)| mode.solo)
+                .map(|(node, _)| *node)
+                .collect();
+            let mut heard = self.reachable(&soloed, |edge| (edge.from, edge.to));
+            heard.extend(self.reachable(&soloed, |edge| (edge.to, edge.from)));
+
+            silenced.extend(self.positions.keys().filter(|node| !heard.contains(node)));
+        }
+        silenced
+    }
+
+    /// Returns `start` and every node reachable from it, following each edge
+    /// from the first node `direction` returns for it to the second.
+    fn reachable<F>(&self, start: &[Node], direction: F) -> HashSet<Node>
+    where
+        F: Fn(&Edge) -> (Node, Node),
+    {
+        let mut reached: HashSet<Node> = start.iter().copied().collect();
+        let mut pending = start.to_vec();
+        while let Some(node) = pending.pop() {
+            for edge in &self.edges {
+                let (from, to) = direction(edge);
+                if from == node && reached.insert(to) {
+                    pending.push(to);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Returns the note and colour tag of a node, if it has either.
+    pub fn annotation(&self, node: &Node) -> Option<&Annotation> {
+        self.annotations.get(node)
+    }
+
+    /// Sets the note of a node, returning false if it does not exist. An
+    /// empty note removes it.
+    pub fn set_note(&mut self, node: Node, note: &str) -> bool {
+        self.annotate(node, |annotation| annotation.note = note.to_string())
+    }
+
+    /// Tags a node with a colour, or removes its tag, returning false if it
+    /// does not exist.
+    pub fn set_color(&mut self, node: Node, color: Option<[u8; 3]>) -> bool {
+        self.annotate(node, |annotation| annotation.color = color)
+    }
+
+    fn annotate<F: FnOnce(&mut Annotation)>(&mut self, node: Node, f: F) -> bool {
+        if !self.contains(&node) {
+            return false;
+        }
+
+        let annotation = self.annotations.entry(node).or_default();
+        f(annotation);
+        if annotation.is_empty() {
+            self.annotations.remove(&node);
+        }
+        true
+    }
+
+    /// Adds a frame, returning `None` if there are no identifiers left.
+    pub fn add_frame(&mut self, frame: Frame) -> Option<FrameId> {
+        let id = FrameId(self.next_frame);
+        self.next_frame = self.next_frame.checked_add(1)?;
+        self.frames.insert(id, frame);
+        Some(id)
+    }
+
+    /// Adds a frame titled `title` around `nodes`, leaving [`FRAME_PADDING`]
+    /// around them and room for the title above. Returns `None` if none of
+    /// the nodes exist.
+    pub fn frame_around(&mut self, nodes: &[Node], title: &str) -> Option<FrameId> {
+        let bounds = nodes
+            .iter()
+            .filter_map(|node| self.region(node))
+            .fold(None, |bounds: Option<Rectangle>, region| {
+                Some(bounds.map_or(region, |bounds| bounds.union(&region)))
+            })?;
+
+        let top = (bounds.y - FRAME_PADDING - FRAME_HEADER).max(0.0);
+        let left = (bounds.x - FRAME_PADDING).max(0.0);
+        let frame = Frame::new(
+            Rectangle {
+                x: left,
+                y: top,
+                width: bounds.x + bounds.width + FRAME_PADDING - left,
+                height: bounds.y + bounds.height + FRAME_PADDING - top,
+            },
+            title);
+        self.add_frame(frame)
+    }
+
+    /// Removes a frame, leaving the nodes within it in place.
+    pub fn remove_frame(&mut self, id: FrameId) -> Option<Frame> {
+        if self.selected_frame == Some(id) {
+            self.selected_frame = None;
+        }
+        self.frames.remove(&id)
+    }
+
+    pub fn frame(&self, id: FrameId) -> Option<&Frame> {
+        self.frames.get(&id)
+    }
+
+    /// Returns the frames in the order they were added, which is the order
+    /// they are drawn in.
+    pub fn frames(&self) -> impl Iterator<Item = (FrameId, &Frame)> {
+        self.frames.iter().map(|(id, frame)| (*id, frame))
+    }
+
+    pub fn set_frame_title(&mut self, id: FrameId, title: &str) -> bool {
+        self.frames
+            .get_mut(&id)
+            .map(|frame| frame.title = title.to_string())
+            .is_some()
+    }
+
+    pub fn set_frame_color(&mut self, id: FrameId, color: Option<[u8; 3]>) -> bool {
+        self.frames
+            .get_mut(&id)
+            .map(|frame| frame.color = color)
+            .is_some()
+    }
+
+    /// Returns the nodes lying entirely within a frame, ordered by creation.
+    pub fn enclosed(&self, id: FrameId) -> Vec<Node> {
+        let frame = match self.frames.get(&id) {
+            Some(frame) => frame,
+            None => return Vec::new(),
+        };
+
+        self.nodes()
+            .into_iter()
+            .filter(|node| self.region(node).is_some_and(|region| frame.encloses(&region)))
+            .collect()
+    }
+
+    /// Returns the other frames lying entirely within a frame.
+    pub fn enclosed_frames(&self, id: FrameId) -> Vec<FrameId> {
+        let frame = match self.frames.get(&id) {
+            Some(frame) => frame,
+            None => return Vec::new(),
+        };
+
+        self.frames
+            .iter()
+            .filter(|(other, inner)| **other != id && frame.encloses(&inner.bounds))
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    /// Moves a frame by `offset`, along with the nodes and frames it
+    /// encloses and the reroute points of edges between those nodes.
+    ///
+    /// The offset is cut short where the frame would leave the positive
+    /// quadrant, so that everything within it keeps its place in the frame.
+    pub fn move_frame(&mut self, id: FrameId, offset: Vector) -> bool {
+        let nodes = self.enclosed(id);
+        let frames = self.enclosed_frames(id);
+        self.move_frame_with(id, &nodes, &frames, offset)
+    }
+
+    /// Moves a frame by `offset` as [`Graph::move_frame`] does, but along
+    /// with the given nodes and frames rather than those it encloses now,
+    /// e.g. those it enclosed when it was picked up to be dragged.
+    pub fn move_frame_with(&mut self, id: FrameId, nodes: &[Node], frames: &[FrameId], offset: Vector) -> bool {
+        let bounds = match self.frames.get(&id) {
+            Some(frame) => frame.bounds,
+            None => return false,
+        };
+        let offset = Vector::new(offset.x.max(-bounds.x), offset.y.max(-bounds.y));
+
+        for node in nodes {
+            let _ = self.translate(*node, offset);
+        }
+        for (edge, route) in self.routes.iter_mut() {
+            if nodes.contains(&edge.from) && nodes.contains(&edge.to) {
+                for point in route.iter_mut() {
+                    *point = clamp(*point + offset);
+                }
+            }
+        }
+        for (other, inner) in self.frames.iter_mut() {
+            if *other == id || frames.contains(other) {
+                inner.translate(offset);
+            }
+        }
+        true
+    }
+
+    /// Returns the frame whose header is at `point`. Where frames overlap the
+    /// most recently added one, which is drawn on top, is returned.
+    pub fn frame_at(&self, point: Point) -> Option<FrameId> {
+        self.frames
+            .iter()
+            .rev()
+            .find(|(_, frame)| frame.header().contains(point))
+            .map(|(id, _)| *id)
+    }
+
+    /// Returns the selected frame, if any.
+    pub fn selected_frame(&self) -> Option<FrameId> {
+        self.selected_frame
+    }
+
+    /// Makes a frame the only thing selected.
+    pub fn select_frame(&mut self, id: FrameId) {
+        if self.frames.contains_key(&id) {
+            self.selection.clear();
+            self.selected_edges.clear();
+            self.selected_frame = Some(id);
+        }
+    }
+
+    /// Returns the node with keyboard focus, if any.
+    pub fn focus(&self) -> Option<Node> {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, node: Node) {
+        if self.contains(&node) {
+            self.focus = Some(node);
+        }
+    }
+
+    /// Moves focus to the next (or previous) node in creation order, making
+    /// it the only selected node.
+    pub fn cycle_focus(&mut self, forward: bool) -> Option<Node> {
+        let nodes = self.nodes();
+
+        let current = self
+            .focus
+            .and_then(|focus| nodes.iter().position(|node| *node == focus));
+
+        let len = nodes.len();
+        let next = match current {
+            _ if len == 0 => return None,
+            None if forward => 0,
+            None => len - 1,
+            Some(index) if forward => (index + 1) % len,
+            Some(index) => (index + len - 1) % len,
+        };
+
+        let node = nodes[next];
+        self.focus = Some(node);
+        self.selection.clear();
+        self.selection.insert(node);
+        Some(node)
+    }
+
+    /// Returns the region a node is laid out in.
+    pub fn region(&self, node: &Node) -> Option<Rectangle> {
+        self.position(node)
+            .map(|position| Rectangle::new(position, self.node_size))
+    }
+
+    /// Returns the smallest rectangle containing every node, or `None` if
+    /// the graph is empty.
+    pub fn bounds(&self) -> Option<Rectangle> {
+        self.positions
+            .values()
+            .map(|position| Rectangle::new(*position, self.node_size))
+            .fold(None, |bounds: Option<Rectangle>, region| {
+                Some(bounds.map_or(region, |bounds| bounds.union(&region)))
+            })
+    }
+
+    /// Returns the node at `point`. Where nodes overlap the most recently
+    /// created one, which is drawn on top, is returned.
+    pub fn node_at(&self, point: Point) -> Option<Node> {
+        self.positions
+            .keys()
+            .filter(|node| self.region(node).is_some_and(|r| r.contains(point)))
+            .max_by_key(|node| node.id)
+            .copied()
+    }
+
+    /// Returns the nodes overlapping `area`, ordered by creation.
+    pub fn nodes_in(&self, area: &Rectangle) -> Vec<Node> {
+        let mut nodes: Vec<_> = self
+            .positions
+            .keys()
+            .filter(|node| self.region(node).is_some_and(|r| r.intersects(area)))
+            .copied()
+            .collect();
+        nodes.sort_by_key(|node| node.id);
+        nodes
+    }
+
+    /// Returns the edge passing through `area` closest to its centre, e.g.
+    /// one that a node dragged over it would be spliced into. `endpoints` is
+    /// as for [`Graph::edge_at`], and edges it gives none for are skipped.
+    pub fn edge_through<F>(&self, area: &Rectangle, endpoints: F) -> Option<Edge>
+    where
+        F: Fn(&Edge) -> Option<(Point, Point)>,
+    {
+        self.edges
+            .iter()
+            .filter_map(|e| {
+                let (from, to) = endpoints(e)?;
+                let route = self.route(e);
+                edge::route_crosses(from, route, to, area)
+                    .then(|| (*e, edge::route_distance(from, route, to, area.center())))
+            })
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(e, _)| e)
+    }
+
+    /// Returns the edge closest to `point`, following its reroute points, if
+    /// it is within [`EDGE_PICK_DISTANCE`].
+    ///
+    /// Where ports sit on a node depends on how it is drawn, so `endpoints`
+    /// gives the positions of the output and input socket of each edge.
+    pub fn edge_at<F>(&self, point: Point, endpoints: F) -> Option<Edge>
+    where
+        F: Fn(&Edge) -> Option<(Point, Point)>,
+    {
+        self.edges
+            .iter()
+            .filter_map(|e| {
+                let (from, to) = endpoints(e)?;
+                Some((*e, edge::route_distance(from, self.route(e), to, point)))
+            })
+            .filter(|(_, distance)| *distance <= EDGE_PICK_DISTANCE)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(e, _)| e)
+    }
+}
+
+/// Keeps a node position within the positive quadrant.
+fn clamp(position: Point) -> Point {
+    Point::new(position.x.max(0.0), position.y.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three nodes in a row, 400 apart, with the first two connected.
+    fn graph() -> (Graph, [Node; 3]) {
+        let mut graph = Graph::new();
+        let a = graph.insert(Point::new(0.0, 0.0)).unwrap();
+        let b = graph.insert(Point::new(400.0, 0.0)).unwrap();
+        let c = graph.insert(Point::new(800.0, 0.0)).unwrap();
+        graph.connect(a, 1, b, 1).unwrap();
+        (graph, [a, b, c])
+    }
+
+    #[test]
+    fn test_insert_gives_unique_ids() {
+        let (mut graph, [a, b, c]) = graph();
+        assert_eq!(graph.nodes(), vec![a, b, c]);
+
+        graph.remove(&c);
+        let d = graph.insert(Point::ORIGIN).unwrap();
+        assert!(d != c);
+        assert_eq!(graph.len(), 3);
+    }
+
+    #[test]
+    fn test_remove_drops_edges_selection_and_focus() {
+        let (mut graph, [a, b, c]) = graph();
+        graph.connect(b, 0, c, 0).unwrap();
+        graph.select(&[a, b]);
+        graph.set_focus(b);
+
+        assert!(graph.remove(&b));
+        assert!(!graph.remove(&b));
+        assert!(graph.edges().is_empty());
+        assert_eq!(graph.selection(), vec![a]);
+        assert_eq!(graph.focus(), None);
+        assert_eq!(graph.position(&b), None);
+    }
+
+    #[test]
+    fn test_connect_rejects_duplicates_and_missing_nodes() {
+        let (mut graph, [a, b, c]) = graph();
+        assert_eq!(graph.connect(a, 1, b, 1), Err(ConnectError::Exists));
+        assert!(graph.connect(a, 0, b, 0).is_ok());
+
+        graph.remove(&c);
+        assert_eq!(graph.connect(a, 0, c, 0), Err(ConnectError::MissingNode));
+
+        assert!(graph.disconnect(&Edge::new(a, 1, b, 1)));
+        assert!(!graph.disconnect(&Edge::new(a, 1, b, 1)));
+        assert_eq!(graph.edges(), &[Edge::new(a, 0, b, 0)]);
+    }
+
+    #[test]
+    fn test_loops_need_feedback_or_delay() {
+        let (mut graph, [a, b, c]) = graph();
+        graph.connect(b, 0, c, 0).unwrap();
+
+        assert_eq!(graph.connect(c, 0, a, 0), Err(ConnectError::Loop(vec![a, b, c])));
+        assert_eq!(graph.connect(a, 0, a, 1), Err(ConnectError::Loop(vec![a])));
+        assert!(graph.connect(c, 0, a, 0).unwrap_err().to_string().contains("0 -> 1 -> 2"));
+
+        let feedback = graph.connect_feedback(c, 0, a, 0).unwrap();
+        assert!(graph.is_feedback(&feedback));
+        assert!(graph.set_feedback(&feedback, false).is_err());
+        assert_eq!(graph.set_feedback(&Edge::new(a, 0, c, 0), true), Err(ConnectError::MissingEdge));
+        assert!(graph.is_feedback(&feedback));
+        graph.disconnect(&feedback);
+        assert!(!graph.is_feedback(&feedback));
+
+        // a delay anywhere on the loop is enough
+        assert!(graph.set_delay(b, true));
+        assert!(graph.connect(c, 0, a, 0).is_ok());
+
+        // splicing into a loop through a feedback edge keeps it in time
+        let (mut graph, [a, b, c]) = self::graph();
+        let feedback = graph.connect_feedback(b, 0, a, 0).unwrap();
+        assert!(graph.splice(c, &feedback, 0, 0));
+        assert!(graph.is_feedback(&Edge::new(b, 0, c, 0)));
+        assert!(!graph.is_feedback(&Edge::new(c, 0, a, 0)));
+    }
+
+    #[test]
+    fn test_translate_stays_in_positive_quadrant() {
+        let (mut graph, [a, b, _]) = graph();
+        assert_eq!(graph.translate(b, Vector::new(-500.0, 20.0)), Some(Point::new(400.0, 0.0)));
+        assert_eq!(graph.position(&b), Some(Point::new(0.0, 20.0)));
+
+        graph.select(&[a, b]);
+        graph.translate_selection(Vector::new(10.0, -10.0));
+        assert_eq!(graph.position(&a), Some(Point::new(10.0, 0.0)));
+        assert_eq!(graph.position(&b), Some(Point::new(10.0, 10.0)));
+    }
+
+    #[test]
+    fn test_click_select() {
+        let (mut graph, [a, b, c]) = graph();
+        graph.click_select(&a, false);
+        graph.click_select(&b, true);
+        assert_eq!(graph.selection(), vec![a, b]);
+
+        // clicking a selected node keeps the selection, so it can be dragged
+        graph.click_select(&a, false);
+        assert_eq!(graph.selection(), vec![a, b]);
+
+        graph.click_select(&a, true);
+        assert_eq!(graph.selection(), vec![b]);
+
+        graph.click_select(&c, false);
+        assert_eq!(graph.selection(), vec![c]);
+    }
+
+    #[test]
+    fn test_select_all() {
+        let (mut graph, [a, b, c]) = graph();
+        let edge = graph.connect(b, 0, c, 0).unwrap();
+        graph.click_select_edge(&edge, false);
+        graph.select_all();
+        assert_eq!(graph.selection(), vec![a, b, c]);
+        assert!(graph.selected_edges().is_empty());
+
+        let frame = graph.frame_around(&[a], "Osc").unwrap();
+        graph.select_frame(frame);
+        graph.select_all();
+        assert_eq!(graph.selected_frame(), None);
+        assert_eq!(graph.selection(), vec![a, b, c]);
+    }
+
+    #[test]
+    fn test_cycle_focus_wraps() {
+        let (mut graph, [a, _, c]) = graph();
+        assert_eq!(graph.cycle_focus(false), Some(c));
+        assert_eq!(graph.cycle_focus(true), Some(a));
+        assert_eq!(graph.selection(), vec![a]);
+    }
+
+    #[test]
+    fn test_hit_testing() {
+        let (mut graph, [a, b, c]) = graph();
+        assert_eq!(graph.node_at(Point::new(150.0, 150.0)), Some(a));
+        assert_eq!(graph.node_at(Point::new(350.0, 150.0)), None);
+
+        // the newest node is on top
+        graph.move_to(c, Point::new(100.0, 100.0));
+        assert_eq!(graph.node_at(Point::new(150.0, 150.0)), Some(c));
+
+        let area = Rectangle::spanning(Point::new(350.0, 50.0), Point::new(450.0, 60.0));
+        assert_eq!(graph.nodes_in(&area), vec![b]);
+
+        assert_eq!(
+            graph.bounds(),
+            Some(Rectangle::new(Point::ORIGIN, Size::new(700.0, 400.0))));
+    }
+
+    #[test]
+    fn test_edge_at() {
+        let (graph, [a, b, _]) = graph();
+        let endpoints = |e: &Edge| {
+            let from = graph.region(&e.from)?;
+            let to = graph.region(&e.to)?;
+            Some((
+                Point::new(from.x + from.width, from.y + 50.0),
+                Point::new(to.x, to.y + 50.0)))
+        };
+
+        assert_eq!(graph.edge_at(Point::new(350.0, 52.0), endpoints), Some(Edge::new(a, 1, b, 1)));
+        assert_eq!(graph.edge_at(Point::new(350.0, 80.0), endpoints), None);
+
+        // a node dragged over the edge, though not over it at its centre
+        let area = Rectangle::new(Point::new(320.0, 0.0), Size::new(60.0, 100.0));
+        assert_eq!(graph.edge_through(&area, endpoints), Some(Edge::new(a, 1, b, 1)));
+        let area = Rectangle::new(Point::new(320.0, 60.0), Size::new(60.0, 100.0));
+        assert_eq!(graph.edge_through(&area, endpoints), None);
+    }
+
+    #[test]
+    fn test_splice_and_extract() {
+        let (mut graph, [a, b, c]) = graph();
+        let edge = Edge::new(a, 1, b, 1);
+
+        assert!(!graph.splice(a, &edge, 0, 0));
+        assert!(graph.splice(c, &edge, 0, 1));
+        assert_eq!(graph.edges(), &[Edge::new(a, 1, c, 0), Edge::new(c, 1, b, 1)]);
+
+        let extraction = graph.extract(c, &[(0, 1)], &[edge]);
+        assert_eq!(extraction.removed.len(), 2);
+        assert!(extraction.refused.is_empty());
+        assert_eq!(graph.edges(), &[edge]);
+
+        graph.extract(b, &[(1, 0)], &[]);
+        assert!(graph.edges().is_empty());
+    }
+
+    #[test]
+    fn test_extract_stereo_and_keep_modulation() {
+        let (mut graph, [a, b, c]) = graph();
+        graph.disconnect(&Edge::new(a, 1, b, 1));
+        // a stereo chain through c, whose input 2 is modulated by a
+        for port in 0..2 {
+            graph.connect(a, port, c, port).unwrap();
+            graph.connect(c, port, b, port).unwrap();
+        }
+        graph.connect(a, 2, c, 2).unwrap();
+
+        let heals = [Edge::new(a, 0, b, 0), Edge::new(a, 1, b, 1)];
+        let extraction = graph.extract(c, &[(0, 0), (1, 1)], &heals);
+        assert_eq!(extraction.removed.len(), 4);
+        assert!(extraction.refused.is_empty());
+        assert_eq!(graph.edges(), &[Edge::new(a, 2, c, 2), heals[0], heals[1]]);
+    }
+
+    #[test]
+    fn test_extract_from_loop() {
+        // the heal takes over the feedback edge it replaces
+        let (mut graph, [a, b, c]) = graph();
+        graph.connect(b, 0, c, 0).unwrap();
+        graph.connect_feedback(c, 0, a, 0).unwrap();
+        assert!(graph.extract(c, &[(0, 0)], &[Edge::new(b, 0, a, 0)]).refused.is_empty());
+        assert!(graph.is_feedback(&Edge::new(b, 0, a, 0)));
+
+        // and is refused where it would close a loop without the delay
+        let (mut graph, [a, b, c]) = self::graph();
+        graph.set_delay(c, true);
+        graph.connect(b, 0, c, 0).unwrap();
+        graph.connect(c, 0, a, 0).unwrap();
+        let heal = Edge::new(b, 0, a, 0);
+        let extraction = graph.extract(c, &[(0, 0)], &[heal]);
+        assert_eq!(extraction.refused, vec![(heal, ConnectError::Loop(vec![a, b]))]);
+        assert_eq!(graph.edges(), &[Edge::new(a, 1, b, 1)]);
+    }
+
+    #[test]
+    fn test_reroutes() {
+        let (mut graph, [a, b, _]) = graph();
+        let edge = Edge::new(a, 1, b, 1);
+        let (from, to) = (Point::new(300.0, 50.0), Point::new(400.0, 50.0));
+
+        assert_eq!(graph.add_reroute(&edge, from, to, Point::new(350.0, 200.0)), Some(0));
+        // nearer the second part of the route than the first
+        assert_eq!(graph.add_reroute(&edge, from, to, Point::new(380.0, 120.0)), Some(1));
+        assert_eq!(graph.route(&edge), &[Point::new(350.0, 200.0), Point::new(380.0, 120.0)]);
+
+        let endpoints = |_: &Edge| Some((from, to));
+        assert_eq!(graph.edge_at(Point::new(350.0, 200.0), endpoints), Some(edge));
+        assert_eq!(graph.reroute_at(Point::new(352.0, 198.0)), Some((edge, 0)));
+
+        assert_eq!(graph.move_reroute(&edge, 0, Point::new(-10.0, 10.0)), Some(Point::new(350.0, 200.0)));
+        assert_eq!(graph.route(&edge)[0], Point::new(0.0, 10.0));
+        assert_eq!(graph.remove_reroute(&edge, 1), Some(Point::new(380.0, 120.0)));
+
+        graph.disconnect(&edge);
+        assert!(graph.route(&edge).is_empty());
+    }
+
+    #[test]
+    fn test_annotations() {
+        let (mut graph, [a, b, _]) = graph();
+        assert!(graph.set_note(a, "Main oscillator"));
+        assert!(graph.set_color(a, Some([1, 2, 3])));
+        assert_eq!(graph.annotation(&a).unwrap().note, "Main oscillator");
+
+        assert!(graph.set_note(a, ""));
+        assert!(graph.set_color(a, None));
+        assert_eq!(graph.annotation(&a), None);
+
+        graph.set_note(b, "Filter");
+        graph.remove(&b);
+        assert!(!graph.set_note(b, "Filter"));
+        assert_eq!(graph.annotation(&b), None);
+    }
+
+    #[test]
+    fn test_modes() {
+        let (mut graph, [a, b, c]) = graph();
+        assert!(graph.mode(&a).is_normal());
+        assert!(graph.silenced().is_empty());
+
+        assert!(graph.set_mode(a, NodeMode::default().mute(true)));
+        assert_eq!(graph.silenced(), [a].iter().copied().collect());
+
+        // only the chain through the soloed node is heard
+        graph.set_mode(a, NodeMode::default());
+        graph.set_mode(b, NodeMode::default().solo(true).bypass(true));
+        assert!(graph.has_solo());
+        assert_eq!(graph.silenced(), [c].iter().copied().collect());
+
+        graph.remove(&b);
+        assert!(!graph.set_mode(b, NodeMode::default()));
+        assert!(!graph.has_solo());
+    }
+
+    #[test]
+    fn test_frames() {
+        let (mut graph, [a, b, c]) = graph();
+        let edge = Edge::new(a, 1, b, 1);
+        graph.set_route(&edge, vec![Point::new(350.0, 200.0)]);
+
+        let frame = graph.frame_around(&[a, b], "Voice").unwrap();
+        assert_eq!(graph.frame(frame).unwrap().bounds, Rectangle::new(
+            Point::ORIGIN,
+            Size::new(720.0, 320.0)));
+        assert_eq!(graph.enclosed(frame), vec![a, b]);
+        assert_eq!(graph.frame_at(Point::new(10.0, 10.0)), Some(frame));
+        assert_eq!(graph.frame_at(Point::new(10.0, 100.0)), None);
+
+        // cut short at the edge of the quadrant
+        assert!(graph.move_frame(frame, Vector::new(100.0, -50.0)));
+        assert_eq!(graph.position(&a), Some(Point::new(100.0, 0.0)));
+        assert_eq!(graph.position(&b), Some(Point::new(500.0, 0.0)));
+        assert_eq!(graph.position(&c), Some(Point::new(800.0, 0.0)));
+        assert_eq!(graph.route(&edge), &[Point::new(450.0, 200.0)]);
+
+        graph.select_frame(frame);
+        graph.click_select(&c, false);
+        assert_eq!(graph.selected_frame(), None);
+
+        assert!(graph.remove_frame(frame).is_some());
+        assert!(!graph.move_frame(frame, Vector::new(1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_move_frame_with_picked_contents() {
+        let (mut graph, [a, b, c]) = graph();
+        let outer = graph.frame_around(&[a, b], "Voice").unwrap();
+        let inner = graph.frame_around(&[a], "Osc").unwrap();
+        assert_eq!(graph.enclosed_frames(outer), vec![inner]);
+
+        // only what was picked moves, though the frame now covers `c`
+        assert!(graph.move_frame_with(outer, &[a], &[], Vector::new(600.0, 0.0)));
+        assert_eq!(graph.position(&a), Some(Point::new(600.0, 0.0)));
+        assert_eq!(graph.position(&b), Some(Point::new(400.0, 0.0)));
+        assert_eq!(graph.position(&c), Some(Point::new(800.0, 0.0)));
+        assert_eq!(graph.frame(inner).unwrap().bounds.x, 0.0);
+        assert_eq!(graph.frame(outer).unwrap().bounds.x, 600.0);
+    }
+
+    #[test]
+    fn test_edge_selection() {
+        let (mut graph, [a, b, c]) = graph();
+        let first = Edge::new(a, 1, b, 1);
+        let second = graph.connect(b, 0, c, 0).unwrap();
+
+        graph.select(&[a]);
+        graph.click_select_edge(&first, false);
+        assert!(graph.selection().is_empty());
+        assert_eq!(graph.selected_edges(), vec![first]);
+
+        graph.click_select_edge(&second, true);
+        assert_eq!(graph.selected_edges(), vec![first, second]);
+
+        graph.remove(&c);
+        assert_eq!(graph.selected_edges(), vec![first]);
+
+        graph.click_select(&a, false);
+        assert!(graph.selected_edges().is_empty());
+    }
+}
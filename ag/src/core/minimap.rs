@@ -0,0 +1,122 @@
+//! The scaling between a graph and its minimap.
+
+use super::geometry::{Point, Rectangle, Size, Vector};
+
+/// The space kept around the nodes and the viewport in a minimap, in graph
+/// units.
+pub const MINIMAP_PADDING: f32 = 40.0;
+
+/// Maps the part of a graph worth showing onto the area of a minimap,
+/// keeping its aspect ratio and centring it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// The region of the graph shown.
+    world: Rectangle,
+    /// The area the graph is drawn in.
+    area: Rectangle,
+    scale: f32,
+}
+
+impl Projection {
+    /// Creates a [`Projection`] showing every rectangle in `nodes` and the
+    /// `viewport`, all relative to the graph, within `area`.
+    pub fn new(area: Rectangle, nodes: &[Rectangle], viewport: Rectangle) -> Self {
+        let bounds = nodes
+            .iter()
+            .fold(viewport, |bounds, node| bounds.union(node));
+        let world = Rectangle {
+            x: bounds.x - MINIMAP_PADDING,
+            y: bounds.y - MINIMAP_PADDING,
+            width: bounds.width + 2.0 * MINIMAP_PADDING,
+            height: bounds.height + 2.0 * MINIMAP_PADDING,
+        };
+        let scale = (area.width / world.width).min(area.height / world.height);
+
+        Self { world, area, scale }
+    }
+
+    /// Returns the number of minimap units per graph unit.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Returns where a point of the graph is on the minimap.
+    pub fn to_map(&self, point: Point) -> Point {
+        let margin = self.margin();
+        Point::new(
+            self.area.x + margin.x + (point.x - self.world.x) * self.scale,
+            self.area.y + margin.y + (point.y - self.world.y) * self.scale)
+    }
+
+    /// Returns the point of the graph shown at `point` on the minimap.
+    pub fn to_graph(&self, point: Point) -> Point {
+        let margin = self.margin();
+        Point::new(
+            self.world.x + (point.x - self.area.x - margin.x) / self.scale,
+            self.world.y + (point.y - self.area.y - margin.y) / self.scale)
+    }
+
+    /// Returns where a rectangle of the graph is on the minimap.
+    pub fn rectangle(&self, rectangle: Rectangle) -> Rectangle {
+        Rectangle::new(
+            self.to_map(Point::new(rectangle.x, rectangle.y)),
+            Size::new(rectangle.width * self.scale, rectangle.height * self.scale))
+    }
+
+    /// Returns the scroll offset that centres a viewport of `size` on the
+    /// graph point shown at `point` on the minimap, kept within the positive
+    /// quadrant.
+    pub fn scroll_to(&self, point: Point, size: Size) -> Vector {
+        let center = self.to_graph(point);
+        Vector::new(
+            (center.x - size.width / 2.0).max(0.0),
+            (center.y - size.height / 2.0).max(0.0))
+    }
+
+    /// The space left on either side of the graph, along the axis it does not
+    /// fill.
+    fn margin(&self) -> Vector {
+        Vector::new(
+            (self.area.width - self.world.width * self.scale) / 2.0,
+            (self.area.height - self.world.height * self.scale) / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_projection() {
+        let area = Rectangle::new(Point::new(500.0, 400.0), Size::new(200.0, 100.0));
+        let viewport = Rectangle::new(Point::ORIGIN, Size::new(320.0, 120.0));
+        let node = Rectangle::new(Point::new(400.0, 40.0), Size::new(100.0, 80.0));
+        let projection = Projection::new(area, &[node], viewport);
+
+        // 580 x 200 graph units, including padding, into 200 x 100
+        assert!((projection.scale() - 200.0 / 580.0).abs() < 1e-6);
+
+        let corner = projection.to_map(Point::new(-MINIMAP_PADDING, -MINIMAP_PADDING));
+        assert!((corner.x - 500.0).abs() < 1e-3);
+        assert!((corner.y - (400.0 + (100.0 - 200.0 * projection.scale()) / 2.0)).abs() < 1e-3);
+
+        let point = Point::new(123.0, 45.0);
+        let back = projection.to_graph(projection.to_map(point));
+        assert!(back.distance(point) < 1e-3);
+    }
+
+    #[test]
+    fn test_scroll_to() {
+        let area = Rectangle::new(Point::ORIGIN, Size::new(100.0, 100.0));
+        let viewport = Rectangle::new(Point::ORIGIN, Size::new(200.0, 200.0));
+        let node = Rectangle::new(Point::new(700.0, 700.0), Size::new(100.0, 100.0));
+        let projection = Projection::new(area, &[node], viewport);
+
+        let far = projection.to_map(Point::new(750.0, 750.0));
+        let scroll = projection.scroll_to(far, Size::new(200.0, 200.0));
+        assert!((scroll.x - 650.0).abs() < 1e-3 && (scroll.y - 650.0).abs() < 1e-3);
+
+        let near = projection.to_map(Point::new(10.0, 10.0));
+        assert_eq!(projection.scroll_to(near, Size::new(200.0, 200.0)), Vector::new(0.0, 0.0));
+    }
+}
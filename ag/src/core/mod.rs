@@ -0,0 +1,25 @@
+//! The model behind an [`AudioGraph`]: nodes, edges, port types, positions,
+//! selection, layout and hit-testing.
+//!
+//! Nothing here depends on iced, so the model can be used, and tested,
+//! without a window.
+//!
+//! [`AudioGraph`]: crate::AudioGraph
+
+pub mod annotation;
+pub mod edge;
+pub mod geometry;
+pub mod graph;
+pub mod minimap;
+pub mod mode;
+pub mod node;
+pub mod port;
+
+pub use annotation::{Annotation, Frame, FrameId, TAGS};
+pub use graph::{ConnectError, Extraction, Graph, NODE_HEIGHT, NODE_WIDTH};
+pub use minimap::Projection;
+pub use mode::NodeMode;
+pub use port::{
+    Conversion, Direction, PortLabel, PortRegistry, PortType, PortTypeInfo, Signal,
+    SHORT_LABEL_LENGTH,
+};
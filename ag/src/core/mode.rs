@@ -0,0 +1,33 @@
+//! Bypass, mute and solo: how a node takes part in what is heard, without
+//! taking it out of the graph.
+
+/// How a node is processed.
+///
+/// A bypassed node passes its inputs straight through to the outputs of the
+/// same type, a muted one outputs silence, and while any node is soloed only
+/// the chains running through soloed nodes are heard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct NodeMode {
+    pub bypass: bool,
+    pub mute: bool,
+    pub solo: bool,
+}
+
+impl NodeMode {
+    /// Returns true if the node is processed as usual.
+    pub fn is_normal(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn bypass(self, bypass: bool) -> Self {
+        Self { bypass, ..self }
+    }
+
+    pub fn mute(self, mute: bool) -> Self {
+        Self { mute, ..self }
+    }
+
+    pub fn solo(self, solo: bool) -> Self {
+        Self { solo, ..self }
+    }
+}
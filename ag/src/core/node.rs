@@ -0,0 +1,21 @@
+/// A rectangular region in a [`AudioGraph`] used to display widgets for an audio node.
+///
+/// [`AudioGraph`]: crate::AudioGraph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Node {
+    pub(crate) id: usize,
+
+}
+
+impl Node {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id
+        }
+    }
+
+    /// Returns the numeric identifier of the [`Node`].
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
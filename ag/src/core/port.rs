@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+/// Identifies the type of a port in a [`PortRegistry`].
+///
+/// The standard types have fixed ids, so they are the same in every
+/// registry created with [`PortRegistry::default`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PortType(pub(crate) usize);
+
+impl PortType {
+    /// MIDI messages.
+    pub const MIDI: PortType = PortType(0);
+    /// Mono audio.
+    pub const AUDIO: PortType = PortType(1);
+    /// Stereo audio.
+    pub const AUDIO_STEREO: PortType = PortType(2);
+    /// Control-rate CV, one value per block.
+    pub const CONTROL: PortType = PortType(3);
+    /// Timestamped events other than MIDI, e.g. triggers and transport.
+    pub const EVENTS: PortType = PortType(4);
+
+    pub const fn new() -> Self {
+        Self (0)
+    }
+
+    pub const fn fresh(self) -> Self {
+        Self(self.0 + 1)
+    }
+
+    /// Returns a number identifying the type, e.g. to pick its colour.
+    pub const fn id(self) -> usize {
+        self.0
+    }
+}
+
+/// The kind of signal carried by a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    /// Audio-rate samples.
+    Audio,
+    /// MIDI messages.
+    Midi,
+    /// Control-rate values.
+    Control,
+    /// Timestamped events.
+    Events,
+}
+
+/// The number of characters of a port's name shown beside its socket.
+pub const SHORT_LABEL_LENGTH: usize = 4;
+
+/// Whether a port receives or produces a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+impl Direction {
+    /// Returns the direction a port must have to be connected to this one.
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Input => Direction::Output,
+            Direction::Output => Direction::Input,
+        }
+    }
+
+    /// Returns the name of port `index` when it has no [`PortLabel`], e.g.
+    /// "Input 1" for the first input.
+    pub fn port_name(self, index: usize) -> String {
+        match self {
+            Direction::Input => format!("Input {}", index + 1),
+            Direction::Output => format!("Output {}", index + 1),
+        }
+    }
+}
+
+/// The name of a port, what it is for, and the unit of its signal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PortLabel {
+    pub name: String,
+    pub description: Option<String>,
+    pub unit: Option<String>,
+}
+
+impl PortLabel {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn description(self, description: &str) -> Self {
+        Self {
+            description: Some(description.to_string()),
+            ..self
+        }
+    }
+
+    pub fn unit(self, unit: &str) -> Self {
+        Self {
+            unit: Some(unit.to_string()),
+            ..self
+        }
+    }
+
+    /// Returns the label shown beside the socket: the name, cut to
+    /// [`SHORT_LABEL_LENGTH`] characters.
+    pub fn short(&self) -> String {
+        self.name.chars().take(SHORT_LABEL_LENGTH).collect()
+    }
+
+    /// Returns the first line of the port's tooltip: the name, followed by
+    /// the unit if there is one.
+    pub fn title(&self) -> String {
+        match &self.unit {
+            Some(unit) => format!("{} ({})", self.name, unit),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// What a [`PortType`] is called and what it carries.
+///
+/// How ports of a type are drawn is up to the theme, see
+/// `PortStyle::types`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortTypeInfo {
+    pub name: String,
+    pub description: String,
+    pub signal: Signal,
+    /// The number of channels, e.g. 2 for stereo audio.
+    pub channels: usize,
+}
+
+impl PortTypeInfo {
+    pub fn new(name: &str, signal: Signal, channels: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            description: String::new(),
+            signal,
+            channels,
+        }
+    }
+
+    pub fn description(self, description: &str) -> Self {
+        Self {
+            description: description.to_string(),
+            ..self
+        }
+    }
+}
+
+/// How a signal is converted when it crosses an edge between ports of
+/// different types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Conversion {
+    /// The ports are the same type, the signal is passed on as is.
+    Direct,
+    /// Control-rate values are brought up to audio rate.
+    UpSample,
+    /// Audio is reduced to one value per block.
+    DownSample,
+    /// Each input channel is copied to several output channels.
+    UpMix,
+    /// Several input channels are summed into fewer output channels.
+    DownMix,
+}
+
+/// The port types known to a graph, and the rules for connecting them.
+///
+/// A port may only be connected to a port of the same type, or of a type
+/// the registry has a [`Conversion`] for.
+#[derive(Debug, Clone)]
+pub struct PortRegistry {
+    types: Vec<PortTypeInfo>,
+    conversions: HashMap<(PortType, PortType), Conversion>,
+}
+
+impl PortRegistry {
+    /// Creates a registry with no types at all.
+    pub fn empty() -> Self {
+        Self {
+            types: Vec::new(),
+            conversions: HashMap::new(),
+        }
+    }
+
+    /// Adds a type, returning its id.
+    pub fn register(&mut self, info: PortTypeInfo) -> PortType {
+        self.types.push(info);
+        PortType(self.types.len() - 1)
+    }
+
+    /// Allows ports of type `from` to feed ports of type `to`, converting the
+    /// signal as given.
+    pub fn allow(&mut self, from: PortType, to: PortType, conversion: Conversion) {
+        self.conversions.insert((from, to), conversion);
+    }
+
+    pub fn get(&self, port_type: PortType) -> Option<&PortTypeInfo> {
+        self.types.get(port_type.0)
+    }
+
+    /// Returns the type with the given name, ignoring case.
+    pub fn by_name(&self, name: &str) -> Option<PortType> {
+        self.types
+            .iter()
+            .position(|info| info.name.eq_ignore_ascii_case(name))
+            .map(PortType)
+    }
+
+    /// Returns the registered types in the order of their ids.
+    pub fn iter(&self) -> impl Iterator<Item = (PortType, &PortTypeInfo)> {
+        self.types
+            .iter()
+            .enumerate()
+            .map(|(id, info)| (PortType(id), info))
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// Returns how an output of type `from` feeds an input of type `to`, or
+    /// `None` if they cannot be connected.
+    pub fn conversion(&self, from: PortType, to: PortType) -> Option<Conversion> {
+        if from == to {
+            return self.get(from).map(|_| Conversion::Direct);
+        }
+
+        self.conversions.get(&(from, to)).copied()
+    }
+
+    /// Returns true if an output of type `from` may be connected to an input
+    /// of type `to`.
+    pub fn can_connect(&self, from: PortType, to: PortType) -> bool {
+        self.conversion(from, to).is_some()
+    }
+}
+
+impl Default for PortRegistry {
+    /// The standard types, with control feeding audio and mono and stereo
+    /// audio feeding each other.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register(
+            PortTypeInfo::new("MIDI", Signal::Midi, 1)
+                .description("MIDI messages"));
+        registry.register(
+            PortTypeInfo::new("Audio", Signal::Audio, 1)
+                .description("Mono audio"));
+        registry.register(
+            PortTypeInfo::new("Stereo", Signal::Audio, 2)
+                .description("Stereo audio, left then right"));
+        registry.register(
+            PortTypeInfo::new("Control", Signal::Control, 1)
+                .description("Control-rate CV, one value per block"));
+        registry.register(
+            PortTypeInfo::new("Events", Signal::Events, 1)
+                .description("Triggers and other timestamped events"));
+
+        registry.allow(PortType::CONTROL, PortType::AUDIO, Conversion::UpSample);
+        registry.allow(PortType::AUDIO, PortType::CONTROL, Conversion::DownSample);
+        registry.allow(PortType::AUDIO, PortType::AUDIO_STEREO, Conversion::UpMix);
+        registry.allow(PortType::AUDIO_STEREO, PortType::AUDIO, Conversion::DownMix);
+
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_types() {
+        let registry = PortRegistry::default();
+        assert_eq!(registry.by_name("midi"), Some(PortType::MIDI));
+        assert_eq!(registry.by_name("stereo"), Some(PortType::AUDIO_STEREO));
+        assert_eq!(registry.get(PortType::AUDIO_STEREO).unwrap().channels, 2);
+        assert_eq!(registry.get(PortType::CONTROL).unwrap().signal, Signal::Control);
+        assert_eq!(PortType::new(), PortType::MIDI);
+        assert_eq!(PortType::MIDI.fresh(), PortType::AUDIO);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let registry = PortRegistry::default();
+        assert_eq!(
+            registry.conversion(PortType::AUDIO, PortType::AUDIO),
+            Some(Conversion::Direct));
+        assert_eq!(
+            registry.conversion(PortType::CONTROL, PortType::AUDIO),
+            Some(Conversion::UpSample));
+        assert!(!registry.can_connect(PortType::MIDI, PortType::AUDIO));
+        assert!(!registry.can_connect(PortType::EVENTS, PortType::MIDI));
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = PortRegistry::default();
+        let surround = registry.register(PortTypeInfo::new("5.1", Signal::Audio, 6));
+        assert_eq!(surround.id(), 5);
+        assert!(!registry.can_connect(PortType::AUDIO, surround));
+
+        registry.allow(PortType::AUDIO_STEREO, surround, Conversion::UpMix);
+        assert!(registry.can_connect(PortType::AUDIO_STEREO, surround));
+        assert!(!registry.can_connect(PortType(6), PortType(6)));
+    }
+
+    #[test]
+    fn test_short_label() {
+        assert_eq!(PortLabel::new("Frequency").short(), "Freq");
+        assert_eq!(PortLabel::new("In").short(), "In");
+        assert_eq!(PortLabel::new("").short(), "");
+
+        // cut by characters, never inside one
+        assert_eq!(PortLabel::new("Fréquence").short(), "Fréq");
+        assert_eq!(PortLabel::new("振幅信号入力").short(), "振幅信号");
+        assert_eq!(PortLabel::new("🎵🎶 notes").short(), "🎵🎶 n");
+    }
+
+    #[test]
+    fn test_port_titles() {
+        let label = PortLabel::new("Cutoff").description("Where the filter starts");
+        assert_eq!(label.title(), "Cutoff");
+        assert_eq!(label.unit("Hz").title(), "Cutoff (Hz)");
+
+        assert_eq!(Direction::Input.port_name(0), "Input 1");
+        assert_eq!(Direction::Output.port_name(2), "Output 3");
+        assert_eq!(Direction::Input.opposite(), Direction::Output);
+    }
+}
@@ -2,11 +2,20 @@
 
 //use crate::native::audio_graph;
 use crate::native::*;
-use crate::style::style::*;
+use crate::native::edge::{edge_controls, edge_point, EdgePath};
+use crate::native::frame::{FrameBox, FRAME_HEADER};
+use crate::native::minimap::Minimap;
+use crate::native::title_bar::NOTE_HEIGHT;
+use crate::native::ports::{socket_position, Connectors, Direction, Level, Tooltip, SOCKET_RADIUS};
+use crate::core::mode::NodeMode;
+use crate::style::style::{quad, PortStyle, Theme};
 
 use iced_graphics::{Backend, Primitive, Renderer, Background, defaults};
 use iced_graphics::canvas::{path::Arc, Frame, Path, Stroke};
-use iced_native::{mouse, Point, Rectangle, Layout, Vector, Element, Color, Size};
+use iced_native::{
+    mouse, Point, Rectangle, Layout, Vector, Element, Color, Size, Font, HorizontalAlignment,
+    VerticalAlignment,
+};
 
 pub type AudioGraph<'a, Message, Backend> = 
     audio_graph::AudioGraph<'a, Message, Renderer<Backend>>;
@@ -15,13 +24,24 @@ impl<B> audio_graph::Renderer for Renderer<B>
 where
     B: Backend,
 {
-    type Style = Box<dyn StyleSheet>;
+    // shared so that views built every frame need not copy the theme
+    type Style = std::sync::Arc<Theme>;
 
     fn draw<Message>(
         &mut self,
         defaults: &Self::Defaults,
         content: &[(node::Node, content::Content<'_, Message, Self>)],
         dragging: Option<(node::Node, Point, Point)>,
+        selection: &[node::Node],
+        focus: Option<node::Node>,
+        modes: &[(NodeMode, bool)],
+        frames: &[FrameBox],
+        edges: &[EdgePath],
+        cable: Option<(Point, Point)>,
+        flow: Option<f32>,
+        tooltip: Option<Tooltip>,
+        minimap: Option<Minimap>,
+        scroll: Vector,
         layout: Layout<'_>,
         style: &<Self as crate::native::audio_graph::Renderer>::Style,
         cursor_position: Point,
@@ -44,8 +64,30 @@ where
             .zip(layout.children())
             .enumerate()
             .map(|(i, ((id, node), layout))| {
-                let (primitive, new_mouse_interaction) =
-                    node.draw(self, defaults, layout, node_cursor_position);
+                let (primitive, new_mouse_interaction) = node.draw(
+                    self,
+                    defaults,
+                    layout,
+                    node_cursor_position,
+                    style,
+                    focus == Some(*id));
+
+                let primitive = match modes.get(i).and_then(|(mode, silenced)| {
+                    mode_overlay(layout.bounds(), *mode, *silenced, style)
+                }) {
+                    Some(overlay) => Primitive::Group {
+                        primitives: vec![primitive, overlay],
+                    },
+                    None => primitive,
+                };
+
+                let primitive = if selection.contains(id) {
+                    Primitive::Group {
+                        primitives: vec![selection_outline(layout.bounds(), style), primitive],
+                    }
+                } else {
+                    primitive
+                };
 
                 if new_mouse_interaction > mouse_interaction {
                     mouse_interaction = new_mouse_interaction;
@@ -100,53 +142,44 @@ where
             };
 
             // draw patches
-            let (n1, c1) = &content[0];
-            let (n2, c2) = &content[1];
-
-            let l: Vec<_> = layout.children().collect();
-            
-            let l2 = l[0].bounds();
-            let l1 = l[1].bounds();
-
-            //println!("{:?} {:?} {:?}", l1, l2, ag_bounds);
-
-            let p = 
-                if let Some((index, layout, origin)) = dragged_node {
-                    let bounds = layout.bounds();
+            let mut frame = Frame::new(Size::new(
+                ag_bounds.x + ag_bounds.width,
+                ag_bounds.y + ag_bounds.height));
 
-                    let x = cursor_position.x  - origin.x;
-                    let y = cursor_position.y - origin.y;
-
-                    Path::new(|path| {
-                        path.move_to(Point::new(l1.x + l1.width, l1.y));
-                        path.quadratic_curve_to(
-                            Point::new(l1.x + 80.0 + l1.width, l1.y + 90.0), 
-                            Point::new(x,  y));
-                    })
-                }
-                else {
-                    Path::new(|path| {
-                        path.move_to(Point::new(l1.x + l1.width, l1.y));
-                        path.quadratic_curve_to(
-                            Point::new(l1.x + 80.0 + l1.width, l1.y + 90.0), 
-                            Point::new(l2.x,  l2.y));
-                    })
-                };
-            
-            let mut frame = Frame::new(Size::new(900.0, 900.0));
+            for edge in edges {
+                draw_edge(&mut frame, edge, flow, style);
+            }
 
-            // let e_stroke = Stroke {
-            //     width: 6.0,
-            //     color: Color::Black,
-            //     ..Stroke::default()
-            // };
-            frame.stroke(&p, Stroke::default().with_width(4.0));
+            if let Some(cable) = cable {
+                frame.stroke(
+                    &curves_path(&[cable]),
+                    Stroke::default()
+                        .with_width(style.edges.cable.width)
+                        .with_color(style.edges.cable.color));
+            }
 
             let bounds = layout.bounds();
-            let style = style.active();
-            let bg = background(bounds, &style).unwrap();
-            primitives.insert(0, bg);
-            primitives.push(frame.into_geometry().into_primitive());
+            primitives.insert(0, Primitive::Clip {
+                bounds,
+                offset: Vector::new(0, 0),
+                content: Box::new(Primitive::Group {
+                    primitives: frames.iter().map(|frame| frame_box(frame, style)).collect(),
+                }),
+            });
+            primitives.insert(0, canvas(bounds, scroll, style));
+            primitives.push(Primitive::Clip {
+                bounds,
+                offset: Vector::new(0, 0),
+                content: Box::new(frame.into_geometry().into_primitive()),
+            });
+
+            if let Some(minimap) = minimap {
+                primitives.push(minimap_box(&minimap, style));
+            }
+
+            if let Some(tooltip) = tooltip {
+                primitives.push(tooltip_box(&tooltip, bounds, style));
+            }
 
         (
             Primitive::Group { primitives },
@@ -162,19 +195,24 @@ where
         &mut self,
         defaults: &Self::Defaults,
         bounds: Rectangle,
-        style_sheet: &<Self as crate::native::audio_graph::Renderer>::Style,
+        style: &<Self as crate::native::audio_graph::Renderer>::Style,
+        is_focused: bool,
         title_bar: Option<(&crate::native::title_bar::TitleBar<'_, Message, Self>, Layout<'_>)>,
-        ports: Option<(&crate::native::ports::Ports<Self>, Layout<'_>, Layout<'_>)>,
+        ports: Option<(&crate::native::ports::Ports<Self>, Option<Layout<'_>>, Option<Layout<'_>>)>,
         body: (&Element<'_, Message, Self>, Layout<'_>),
         cursor_position: Point,
     ) -> Self::Output {
-        let style = style_sheet.active();
         let (body, body_layout) = body;
 
         let (body_primitive, body_interaction) =
             body.draw(self, defaults, body_layout, cursor_position, &bounds);
 
-        let background =  background(bounds, &style);
+        let background = Some(quad(
+            bounds,
+            style.node.background,
+            style.node.border_color,
+            style.node.border_width,
+            style.node.border_radius));
         if let Some((title_bar, title_bar_layout)) = title_bar {
             let show_controls = bounds.contains(cursor_position);
             let is_over_pick_area =
@@ -186,6 +224,8 @@ where
                 title_bar_layout,
                 cursor_position,
                 show_controls,
+                style,
+                is_focused,
             );
 
             if let Some((ports, input_bounds, output_bounds)) = ports {
@@ -195,6 +235,7 @@ where
                     input_bounds,
                     output_bounds,
                     cursor_position,
+                    style,
                 );
 
                 (
@@ -251,22 +292,33 @@ where
         &mut self,
         defaults: &Self::Defaults,
         bounds: Rectangle,
-        style_sheet: &<Self as crate::native::audio_graph::Renderer>::Style,
+        style: &<Self as crate::native::audio_graph::Renderer>::Style,
+        is_focused: bool,
         content: (&Element<'_, Message, Self>, Layout<'_>),
         controls: Option<(&Element<'_, Message, Self>, Layout<'_>)>,
+        note: Option<&str>,
+        tag: Option<Color>,
         cursor_position: Point,
     ) -> Self::Output {
-        let style = style_sheet.style();
+        let style = if is_focused {
+            style.title_bar.focused
+        } else {
+            style.title_bar.unfocused
+        };
         let (title_content, title_layout) = content;
 
         let defaults = Self::Defaults {
             text: defaults::Text {
-                //color: style.text_color.unwrap_or(defaults.text.color),
-                color: defaults.text.color, // TODO: Fix this up to use AudioWidget Style
+                color: style.text_color,
             },
         };
 
-        let background = background(bounds, &style);
+        let background = Some(quad(
+            bounds,
+            style.background,
+            style.border_color,
+            style.border_width,
+            style.border_radius));
 
         let (title_primitive, title_interaction) = title_content.draw(
             self,
@@ -276,6 +328,40 @@ where
             &bounds,
         );
 
+        let mut annotations = Vec::new();
+        if let Some(tag) = tag {
+            annotations.push(quad(
+                Rectangle { width: TAG_WIDTH, ..bounds },
+                tag,
+                Color::TRANSPARENT,
+                0.0,
+                style.border_radius));
+        }
+        if let Some(note) = note {
+            let padding = title_layout.position().x - bounds.x;
+            annotations.push(Primitive::Clip {
+                bounds,
+                offset: Vector::new(0, 0),
+                content: Box::new(Primitive::Text {
+                    content: note.to_string(),
+                    bounds: Rectangle {
+                        x: bounds.x + padding,
+                        y: bounds.y + bounds.height - padding - NOTE_HEIGHT,
+                        width: bounds.width - 2.0 * padding,
+                        height: NOTE_HEIGHT,
+                    },
+                    color: Color { a: style.text_color.a * 0.75, ..style.text_color },
+                    size: NOTE_SIZE,
+                    font: Font::Default,
+                    horizontal_alignment: HorizontalAlignment::Left,
+                    vertical_alignment: VerticalAlignment::Top,
+                }),
+            });
+        }
+        let title_primitive = Primitive::Group {
+            primitives: vec![title_primitive, Primitive::Group { primitives: annotations }],
+        };
+
         if let Some((controls, controls_layout)) = controls {
             let (controls_primitive, controls_interaction) = controls.draw(
                 self,
@@ -312,57 +398,493 @@ where
     fn draw_ports(
         &mut self,
         defaults: &Self::Defaults,
-        input_layout: Layout<'_>,
-        output_layout: Layout<'_>,
-        style_sheet: &<Self as crate::native::audio_graph::Renderer>::Style,
-        num: usize,
+        input_layout: Option<Layout<'_>>,
+        output_layout: Option<Layout<'_>>,
+        style: &<Self as crate::native::audio_graph::Renderer>::Style,
+        ports: &dyn Connectors,
         cursor_position: Point,
     ) -> Self::Output {
-        let mut mouse_interaction = mouse::Interaction::default();
-        
-        let inputs_primitives = Primitive::Quad {
-            bounds: input_layout.bounds(),
-            background: Background::Color(Color::from_rgba(
-                0xF0 as f32 / 255.0,
-                0xF3 as f32 / 255.0,
-                0xA5 as f32 / 255.0,
-                1.0
-            )),
-            border_radius: 1.0,
-            border_width: 0.5,
-            border_color: Color::from_rgba(
-                0xF2 as f32 / 255.0,
-                0xF3 as f32 / 255.0,
-                0xF5 as f32 / 255.0,
-                1.0
-            ),
-        };
-
-        let outputs_primitives = Primitive::Quad {
-            bounds: output_layout.bounds(),
-            background: Background::Color(Color::from_rgba(
-                0xF0 as f32 / 255.0,
-                0xF3 as f32 / 255.0,
-                0xA5 as f32 / 255.0,
-                1.0
-            )),
-            border_radius: 1.0,
-            border_width: 0.5,
-            border_color: Color::from_rgba(
-                0xF2 as f32 / 255.0,
-                0xF3 as f32 / 255.0,
-                0xF5 as f32 / 255.0,
-                1.0
-            ),
-        };
+        let mouse_interaction = mouse::Interaction::default();
+
+        let mut primitives =
+            ports_column(input_layout, Direction::Input, ports, &style.ports, cursor_position);
+        primitives.extend(
+            ports_column(output_layout, Direction::Output, ports, &style.ports, cursor_position));
+
+        if let Some(layout) = output_layout {
+            primitives.extend((0..ports.output_connections()).filter_map(|index| {
+                ports
+                    .output_level(index)
+                    .map(|level| meter(layout.bounds(), index, level))
+            }));
+        }
 
         (
             Primitive::Group {
-                primitives: vec![inputs_primitives, outputs_primitives],
+                primitives,
             },
             mouse_interaction
         )
     }
 }
-    
-    
\ No newline at end of file
+
+/// The space before the title of a comment frame.
+const FRAME_TITLE_PADDING: f32 = 8.0;
+
+/// The width of the colour tag along the left of a title bar.
+const TAG_WIDTH: f32 = 4.0;
+
+/// The text size of the note in a title bar.
+const NOTE_SIZE: f32 = 11.0;
+
+/// The distance between the marks showing the direction of flow.
+const FLOW_SPACING: f32 = 24.0;
+
+/// The number of straight pieces used to measure a curve.
+const FLOW_STEPS: usize = 16;
+
+/// The number of straight pieces a curve is dashed along.
+const DASH_STEPS: usize = 64;
+
+/// Returns a path through consecutive cubic curves, as drawn for edges.
+fn curves_path(curves: &[(Point, Point)]) -> Path {
+    Path::new(|path| {
+        if let Some((from, _)) = curves.first() {
+            path.move_to(*from);
+        }
+        for (from, to) in curves {
+            let (a, b) = edge_controls(*from, *to);
+            path.bezier_curve_to(a, b, *to);
+        }
+    })
+}
+
+/// Returns a path of dashes along consecutive cubic curves, with dashes and
+/// the gaps between them `dash` long.
+fn dashed_path(curves: &[(Point, Point)], dash: f32) -> Path {
+    Path::new(|path| {
+        // how far the current dash or gap has gone
+        let mut length = 0.0;
+        let mut drawing = true;
+
+        for (from, to) in curves {
+            let mut previous = *from;
+            if drawing {
+                path.move_to(previous);
+            }
+
+            for i in 1..=DASH_STEPS {
+                let point = edge_point(*from, *to, i as f32 / DASH_STEPS as f32);
+                length += previous.distance(point);
+                if drawing {
+                    path.line_to(point);
+                }
+                if length >= dash {
+                    length = 0.0;
+                    drawing = !drawing;
+                    if drawing {
+                        path.move_to(point);
+                    }
+                }
+                previous = point;
+            }
+        }
+    })
+}
+
+/// Draws an edge along its route, coloured by its port type unless selected
+/// or hovered, with its reroute points and, when animated, its flow marks.
+/// Feedback edges are dashed.
+fn draw_edge(frame: &mut Frame, edge: &EdgePath, flow: Option<f32>, style: &Theme) {
+    let styles = &style.edges;
+
+    let edge_style = if edge.selected {
+        styles.selected
+    } else if edge.hovered {
+        styles.hovered
+    } else {
+        styles.normal
+    };
+
+    let color = match edge.port_type {
+        Some(port_type) if styles.color_by_type && !edge.selected => {
+            let color = style.ports.color(port_type);
+            if edge.hovered { mix(color, edge_style.color) } else { color }
+        }
+        _ => edge_style.color,
+    };
+
+    let curves = edge.curves();
+    let path = if edge.feedback {
+        dashed_path(&curves, styles.feedback_dash.max(1.0))
+    } else {
+        curves_path(&curves)
+    };
+    frame.stroke(
+        &path,
+        Stroke::default()
+            .with_width(edge_style.width)
+            .with_color(color));
+
+    for reroute in &edge.reroutes {
+        let handle = Path::circle(*reroute, edge_style.width * 0.5 + 3.0);
+        frame.fill(&handle, color);
+        frame.stroke(&handle, Stroke::default().with_width(1.0).with_color(style.ports.socket_border));
+    }
+
+    if let Some(phase) = flow {
+        let phase = phase.rem_euclid(1.0);
+        for (from, to) in curves {
+            let length: f32 = (0..FLOW_STEPS)
+                .map(|i| {
+                    let a = edge_point(from, to, i as f32 / FLOW_STEPS as f32);
+                    let b = edge_point(from, to, (i + 1) as f32 / FLOW_STEPS as f32);
+                    a.distance(b)
+                })
+                .sum();
+
+            let marks = (length / FLOW_SPACING).round().max(1.0) as usize;
+            for i in 0..marks {
+                let t = (i as f32 + phase) / marks as f32;
+                frame.fill(
+                    &Path::circle(edge_point(from, to, t), styles.flow.width / 2.0),
+                    styles.flow.color);
+            }
+        }
+    }
+}
+
+/// Returns the colour halfway between `a` and `b`.
+fn mix(a: Color, b: Color) -> Color {
+    Color::from_rgba(
+        (a.r + b.r) / 2.0,
+        (a.g + b.g) / 2.0,
+        (a.b + b.b) / 2.0,
+        (a.a + b.a) / 2.0)
+}
+
+/// The canvas background, with its grid moved along as the view scrolls.
+fn canvas(bounds: Rectangle, scroll: Vector, style: &Theme) -> Primitive {
+    let background = quad(
+        bounds,
+        style.canvas.background,
+        style.canvas.border_color,
+        style.canvas.border_width,
+        0.0);
+
+    let grid = match style.canvas.grid {
+        Some(grid) if grid.spacing > 0.0 => grid,
+        _ => return background,
+    };
+
+    let line = |bounds| quad(bounds, grid.color, Color::TRANSPARENT, 0.0, 0.0);
+    // the distance from the edge of the canvas to the first line
+    let first = |scroll: f32| grid.spacing - scroll.rem_euclid(grid.spacing);
+    let (first_column, first_row) = (first(scroll.x), first(scroll.y));
+    let columns = ((bounds.width - first_column) / grid.spacing).max(0.0) as usize;
+    let rows = ((bounds.height - first_row) / grid.spacing).max(0.0) as usize;
+
+    let mut primitives = vec![background];
+    primitives.extend((0..=columns).map(|i| line(Rectangle {
+        x: bounds.x + first_column + i as f32 * grid.spacing,
+        width: grid.width,
+        ..bounds
+    })));
+    primitives.extend((0..=rows).map(|i| line(Rectangle {
+        y: bounds.y + first_row + i as f32 * grid.spacing,
+        height: grid.width,
+        ..bounds
+    })));
+
+    Primitive::Group { primitives }
+}
+
+/// A comment frame, tinted with its colour tag if it has one.
+fn frame_box(frame: &FrameBox, style: &Theme) -> Primitive {
+    let outline = &style.selection;
+    let style = &style.frame;
+
+    let tint = |alpha: f32| frame.color.map(|color| Color { a: alpha, ..color });
+    let (border_color, border_width) = if frame.selected {
+        (outline.border_color, outline.border_width)
+    } else {
+        (style.border_color, style.border_width)
+    };
+    let header = Rectangle {
+        height: FRAME_HEADER.min(frame.bounds.height),
+        ..frame.bounds
+    };
+
+    let mut primitives = vec![
+        quad(
+            frame.bounds,
+            tint(style.tint).unwrap_or(style.background),
+            border_color,
+            border_width,
+            style.border_radius),
+        quad(
+            header,
+            tint((2.0 * style.tint).min(1.0)).unwrap_or(style.background),
+            Color::TRANSPARENT,
+            0.0,
+            style.border_radius),
+    ];
+
+    if !frame.title.is_empty() {
+        primitives.push(Primitive::Clip {
+            bounds: header,
+            offset: Vector::new(0, 0),
+            content: Box::new(Primitive::Text {
+                content: frame.title.clone(),
+                bounds: Rectangle {
+                    x: header.x + FRAME_TITLE_PADDING,
+                    y: header.y + (header.height - style.title_size) / 2.0,
+                    ..header
+                },
+                color: style.title_color,
+                size: style.title_size,
+                font: Font::Default,
+                horizontal_alignment: HorizontalAlignment::Left,
+                vertical_alignment: VerticalAlignment::Top,
+            }),
+        });
+    }
+
+    Primitive::Group { primitives }
+}
+
+/// The minimap: every node, and the part of the graph in view.
+fn minimap_box(minimap: &Minimap, style: &Theme) -> Primitive {
+    let style = &style.minimap;
+
+    let mut primitives = vec![quad(
+        minimap.bounds,
+        style.background,
+        style.border_color,
+        style.border_width,
+        0.0)];
+
+    primitives.extend(minimap.nodes.iter().map(|(bounds, selected)| {
+        let color = if *selected { style.selected_color } else { style.node_color };
+        quad(*bounds, color, Color::TRANSPARENT, 0.0, 0.0)
+    }));
+
+    primitives.push(quad(
+        minimap.viewport,
+        style.viewport_color,
+        style.viewport_border_color,
+        1.0,
+        0.0));
+
+    Primitive::Clip {
+        bounds: minimap.bounds,
+        offset: Vector::new(0, 0),
+        content: Box::new(Primitive::Group { primitives }),
+    }
+}
+
+/// Draws the outline of a selected node.
+fn selection_outline(bounds: Rectangle, style: &Theme) -> Primitive {
+    let offset = style.selection.offset;
+    quad(
+        Rectangle {
+            x: bounds.x - offset,
+            y: bounds.y - offset,
+            width: bounds.width + 2.0 * offset,
+            height: bounds.height + 2.0 * offset,
+        },
+        Color::TRANSPARENT,
+        style.selection.border_color,
+        style.selection.border_width,
+        style.node.border_radius + offset)
+}
+
+/// Dims a node that is not heard and outlines one that is bypassed or
+/// soloed, bypass taking precedence. Returns `None` for a node processed as
+/// usual.
+fn mode_overlay(bounds: Rectangle, mode: NodeMode, silenced: bool, style: &Theme) -> Option<Primitive> {
+    let border_color = if mode.bypass {
+        Some(style.modes.bypass_color)
+    } else if mode.solo {
+        Some(style.modes.solo_color)
+    } else {
+        None
+    };
+
+    if !silenced && border_color.is_none() {
+        return None;
+    }
+
+    Some(quad(
+        bounds,
+        if silenced { style.modes.silenced } else { Color::TRANSPARENT },
+        border_color.unwrap_or(Color::TRANSPARENT),
+        if border_color.is_some() { style.modes.border_width } else { 0.0 },
+        style.node.border_radius))
+}
+
+/// The size of the labels drawn beside sockets.
+const LABEL_SIZE: f32 = 9.0;
+
+/// Draws a column of sockets, each coloured by its type and with its short
+/// label, if any, on the side facing the node's body.
+fn ports_column(
+    layout: Option<Layout<'_>>,
+    direction: Direction,
+    ports: &dyn Connectors,
+    style: &PortStyle,
+    cursor_position: Point) -> Vec<Primitive> {
+    let bounds = match layout {
+        Some(layout) => layout.bounds(),
+        None => return Vec::new(),
+    };
+
+    let types = match direction {
+        Direction::Input => ports.inputs(),
+        Direction::Output => ports.outputs(),
+    };
+
+    let mut primitives = vec![quad(bounds, style.background, style.border_color, 0.5, 1.0)];
+
+    for (index, port_type) in types.enumerate() {
+        let centre = socket_position(bounds, index);
+        let hovered = centre.distance(cursor_position) <= SOCKET_RADIUS * 2.0;
+        let radius = if hovered { SOCKET_RADIUS + 1.0 } else { SOCKET_RADIUS };
+
+        primitives.push(Primitive::Quad {
+            bounds: Rectangle {
+                x: centre.x - radius,
+                y: centre.y - radius,
+                width: radius * 2.0,
+                height: radius * 2.0,
+            },
+            background: Background::Color(style.color(*port_type)),
+            border_radius: radius,
+            border_width: 1.0,
+            border_color: style.socket_border,
+        });
+
+        let label = match direction {
+            Direction::Input => ports.input_label(index),
+            Direction::Output => ports.output_label(index),
+        };
+
+        if let Some(label) = label {
+            let (x, horizontal_alignment) = match direction {
+                Direction::Input => (centre.x + SOCKET_RADIUS + 3.0, HorizontalAlignment::Left),
+                Direction::Output => (centre.x - SOCKET_RADIUS - 3.0, HorizontalAlignment::Right),
+            };
+
+            primitives.push(Primitive::Text {
+                content: label.short(),
+                bounds: Rectangle {
+                    x,
+                    y: centre.y,
+                    width: bounds.width / 2.0,
+                    height: LABEL_SIZE,
+                },
+                color: style.label_color,
+                size: LABEL_SIZE,
+                font: Font::Default,
+                horizontal_alignment,
+                vertical_alignment: VerticalAlignment::Center,
+            });
+        }
+    }
+
+    primitives
+}
+
+/// Draws a [`Tooltip`] below and to the right of its socket, or to the left
+/// when it would leave the canvas.
+fn tooltip_box(tooltip: &Tooltip, canvas: Rectangle, style: &Theme) -> Primitive {
+    let style = &style.tooltip;
+    let line_height = style.text_size * 1.3;
+
+    // an estimate, as the renderer cannot measure text here
+    let longest = tooltip.lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    let size = Size::new(
+        longest as f32 * style.text_size * 0.6 + 2.0 * style.padding,
+        tooltip.lines.len() as f32 * line_height + 2.0 * style.padding);
+
+    let offset = SOCKET_RADIUS * 2.0;
+    let x = if tooltip.position.x + offset + size.width > canvas.x + canvas.width {
+        tooltip.position.x - offset - size.width
+    } else {
+        tooltip.position.x + offset
+    };
+    let y = (tooltip.position.y + offset)
+        .min(canvas.y + canvas.height - size.height)
+        .max(canvas.y);
+    let bounds = Rectangle { x, y, width: size.width, height: size.height };
+
+    let mut primitives = vec![quad(
+        bounds,
+        style.background,
+        style.border_color,
+        style.border_width,
+        style.border_radius)];
+
+    primitives.extend(tooltip.lines.iter().enumerate().map(|(i, line)| Primitive::Text {
+        content: line.clone(),
+        bounds: Rectangle {
+            x: bounds.x + style.padding,
+            y: bounds.y + style.padding + i as f32 * line_height,
+            width: bounds.width - 2.0 * style.padding,
+            height: line_height,
+        },
+        color: style.text_color,
+        size: style.text_size,
+        font: Font::Default,
+        horizontal_alignment: HorizontalAlignment::Left,
+        vertical_alignment: VerticalAlignment::Top,
+    }));
+
+    Primitive::Group { primitives }
+}
+
+/// The height of the meter drawn under an output socket.
+const METER_HEIGHT: f32 = 3.0;
+
+/// Draws the meter for output `index` under its socket: the RMS level as a
+/// bar, the peak as a tick, both red while clipping.
+fn meter(bounds: Rectangle, index: usize, level: Level) -> Primitive {
+    let centre = socket_position(bounds, index);
+    let track = Rectangle {
+        x: bounds.x + 1.0,
+        y: centre.y + SOCKET_RADIUS + 2.0,
+        width: bounds.width - 2.0,
+        height: METER_HEIGHT,
+    };
+
+    let colour = if level.clip {
+        Color::from_rgb(0.9, 0.2, 0.2)
+    } else {
+        Color::from_rgb(0.3, 0.8, 0.4)
+    };
+
+    let rms = level.rms.max(0.0).min(1.0);
+    let peak = level.peak.max(0.0).min(1.0);
+
+    let quad = |bounds, colour| Primitive::Quad {
+        bounds,
+        background: Background::Color(colour),
+        border_radius: 0.0,
+        border_width: 0.0,
+        border_color: Color::TRANSPARENT,
+    };
+
+    Primitive::Group {
+        primitives: vec![
+            quad(track, Color::from_rgb(0.15, 0.15, 0.15)),
+            quad(Rectangle { width: track.width * rms, ..track }, colour),
+            quad(
+                Rectangle {
+                    x: track.x + (track.width * peak - 1.0).max(0.0),
+                    width: 1.0,
+                    ..track
+                },
+                colour),
+        ],
+    }
+}
@@ -26,7 +26,13 @@ pub use platform::*;
 pub use native::node::Node;
 pub use native::state::State;
 pub use native::content::Content;
-pub use native::audio_graph::DragEvent;
+pub use native::audio_graph::{DragEvent, DropEdit, ClipboardEvent, PaletteRequest};
+pub use native::edge::{Edge, EdgePath};
+pub use native::frame::{tag_color, FrameBox};
+pub use native::patch::*;
+pub use native::bindings::*;
+pub use native::history::History;
+pub use native::minimap::{Minimap, MINIMAP_SIZE};
 pub use native::title_bar::*;
 pub use native::layout_node::*;
 pub use native::ports::*;
@@ -1,14 +1,21 @@
 
 use super::node::Node;
 use super::content::Content;
+use super::bindings::{Bindings, Shortcut};
+use super::edge::{Edge, EdgePath};
+use super::frame::{tag_color, FrameBox, FrameId};
+use super::ports::{Direction, Level, Socket, Tooltip};
+use super::minimap::{minimap_bounds, projection, Minimap};
+use crate::core::mode::NodeMode;
+use crate::core::port::{PortRegistry, PortType};
 
-use crate::style::style::{StyleSheet};
+use std::collections::HashMap;
 
 use std::fmt::Debug;
 
 use iced_native::{
     event, keyboard, layout, mouse, Clipboard, Element, Event, Hasher, Layout,
-    Length, Point, Rectangle, Size, Widget, Vector, overlay, container, row
+    Color, Length, Point, Rectangle, Size, Widget, Vector, overlay, container, row
 };
 
 #[allow(missing_debug_implementations)]
@@ -20,6 +27,17 @@ pub struct AudioGraph<'a, Message, Renderer: self::Renderer> {
     spacing: u16,
     on_click: Option<Box<dyn Fn(super::node::Node) -> Message + 'a>>,
     on_drag: Option<Box<dyn Fn(DragEvent) -> Message + 'a>>,
+    on_clipboard: Option<Box<dyn Fn(ClipboardEvent) -> Message + 'a>>,
+    on_shortcut: Option<Box<dyn Fn(Shortcut) -> Message + 'a>>,
+    on_focus: Option<Box<dyn Fn(super::node::Node) -> Message + 'a>>,
+    on_connect: Option<Box<dyn Fn(Edge) -> Message + 'a>>,
+    on_feedback: Option<Box<dyn Fn(Edge) -> Message + 'a>>,
+    on_palette: Option<Box<dyn Fn(PaletteRequest) -> Message + 'a>>,
+    on_edge_click: Option<Box<dyn Fn(Edge) -> Message + 'a>>,
+    bindings: Option<&'a Bindings>,
+    port_types: Option<&'a PortRegistry>,
+    flow: Option<f32>,
+    minimap: Option<Size>,
     //on_resize: Option<(u16, Box<dyn Fn(ResizeEvent) -> Message + 'a>)>,
     style_sheet: <Renderer as super::audio_graph::Renderer>::Style,
 }
@@ -52,6 +70,17 @@ where
             spacing: 0,
             on_click: None,
             on_drag: None,
+            on_clipboard: None,
+            on_shortcut: None,
+            on_focus: None,
+            on_connect: None,
+            on_feedback: None,
+            on_palette: None,
+            on_edge_click: None,
+            bindings: None,
+            port_types: None,
+            flow: None,
+            minimap: None,
             //on_resize: None,
             style_sheet: Default::default(),
         }
@@ -95,6 +124,131 @@ where
         self
     }
 
+    /// Enables copy, cut, paste and duplicate of the selected nodes, which will
+    /// use the provided function to produce messages.
+    ///
+    /// The [`AudioGraph`] only reports the request, the application is
+    /// expected to act on it with [`State::copy`], [`State::paste`], etc.
+    ///
+    /// [`State::copy`]: crate::native::state::State::copy
+    /// [`State::paste`]: crate::native::state::State::paste
+    pub fn on_clipboard<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(ClipboardEvent) -> Message,
+    {
+        self.on_clipboard = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the message that will be produced for keyboard shortcuts the
+    /// [`AudioGraph`] does not handle itself, such as delete, nudge, undo and
+    /// redo, and any [`Shortcut::Custom`] bindings.
+    pub fn on_shortcut<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(Shortcut) -> Message,
+    {
+        self.on_shortcut = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the message that will be produced when a [`Node`] gains keyboard
+    /// focus, either by clicking it or cycling with tab.
+    pub fn on_focus<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(super::node::Node) -> Message,
+    {
+        self.on_focus = Some(Box::new(f));
+        self
+    }
+
+    /// Enables connecting nodes by dragging a cable between two compatible
+    /// sockets, which will use the provided function to produce messages.
+    pub fn on_connect<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(Edge) -> Message,
+    {
+        self.on_connect = Some(Box::new(f));
+        self
+    }
+
+    /// Enables connecting nodes with a feedback edge, which may close a loop,
+    /// by dropping a cable with alt held. Without it, such a cable makes an
+    /// ordinary connection.
+    pub fn on_feedback<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(Edge) -> Message,
+    {
+        self.on_feedback = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the message that will be produced when the user asks for the node
+    /// palette, by double clicking empty canvas, the palette shortcut or
+    /// dropping a cable on empty canvas.
+    pub fn on_palette<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(PaletteRequest) -> Message,
+    {
+        self.on_palette = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the message that will be produced when an edge is clicked.
+    ///
+    /// Clicking an edge also selects it, so that [`Shortcut::Delete`] can
+    /// disconnect it, see [`State::remove_selection`].
+    ///
+    /// [`State::remove_selection`]: crate::native::state::State::remove_selection
+    pub fn on_edge_click<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(Edge) -> Message,
+    {
+        self.on_edge_click = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the keyboard [`Bindings`] of the [`AudioGraph`], replacing the
+    /// defaults.
+    pub fn bindings(mut self, bindings: &'a Bindings) -> Self {
+        self.bindings = Some(bindings);
+        self
+    }
+
+    /// Sets the [`PortRegistry`] deciding which sockets a cable may join.
+    ///
+    /// Without one, only sockets of the same [`PortType`] can be connected.
+    ///
+    /// [`PortType`]: crate::core::port::PortType
+    pub fn port_types(mut self, port_types: &'a PortRegistry) -> Self {
+        self.port_types = Some(port_types);
+        self
+    }
+
+    /// Animates the direction signals flow along each edge, as marks moving
+    /// from output to input.
+    ///
+    /// `phase` places the marks, and is expected to grow steadily between
+    /// frames; only its fractional part is used.
+    pub fn flow(mut self, phase: f32) -> Self {
+        self.flow = Some(phase);
+        self
+    }
+
+    /// Shows a minimap of `size` in the bottom right corner, with every node
+    /// and the part of the graph in view. Clicking or dragging on it moves
+    /// the view.
+    ///
+    /// See [`MINIMAP_SIZE`] for a default size.
+    ///
+    /// [`MINIMAP_SIZE`]: crate::native::minimap::MINIMAP_SIZE
+    pub fn minimap(mut self, size: Size) -> Self {
+        self.minimap = Some(size);
+        self
+    }
+
+    /// Sets the style the [`AudioGraph`] and its nodes are drawn with.
+    ///
+    /// Nodes, title bars and ports given a style of their own keep it.
     pub fn set_style_sheet(mut self, style_sheet: <Renderer as super::audio_graph::Renderer>::Style) -> Self {
         self.style_sheet = style_sheet;
         self
@@ -105,18 +259,96 @@ impl<'a, Message, Renderer> AudioGraph<'a, Message, Renderer>
 where
     Renderer: self::Renderer,
 {
+    /// Returns where the origin of the graph is in the window, given how far
+    /// the view is scrolled.
+    fn origin(&self, layout: Layout<'_>) -> Vector {
+        let position = layout.position();
+        Vector::new(position.x, position.y) - self.state.scroll()
+    }
+
+    /// Converts a point in the window to a point relative to the graph.
+    fn to_graph(&self, layout: Layout<'_>, point: Point) -> Point {
+        point - self.origin(layout)
+    }
+
+    /// Returns the region of every node and whether it is selected, and the
+    /// region in view, all relative to the graph.
+    fn overview(&self, layout: Layout<'_>) -> (Vec<(Rectangle, bool)>, Rectangle) {
+        let nodes = self
+            .elements
+            .iter()
+            .filter_map(|(node, _)| {
+                Some((self.state.region(node)?, self.state.is_selected(node)))
+            })
+            .collect();
+        let scroll = self.state.scroll();
+        let viewport = Rectangle::new(Point::new(scroll.x, scroll.y), layout.bounds().size());
+
+        (nodes, viewport)
+    }
+
+    /// Returns each comment frame as it is to be drawn.
+    fn frames(&self, layout: Layout<'_>) -> Vec<FrameBox> {
+        let origin = self.origin(layout);
+        let selected = self.state.selected_frame();
+
+        self.state
+            .graph()
+            .frames()
+            .map(|(id, frame)| {
+                let bounds = Rectangle::from(frame.bounds);
+                FrameBox {
+                    id,
+                    bounds: Rectangle {
+                        x: bounds.x + origin.x,
+                        y: bounds.y + origin.y,
+                        ..bounds
+                    },
+                    title: frame.title.clone(),
+                    color: frame.color.map(tag_color),
+                    selected: selected == Some(id),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the area of the minimap, if shown.
+    fn minimap_area(&self, layout: Layout<'_>) -> Option<Rectangle> {
+        self.minimap.map(|size| minimap_bounds(layout.bounds(), size))
+    }
+
+    /// Centres the view on the part of the graph under the cursor on the
+    /// minimap.
+    fn navigate(&mut self, layout: Layout<'_>, cursor_position: Point) {
+        let area = match self.minimap_area(layout) {
+            Some(area) => area,
+            None => return,
+        };
+        let (nodes, viewport) = self.overview(layout);
+        let projection = projection(area, nodes.into_iter().map(|(node, _)| node), viewport);
+        let scroll = projection.scroll_to(cursor_position.into(), viewport.size().into());
+        self.state.scroll_to(scroll.into());
+    }
+
     fn click_node(
         &mut self,
         layout: Layout<'_>,
         cursor_position: Point,
         messages: &mut Vec<Message>,
-    ) {
+    ) -> bool {
         let mut clicked_region =
             self.elements.iter().zip(layout.children()).filter(
                 |(_, layout)| layout.bounds().contains(cursor_position),
             );
 
         if let Some(((node, content), layout)) = clicked_region.next() {
+            self.state.click_select(node);
+            self.state.set_focus(*node);
+
+            if let Some(on_focus) = &self.on_focus {
+                messages.push(on_focus(*node));
+            }
+
             if let Some(on_click) = &self.on_click {
                 messages.push(on_click(*node));
             }
@@ -134,12 +366,567 @@ where
                     messages.push(on_drag(DragEvent::Picked { node: *node }));
                 }
             }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn click_canvas(
+        &mut self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+    ) {
+        if !self.state.modifiers().shift {
+            self.state.clear_selection();
+        }
+
+        if self.state.click_canvas(cursor_position) {
+            self.request_palette(layout, cursor_position, None, messages);
+        }
+    }
+
+    fn request_palette(
+        &self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        cable: Option<(Node, Socket)>,
+        messages: &mut Vec<Message>,
+    ) {
+        if let Some(on_palette) = &self.on_palette {
+            messages.push(on_palette(PaletteRequest {
+                position: self.to_graph(layout, cursor_position),
+                cable,
+            }));
+        }
+    }
+
+    /// Returns the socket under the cursor, and the [`Node`] it belongs to.
+    fn socket_at(
+        &self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+    ) -> Option<(Node, Socket)> {
+        self.elements
+            .iter()
+            .zip(layout.children())
+            .find_map(|((node, content), layout)| {
+                content
+                    .socket_at(layout, cursor_position)
+                    .map(|socket| (*node, socket))
+            })
+    }
+
+    /// Returns the details of the port under the cursor, unless a node or a
+    /// cable is being dragged.
+    ///
+    /// An input shows the level of the output feeding it.
+    fn tooltip(&self, layout: Layout<'_>, cursor_position: Point) -> Option<Tooltip> {
+        if self.state.picked_node().is_some() || self.state.cable().is_some() {
+            return None;
+        }
+
+        let ((node, content), node_layout) = self
+            .elements
+            .iter()
+            .zip(layout.children())
+            .find(|((_, content), layout)| content.socket_at(*layout, cursor_position).is_some())?;
+        let socket = content.socket_at(node_layout, cursor_position)?;
+        let position = content.socket_position(node_layout, socket.direction, socket.index)?;
+        let connectors = content.ports()?.connectors();
+
+        let output_level = |node: Node, index: usize| {
+            self.elements
+                .iter()
+                .find(|(n, _)| *n == node)
+                .and_then(|(_, content)| content.ports())
+                .and_then(|ports| ports.connectors().output_level(index))
+        };
+
+        let (label, level) = match socket.direction {
+            Direction::Input => (
+                connectors.input_label(socket.index),
+                self.state
+                    .edges()
+                    .iter()
+                    .find(|edge| edge.to == *node && edge.input == socket.index)
+                    .and_then(|edge| output_level(edge.from, edge.output)),
+            ),
+            Direction::Output => (
+                connectors.output_label(socket.index),
+                connectors.output_level(socket.index),
+            ),
+        };
+
+        let mut lines = vec![label.map_or_else(
+            || socket.direction.port_name(socket.index),
+            |label| label.title())];
+
+        lines.push(match self.port_types.and_then(|types| types.get(socket.port_type)) {
+            Some(info) if info.channels > 1 => format!("{}, {} channels", info.name, info.channels),
+            Some(info) => info.name.clone(),
+            None => format!("Type {}", socket.port_type.id()),
+        });
+
+        if let Some(description) = label.and_then(|label| label.description.as_ref()) {
+            lines.push(description.clone());
+        }
+
+        if let Some(level) = level {
+            lines.push(level_text(level));
+        }
+
+        Some(Tooltip { position, lines })
+    }
+
+    /// Returns true if an output of type `from` may feed an input of type
+    /// `to`, by the [`PortRegistry`] if there is one.
+    fn connectable(&self, from: PortType, to: PortType) -> bool {
+        match self.port_types {
+            Some(registry) => registry.can_connect(from, to),
+            None => from == to,
+        }
+    }
+
+    /// Returns the type of a port of `node`, if it exists.
+    fn port_type(&self, node: Node, direction: Direction, index: usize) -> Option<PortType> {
+        let (_, content) = self.elements.iter().find(|(n, _)| *n == node)?;
+        let connectors = content.ports()?.connectors();
+        match direction {
+            Direction::Input => connectors.input_port_type(index),
+            Direction::Output => connectors.output_port_type(index),
+        }
+    }
+
+    /// Returns the edge under the dragged `node` that it could be spliced
+    /// into, with the first of its inputs and outputs that fit the edge.
+    fn splice_at(&self, layout: Layout<'_>, cursor_position: Point, node: Node) -> Option<DropEdit> {
+        let edge = self.edge_under(layout, cursor_position, node)?;
+
+        let (_, content) = self.elements.iter().find(|(n, _)| *n == node)?;
+        let connectors = content.ports()?.connectors();
+        let from = self.port_type(edge.from, Direction::Output, edge.output)?;
+        let to = self.port_type(edge.to, Direction::Input, edge.input)?;
+
+        let input = connectors.inputs().position(|input| self.connectable(from, *input))?;
+        let output = connectors.outputs().position(|output| self.connectable(*output, to))?;
+
+        Some(DropEdit::Splice { edge, input, output })
+    }
+
+    /// Returns the ports a chain passes through `node` by, as pairs of an
+    /// input and an output: the n-th output of a type is fed by the n-th
+    /// input of that type, as when the node is bypassed.
+    fn through(&self, node: Node) -> Vec<(usize, usize)> {
+        let connectors = match self.elements.iter().find(|(n, _)| *n == node) {
+            Some((_, content)) => match content.ports() {
+                Some(ports) => ports.connectors(),
+                None => return Vec::new(),
+            },
+            None => return Vec::new(),
+        };
+        let inputs: Vec<PortType> = connectors.inputs().copied().collect();
+        let outputs: Vec<PortType> = connectors.outputs().copied().collect();
+
+        outputs
+            .iter()
+            .enumerate()
+            .filter_map(|(output, port_type)| {
+                let nth = outputs[..output].iter().filter(|t| *t == port_type).count();
+                let (input, _) = inputs.iter().enumerate().filter(|(_, t)| *t == port_type).nth(nth)?;
+                Some((input, output))
+            })
+            .collect()
+    }
+
+    /// Returns how `node` is taken out of its chain: the ports the chain
+    /// passes through it by, and the edges that reconnect its neighbours,
+    /// from what fed each of those inputs to what its paired output fed,
+    /// wherever they fit.
+    fn extraction(&self, node: Node) -> DropEdit {
+        let through = self.through(node);
+        let edges = self.state.edges();
+
+        let mut heal = Vec::new();
+        for (input, output) in &through {
+            let incoming = edges.iter().filter(|edge| edge.to == node && edge.input == *input);
+            for i in incoming {
+                let outgoing = edges.iter().filter(|edge| edge.from == node && edge.output == *output);
+                for o in outgoing.filter(|o| o.to != i.from) {
+                    heal.push(Edge::new(i.from, i.output, o.to, o.input));
+                }
+            }
+        }
+        heal.retain(|edge| {
+            match (
+                self.port_type(edge.from, Direction::Output, edge.output),
+                self.port_type(edge.to, Direction::Input, edge.input),
+            ) {
+                (Some(from), Some(to)) => self.connectable(from, to),
+                _ => false,
+            }
+        });
+
+        DropEdit::Extract { through, heal }
+    }
+
+    /// Finishes dragging a cable, connecting it if dropped on a compatible
+    /// socket or asking for the palette if dropped on empty canvas.
+    fn drop_cable(
+        &mut self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+    ) {
+        if let Some((node, socket, _)) = self.state.cable() {
+            match self.socket_at(layout, cursor_position) {
+                Some((target, target_socket)) => {
+                    let (from, to) = match socket.direction {
+                        Direction::Output => (socket.port_type, target_socket.port_type),
+                        Direction::Input => (target_socket.port_type, socket.port_type),
+                    };
+                    let compatible = target != node
+                        && target_socket.direction == socket.direction.opposite()
+                        && self.connectable(from, to);
+
+                    let on_connect = match &self.on_feedback {
+                        Some(on_feedback) if self.state.modifiers().alt => Some(on_feedback),
+                        _ => self.on_connect.as_ref(),
+                    };
+
+                    if let (true, Some(on_connect)) = (compatible, on_connect) {
+                        let edge = match socket.direction {
+                            Direction::Output => Edge::new(
+                                node, socket.index, target, target_socket.index),
+                            Direction::Input => Edge::new(
+                                target, target_socket.index, node, socket.index),
+                        };
+                        messages.push(on_connect(edge));
+                    }
+                }
+                None => {
+                    let over_node = layout
+                        .children()
+                        .any(|layout| layout.bounds().contains(cursor_position));
+
+                    if !over_node {
+                        self.request_palette(
+                            layout, cursor_position, Some((node, socket)), messages);
+                    }
+                }
+            }
+        }
+
+        self.state.idle();
+    }
+
+    /// Returns how far the dragged node, if any, is drawn from its layout.
+    fn drag_translation(
+        &self,
+        node: Node,
+        layout: Layout<'_>,
+        cursor_position: Point,
+    ) -> Vector {
+        match self.state.picked_node() {
+            Some((dragging, origin, _)) if dragging == node => {
+                let bounds = layout.bounds();
+                Vector::new(
+                    cursor_position.x - bounds.x - origin.x,
+                    cursor_position.y - bounds.y - origin.y)
+            }
+            _ => Vector::new(0.0, 0.0),
+        }
+    }
+
+    /// Returns the edge passing under the body of `node`, where it is drawn
+    /// while dragged, other than those attached to it.
+    fn edge_under(&self, layout: Layout<'_>, cursor_position: Point, node: Node) -> Option<Edge> {
+        let relative = |point: Point| self.to_graph(layout, point);
+
+        let (_, node_layout) = self
+            .elements
+            .iter()
+            .zip(layout.children())
+            .find(|((n, _), _)| *n == node)?;
+        let bounds = node_layout.bounds();
+        let top_left = Point::new(bounds.x, bounds.y) + self.drag_translation(node, node_layout, cursor_position);
+        let area = Rectangle::new(relative(top_left), bounds.size());
+
+        let (edges, _) = self.cables(layout, cursor_position);
+        let sockets: HashMap<_, _> = edges
+            .into_iter()
+            .filter(|path| !path.edge.touches(node))
+            .map(|path| (path.edge, (relative(path.from).into(), relative(path.to).into())))
+            .collect();
+
+        self.state
+            .graph()
+            .edge_through(&area.into(), |edge| sockets.get(edge).copied())
+    }
+
+    /// Returns the edge closest to the cursor, if it is close enough to pick.
+    fn edge_at(&self, layout: Layout<'_>, cursor_position: Point) -> Option<Edge> {
+        let relative = |point: Point| self.to_graph(layout, point);
+
+        let (edges, _) = self.cables(layout, cursor_position);
+        let sockets: HashMap<_, _> = edges
+            .into_iter()
+            .map(|path| (path.edge, (relative(path.from).into(), relative(path.to).into())))
+            .collect();
+
+        self.state
+            .graph()
+            .edge_at(relative(cursor_position).into(), |edge| sockets.get(edge).copied())
+    }
+
+    /// Returns each edge as it is to be drawn, and the end points of the
+    /// cable being dragged if any, leaving outputs and arriving at inputs.
+    fn cables(
+        &self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+    ) -> (Vec<EdgePath>, Option<(Point, Point)>) {
+        let origin = self.origin(layout);
+        let nodes: HashMap<_, _> = self
+            .elements
+            .iter()
+            .zip(layout.children())
+            .map(|((node, content), layout)| (*node, (content, layout)))
+            .collect();
+
+        let socket = |node: Node, direction, index| {
+            let (content, layout) = nodes.get(&node)?;
+            let position = content.socket_position(*layout, direction, index)?;
+            Some(position + self.drag_translation(node, *layout, cursor_position))
+        };
+
+        let edges = self
+            .state
+            .edges()
+            .iter()
+            .filter_map(|edge| {
+                Some(EdgePath {
+                    edge: *edge,
+                    from: socket(edge.from, Direction::Output, edge.output)?,
+                    to: socket(edge.to, Direction::Input, edge.input)?,
+                    reroutes: self
+                        .state
+                        .route(edge)
+                        .into_iter()
+                        .map(|point| point + origin)
+                        .collect(),
+                    port_type: nodes
+                        .get(&edge.from)
+                        .and_then(|(content, _)| content.ports())
+                        .and_then(|ports| ports.connectors().output_port_type(edge.output)),
+                    feedback: self.state.graph().is_feedback(edge),
+                    hovered: false,
+                    selected: self.state.is_edge_selected(edge),
+                })
+            })
+            .collect();
+
+        let cable = self.state.cable().and_then(|(node, pending, end)| {
+            let start = socket(node, pending.direction, pending.index)?;
+            match pending.direction {
+                Direction::Output => Some((start, end)),
+                Direction::Input => Some((end, start)),
+            }
+        });
+
+        (edges, cable)
+    }
+
+    /// Handles a click that missed every node and socket: picks up a reroute
+    /// point, selects an edge, picks up a frame by its header, or falls
+    /// through to the canvas.
+    ///
+    /// Double clicking an edge adds a reroute point, and double clicking a
+    /// reroute point removes it.
+    fn click_edges(
+        &mut self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+    ) {
+        let relative = self.to_graph(layout, cursor_position);
+
+        if let Some((edge, index)) = self.state.reroute_at(relative) {
+            let route = self.state.route(&edge);
+            if self.state.click_canvas(cursor_position) {
+                // handed to the application to apply as an edit
+                if let Some(on_drag) = &self.on_drag {
+                    let mut removed = route;
+                    removed.remove(index);
+                    messages.push(on_drag(DragEvent::Rerouted { edge, route: removed }));
+                }
+            } else {
+                self.state.click_select_edge(&edge);
+                self.state.pick_reroute(edge, index, route);
+            }
+            return;
+        }
+
+        let edge = match self.edge_at(layout, cursor_position) {
+            Some(edge) => edge,
+            None => {
+                match self.state.frame_at(relative) {
+                    Some(frame) => self.state.pick_frame(frame, cursor_position),
+                    None => self.click_canvas(layout, cursor_position, messages),
+                }
+                return;
+            }
+        };
+
+        if self.state.click_canvas(cursor_position) {
+            let (edges, _) = self.cables(layout, cursor_position);
+            if let Some(path) = edges.iter().find(|path| path.edge == edge) {
+                let (from, to) = (self.to_graph(layout, path.from), self.to_graph(layout, path.to));
+                let route = self.state.route(&edge);
+                if let Some(index) = self.state.add_reroute(&edge, from, to, relative) {
+                    self.state.pick_reroute(edge, index, route);
+                }
+            }
+        } else {
+            self.state.click_select_edge(&edge);
+            if let Some(on_edge_click) = &self.on_edge_click {
+                messages.push(on_edge_click(edge));
+            }
+        }
+    }
+
+    fn shortcut(
+        &mut self,
+        shortcut: Shortcut,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+        clipboard: Option<&dyn Clipboard>,
+    ) -> event::Status {
+        let clipboard_event = match shortcut {
+            Shortcut::Copy => Some(ClipboardEvent::Copy),
+            Shortcut::Cut => Some(ClipboardEvent::Cut),
+            Shortcut::Duplicate => Some(ClipboardEvent::Duplicate),
+            Shortcut::Paste | Shortcut::PasteInPlace => {
+                let position = if shortcut == Shortcut::Paste {
+                    Some(self.to_graph(layout, cursor_position))
+                } else {
+                    None
+                };
+
+                Some(ClipboardEvent::Paste {
+                    contents: clipboard.and_then(|c| c.content()),
+                    position,
+                })
+            }
+            _ => None,
+        };
+
+        if let Some(clipboard_event) = clipboard_event {
+            return match &self.on_clipboard {
+                Some(on_clipboard) => {
+                    messages.push(on_clipboard(clipboard_event));
+                    event::Status::Captured
+                }
+                None => event::Status::Ignored,
+            };
+        }
+
+        match shortcut {
+            Shortcut::SelectAll => {
+                self.state.select_all();
+                event::Status::Captured
+            }
+            Shortcut::Palette => {
+                self.request_palette(layout, cursor_position, None, messages);
+                event::Status::Captured
+            }
+            Shortcut::FocusNext | Shortcut::FocusPrevious => {
+                let forward = shortcut == Shortcut::FocusNext;
+                if let Some(node) = self.state.cycle_focus(forward) {
+                    if let Some(on_focus) = &self.on_focus {
+                        messages.push(on_focus(node));
+                    }
+                }
+                event::Status::Captured
+            }
+            _ => match &self.on_shortcut {
+                Some(on_shortcut) => {
+                    messages.push(on_shortcut(shortcut));
+                    event::Status::Captured
+                }
+                None => event::Status::Ignored,
+            },
         }
     }
 }
 
-/// An event produced during a drag and drop interaction of a [`PaneGrid`].
+/// How far one line of a mouse wheel scrolls the view.
+const SCROLL_LINE: f32 = 40.0;
+
+/// A request, produced by an [`AudioGraph`], to show the node palette.
 #[derive(Debug, Clone, Copy)]
+pub struct PaletteRequest {
+    /// Where a new node should be placed, relative to the graph.
+    pub position: Point,
+    /// The socket a cable was dragged from, if the palette was opened by
+    /// dropping a cable. The new node should be connected to it.
+    pub cable: Option<(Node, Socket)>,
+}
+
+/// A clipboard request produced by an [`AudioGraph`] for the current
+/// selection.
+///
+/// The data is exchanged in the patch text format, see [`Patch`].
+///
+/// [`Patch`]: crate::native::patch::Patch
+#[derive(Debug, Clone)]
+pub enum ClipboardEvent {
+    /// Copy the selected nodes.
+    Copy,
+    /// Copy the selected nodes and remove them.
+    Cut,
+    /// Paste nodes.
+    Paste {
+        /// The contents of the system clipboard, if available. Applications
+        /// should fall back to their own copy if this is not a valid patch.
+        contents: Option<String>,
+        /// Where to place the pasted nodes, relative to the graph, or `None`
+        /// to offset them from their original position.
+        position: Option<Point>,
+    },
+    /// Duplicate the selected nodes in place.
+    Duplicate,
+}
+
+/// A change to the edges of a dropped [`Node`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropEdit {
+    /// The node was dropped on `edge`, and should be inserted into it using
+    /// its ports `input` and `output`, see [`State::splice`].
+    ///
+    /// [`State::splice`]: crate::native::state::State::splice
+    Splice {
+        edge: Edge,
+        input: usize,
+        output: usize,
+    },
+    /// The node was dragged with alt held, and should be taken out of the
+    /// chain that passes through its `through` ports, reconnecting its
+    /// neighbours with the `heal` edges, paired by port, see
+    /// [`State::extract`].
+    ///
+    /// [`State::extract`]: crate::native::state::State::extract
+    Extract {
+        through: Vec<(usize, usize)>,
+        heal: Vec<Edge>,
+    },
+}
+
+/// An event produced during a drag and drop interaction of a [`PaneGrid`].
+#[derive(Debug, Clone)]
 pub enum DragEvent {
     /// A [`Node`] was picked for dragging.
     Picked {
@@ -151,6 +938,9 @@ pub enum DragEvent {
         // The dropped [`Node`].
         node: super::node::Node,
         diff: Point,
+        /// The change to the edges that goes with the move, if any. It is
+        /// meant to be applied, with the move, as a single edit.
+        edit: Option<DropEdit>,
     },
     /// A [`Node`] was picked and then dropped outside of other [`Node`]
     /// boundaries.
@@ -158,6 +948,24 @@ pub enum DragEvent {
         /// The picked [`Node`].
         node: super::node::Node,
     },
+    /// A comment frame was dragged by its header and dropped. It is meant to
+    /// be moved by `diff`, with everything within it, see
+    /// [`State::move_frame`].
+    ///
+    /// [`State::move_frame`]: crate::native::state::State::move_frame
+    FrameDropped {
+        frame: FrameId,
+        diff: Vector,
+    },
+    /// Reroute points of `edge` were added, dragged or removed. Its reroute
+    /// points are meant to be set to `route`, relative to the graph, see
+    /// [`State::set_route`].
+    ///
+    /// [`State::set_route`]: crate::native::state::State::set_route
+    Rerouted {
+        edge: Edge,
+        route: Vec<Point>,
+    },
 }
 
 impl<'a, Message, Renderer> Widget<Message, Renderer>
@@ -181,21 +989,13 @@ where
         let limits = limits.width(self.width).height(self.height);
         let size = limits.resolve(Size::ZERO);
 
-        let regions = self.state.positions();
         //let regions = self.state.node_regions(f32::from(self.spacing), size);
 
-
         let children = self
             .elements
             .iter()
             .filter_map(|(node, element)| {
-                let mut region = regions.get(node)?;
-                let region = Rectangle {
-                    x: region.x,
-                    y: region.y,
-                    width: 300.0,
-                    height: 300.0
-                };
+                let region = self.state.region(node)?;
                 let size = Size::new(region.width, region.height);
                 //let size = Size::new(300.0,300.0);
 
@@ -204,7 +1004,7 @@ where
                 let mut node =
                     element.layout(renderer, &layout::Limits::new(size, size));
 
-                node.move_to(Point::new(region.x, region.y));
+                node.move_to(Point::new(region.x, region.y) - self.state.scroll());
 
                 Some(node)
             })
@@ -226,16 +1026,84 @@ where
 
         match event {
             Event::Mouse(mouse_event) => match mouse_event {
+                mouse::Event::ButtonPressed(mouse::Button::Left)
+                    if self.minimap_area(layout).map_or(false, |area| area.contains(cursor_position)) => {
+                    self.state.pick_minimap();
+                    self.navigate(layout, cursor_position);
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::ButtonPressed(mouse::Button::Middle)
+                    if layout.bounds().contains(cursor_position) => {
+                    self.state.pick_canvas(cursor_position);
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::ButtonReleased(mouse::Button::Middle) if self.state.is_panning() => {
+                    self.state.idle();
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::ButtonReleased(mouse::Button::Left) if self.state.is_navigating() => {
+                    self.state.idle();
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::ButtonReleased(mouse::Button::Left)
+                    if self.state.picked_frame().is_some() => {
+                    // hand the move to the application to apply as an edit
+                    if let (Some((frame, diff)), Some(on_drag)) = (self.state.drop_frame(), &self.on_drag) {
+                        if diff != Vector::new(0.0, 0.0) {
+                            messages.push(on_drag(DragEvent::FrameDropped { frame, diff }));
+                        }
+                    }
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::WheelScrolled { delta }
+                    if layout.bounds().contains(cursor_position)
+                        && !layout.children().any(|node| node.bounds().contains(cursor_position)) => {
+                    let (x, y) = match delta {
+                        mouse::ScrollDelta::Lines { x, y } => (x * SCROLL_LINE, y * SCROLL_LINE),
+                        mouse::ScrollDelta::Pixels { x, y } => (x, y),
+                    };
+                    // shift turns the wheel sideways, for mice with one wheel
+                    let offset = if self.state.modifiers().shift {
+                        Vector::new(-y, -x)
+                    } else {
+                        Vector::new(-x, -y)
+                    };
+                    self.state.scroll_by(offset);
+                    event_status = event::Status::Captured;
+                }
                 mouse::Event::ButtonPressed(mouse::Button::Left) => {
                     let bounds = layout.bounds();
 
                     if bounds.contains(cursor_position) {
                         event_status = event::Status::Captured;
-                        self.click_node(
-                            layout,
-                            cursor_position,
-                            messages);
+
+                        let socket = self.socket_at(layout, cursor_position);
+
+                        match socket {
+                            Some((node, socket)) if self.on_connect.is_some() => {
+                                self.state.pick_socket(node, socket, cursor_position);
+                            }
+                            _ => {
+                                if !self.click_node(layout, cursor_position, messages) {
+                                    self.click_edges(layout, cursor_position, messages);
+                                }
+                            }
+                        }
+                    }
+                }
+                mouse::Event::ButtonReleased(mouse::Button::Left)
+                    if self.state.picked_reroute().is_some() => {
+                    // hand the new route to the application to apply as an
+                    // edit
+                    if let (Some((edge, route)), Some(on_drag)) = (self.state.drop_reroute(), &self.on_drag) {
+                        messages.push(on_drag(DragEvent::Rerouted { edge, route }));
                     }
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::ButtonReleased(mouse::Button::Left)
+                    if self.state.cable().is_some() => {
+                    self.drop_cable(layout, cursor_position, messages);
+                    event_status = event::Status::Captured;
                 }
                 mouse::Event::ButtonReleased(mouse::Button::Left) => {
                     if let Some((node, _, _)) = self.state.picked_node() {
@@ -251,7 +1119,12 @@ where
                                 println!("Dropped o: {:?} {:?} {:?} {:?}", layout.bounds(), cursor_position, origin, prev_cursor_position);
                                 let diff = cursor_position - Vector::new(prev_cursor_position.x, prev_cursor_position.y);
                                 println!("Dropped o': {:?}", origin);
-                                let event = DragEvent::Dropped { node, diff };
+                                let edit = if self.state.modifiers().alt {
+                                    Some(self.extraction(node))
+                                } else {
+                                    self.splice_at(layout, cursor_position, node)
+                                };
+                                let event = DragEvent::Dropped { node, diff, edit };
                                 messages.push(on_drag(event));
                             }
                             
@@ -273,16 +1146,61 @@ where
                         event_status = event::Status::Captured;
                     }
                 }
+                mouse::Event::CursorMoved { .. } if self.state.picked_reroute().is_some() => {
+                    self.state.drag_reroute(self.to_graph(layout, cursor_position));
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::CursorMoved { .. } if self.state.picked_frame().is_some() => {
+                    self.state.drag_frame(cursor_position);
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::CursorMoved { .. } if self.state.is_panning() => {
+                    self.state.drag_canvas(cursor_position);
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::CursorMoved { .. } if self.state.is_navigating() => {
+                    self.navigate(layout, cursor_position);
+                    event_status = event::Status::Captured;
+                }
+                mouse::Event::CursorMoved { .. } if self.state.cable().is_some() => {
+                    self.state.move_cable(cursor_position);
+                    event_status = event::Status::Captured;
+                }
                 mouse::Event::CursorMoved { .. } => {
                     // event_status =
                     //     self.trigger_resize(layout, cursor_position, messages);
                 }
                 _ => {}
             },
+            Event::Keyboard(keyboard_event) => match keyboard_event {
+                keyboard::Event::ModifiersChanged(modifiers) => {
+                    self.state.set_modifiers(modifiers);
+                }
+                keyboard::Event::KeyPressed { key_code, modifiers } => {
+                    self.state.set_modifiers(modifiers);
+
+                    if layout.bounds().contains(cursor_position) {
+                        let shortcut = match self.bindings {
+                            Some(bindings) => bindings.lookup(key_code, modifiers),
+                            None => Bindings::default().lookup(key_code, modifiers),
+                        };
+
+                        if let Some(shortcut) = shortcut {
+                            event_status = self.shortcut(
+                                shortcut,
+                                layout,
+                                cursor_position,
+                                messages,
+                                clipboard);
+                        }
+                    }
+                }
+                _ => {}
+            },
             _ => {}
         }
 
-        if self.state.picked_node().is_none() {
+        if self.state.is_idle() {
             self.elements
                 .iter_mut()
                 .zip(layout.children())
@@ -310,11 +1228,53 @@ where
         cursor_position: Point,
         _viewport: &Rectangle,
     ) -> Renderer::Output {
+        let (mut edges, cable) = self.cables(layout, cursor_position);
+        let highlighted = match self.state.picked_node() {
+            // the edge the dragged node would be spliced into
+            Some((node, _, _)) if !self.state.modifiers().alt => {
+                match self.splice_at(layout, cursor_position, node) {
+                    Some(DropEdit::Splice { edge, .. }) => Some(edge),
+                    _ => None,
+                }
+            }
+            None if self.state.is_idle() => self.edge_at(layout, cursor_position),
+            _ => None,
+        };
+        if let Some(highlighted) = highlighted {
+            edges
+                .iter_mut()
+                .filter(|path| path.edge == highlighted)
+                .for_each(|path| path.hovered = true);
+        }
+
+        let graph = self.state.graph();
+        let silenced = graph.silenced();
+        let modes: Vec<_> = self
+            .elements
+            .iter()
+            .map(|(node, _)| (graph.mode(node), silenced.contains(node)))
+            .collect();
+
+        let minimap = self.minimap.map(|size| {
+            let (nodes, viewport) = self.overview(layout);
+            Minimap::new(layout.bounds(), size, &nodes, viewport)
+        });
+
         self::Renderer::draw(
             renderer,
             defaults,
             &self.elements,
             self.state.picked_node(),
+            &self.state.selection(),
+            self.state.focus(),
+            &modes,
+            &self.frames(layout),
+            &edges,
+            cable,
+            self.flow,
+            self.tooltip(layout, cursor_position),
+            minimap,
+            self.state.scroll(),
             layout,
             &self.style_sheet,
             cursor_position)
@@ -346,15 +1306,34 @@ where
     }
 }
 
+/// Describes a meter [`Level`] for a tooltip, as positions on the meter's
+/// scale.
+fn level_text(level: Level) -> String {
+    format!(
+        "Level {:.0}%, peak {:.0}%{}",
+        level.rms * 100.0,
+        level.peak * 100.0,
+        if level.clip { ", clipping" } else { "" })
+}
+
 pub trait Renderer: iced_native::Renderer + iced_native::container::Renderer + Sized  {
     /// The style supported by this renderer.
-    type Style: Default;
+    type Style: Default + Clone;
 
     /// Draws an [`AudioGraph`].
     ///
     /// It receives:
     /// - the nodes of the [`AudioGraph`]
     /// - the [`Node`] that is currently being dragged
+    /// - the selected nodes, and the node with focus
+    /// - the mode of each node, in order, and whether it is silenced
+    /// - the comment frames, in the order they are drawn
+    /// - each edge, with its route and highlighting
+    /// - the end points of the cable being dragged, if any
+    /// - the phase of the flow marks along each edge, if animated
+    /// - the [`Tooltip`] of the port under the cursor, if any
+    /// - the [`Minimap`], if shown
+    /// - how far the view is scrolled
     /// - the [`Layout`] of the [`AudioGraph`] and its nodes
     /// - the cursor position
     /// [`AudioGraph`]: crate::AudioGraph
@@ -363,6 +1342,16 @@ pub trait Renderer: iced_native::Renderer + iced_native::container::Renderer + S
         defaults: &Self::Defaults,
         nodes: &[(Node, Content<'_, Message, Self>)],
         dragging: Option<(Node, Point, Point)>,
+        selection: &[Node],
+        focus: Option<Node>,
+        modes: &[(NodeMode, bool)],
+        frames: &[FrameBox],
+        edges: &[EdgePath],
+        cable: Option<(Point, Point)>,
+        flow: Option<f32>,
+        tooltip: Option<Tooltip>,
+        minimap: Option<Minimap>,
+        scroll: Vector,
         layout: Layout<'_>,
         style: &<Self as super::audio_graph::Renderer>::Style,
         cursor_position: Point,
@@ -371,6 +1360,7 @@ pub trait Renderer: iced_native::Renderer + iced_native::container::Renderer + S
     // Draws a [`Pane`].
     ///
     /// It receives:
+    /// - the bounds and style of the [`Node`], and whether it has focus
     /// - the [`Content`] of the [`Node`]
     /// - the [`Layout`] of the [`Node`] and its elements
     /// - the cursor position
@@ -380,8 +1370,9 @@ pub trait Renderer: iced_native::Renderer + iced_native::container::Renderer + S
         defaults: &Self::Defaults,
         bounds: Rectangle,
         style: &<Self as super::audio_graph::Renderer>::Style,
+        is_focused: bool,
         title_bar: Option<(&super::title_bar::TitleBar<'_, Message, Self>, Layout<'_>)>,
-        ports: Option<(&super::ports::Ports<Self>, Layout<'_>, Layout<'_>)>,
+        ports: Option<(&super::ports::Ports<Self>, Option<Layout<'_>>, Option<Layout<'_>>)>,
         body: (&Element<'_, Message, Self>, Layout<'_>),
         cursor_position: Point,
     ) -> Self::Output;
@@ -390,17 +1381,21 @@ pub trait Renderer: iced_native::Renderer + iced_native::container::Renderer + S
     ///
     /// It receives:
     /// - the bounds, style of the [`TitleBar`]
-    /// - the style of the [`TitleBar`]
+    /// - the style of the [`TitleBar`], and whether its node has focus
     /// - the content of the [`TitleBar`] with its layout
     /// - the controls of the [`TitleBar`] with their [`Layout`], if any
+    /// - the note of the [`TitleBar`] and its colour tag, if any
     /// - the cursor position
     fn draw_title_bar<Message>(
         &mut self,
         defaults: &Self::Defaults,
         bounds: Rectangle,
         style: &<Self as super::audio_graph::Renderer>::Style,
+        is_focused: bool,
         content: (&Element<'_, Message, Self>, Layout<'_>),
         controls: Option<(&Element<'_, Message, Self>, Layout<'_>)>,
+        note: Option<&str>,
+        tag: Option<Color>,
         cursor_position: Point,
     ) -> Self::Output;
 
@@ -409,15 +1404,15 @@ pub trait Renderer: iced_native::Renderer + iced_native::container::Renderer + S
     /// It receives:
     /// - the bounds, style of the [`InputPorts`]
     /// - the style of the [`InputPorts`]
-    /// - the number of the [`InputPorts`] with its layout
+    /// - the [`Connectors`] describing each port
     /// - the cursor position
     fn draw_ports(
         &mut self,
         defaults: &Self::Defaults,
-        input_layout: Layout<'_>,
-        output_layout: Layout<'_>,
+        input_layout: Option<Layout<'_>>,
+        output_layout: Option<Layout<'_>>,
         style: &<Self as super::audio_graph::Renderer>::Style,
-        num: usize,
+        ports: &dyn super::ports::Connectors,
         cursor_position: Point,
     ) -> Self::Output;
 }
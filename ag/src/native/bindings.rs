@@ -0,0 +1,177 @@
+use iced_native::{keyboard, Vector};
+
+/// The distance, in pixels, a node moves for a single nudge.
+pub const NUDGE_STEP: f32 = 1.0;
+
+/// The distance, in pixels, a node moves for a nudge with shift held.
+pub const NUDGE_STEP_LARGE: f32 = 10.0;
+
+/// A shortcut triggered from the keyboard in an [`AudioGraph`].
+///
+/// [`AudioGraph`]: crate::audio_graph::AudioGraph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shortcut {
+    /// Remove the selected nodes.
+    Delete,
+    /// Move the selected nodes by the given amount.
+    Nudge(Vector),
+    /// Select every node.
+    SelectAll,
+    Undo,
+    Redo,
+    /// Move focus to the next node.
+    FocusNext,
+    /// Move focus to the previous node.
+    FocusPrevious,
+    Copy,
+    Cut,
+    /// Paste at the cursor.
+    Paste,
+    /// Paste at an offset from the original nodes.
+    PasteInPlace,
+    Duplicate,
+    /// Open the node palette at the cursor.
+    Palette,
+    /// Put a comment frame around the selected nodes.
+    Frame,
+    /// An application defined shortcut.
+    Custom(&'static str),
+}
+
+/// A key together with the modifiers that must be held for a binding to
+/// trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub key_code: keyboard::KeyCode,
+    /// Control, or the logo key on macOS.
+    pub command: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Chord {
+    /// Creates a new [`Chord`] for a key pressed without modifiers.
+    pub fn new(key_code: keyboard::KeyCode) -> Self {
+        Self {
+            key_code,
+            command: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    /// Requires the platform command modifier.
+    pub fn command(mut self) -> Self {
+        self.command = true;
+        self
+    }
+
+    /// Requires shift.
+    pub fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Requires alt.
+    pub fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// Returns true if the [`Chord`] matches a key press.
+    pub fn matches(
+        &self,
+        key_code: keyboard::KeyCode,
+        modifiers: keyboard::Modifiers) -> bool {
+        self.key_code == key_code
+            && self.command == modifiers.is_command_pressed()
+            && self.shift == modifiers.shift
+            && self.alt == modifiers.alt
+    }
+}
+
+/// The keyboard bindings of an [`AudioGraph`].
+///
+/// The default bindings cover editing of the graph; applications can add
+/// their own with [`Bindings::bind`], typically mapping to
+/// [`Shortcut::Custom`].
+///
+/// [`AudioGraph`]: crate::audio_graph::AudioGraph
+#[derive(Debug, Clone)]
+pub struct Bindings {
+    bindings: Vec<(Chord, Shortcut)>,
+}
+
+impl Bindings {
+    /// Creates an empty set of [`Bindings`].
+    pub const fn empty() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds `chord` to `shortcut`, replacing any existing binding for the
+    /// same chord.
+    pub fn bind(mut self, chord: Chord, shortcut: Shortcut) -> Self {
+        self.bindings.retain(|(c, _)| *c != chord);
+        self.bindings.push((chord, shortcut));
+        self
+    }
+
+    /// Removes the binding for `chord`, if any.
+    pub fn unbind(mut self, chord: Chord) -> Self {
+        self.bindings.retain(|(c, _)| *c != chord);
+        self
+    }
+
+    /// Returns the [`Shortcut`] bound to a key press, if any.
+    pub fn lookup(
+        &self,
+        key_code: keyboard::KeyCode,
+        modifiers: keyboard::Modifiers) -> Option<Shortcut> {
+        self.bindings
+            .iter()
+            .find(|(chord, _)| chord.matches(key_code, modifiers))
+            .map(|(_, shortcut)| *shortcut)
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use keyboard::KeyCode;
+
+        let nudges = [
+            (KeyCode::Left, Vector::new(-1.0, 0.0)),
+            (KeyCode::Right, Vector::new(1.0, 0.0)),
+            (KeyCode::Up, Vector::new(0.0, -1.0)),
+            (KeyCode::Down, Vector::new(0.0, 1.0)),
+        ];
+
+        let bindings = Self::empty()
+            .bind(Chord::new(KeyCode::Delete), Shortcut::Delete)
+            .bind(Chord::new(KeyCode::Backspace), Shortcut::Delete)
+            .bind(Chord::new(KeyCode::A).command(), Shortcut::SelectAll)
+            .bind(Chord::new(KeyCode::Z).command(), Shortcut::Undo)
+            .bind(Chord::new(KeyCode::Y).command(), Shortcut::Redo)
+            .bind(Chord::new(KeyCode::Z).command().shift(), Shortcut::Redo)
+            .bind(Chord::new(KeyCode::Tab), Shortcut::FocusNext)
+            .bind(Chord::new(KeyCode::Tab).shift(), Shortcut::FocusPrevious)
+            .bind(Chord::new(KeyCode::C).command(), Shortcut::Copy)
+            .bind(Chord::new(KeyCode::X).command(), Shortcut::Cut)
+            .bind(Chord::new(KeyCode::V).command(), Shortcut::Paste)
+            .bind(Chord::new(KeyCode::V).command().shift(), Shortcut::PasteInPlace)
+            .bind(Chord::new(KeyCode::D).command(), Shortcut::Duplicate)
+            .bind(Chord::new(KeyCode::Space), Shortcut::Palette)
+            .bind(Chord::new(KeyCode::G).command(), Shortcut::Frame);
+
+        nudges
+            .iter()
+            .fold(bindings, |bindings, (key_code, direction)| {
+                bindings
+                    .bind(Chord::new(*key_code), Shortcut::Nudge(*direction * NUDGE_STEP))
+                    .bind(
+                        Chord::new(*key_code).shift(),
+                        Shortcut::Nudge(*direction * NUDGE_STEP_LARGE))
+            })
+    }
+}
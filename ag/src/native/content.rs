@@ -4,7 +4,7 @@ use iced_native::{
 };
 
 use super::title_bar::TitleBar;
-use super::ports::Ports;
+use super::ports::{self, Direction, Ports, Socket};
 
 /// The content of a [`Node`].
 ///
@@ -14,7 +14,7 @@ pub struct Content<'a, Message, Renderer: super::audio_graph::Renderer> {
     title_bar: Option<TitleBar<'a, Message, Renderer>>,
     ports: Option<Ports<Renderer>>,
     body: Element<'a, Message, Renderer>,
-    style: <Renderer as super::audio_graph::Renderer>::Style,
+    style: Option<<Renderer as super::audio_graph::Renderer>::Style>,
 }
 
 
@@ -29,7 +29,7 @@ where
             title_bar: None,
             ports: None,
             body: body.into(),
-            style: Default::default(),
+            style: None,
         }
     }
 
@@ -49,9 +49,12 @@ where
         self
     }
 
-    /// Sets the style of the [`Content`].
+    /// Sets the style of the [`Content`], in place of the style of the
+    /// [`AudioGraph`] it is shown in.
+    ///
+    /// [`AudioGraph`]: crate::AudioGraph
     pub fn style(mut self, style: impl Into<<Renderer as super::audio_graph::Renderer>::Style>) -> Self {
-        self.style = style.into();
+        self.style = Some(style.into());
         self
     }
 }
@@ -60,7 +63,8 @@ impl<'a, Message, Renderer> Content<'a, Message, Renderer>
 where
     Renderer: super::audio_graph::Renderer,
 {
-    /// Draws the [`Content`] with the provided [`Renderer`] and [`Layout`].
+    /// Draws the [`Content`] with the provided [`Renderer`] and [`Layout`],
+    /// using `style` unless the [`Content`] has its own.
     ///
     /// [`Renderer`]: crate::audio_graph::Renderer
     pub fn draw(
@@ -68,70 +72,84 @@ where
         renderer: &mut Renderer,
         defaults: &Renderer::Defaults,
         layout: Layout<'_>,
-        cursor_position: Point) -> Renderer::Output {
+        cursor_position: Point,
+        style: &<Renderer as super::audio_graph::Renderer>::Style,
+        is_focused: bool) -> Renderer::Output {
+        let (title_bar_layout, input_layout, output_layout, body_layout) =
+            self.split_layout(layout);
+
+        renderer.draw_node(
+            defaults,
+            layout.bounds(),
+            self.style.as_ref().unwrap_or(style),
+            is_focused,
+            self.title_bar.as_ref().zip(title_bar_layout),
+            self.ports.as_ref().map(|ports| (ports, input_layout, output_layout)),
+            (&self.body, body_layout),
+            cursor_position)
+    }
 
-        if let Some(title_bar) = &self.title_bar {
-            let mut children = layout.children();
-            let title_bar_layout = children.next().unwrap();
-           
-            if let Some(ports) = &self.ports {
-                let input_layout = children.next().unwrap();
-                let output_layout = children.next().unwrap();
-                
-                let input_bounds = input_layout.bounds();
-                let output_bounds = output_layout.bounds();
-
-                let body_layout = children.next().unwrap();
-
-                renderer.draw_node(
-                    defaults,
-                    layout.bounds(),
-                    &self.style,
-                    Some((title_bar, title_bar_layout)),
-                    Some((ports, input_layout, output_layout)),
-                    (&self.body, body_layout),
-                    cursor_position)
-            }
-            else {
-                let body_layout = children.next().unwrap();
-
-                renderer.draw_node(
-                    defaults,
-                    layout.bounds(),
-                    &self.style,
-                    Some((title_bar, title_bar_layout)),
-                    None,
-                    (&self.body, body_layout),
-                    cursor_position)
-            }
+    /// Splits the [`Layout`] of the [`Content`] into the layouts of its title
+    /// bar, input ports, output ports and body.
+    fn split_layout<'b>(
+        &self,
+        layout: Layout<'b>,
+    ) -> (Option<Layout<'b>>, Option<Layout<'b>>, Option<Layout<'b>>, Layout<'b>) {
+        let (has_inputs, has_outputs) = self.ports.as_ref().map_or((false, false), |ports| {
+            (ports.ports.input_connections() > 0, ports.ports.output_connections() > 0)
+        });
+
+        if self.title_bar.is_none() && !has_inputs && !has_outputs {
+            return (None, None, None, layout);
+        }
+
+        let mut children = layout.children();
+        let title_bar_layout = if self.title_bar.is_some() { children.next() } else { None };
+        let input_layout = if has_inputs { children.next() } else { None };
+        let output_layout = if has_outputs { children.next() } else { None };
+        let body_layout = children.next().unwrap_or(layout);
+
+        (title_bar_layout, input_layout, output_layout, body_layout)
+    }
+
+    /// Returns the [`Socket`] under the cursor, if any.
+    pub fn socket_at(
+        &self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+    ) -> Option<Socket> {
+        let ports = self.ports.as_ref()?;
+        let (_, input_layout, output_layout, _) = self.split_layout(layout);
+
+        ports.socket_at(input_layout, output_layout, cursor_position)
+    }
+
+    /// Returns the centre of the given socket, if it exists.
+    pub fn socket_position(
+        &self,
+        layout: Layout<'_>,
+        direction: Direction,
+        index: usize,
+    ) -> Option<Point> {
+        let (_, input_layout, output_layout, _) = self.split_layout(layout);
+
+        let (ports_layout, count) = match direction {
+            Direction::Input => (input_layout?, self.ports.as_ref()?.ports.input_connections()),
+            Direction::Output => (output_layout?, self.ports.as_ref()?.ports.output_connections()),
+        };
+
+        if index < count {
+            Some(ports::socket_position(ports_layout.bounds(), index))
         } else {
-            if let Some(ports) = &self.ports {
-                let mut children = layout.children();
-                let input_layout = children.next().unwrap();
-                let output_layout = children.next().unwrap();
-              
-                renderer.draw_node(
-                    defaults,
-                    layout.bounds(),
-                    &self.style,
-                    None,
-                    Some((ports, input_layout, output_layout)),
-                    (&self.body, layout),
-                    cursor_position)
-            }
-            else {
-                renderer.draw_node(
-                    defaults,
-                    layout.bounds(),
-                    &self.style,
-                    None,
-                    None,
-                    (&self.body, layout),
-                    cursor_position)
-            }
+            None
         }
     }
 
+    /// Returns the [`Ports`] of the [`Content`], if any.
+    pub fn ports(&self) -> Option<&Ports<Renderer>> {
+        self.ports.as_ref()
+    }
+
     /// Returns whether the [`Content`] with the given [`Layout`] can be picked
     /// at the provided cursor position.
     pub fn can_be_picked_at(
@@ -159,37 +177,18 @@ where
         clipboard: Option<&dyn Clipboard>) -> event::Status {
         let mut event_status = event::Status::Ignored;
 
-        let body_layout = if let Some(title_bar) = &mut self.title_bar {
-            let mut children = layout.children();
+        let (title_bar_layout, _, _, body_layout) = self.split_layout(layout);
 
+        if let Some((title_bar, title_bar_layout)) = self.title_bar.as_mut().zip(title_bar_layout) {
             event_status = title_bar.on_event(
                 event.clone(),
-                children.next().unwrap(),
+                title_bar_layout,
                 cursor_position,
                 messages,
                 renderer,
                 clipboard,
             );
-
-            if self.ports.is_some() {
-                let _input_layout = children.next().unwrap();
-                let _output_layout = children.next().unwrap();
-            }
-
-            children.next().unwrap()
-        } else {
-            if self.ports.is_some() {
-                let mut children = layout.children();
-
-                let _input_layout = children.next().unwrap();
-                let _output_layout = children.next().unwrap();
-
-                children.next().unwrap()
-            }
-            else {
-                layout
-            }
-        };
+        }
 
         let body_status = self.body.on_event(
             event,
@@ -414,40 +413,8 @@ where
     pub(crate) fn overlay(
         &mut self,
         layout: Layout<'_>) -> Option<overlay::Element<'_, Message, Renderer>> {
-
-        let body_layout = if self.title_bar.is_some() {
-            let mut children = layout.children();
-
-            // Overlays only allowed in the pane body, for now at least.
-            let _title_bar_layout = children.next();
-
-            if let Some(ports) = &self.ports {
-                if ports.ports.input_connections() > 0 {
-                    let _inputs_layout = children.next();
-                }
-                if ports.ports.output_connections() > 0 {
-                    let _outputs_layout = children.next();
-                }
-            }
-
-            children.next()?
-        } else {
-            if let Some(ports) = &self.ports {
-                let mut children = layout.children();
-
-                if ports.ports.input_connections() > 0 {
-                    let _inputs_layout = children.next();
-                }
-                if ports.ports.output_connections() > 0 {
-                    let _outputs_layout = children.next();
-                }
-
-                children.next()?
-            }
-            else {
-                layout
-            }
-        };
+        // Overlays only allowed in the node body, for now at least.
+        let (_, _, _, body_layout) = self.split_layout(layout);
 
         self.body.overlay(body_layout)
     }
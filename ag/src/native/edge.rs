@@ -0,0 +1,56 @@
+use iced_native::Point;
+
+use crate::core::edge;
+use crate::core::port::PortType;
+
+pub use crate::core::edge::{Edge, EDGE_PICK_DISTANCE, REROUTE_PICK_DISTANCE};
+
+/// An [`Edge`] as it is to be drawn: where it runs and how it is highlighted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgePath {
+    pub edge: Edge,
+    /// The centre of the output socket.
+    pub from: Point,
+    /// The centre of the input socket.
+    pub to: Point,
+    /// The reroute points the edge passes through, in order from its output.
+    pub reroutes: Vec<Point>,
+    /// The type of the output the edge leaves, if known.
+    pub port_type: Option<PortType>,
+    /// True for a feedback edge, which is drawn dashed.
+    pub feedback: bool,
+    /// True while the cursor is over the edge, or a node dragged over it
+    /// would be spliced into it.
+    pub hovered: bool,
+    pub selected: bool,
+}
+
+impl EdgePath {
+    /// Returns the curves the edge is drawn as, see [`edge_controls`].
+    pub fn curves(&self) -> Vec<(Point, Point)> {
+        let reroutes: Vec<_> = self.reroutes.iter().map(|p| (*p).into()).collect();
+        edge::route(self.from.into(), &reroutes, self.to.into())
+            .into_iter()
+            .map(|(a, b)| (a.into(), b.into()))
+            .collect()
+    }
+}
+
+/// Returns the control points of the curve drawn between an output socket at
+/// `from` and an input socket at `to`.
+pub fn edge_controls(from: Point, to: Point) -> (Point, Point) {
+    let (a, b) = edge::controls(from.into(), to.into());
+    (a.into(), b.into())
+}
+
+/// Returns the point at `t`, in `0..=1`, along the curve drawn between `from`
+/// and `to`.
+pub fn edge_point(from: Point, to: Point, t: f32) -> Point {
+    edge::point(from.into(), to.into(), t).into()
+}
+
+/// Returns the distance from `point` to the curve drawn between `from` and
+/// `to`.
+pub fn edge_distance(from: Point, to: Point, point: Point) -> f32 {
+    edge::distance(from.into(), to.into(), point.into())
+}
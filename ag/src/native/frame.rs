@@ -0,0 +1,19 @@
+use iced_native::{Color, Rectangle};
+
+pub use crate::core::annotation::{Annotation, Frame, FrameId, FRAME_HEADER, TAGS};
+
+/// A comment [`Frame`] as it is to be drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameBox {
+    pub id: FrameId,
+    pub bounds: Rectangle,
+    pub title: String,
+    /// The colour tag of the frame, if any.
+    pub color: Option<Color>,
+    pub selected: bool,
+}
+
+/// Returns the colour of a tag given as red, green and blue.
+pub fn tag_color(color: [u8; 3]) -> Color {
+    Color::from_rgb8(color[0], color[1], color[2])
+}
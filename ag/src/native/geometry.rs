@@ -0,0 +1,55 @@
+//! Conversions between the model's geometry and iced's.
+
+use crate::core::geometry;
+
+impl From<geometry::Point> for iced_native::Point {
+    fn from(point: geometry::Point) -> Self {
+        iced_native::Point::new(point.x, point.y)
+    }
+}
+
+impl From<iced_native::Point> for geometry::Point {
+    fn from(point: iced_native::Point) -> Self {
+        geometry::Point::new(point.x, point.y)
+    }
+}
+
+impl From<iced_native::Vector> for geometry::Vector {
+    fn from(vector: iced_native::Vector) -> Self {
+        geometry::Vector::new(vector.x, vector.y)
+    }
+}
+
+impl From<geometry::Vector> for iced_native::Vector {
+    fn from(vector: geometry::Vector) -> Self {
+        iced_native::Vector::new(vector.x, vector.y)
+    }
+}
+
+impl From<iced_native::Size> for geometry::Size {
+    fn from(size: iced_native::Size) -> Self {
+        geometry::Size::new(size.width, size.height)
+    }
+}
+
+impl From<geometry::Rectangle> for iced_native::Rectangle {
+    fn from(rectangle: geometry::Rectangle) -> Self {
+        iced_native::Rectangle {
+            x: rectangle.x,
+            y: rectangle.y,
+            width: rectangle.width,
+            height: rectangle.height,
+        }
+    }
+}
+
+impl From<iced_native::Rectangle> for geometry::Rectangle {
+    fn from(rectangle: iced_native::Rectangle) -> Self {
+        geometry::Rectangle {
+            x: rectangle.x,
+            y: rectangle.y,
+            width: rectangle.width,
+            height: rectangle.height,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::state::State;
+
+use std::collections::VecDeque;
+
+/// The default number of edits kept by a [`History`].
+pub const HISTORY_LIMIT: usize = 100;
+
+/// Undo and redo for a [`State`].
+///
+/// Edits are recorded as snapshots of the whole [`State`], taken just before
+/// the edit is applied with [`History::record`].
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    undo: VecDeque<State<T>>,
+    redo: Vec<State<T>>,
+    limit: usize,
+}
+
+impl<T: Clone> History<T> {
+    /// Creates a new [`History`] keeping at most `limit` edits.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Records `state` as it is before an edit, clearing anything that could
+    /// have been redone.
+    pub fn record(&mut self, state: &State<T>) {
+        self.redo.clear();
+        self.undo.push_back(state.clone());
+        if self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Restores the state before the last edit, returning false if there is
+    /// nothing to undo.
+    pub fn undo(&mut self, state: &mut State<T>) -> bool {
+        if let Some(previous) = self.undo.pop_back() {
+            let current = std::mem::replace(state, previous);
+            self.redo.push(current);
+            state.internal.idle();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Reapplies the last undone edit, returning false if there is nothing to
+    /// redo.
+    pub fn redo(&mut self, state: &mut State<T>) -> bool {
+        if let Some(next) = self.redo.pop() {
+            let current = std::mem::replace(state, next);
+            self.undo.push_back(current);
+            state.internal.idle();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+impl<T: Clone> Default for History<T> {
+    fn default() -> Self {
+        Self::new(HISTORY_LIMIT)
+    }
+}
@@ -53,6 +53,21 @@ impl LayoutNode {
         }
     }
 
+    pub fn remove(ln: Self, node: Node) -> Self {
+        match ln {
+            LayoutNode::Node(node_prev) if node_prev.0 == node => {
+                LayoutNode::Nodes(Vec::new())
+            },
+            LayoutNode::Node(node_prev) => {
+                LayoutNode::Node(node_prev)
+            },
+            LayoutNode::Nodes(mut nodes) => {
+                nodes.retain(|n| n.0 != node);
+                LayoutNode::Nodes(nodes)
+            }
+        }
+    }
+
     fn node(&self) -> Option<Node> {
         match self {
             LayoutNode::Node(node) => Some(node.0),
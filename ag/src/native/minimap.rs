@@ -0,0 +1,72 @@
+use iced_native::{Rectangle, Size};
+
+use crate::core::minimap::Projection;
+
+/// The size of a minimap, unless given to [`AudioGraph::minimap`].
+///
+/// [`AudioGraph::minimap`]: crate::native::audio_graph::AudioGraph::minimap
+pub const MINIMAP_SIZE: Size = Size { width: 200.0, height: 140.0 };
+
+/// The space between a minimap and the edges of the [`AudioGraph`].
+///
+/// [`AudioGraph`]: crate::AudioGraph
+pub const MINIMAP_MARGIN: f32 = 12.0;
+
+/// An overview of the whole graph as it is to be drawn, in the corner of the
+/// [`AudioGraph`].
+///
+/// [`AudioGraph`]: crate::AudioGraph
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimap {
+    /// The area of the minimap.
+    pub bounds: Rectangle,
+    /// The part of the graph currently visible.
+    pub viewport: Rectangle,
+    /// Each node, and whether it is selected.
+    pub nodes: Vec<(Rectangle, bool)>,
+}
+
+impl Minimap {
+    /// Lays out a minimap of `size` in the bottom right corner of `bounds`,
+    /// showing `nodes` and the `viewport`, given relative to the graph.
+    pub fn new(
+        bounds: Rectangle,
+        size: Size,
+        nodes: &[(Rectangle, bool)],
+        viewport: Rectangle,
+    ) -> Self {
+        let area = minimap_bounds(bounds, size);
+        let projection = projection(area, nodes.iter().map(|(node, _)| *node), viewport);
+        let to_map = |rectangle: Rectangle| Rectangle::from(projection.rectangle(rectangle.into()));
+
+        Self {
+            bounds: area,
+            viewport: to_map(viewport),
+            nodes: nodes
+                .iter()
+                .map(|(node, selected)| (to_map(*node), *selected))
+                .collect(),
+        }
+    }
+}
+
+/// Returns the area of a minimap of `size` in the bottom right corner of
+/// `bounds`.
+pub fn minimap_bounds(bounds: Rectangle, size: Size) -> Rectangle {
+    Rectangle {
+        x: bounds.x + bounds.width - size.width - MINIMAP_MARGIN,
+        y: bounds.y + bounds.height - size.height - MINIMAP_MARGIN,
+        width: size.width,
+        height: size.height,
+    }
+}
+
+/// Returns the mapping between the graph and a minimap drawn in `area`.
+pub(crate) fn projection(
+    area: Rectangle,
+    nodes: impl Iterator<Item = Rectangle>,
+    viewport: Rectangle,
+) -> Projection {
+    let nodes: Vec<_> = nodes.map(Into::into).collect();
+    Projection::new(area.into(), &nodes, viewport.into())
+}
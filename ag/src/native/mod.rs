@@ -5,6 +5,13 @@ pub mod layout_node;
 pub mod configuration;
 pub mod title_bar;
 pub mod ports;
+pub mod edge;
+pub mod frame;
+pub mod geometry;
+pub mod patch;
+pub mod bindings;
+pub mod history;
+pub mod minimap;
 
 pub mod audio_graph;
 
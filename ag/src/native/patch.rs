@@ -0,0 +1,285 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::core::node::Node;
+use crate::core::{Frame, NodeMode};
+
+use std::collections::{BTreeMap, HashMap};
+
+/// The current version of the patch text format.
+pub const PATCH_VERSION: u32 = 1;
+
+/// The persistent description of a single node's contents, independent of
+/// where it sits in an [`AudioGraph`].
+///
+/// [`AudioGraph`]: crate::audio_graph::AudioGraph
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct NodeData {
+    /// The module type the node is an instance of.
+    pub kind: String,
+    /// Parameter values, keyed by parameter name.
+    #[serde(default)]
+    pub parameters: BTreeMap<String, f64>,
+}
+
+/// A node as stored in a [`Patch`].
+///
+/// The `id` is local to the [`Patch`] and only used to resolve [`PatchEdge`]s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchNode {
+    pub id: usize,
+    pub x: f32,
+    pub y: f32,
+    #[serde(flatten)]
+    pub data: NodeData,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    /// The colour tag, as red, green and blue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bypass: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub mute: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub solo: bool,
+    /// Whether loops may run through the node, see [`Graph::set_delay`].
+    ///
+    /// [`Graph::set_delay`]: crate::core::Graph::set_delay
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub delay: bool,
+}
+
+impl PatchNode {
+    /// Returns whether the node is bypassed, muted or soloed.
+    pub fn mode(&self) -> NodeMode {
+        NodeMode {
+            bypass: self.bypass,
+            mute: self.mute,
+            solo: self.solo,
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// A connection as stored in a [`Patch`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchEdge {
+    pub from: usize,
+    pub output: usize,
+    pub to: usize,
+    pub input: usize,
+    /// Whether the edge is a feedback edge, which may close a loop.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub feedback: bool,
+    /// The points the edge is rerouted through, in the same coordinates as
+    /// the nodes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub route: Vec<(f32, f32)>,
+}
+
+impl PatchEdge {
+    /// Returns the nodes the edge runs between, given the node each patch id
+    /// was inserted as, or `None` if either end was not inserted.
+    pub fn resolve(&self, ids: &HashMap<usize, Node>) -> Option<(Node, Node)> {
+        Some((*ids.get(&self.from)?, *ids.get(&self.to)?))
+    }
+}
+
+/// A comment frame as stored in a [`Patch`], in the same coordinates as the
+/// nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchFrame {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+}
+
+impl From<&Frame> for PatchFrame {
+    fn from(frame: &Frame) -> Self {
+        Self {
+            x: frame.bounds.x,
+            y: frame.bounds.y,
+            width: frame.bounds.width,
+            height: frame.bounds.height,
+            title: frame.title.clone(),
+            color: frame.color,
+        }
+    }
+}
+
+/// A serialisable set of nodes and the connections between them.
+///
+/// This is the format used both for patch files and for the clipboard, so that
+/// nodes copied in one instance can be pasted into another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Patch {
+    pub version: u32,
+    #[serde(default)]
+    pub nodes: Vec<PatchNode>,
+    #[serde(default)]
+    pub edges: Vec<PatchEdge>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<PatchFrame>,
+}
+
+impl Patch {
+    /// Creates an empty [`Patch`].
+    pub fn new() -> Self {
+        Self {
+            version: PATCH_VERSION,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Returns true if the [`Patch`] contains no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the top left corner of the bounding box of all nodes and
+    /// frames, if any.
+    pub fn origin(&self) -> Option<(f32, f32)> {
+        self.nodes
+            .iter()
+            .map(|node| (node.x, node.y))
+            .chain(self.frames.iter().map(|frame| (frame.x, frame.y)))
+            .fold(None, |origin, (px, py)| match origin {
+                None => Some((px, py)),
+                Some((x, y)) => Some((x.min(px), y.min(py))),
+            })
+    }
+
+    /// Returns a copy of the [`Patch`] with every node, frame and reroute
+    /// point moved by `(dx, dy)`.
+    pub fn translated(&self, dx: f32, dy: f32) -> Self {
+        let mut patch = self.clone();
+        for node in &mut patch.nodes {
+            node.x += dx;
+            node.y += dy;
+        }
+        for edge in &mut patch.edges {
+            for (x, y) in &mut edge.route {
+                *x += dx;
+                *y += dy;
+            }
+        }
+        for frame in &mut patch.frames {
+            frame.x += dx;
+            frame.y += dy;
+        }
+        patch
+    }
+
+    /// Serialises the [`Patch`] to its text format.
+    pub fn to_text(&self) -> String {
+        // a patch only contains strings, numbers and maps with string keys,
+        // so serialisation cannot fail
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Parses a [`Patch`] from its text format.
+    pub fn from_text(text: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(text)
+    }
+}
+
+/// Node state that can be stored in, and restored from, a [`Patch`].
+///
+/// Implement this for the `T` of a [`State`] to enable copy and paste.
+///
+/// [`State`]: crate::native::state::State
+pub trait Persist: Sized {
+    /// Captures the persistent parts of the node.
+    fn save(&self) -> NodeData;
+
+    /// Recreates a node from persisted data, returning `None` if it can not be
+    /// restored (e.g. the module kind is unknown).
+    fn load(data: &NodeData) -> Option<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: usize, x: f32, y: f32) -> PatchNode {
+        PatchNode {
+            id,
+            x,
+            y,
+            data: NodeData { kind: "Gain".to_string(), parameters: BTreeMap::new() },
+            note: String::new(),
+            color: None,
+            bypass: false,
+            mute: false,
+            solo: false,
+            delay: false,
+        }
+    }
+
+    fn edge(from: usize, to: usize, route: Vec<(f32, f32)>) -> PatchEdge {
+        PatchEdge { from, output: 0, to, input: 1, feedback: false, route }
+    }
+
+    fn patch() -> Patch {
+        let mut patch = Patch::new();
+        patch.nodes = vec![node(10, 100.0, 50.0), node(11, 400.0, 20.0), node(12, 700.0, 80.0)];
+        patch.edges = vec![edge(10, 11, vec![(250.0, 200.0)]), edge(11, 12, Vec::new())];
+        patch
+    }
+
+    #[test]
+    fn test_patch_text_round_trip() {
+        let patch = patch();
+        assert_eq!(Patch::from_text(&patch.to_text()).unwrap(), patch);
+        assert!(Patch::from_text("not a patch").is_err());
+        assert!(Patch::new().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_remaps_patch_ids() {
+        let patch = patch();
+
+        // the patch ids are those of the copied nodes; pasting inserts new
+        // nodes with ids of their own, and only some may be restored
+        let mut ids = HashMap::new();
+        ids.insert(10, Node::new(3));
+        ids.insert(11, Node::new(4));
+
+        assert_eq!(patch.edges[0].resolve(&ids), Some((Node::new(3), Node::new(4))));
+        assert_eq!(patch.edges[1].resolve(&ids), None);
+
+        ids.insert(12, Node::new(9));
+        assert_eq!(patch.edges[1].resolve(&ids), Some((Node::new(4), Node::new(9))));
+    }
+
+    #[test]
+    fn test_translated_moves_nodes_frames_and_routes() {
+        let mut patch = patch();
+        patch.frames.push(PatchFrame {
+            x: 80.0,
+            y: 0.0,
+            width: 700.0,
+            height: 400.0,
+            title: "Voice".to_string(),
+            color: None,
+        });
+        assert_eq!(patch.origin(), Some((80.0, 0.0)));
+
+        let moved = patch.translated(20.0, -10.0);
+        assert_eq!(moved.origin(), Some((100.0, -10.0)));
+        assert_eq!((moved.nodes[0].x, moved.nodes[0].y), (120.0, 40.0));
+        assert_eq!(moved.edges[0].route, vec![(270.0, 190.0)]);
+        assert_eq!(moved.nodes[1].id, 11);
+        assert_eq!(moved.edges[1], patch.edges[1]);
+    }
+}
@@ -3,11 +3,64 @@ use iced_native::{
     Length, Point, Rectangle, Size, Widget, container,
 };
 
+pub use crate::core::port::{Direction, PortLabel, PortType, SHORT_LABEL_LENGTH};
+
+/// The vertical distance between the centres of two sockets.
+pub const SOCKET_SPACING: f32 = 24.0;
+
+/// The radius of a drawn socket.
+pub const SOCKET_RADIUS: f32 = 6.0;
+
+/// How close, in pixels, the cursor must be to a socket's centre to pick it.
+const SOCKET_PICK_RADIUS: f32 = 10.0;
+
+/// The width of a ports column without labels.
+const COLUMN_WIDTH: f32 = 40.0;
+
+/// The width of a ports column with labels beside its sockets.
+const LABELLED_COLUMN_WIDTH: f32 = 64.0;
+
+/// A single port of a node, as picked on the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Socket {
+    pub direction: Direction,
+    pub index: usize,
+    pub port_type: PortType,
+}
+
+/// The level of a signal, as shown by a meter.
+///
+/// Levels are positions on the meter's scale, in `0..=1`, so that the
+/// mapping from amplitude, e.g. to decibels, is left to the application.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Level {
+    pub peak: f32,
+    pub rms: f32,
+    /// True while the signal has recently clipped.
+    pub clip: bool,
+}
+
+/// The details of a port shown while the cursor is over its socket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tooltip {
+    /// The centre of the socket.
+    pub position: Point,
+    pub lines: Vec<String>,
+}
+
+/// Returns the centre of socket `index` within the bounds of a ports column.
+pub fn socket_position(bounds: Rectangle, index: usize) -> Point {
+    Point::new(
+        bounds.x + bounds.width / 2.0,
+        bounds.y + SOCKET_SPACING * (index as f32 + 0.5),
+    )
+}
+
 #[allow(missing_debug_implementations)]
 pub struct Ports<Renderer: super::audio_graph::Renderer> {
     pub(crate) ports: Box<dyn Connectors>,
     padding: u16,
-    style: <Renderer as super::audio_graph::Renderer>::Style,
+    style: Option<<Renderer as super::audio_graph::Renderer>::Style>,
 }
 
 impl<Renderer> Ports<Renderer>
@@ -19,7 +72,7 @@ where
         Self {
             ports,
             padding: 0,
-            style: Default::default(),
+            style: None,
         }
     }
 
@@ -29,11 +82,11 @@ where
         self
     }
 
-    /// Sets the style of the [`Ports`].
+    /// Sets the style of the [`Ports`], in place of the style of their node.
     pub fn style(
         mut self,
         style: impl Into<<Renderer as super::audio_graph::Renderer>::Style>) -> Self {
-        self.style = style.into();
+        self.style = Some(style.into());
         self
     }
 
@@ -42,25 +95,62 @@ where
         self.padding.hash(hasher);
         self.ports.inputs().for_each(|p|p.hash(hasher));
         self.ports.outputs().for_each(|p|p.hash(hasher));
+        self.has_labels(Direction::Input).hash(hasher);
+        self.has_labels(Direction::Output).hash(hasher);
+    }
+
+    /// Returns true if any port in `direction` has a label.
+    fn has_labels(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::Input => (0..self.ports.input_connections())
+                .any(|index| self.ports.input_label(index).is_some()),
+            Direction::Output => (0..self.ports.output_connections())
+                .any(|index| self.ports.output_label(index).is_some()),
+        }
+    }
+
+    fn column_width(&self, direction: Direction) -> f32 {
+        if self.has_labels(direction) {
+            LABELLED_COLUMN_WIDTH
+        } else {
+            COLUMN_WIDTH
+        }
     }
 
     pub fn draw(
         &self,
         renderer: &mut Renderer,
         defaults: &Renderer::Defaults,
-        input_bounds: Layout<'_>,
-        output_bounds: Layout<'_>,
-        cursor_position: Point) -> Renderer::Output {
+        input_bounds: Option<Layout<'_>>,
+        output_bounds: Option<Layout<'_>>,
+        cursor_position: Point,
+        style: &<Renderer as super::audio_graph::Renderer>::Style) -> Renderer::Output {
             renderer.draw_ports(
                 defaults,
                 input_bounds,
                 output_bounds,
-                &self.style,
-                self.ports.input_connections(),
+                self.style.as_ref().unwrap_or(style),
+                &*self.ports,
                 cursor_position,
             )
     }
 
+    /// Returns the [`Socket`] under the cursor, if any.
+    pub fn socket_at(
+        &self,
+        input_layout: Option<Layout<'_>>,
+        output_layout: Option<Layout<'_>>,
+        cursor_position: Point) -> Option<Socket> {
+        pick_socket(input_layout, Direction::Input, self.ports.inputs(), cursor_position)
+            .or_else(|| pick_socket(
+                output_layout, Direction::Output, self.ports.outputs(), cursor_position))
+    }
+
+    /// Returns the [`Connectors`] describing the ports.
+    pub fn connectors(&self) -> &dyn Connectors {
+        &*self.ports
+    }
+
     pub(crate) fn layout_inputs(
         &self,
         _renderer: &Renderer,
@@ -70,7 +160,7 @@ where
             let limits = limits.pad(padding);
             let max_size = limits.max();
 
-            let connector_size = Size::new(40.0, max_size.height);
+            let connector_size = Size::new(self.column_width(Direction::Input), max_size.height);
             
             Some(layout::Node::new(connector_size))
         }
@@ -88,7 +178,7 @@ where
             let limits = limits.pad(padding);
             let max_size = limits.max();
 
-            let connector_size = Size::new(40.0, max_size.height);
+            let connector_size = Size::new(self.column_width(Direction::Output), max_size.height);
             
             Some(layout::Node::new(connector_size))
         }
@@ -98,17 +188,23 @@ where
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct PortType(pub(super) usize);
-
-impl PortType {
-    pub const fn new() -> Self {
-        Self (0)
-    }
-
-    pub const fn fresh(self) -> Self {
-        Self(self.0 + 1)
-    }
+fn pick_socket(
+    layout: Option<Layout<'_>>,
+    direction: Direction,
+    types: std::slice::Iter<'_, PortType>,
+    cursor_position: Point) -> Option<Socket> {
+    let bounds = layout?.bounds();
+    types
+        .enumerate()
+        .find(|(index, _)| {
+            let centre = socket_position(bounds, *index);
+            centre.distance(cursor_position) <= SOCKET_PICK_RADIUS
+        })
+        .map(|(index, port_type)| Socket {
+            direction,
+            index,
+            port_type: *port_type,
+        })
 }
 
 pub trait Connectors {
@@ -129,6 +225,21 @@ pub trait Connectors {
 
     /// iterator over output ports
     fn outputs(&self) -> std::slice::Iter<'_, PortType> ;
+
+    /// the label of a given input port, if it has one
+    fn input_label(&self, _index: usize) -> Option<&PortLabel> {
+        None
+    }
+
+    /// the label of a given output port, if it has one
+    fn output_label(&self, _index: usize) -> Option<&PortLabel> {
+        None
+    }
+
+    /// the level metered on a given output port, if it is metered
+    fn output_level(&self, _index: usize) -> Option<Level> {
+        None
+    }
 }
 
 
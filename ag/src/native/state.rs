@@ -3,7 +3,16 @@ use iced_native::{
     Length, Point, Vector, Rectangle, Size, Widget,
 };
 
-use std::collections::HashMap;
+use super::edge::Edge;
+use crate::core::{Annotation, ConnectError, Frame, FrameId, Graph, NodeMode};
+use super::ports::Socket;
+use super::patch::{Patch, PatchEdge, PatchFrame, PatchNode, Persist};
+
+use std::collections::{HashMap, HashSet};
+
+/// The offset applied to pasted or duplicated nodes when they are not placed
+/// at the cursor.
+pub const PASTE_OFFSET: Vector = Vector { x: 20.0, y: 20.0 };
 
 /// The state of a [`AudioGraph`].
 ///
@@ -15,6 +24,9 @@ use std::collections::HashMap;
 /// provided to the view function of [`AudioGraph::new`] for displaying each
 /// [`Node`].
 ///
+/// The structure of the graph is kept in a [`Graph`], which can be used
+/// without a window; see [`State::graph`].
+///
 /// [`AudioGraph`]: crate::audio_graph::AudioGraph
 /// [`AudioGraph::new`]: crate::audio_graph::AudioGraph::new
 #[derive(Debug, Clone)]
@@ -31,8 +43,8 @@ impl<T> State<T> {
     /// Alongside the [`State`], it returns the first [`Node`] identifier.
     pub fn new(position: Point, first_node_state: T) -> (Self, super::node::Node) {
         let mut state = Self::with_configuration(super::configuration::Configuration::Node(first_node_state));
-        let new_node = super::node::Node::new(0);
-        state.internal.push_postion(new_node, position);
+        let new_node = state.internal.graph.nodes()[0];
+        let _ = state.internal.graph.move_to(new_node, position.into());
         (
             state,
             new_node,
@@ -42,21 +54,42 @@ impl<T> State<T> {
     /// Creates a new [`State`] with the given [`Configuration`].
     pub fn with_configuration(config: impl Into<super::configuration::Configuration<T>>) -> Self {
         let mut nodes =  HashMap::new();
+        let mut graph = Graph::new();
 
-        let (layout, last_id) =
-            Self::distribute_content(&mut nodes, config.into(), 0);
+        let layout = Self::distribute_content(&mut nodes, &mut graph, config.into());
 
         State {
             nodes,
             internal: Internal {
+                graph,
                 layout,
-                positions: HashMap::new(),
-                last_id,
+                modifiers: keyboard::Modifiers::default(),
+                last_click: None,
                 action: Action::Idle,
+                scroll: Vector::new(0.0, 0.0),
             },
         }
     }
 
+    /// Returns the structure of the graph: positions, edges, selection and
+    /// focus.
+    pub fn graph(&self) -> &Graph {
+        &self.internal.graph
+    }
+
+    /// Returns how far the view is scrolled: the point of the graph shown
+    /// at the top left corner of the [`AudioGraph`].
+    ///
+    /// [`AudioGraph`]: crate::audio_graph::AudioGraph
+    pub fn scroll(&self) -> Vector {
+        self.internal.scroll()
+    }
+
+    /// Scrolls the view so that `offset` is shown at the top left corner.
+    pub fn scroll_to(&mut self, offset: Vector) {
+        self.internal.scroll_to(offset);
+    }
+
     /// Returns the total amount of panes in the [`State`].
     pub fn len(&self) -> usize {
         self.nodes.len()
@@ -73,17 +106,24 @@ impl<T> State<T> {
         self.nodes.get_mut(node)
     }
 
+    /// Returns an iterator over all the nodes in the [`State`], alongside
+    /// their internal state.
+    pub fn iter(&self) -> impl Iterator<Item = (&super::node::Node, &T)> {
+        self.nodes.iter()
+    }
+
+    /// Returns a mutable iterator over all the nodes in the [`State`],
+    /// alongside their internal state.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&super::node::Node, &mut T)> {
+        self.nodes.iter_mut()
+    }
+
     pub fn insert(
         &mut self,
         position: Point,
         state: T) -> Option<super::node::Node> {
-        
-        self.internal.last_id = self.internal.last_id.checked_add(1)?;
-        let id = self.internal.last_id;
-        let new_node = super::node::Node::new(id);
-
+        let new_node = self.internal.graph.insert(position.into())?;
         let _ = self.nodes.insert(new_node, state);
-        self.internal.push_postion(new_node, position);
 
         let layout_node = std::mem::replace(
             &mut self.internal.layout, super::layout_node::LayoutNode::Node((new_node, Point::new(0.0,0.0))));
@@ -92,39 +132,484 @@ impl<T> State<T> {
         Some(new_node)
     }
 
+    /// Removes a [`Node`], along with any edges attached to it, returning its
+    /// internal state.
+    pub fn remove(&mut self, node: &super::node::Node) -> Option<T> {
+        let state = self.nodes.remove(node)?;
+        self.internal.graph.remove(node);
+
+        if let Some((picked, _, _)) = self.internal.picked_node() {
+            if picked == *node {
+                self.internal.idle();
+            }
+        }
+
+        let layout_node = std::mem::replace(
+            &mut self.internal.layout, super::layout_node::LayoutNode::Nodes(Vec::new()));
+        self.internal.layout = super::layout_node::LayoutNode::remove(layout_node, *node);
+
+        Some(state)
+    }
+
+    /// Returns the position of the given [`Node`], if it exists.
+    pub fn position(&self, node: &super::node::Node) -> Option<Point> {
+        self.internal.graph.position(node).map(Point::from)
+    }
+
+    /// Connects output port `output` of `from` to input port `input` of `to`.
+    ///
+    /// Returns an error if either [`Node`] does not exist, the connection is
+    /// already present, or it would close a loop with neither a feedback edge
+    /// nor a delay node in it.
+    pub fn connect(
+        &mut self,
+        from: super::node::Node,
+        output: usize,
+        to: super::node::Node,
+        input: usize) -> Result<Edge, ConnectError> {
+        self.internal.graph.connect(from, output, to, input)
+    }
+
+    /// Connects output port `output` of `from` to input port `input` of `to`
+    /// with a feedback edge, which carries what left `from` a block earlier
+    /// and so may close a loop.
+    pub fn connect_feedback(
+        &mut self,
+        from: super::node::Node,
+        output: usize,
+        to: super::node::Node,
+        input: usize) -> Result<Edge, ConnectError> {
+        self.internal.graph.connect_feedback(from, output, to, input)
+    }
+
+    /// Returns true if the [`Edge`] is a feedback edge.
+    pub fn is_feedback(&self, edge: &Edge) -> bool {
+        self.internal.graph.is_feedback(edge)
+    }
+
+    /// Makes an [`Edge`] a feedback edge or an ordinary one, refusing to if
+    /// that would leave a loop with no delay in it.
+    pub fn set_feedback(&mut self, edge: &Edge, feedback: bool) -> Result<(), ConnectError> {
+        self.internal.graph.set_feedback(edge, feedback)
+    }
+
+    /// Returns true if the [`Node`] is marked as a delay.
+    pub fn is_delay(&self, node: &super::node::Node) -> bool {
+        self.internal.graph.is_delay(node)
+    }
+
+    /// Marks a [`Node`] as a delay, which loops may run through. Meant to be
+    /// called as the node is inserted; returns false if it does not exist.
+    pub fn set_delay(&mut self, node: super::node::Node, delay: bool) -> bool {
+        self.internal.graph.set_delay(node, delay)
+    }
+
+    /// Removes the given [`Edge`], returning true if it was present.
+    pub fn disconnect(&mut self, edge: &Edge) -> bool {
+        self.internal.graph.disconnect(edge)
+    }
+
+    /// Inserts `node` into `edge`, through its ports `input` and `output`,
+    /// returning false if the edge does not exist or already touches `node`.
+    pub fn splice(
+        &mut self,
+        node: super::node::Node,
+        edge: &Edge,
+        input: usize,
+        output: usize) -> bool {
+        self.internal.graph.splice(node, edge, input, output)
+    }
+
+    /// Disconnects the edges of `node` at the `through` ports the chain
+    /// passes by, then connects `heals`, returning the removed edges and the
+    /// heals that were refused, see [`Graph::extract`].
+    pub fn extract(
+        &mut self,
+        node: super::node::Node,
+        through: &[(usize, usize)],
+        heals: &[Edge]) -> crate::core::Extraction {
+        self.internal.graph.extract(node, through, heals)
+    }
+
+    /// Returns all edges in the [`State`].
+    pub fn edges(&self) -> &[Edge] {
+        self.internal.graph.edges()
+    }
+
+    /// Returns the selected nodes, ordered by creation.
+    pub fn selection(&self) -> Vec<super::node::Node> {
+        self.internal.selection()
+    }
+
+    /// Returns the selected edges, in the order they were connected.
+    pub fn selected_edges(&self) -> Vec<Edge> {
+        self.internal.graph.selected_edges()
+    }
+
+    /// Returns the reroute points of an [`Edge`], relative to the
+    /// [`AudioGraph`].
+    ///
+    /// [`AudioGraph`]: crate::audio_graph::AudioGraph
+    pub fn route(&self, edge: &Edge) -> Vec<Point> {
+        self.internal.graph.route(edge).iter().map(|p| Point::from(*p)).collect()
+    }
+
+    /// Selects every node.
+    pub fn select_all(&mut self) {
+        self.internal.select_all();
+    }
+
+    /// Returns the [`Node`] with keyboard focus, if any.
+    pub fn focus(&self) -> Option<super::node::Node> {
+        self.internal.graph.focus()
+    }
+
+    /// Moves each selected node by `offset`.
+    pub fn translate_selection(&mut self, offset: Vector) {
+        self.internal.graph.translate_selection(offset.into());
+    }
+
+    /// Removes each selected node and frame, and disconnects each selected
+    /// edge.
+    pub fn remove_selection(&mut self) {
+        if let Some(frame) = self.selected_frame() {
+            let _ = self.remove_frame(frame);
+        }
+        for edge in self.selected_edges() {
+            let _ = self.disconnect(&edge);
+        }
+        for node in self.selection() {
+            let _ = self.remove(&node);
+        }
+    }
+
+    /// Replaces the current selection.
+    pub fn select(&mut self, nodes: &[super::node::Node]) {
+        self.internal.graph.select(nodes);
+    }
+
+    /// Returns the note and colour tag of a [`Node`], if it has either.
+    pub fn annotation(&self, node: &super::node::Node) -> Option<&Annotation> {
+        self.internal.graph.annotation(node)
+    }
+
+    /// Sets the note shown in the title bar of a [`Node`]. An empty note
+    /// removes it.
+    pub fn set_note(&mut self, node: super::node::Node, note: &str) -> bool {
+        self.internal.graph.set_note(node, note)
+    }
+
+    /// Tags a [`Node`] with a colour, as red, green and blue, or removes its
+    /// tag.
+    pub fn set_color(&mut self, node: super::node::Node, color: Option<[u8; 3]>) -> bool {
+        self.internal.graph.set_color(node, color)
+    }
+
+    /// Returns whether a [`Node`] is bypassed, muted or soloed.
+    pub fn mode(&self, node: &super::node::Node) -> NodeMode {
+        self.internal.graph.mode(node)
+    }
+
+    /// Bypasses, mutes or solos a [`Node`], returning false if it does not
+    /// exist.
+    pub fn set_mode(&mut self, node: super::node::Node, mode: NodeMode) -> bool {
+        self.internal.graph.set_mode(node, mode)
+    }
+
+    /// Returns the nodes that are not heard, because they are muted or,
+    /// while any node is soloed, outside every soloed chain.
+    pub fn silenced(&self) -> HashSet<super::node::Node> {
+        self.internal.graph.silenced()
+    }
+
+    /// Returns the comment frames, in the order they are drawn.
+    pub fn frames(&self) -> impl Iterator<Item = (FrameId, &Frame)> {
+        self.internal.graph.frames()
+    }
+
+    pub fn frame(&self, id: FrameId) -> Option<&Frame> {
+        self.internal.graph.frame(id)
+    }
+
+    /// Adds a comment frame, with its bounds relative to the
+    /// [`AudioGraph`].
+    ///
+    /// [`AudioGraph`]: crate::audio_graph::AudioGraph
+    pub fn add_frame(&mut self, frame: Frame) -> Option<FrameId> {
+        self.internal.graph.add_frame(frame)
+    }
+
+    /// Adds a comment frame titled `title` around the selected nodes, and
+    /// selects it. Returns `None` if no node is selected.
+    pub fn frame_selection(&mut self, title: &str) -> Option<FrameId> {
+        let frame = self.internal.graph.frame_around(&self.selection(), title)?;
+        self.internal.graph.select_frame(frame);
+        Some(frame)
+    }
+
+    /// Removes a frame, leaving the nodes within it in place.
+    pub fn remove_frame(&mut self, id: FrameId) -> Option<Frame> {
+        self.internal.graph.remove_frame(id)
+    }
+
+    /// Moves a frame by `offset`, along with everything within it.
+    pub fn move_frame(&mut self, id: FrameId, offset: Vector) -> bool {
+        self.internal.graph.move_frame(id, offset.into())
+    }
+
+    /// Replaces the reroute points of an [`Edge`], relative to the graph,
+    /// returning false if it does not exist.
+    pub fn set_route(&mut self, edge: &Edge, route: &[Point]) -> bool {
+        self.internal.graph.set_route(edge, route.iter().map(|point| (*point).into()).collect())
+    }
+
+    pub fn set_frame_title(&mut self, id: FrameId, title: &str) -> bool {
+        self.internal.graph.set_frame_title(id, title)
+    }
+
+    pub fn set_frame_color(&mut self, id: FrameId, color: Option<[u8; 3]>) -> bool {
+        self.internal.graph.set_frame_color(id, color)
+    }
+
+    /// Returns the selected frame, if any.
+    pub fn selected_frame(&self) -> Option<FrameId> {
+        self.internal.graph.selected_frame()
+    }
+
+    /// Moves a node by `offset`, returning where it was. Nodes are kept
+    /// within the positive quadrant.
     pub fn translate(&mut self, id: super::node::Node, offset: Point) -> Option<Point> {
-        let pos = self.internal.positions.get_mut(&id)?;
-        let prev = *pos;
-        //*pos = *pos + Vector::new(position.x, position.y);
-        *pos = Point::new( (pos.x + offset.x).max(0.0), (pos.y + offset.y).max(0.0));
-        Some(prev)
+        self.internal
+            .graph
+            .translate(id, Vector::new(offset.x, offset.y).into())
+            .map(Point::from)
+    }
+
+    fn insert_patch(
+        &mut self,
+        patch: &Patch,
+        offset: Vector) -> Vec<super::node::Node>
+    where
+        T: Persist,
+    {
+        let patch = patch.translated(offset.x, offset.y);
+        let mut ids = HashMap::new();
+
+        for patch_node in &patch.nodes {
+            if let Some(state) = T::load(&patch_node.data) {
+                if let Some(node) = self.insert(Point::new(patch_node.x, patch_node.y), state) {
+                    let _ = self.set_note(node, &patch_node.note);
+                    let _ = self.set_color(node, patch_node.color);
+                    let _ = self.set_mode(node, patch_node.mode());
+                    let _ = self.set_delay(node, patch_node.delay);
+                    ids.insert(patch_node.id, node);
+                }
+            }
+        }
+
+        for patch_frame in &patch.frames {
+            let frame = Frame {
+                bounds: Rectangle::new(
+                    Point::new(patch_frame.x, patch_frame.y),
+                    Size::new(patch_frame.width, patch_frame.height)).into(),
+                title: patch_frame.title.clone(),
+                color: patch_frame.color,
+            };
+            let _ = self.add_frame(frame);
+        }
+
+        for edge in &patch.edges {
+            if let Some((from, to)) = edge.resolve(&ids) {
+                let connected = match edge.feedback {
+                    true => self.connect_feedback(from, edge.output, to, edge.input),
+                    false => self.connect(from, edge.output, to, edge.input),
+                };
+                if let Ok(connected) = connected {
+                    let route = edge
+                        .route
+                        .iter()
+                        .map(|(x, y)| Point::new(*x, *y).into())
+                        .collect();
+                    let _ = self.internal.graph.set_route(&connected, route);
+                }
+            }
+        }
+
+        let mut nodes: Vec<_> = ids.values().copied().collect();
+        nodes.sort_by_key(|node| node.id);
+        nodes
     }
 
     fn distribute_content(
         nodes: &mut HashMap<super::node::Node, T>,
+        graph: &mut Graph,
         content: super::configuration::Configuration<T>,
-        next_id: usize,
-    ) -> (super::layout_node::LayoutNode, usize) {
+    ) -> super::layout_node::LayoutNode {
         match content {
             super::configuration::Configuration::Node(state) => {
-                let id = super::node::Node::new(next_id);
+                let id = graph
+                    .insert(crate::core::geometry::Point::ORIGIN)
+                    .expect("an empty graph has identifiers to spare");
                 let _ = nodes.insert(id, state);
 
-                (super::layout_node::LayoutNode::Node((id, Point::new(0.0,0.0))), next_id + 1)
+                super::layout_node::LayoutNode::Node((id, Point::new(0.0,0.0)))
             }
         }
     }
 }
 
+impl<T: Persist> State<T> {
+    /// Captures the given nodes, and the edges between them, as a [`Patch`].
+    ///
+    /// Edges with only one end in `nodes` are not included.
+    pub fn patch(&self, nodes: &[super::node::Node]) -> Patch {
+        let mut patch = Patch::new();
+
+        patch.nodes = nodes
+            .iter()
+            .filter_map(|node| {
+                let state = self.nodes.get(node)?;
+                let position = self.internal.graph.position(node)?;
+                let annotation = self.annotation(node).cloned().unwrap_or_default();
+                let mode = self.mode(node);
+                Some(PatchNode {
+                    id: node.id,
+                    x: position.x,
+                    y: position.y,
+                    data: state.save(),
+                    note: annotation.note,
+                    color: annotation.color,
+                    bypass: mode.bypass,
+                    mute: mode.mute,
+                    solo: mode.solo,
+                    delay: self.is_delay(node),
+                })
+            })
+            .collect();
+
+        patch.edges = self
+            .internal
+            .graph
+            .edges()
+            .iter()
+            .filter(|edge| nodes.contains(&edge.from) && nodes.contains(&edge.to))
+            .map(|edge| PatchEdge {
+                from: edge.from.id,
+                output: edge.output,
+                to: edge.to.id,
+                input: edge.input,
+                feedback: self.is_feedback(edge),
+                route: self
+                    .internal
+                    .graph
+                    .route(edge)
+                    .iter()
+                    .map(|point| (point.x, point.y))
+                    .collect(),
+            })
+            .collect();
+
+        patch.frames = self
+            .frames()
+            .filter(|(id, _)| {
+                let enclosed = self.internal.graph.enclosed(*id);
+                !enclosed.is_empty() && enclosed.iter().all(|node| nodes.contains(node))
+            })
+            .map(|(_, frame)| PatchFrame::from(frame))
+            .collect();
+
+        patch
+    }
+
+    /// Captures every node, edge and frame as a [`Patch`], e.g. to save it to
+    /// a file.
+    ///
+    /// Unlike [`State::patch`], frames are kept even when they enclose no
+    /// nodes.
+    pub fn to_patch(&self) -> Patch {
+        let mut patch = self.patch(&self.internal.graph.nodes());
+        patch.frames = self
+            .frames()
+            .map(|(_, frame)| PatchFrame::from(frame))
+            .collect();
+        patch
+    }
+
+    /// Replaces every node, edge and frame with those of a patch captured
+    /// by [`State::to_patch`], in the same places. Returns the new nodes.
+    pub fn load_patch(&mut self, patch: &Patch) -> Vec<super::node::Node> {
+        for node in self.internal.graph.nodes() {
+            let _ = self.remove(&node);
+        }
+        let frames: Vec<FrameId> = self.frames().map(|(id, _)| id).collect();
+        for frame in frames {
+            let _ = self.remove_frame(frame);
+        }
+
+        self.insert_patch(patch, Vector::new(0.0, 0.0))
+    }
+
+    /// Returns the selected nodes in the patch text format, or `None` if
+    /// nothing is selected.
+    pub fn copy(&self) -> Option<String> {
+        let patch = self.patch(&self.selection());
+        if patch.is_empty() {
+            None
+        } else {
+            Some(patch.to_text())
+        }
+    }
+
+    /// Copies the selected nodes, then removes them from the [`State`].
+    pub fn cut(&mut self) -> Option<String> {
+        let text = self.copy()?;
+        self.remove_selection();
+        Some(text)
+    }
+
+    /// Inserts the nodes and edges of a patch, given in text format.
+    ///
+    /// When `position` is provided the top left of the pasted nodes is placed
+    /// there, otherwise they keep their original positions shifted by
+    /// [`PASTE_OFFSET`]. The pasted nodes become the selection and are
+    /// returned.
+    pub fn paste(
+        &mut self,
+        text: &str,
+        position: Option<Point>) -> serde_json::Result<Vec<super::node::Node>> {
+        let patch = Patch::from_text(text)?;
+
+        let offset = match (position, patch.origin()) {
+            (Some(position), Some((x, y))) => Vector::new(position.x - x, position.y - y),
+            _ => PASTE_OFFSET,
+        };
+
+        let nodes = self.insert_patch(&patch, offset);
+        self.select(&nodes);
+        Ok(nodes)
+    }
+
+    /// Duplicates the selected nodes, and the edges between them, without
+    /// touching the clipboard.
+    pub fn duplicate(&mut self) -> Vec<super::node::Node> {
+        let patch = self.patch(&self.selection());
+        let nodes = self.insert_patch(&patch, PASTE_OFFSET);
+        self.select(&nodes);
+        nodes
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Internal {
+    graph: Graph,
     layout: super::layout_node::LayoutNode,
-    positions: HashMap<super::node::Node, Point>,
-    last_id: usize,
+    modifiers: keyboard::Modifiers,
+    last_click: Option<mouse::Click>,
     action: Action,
+    scroll: Vector,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     Idle,
     Dragging { 
@@ -132,10 +617,41 @@ pub enum Action {
         origin: Point,
         cursor_position: Point
     },
+    Connecting {
+        node: super::node::Node,
+        socket: Socket,
+        cursor_position: Point,
+    },
+    Rerouting {
+        edge: Edge,
+        index: usize,
+        /// The reroute points of the edge before they were changed,
+        /// relative to the graph.
+        route: Vec<Point>,
+    },
+    Panning {
+        cursor_position: Point,
+    },
+    Navigating,
+    MovingFrame {
+        frame: FrameId,
+        /// Where the frame was when it was picked, relative to the graph.
+        origin: Point,
+        cursor_position: Point,
+        /// The nodes and frames the frame enclosed when it was picked,
+        /// which move with it.
+        nodes: Vec<super::node::Node>,
+        frames: Vec<FrameId>,
+    },
 }
 
 
 impl Internal {
+    /// Returns the structure of the graph.
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
     pub fn picked_node(&self) -> Option<(super::node::Node, Point, Point)> {
         match self.action {
             Action::Dragging { node, origin, cursor_position } => Some((node, origin, cursor_position)),
@@ -143,6 +659,203 @@ impl Internal {
         }
     }
 
+    pub fn cable(&self) -> Option<(super::node::Node, Socket, Point)> {
+        match self.action {
+            Action::Connecting { node, socket, cursor_position } => Some((node, socket, cursor_position)),
+            _ => None,
+        }
+    }
+
+    /// Starts dragging a new cable from the given socket.
+    pub fn pick_socket(&mut self, node: super::node::Node, socket: Socket, cursor_position: Point) {
+        self.action = Action::Connecting {
+            node,
+            socket,
+            cursor_position,
+        };
+    }
+
+    /// Moves the loose end of the cable being dragged, if any.
+    pub fn move_cable(&mut self, position: Point) {
+        if let Action::Connecting { cursor_position, .. } = &mut self.action {
+            *cursor_position = position;
+        }
+    }
+
+    /// Returns the reroute point being dragged, if any.
+    pub fn picked_reroute(&self) -> Option<(Edge, usize)> {
+        match &self.action {
+            Action::Rerouting { edge, index, .. } => Some((*edge, *index)),
+            _ => None,
+        }
+    }
+
+    /// Starts dragging a reroute point of an edge whose reroute points
+    /// were `route` before the drag, e.g. before the point was added.
+    pub fn pick_reroute(&mut self, edge: Edge, index: usize, route: Vec<Point>) {
+        self.action = Action::Rerouting { edge, index, route };
+    }
+
+    /// Moves the reroute point being dragged, if any, to `position` relative
+    /// to the graph.
+    pub fn drag_reroute(&mut self, position: Point) {
+        if let Action::Rerouting { edge, index, .. } = &self.action {
+            let _ = self.graph.move_reroute(edge, *index, position.into());
+        }
+    }
+
+    /// Puts the reroute points of the edge being rerouted, if any, back as
+    /// they were picked and stops dragging, returning the edge and its
+    /// reroute points as dragged if they changed.
+    pub fn drop_reroute(&mut self) -> Option<(Edge, Vec<Point>)> {
+        let (edge, route) = match std::mem::replace(&mut self.action, Action::Idle) {
+            Action::Rerouting { edge, route, .. } => (edge, route),
+            _ => return None,
+        };
+
+        let dragged = self.route(&edge);
+        let _ = self.graph.set_route(&edge, route.iter().map(|point| (*point).into()).collect());
+        Some((edge, dragged)).filter(|(_, dragged)| *dragged != route)
+    }
+
+    /// Returns the reroute point at `position`, relative to the graph.
+    pub fn reroute_at(&self, position: Point) -> Option<(Edge, usize)> {
+        self.graph.reroute_at(position.into())
+    }
+
+    /// Adds a reroute point at `position` to an edge with sockets at `from`
+    /// and `to`, all relative to the graph.
+    pub fn add_reroute(&mut self, edge: &Edge, from: Point, to: Point, position: Point) -> Option<usize> {
+        self.graph.add_reroute(edge, from.into(), to.into(), position.into())
+    }
+
+    /// Returns the reroute points of an edge, relative to the graph.
+    pub fn route(&self, edge: &Edge) -> Vec<Point> {
+        self.graph.route(edge).iter().map(|p| Point::from(*p)).collect()
+    }
+
+    pub fn is_edge_selected(&self, edge: &Edge) -> bool {
+        self.graph.is_edge_selected(edge)
+    }
+
+    /// Updates the selection for a click on `edge`, honouring shift to extend
+    /// the selection.
+    pub fn click_select_edge(&mut self, edge: &Edge) {
+        self.graph.click_select_edge(edge, self.modifiers.shift);
+    }
+
+    pub fn scroll(&self) -> Vector {
+        self.scroll
+    }
+
+    /// Scrolls to `offset`, keeping it within the positive quadrant like the
+    /// nodes themselves.
+    pub fn scroll_to(&mut self, offset: Vector) {
+        self.scroll = Vector::new(offset.x.max(0.0), offset.y.max(0.0));
+    }
+
+    pub fn scroll_by(&mut self, offset: Vector) {
+        self.scroll_to(self.scroll + offset);
+    }
+
+    /// Starts panning the view by dragging the canvas.
+    pub fn pick_canvas(&mut self, cursor_position: Point) {
+        self.action = Action::Panning { cursor_position };
+    }
+
+    pub fn is_panning(&self) -> bool {
+        matches!(self.action, Action::Panning { .. })
+    }
+
+    /// Pans the view along with the cursor, if the canvas is being dragged.
+    pub fn drag_canvas(&mut self, position: Point) {
+        if let Action::Panning { cursor_position } = self.action {
+            self.scroll_by(cursor_position - position);
+            self.action = Action::Panning { cursor_position: position };
+        }
+    }
+
+    /// Returns the frame whose header is at `position`, relative to the graph.
+    pub fn frame_at(&self, position: Point) -> Option<FrameId> {
+        self.graph.frame_at(position.into())
+    }
+
+    /// Selects a frame and starts dragging it by its header.
+    pub fn pick_frame(&mut self, frame: FrameId, cursor_position: Point) {
+        let bounds = match self.graph.frame(frame) {
+            Some(picked) => picked.bounds,
+            None => return,
+        };
+
+        self.graph.select_frame(frame);
+        self.action = Action::MovingFrame {
+            frame,
+            origin: Point::new(bounds.x, bounds.y),
+            cursor_position,
+            nodes: self.graph.enclosed(frame),
+            frames: self.graph.enclosed_frames(frame),
+        };
+    }
+
+    /// Returns the frame being dragged, if any, and how far it has moved.
+    pub fn picked_frame(&self) -> Option<(FrameId, Vector)> {
+        match &self.action {
+            Action::MovingFrame { frame, origin, .. } => {
+                let bounds = self.graph.frame(*frame)?.bounds;
+                Some((*frame, Point::new(bounds.x, bounds.y) - *origin))
+            }
+            _ => None,
+        }
+    }
+
+    /// Moves the frame being dragged, if any, along with the cursor and
+    /// with what it enclosed when it was picked.
+    pub fn drag_frame(&mut self, position: Point) {
+        if let Action::MovingFrame { frame, cursor_position, nodes, frames, .. } = &mut self.action {
+            let offset = (position - *cursor_position).into();
+            let _ = self.graph.move_frame_with(*frame, nodes, frames, offset);
+            *cursor_position = position;
+        }
+    }
+
+    /// Puts the frame being dragged, if any, back where it was picked and
+    /// stops dragging it, returning the frame and how far it had moved.
+    pub fn drop_frame(&mut self) -> Option<(FrameId, Vector)> {
+        let moved = self.picked_frame();
+        if let (Some((_, diff)), Action::MovingFrame { frame, nodes, frames, .. }) = (moved, &self.action) {
+            let back = Vector::new(-diff.x, -diff.y).into();
+            let _ = self.graph.move_frame_with(*frame, nodes, frames, back);
+        }
+        self.action = Action::Idle;
+        moved
+    }
+
+    pub fn selected_frame(&self) -> Option<FrameId> {
+        self.graph.selected_frame()
+    }
+
+    /// Starts moving the view with the minimap.
+    pub fn pick_minimap(&mut self) {
+        self.action = Action::Navigating;
+    }
+
+    pub fn is_navigating(&self) -> bool {
+        self.action == Action::Navigating
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.action == Action::Idle
+    }
+
+    /// Registers a click on empty canvas, returning true if it completes a
+    /// double click.
+    pub fn click_canvas(&mut self, position: Point) -> bool {
+        let click = mouse::Click::new(position, self.last_click);
+        self.last_click = Some(click);
+
+        click.kind() == mouse::click::Kind::Double
+    }
+
     pub fn pick_node(&mut self, node: &super::node::Node, origin: Point, cursor_position: Point) {
         self.action = Action::Dragging {
             node: *node,
@@ -159,30 +872,76 @@ impl Internal {
         self.layout.node_regions(spacing, size)
     }
 
-    fn push_postion(&mut self, id: super::node::Node, position: Point) {
-        self.positions.insert(id, position);
-    }
-
-    pub fn positions(&self) -> HashMap<super::node::Node, Point> {
-        self.positions.clone()
+    /// Returns the region a node is laid out in.
+    pub fn region(&self, node: &super::node::Node) -> Option<Rectangle> {
+        self.graph.region(node).map(Rectangle::from)
     }
 
     pub fn idle(&mut self) {
         self.action = Action::Idle;
     }
 
+    pub fn edges(&self) -> &[Edge] {
+        self.graph.edges()
+    }
+
+    pub fn selection(&self) -> Vec<super::node::Node> {
+        self.graph.selection()
+    }
+
+    pub fn is_selected(&self, node: &super::node::Node) -> bool {
+        self.graph.is_selected(node)
+    }
+
+    /// Updates the selection for a click on `node`, honouring shift to extend
+    /// the selection.
+    pub fn click_select(&mut self, node: &super::node::Node) {
+        self.graph.click_select(node, self.modifiers.shift);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.graph.clear_selection();
+    }
+
+    pub fn select_all(&mut self) {
+        self.graph.select_all();
+    }
+
+    pub fn focus(&self) -> Option<super::node::Node> {
+        self.graph.focus()
+    }
+
+    pub fn set_focus(&mut self, node: super::node::Node) {
+        self.graph.set_focus(node);
+    }
+
+    /// Moves focus to the next (or previous) node in creation order, making
+    /// it the only selected node.
+    pub fn cycle_focus(&mut self, forward: bool) -> Option<super::node::Node> {
+        self.graph.cycle_focus(forward)
+    }
+
+    pub fn modifiers(&self) -> keyboard::Modifiers {
+        self.modifiers
+    }
+
+    pub fn set_modifiers(&mut self, modifiers: keyboard::Modifiers) {
+        self.modifiers = modifiers;
+    }
+
     pub fn hash_layout(&self, hasher: &mut Hasher) {
         use std::hash::Hash;
 
         self.layout.hash(hasher);
-        self
-            .positions
-            .iter()
-            .for_each(|(id,pos)| {
-                id.hash(hasher);
-                distance::Distance(pos.x).hash(hasher);
-                distance::Distance(pos.y).hash(hasher);
-            });
+        distance::Distance(self.scroll.x).hash(hasher);
+        distance::Distance(self.scroll.y).hash(hasher);
+        for node in self.graph.nodes() {
+            if let Some(position) = self.graph.position(&node) {
+                node.hash(hasher);
+                distance::Distance(position.x).hash(hasher);
+                distance::Distance(position.y).hash(hasher);
+            }
+        }
     }
 }
 
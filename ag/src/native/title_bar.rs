@@ -1,15 +1,21 @@
 use iced_native::{
     event, keyboard, layout, mouse, Clipboard, Element, Event, Hasher, Layout,
-    Length, Point, Rectangle, Size, Widget, container,
+    Color, Length, Point, Rectangle, Size, Widget, container,
 };
 
+/// The height of the line holding the note of a [`TitleBar`], below its
+/// content.
+pub const NOTE_HEIGHT: f32 = 14.0;
+
 #[allow(missing_debug_implementations)]
 pub struct TitleBar<'a, Message, Renderer: super::audio_graph::Renderer> {
     content: Element<'a, Message, Renderer>,
     controls: Option<Element<'a, Message, Renderer>>,
     padding: u16,
     always_show_controls: bool,
-    style: <Renderer as super::audio_graph::Renderer>::Style,
+    note: Option<String>,
+    tag: Option<Color>,
+    style: Option<<Renderer as super::audio_graph::Renderer>::Style>,
 }
 
 impl<'a, Message, Renderer> TitleBar<'a, Message, Renderer>
@@ -26,7 +32,9 @@ where
             controls: None,
             padding: 0,
             always_show_controls: false,
-            style: Default::default(),
+            note: None,
+            tag: None,
+            style: None,
         }
     }
 
@@ -45,12 +53,28 @@ where
         self
     }
 
-    /// Sets the style of the [`TitleBar`].
+    /// Sets a note shown on a line of its own below the content of the
+    /// [`TitleBar`]. An empty note is not shown.
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        let note = note.into();
+        self.note = if note.is_empty() { None } else { Some(note) };
+        self
+    }
+
+    /// Tags the [`TitleBar`] with a colour, shown as a strip along its left
+    /// edge.
+    pub fn tag(mut self, color: Color) -> Self {
+        self.tag = Some(color);
+        self
+    }
+
+    /// Sets the style of the [`TitleBar`], in place of the style of its
+    /// node.
     pub fn style(
         mut self,
         style: impl Into<<Renderer as super::audio_graph::Renderer>::Style>,
     ) -> Self {
-        self.style = style.into();
+        self.style = Some(style.into());
         self
     }
 
@@ -82,6 +106,8 @@ where
         layout: Layout<'_>,
         cursor_position: Point,
         show_controls: bool,
+        style: &<Renderer as super::audio_graph::Renderer>::Style,
+        is_focused: bool,
     ) -> Renderer::Output {
         let mut children = layout.children();
         let padded = children.next().unwrap();
@@ -106,9 +132,12 @@ where
         renderer.draw_title_bar(
             defaults,
             layout.bounds(),
-            &self.style,
+            self.style.as_ref().unwrap_or(style),
+            is_focused,
             (&self.content, title_layout),
             controls,
+            self.note.as_deref(),
+            self.tag,
             cursor_position,
         )
     }
@@ -145,6 +174,7 @@ where
 
         self.content.hash_layout(hasher);
         self.padding.hash(hasher);
+        self.note.is_some().hash(hasher);
     }
 
     pub(crate) fn layout(
@@ -185,7 +215,12 @@ where
 
         node.move_to(Point::new(padding, padding));
 
-        layout::Node::with_children(node.size().pad(padding), vec![node])
+        let mut size = node.size().pad(padding);
+        if self.note.is_some() {
+            size.height += NOTE_HEIGHT;
+        }
+
+        layout::Node::with_children(size, vec![node])
     }
 
     pub(crate) fn on_event(
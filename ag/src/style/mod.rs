@@ -1 +1,6 @@
-pub mod style;
\ No newline at end of file
+pub mod style;
+
+pub use style::{
+    CanvasStyle, EdgeStyle, EdgeStyles, FrameStyle, GridStyle, MinimapStyle, ModeStyle, NodeStyle,
+    PortStyle, SelectionStyle, Theme, ThemeError, TitleBarStyle, TitleBarStyles, TooltipStyle,
+};
@@ -1,122 +1,671 @@
-use iced_native::{image, Color, Rectangle};
+use iced_native::{Color, Rectangle};
 use iced_graphics::{Background, Primitive};
-#[derive(Debug, Clone)]
-pub enum Style {
-    /// modeled after Muses project
-    Muses(MusesStyle),
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::native::ports::PortType;
+
+use std::path::Path;
+
+/// The colours and sizes used to draw an [`AudioGraph`].
+///
+/// A [`Theme`] can be written and read as JSON, and read as TOML, see
+/// [`Theme::from_json`], [`Theme::from_toml`] and [`Theme::load`]. Anything left out of a theme file keeps the value of
+/// [`Theme::light`], so a file need only list what it changes.
+///
+/// [`AudioGraph`]: crate::AudioGraph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub canvas: CanvasStyle,
+    pub node: NodeStyle,
+    pub modes: ModeStyle,
+    pub title_bar: TitleBarStyles,
+    pub ports: PortStyle,
+    pub edges: EdgeStyles,
+    pub selection: SelectionStyle,
+    pub tooltip: TooltipStyle,
+    pub minimap: MinimapStyle,
+    pub frame: FrameStyle,
 }
 
-#[derive(Debug, Clone)]
-pub struct MusesStyle {
-    pub fill: Color,
+/// The background the nodes sit on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanvasStyle {
+    #[serde(with = "hex")]
     pub background: Color,
+    #[serde(with = "hex")]
+    pub border_color: Color,
+    pub border_width: f32,
+    /// The grid drawn on the canvas, if any.
+    pub grid: Option<GridStyle>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GridStyle {
+    #[serde(with = "hex")]
+    pub color: Color,
+    /// The distance between grid lines.
+    pub spacing: f32,
+    pub width: f32,
+}
+
+/// The body of a node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeStyle {
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub border_color: Color,
+    pub border_width: f32,
     pub border_radius: f32,
+}
+
+/// How bypassed, soloed and silenced nodes stand out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeStyle {
+    /// The border of a bypassed node.
+    #[serde(with = "hex")]
+    pub bypass_color: Color,
+    /// The border of a soloed node.
+    #[serde(with = "hex")]
+    pub solo_color: Color,
     pub border_width: f32,
+    /// Drawn over nodes that are not heard, because they are muted or
+    /// outside every soloed chain.
+    #[serde(with = "hex")]
+    pub silenced: Color,
+}
+
+/// The title bar of a node, which differs for the node with focus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleBarStyles {
+    pub focused: TitleBarStyle,
+    pub unfocused: TitleBarStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TitleBarStyle {
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub text_color: Color,
+    #[serde(with = "hex")]
     pub border_color: Color,
+    pub border_width: f32,
+    pub border_radius: f32,
 }
 
-pub fn background(
-    bounds: Rectangle,
-    style: &Style) -> Option<Primitive> {
-    match style {
-        Style::Muses(style) => {
-            Some(Primitive::Quad {
-                bounds,
-                background: Background::Color(style.background),
-                border_radius: style.border_radius,
-                border_width: style.border_width,
-                border_color: style.border_color,
+/// The columns of sockets either side of a node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortStyle {
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub border_color: Color,
+    #[serde(with = "hex")]
+    pub socket_border: Color,
+    /// The colour of sockets of each [`PortType`], in order of their ids.
+    /// Edges and exported diagrams take their colours from here too.
+    #[serde(with = "hex_list")]
+    pub types: Vec<Color>,
+    /// The colour of sockets of a type not listed in `types`.
+    #[serde(with = "hex")]
+    pub unknown: Color,
+    /// The colour of the short labels beside sockets.
+    #[serde(with = "hex")]
+    pub label_color: Color,
+}
+
+impl PortStyle {
+    /// Returns the colour of sockets of the given type.
+    pub fn color(&self, port_type: PortType) -> Color {
+        self.types.get(port_type.id()).copied().unwrap_or(self.unknown)
+    }
+
+    /// Returns the colour of each type in `types`, as red, green and blue,
+    /// for drawings made outside the editor.
+    pub fn rgb8(&self) -> Vec<[u8; 3]> {
+        self.types
+            .iter()
+            .map(|color| {
+                let [r, g, b, _] = to_rgba8(*color);
+                [r, g, b]
             })
+            .collect()
+    }
+}
+
+/// The edges between nodes, and the cable being dragged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeStyles {
+    pub normal: EdgeStyle,
+    pub hovered: EdgeStyle,
+    pub selected: EdgeStyle,
+    pub cable: EdgeStyle,
+    /// Whether edges take the colour of their output's [`PortType`] rather
+    /// than that of `normal`. Hovered edges then take a colour halfway
+    /// between it and that of `hovered`.
+    pub color_by_type: bool,
+    /// The marks showing the direction of flow, where `width` is their
+    /// diameter.
+    pub flow: EdgeStyle,
+    /// The length of the dashes feedback edges are drawn with, and of the
+    /// gaps between them.
+    pub feedback_dash: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EdgeStyle {
+    #[serde(with = "hex")]
+    pub color: Color,
+    pub width: f32,
+}
+
+/// The outline drawn around selected nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelectionStyle {
+    #[serde(with = "hex")]
+    pub border_color: Color,
+    pub border_width: f32,
+    /// How far the outline is drawn outside the node.
+    pub offset: f32,
+}
+
+/// The box describing the port under the cursor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TooltipStyle {
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub text_color: Color,
+    #[serde(with = "hex")]
+    pub border_color: Color,
+    pub border_width: f32,
+    pub border_radius: f32,
+    pub text_size: f32,
+    /// The space between the border and the text.
+    pub padding: f32,
+}
+
+/// The overview of the graph in the corner of the canvas.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MinimapStyle {
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub border_color: Color,
+    pub border_width: f32,
+    #[serde(with = "hex")]
+    pub node_color: Color,
+    #[serde(with = "hex")]
+    pub selected_color: Color,
+    /// The fill of the part of the graph in view.
+    #[serde(with = "hex")]
+    pub viewport_color: Color,
+    #[serde(with = "hex")]
+    pub viewport_border_color: Color,
+}
+
+/// The comment frames on the canvas.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrameStyle {
+    /// The fill of a frame without a colour tag.
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub border_color: Color,
+    pub border_width: f32,
+    pub border_radius: f32,
+    #[serde(with = "hex")]
+    pub title_color: Color,
+    pub title_size: f32,
+    /// How opaque the fill of a tagged frame is, its header being twice
+    /// as opaque.
+    pub tint: f32,
+}
+
+/// A problem loading a [`Theme`] from a file.
+#[derive(Debug, Error)]
+pub enum ThemeError {
+    #[error("could not read theme: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("could not parse theme: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("could not parse theme: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+fn rgb8(r: u8, g: u8, b: u8) -> Color {
+    rgba8(r, g, b, 0xFF)
+}
+
+fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Color {
+    Color {
+        r: r as f32 / 255.0,
+        g: g as f32 / 255.0,
+        b: b as f32 / 255.0,
+        a: a as f32 / 255.0,
+    }
+}
+
+fn to_rgba8(color: Color) -> [u8; 4] {
+    let channel = |c: f32| (c * 255.0).round().clamp(0.0, 255.0) as u8;
+    [channel(color.r), channel(color.g), channel(color.b), channel(color.a)]
+}
+
+impl Theme {
+    /// A light theme, the default.
+    pub fn light() -> Self {
+        Self {
+            canvas: CanvasStyle {
+                background: rgb8(0xE0, 0xD6, 0x44),
+                border_color: rgb8(0xDA, 0x70, 0xD6),
+                border_width: 1.0,
+                grid: Some(GridStyle {
+                    color: rgba8(0x00, 0x00, 0x00, 0x26),
+                    spacing: 40.0,
+                    width: 1.0,
+                }),
+            },
+            node: NodeStyle {
+                background: rgb8(0xF2, 0xF3, 0xF5),
+                border_color: rgb8(0x42, 0x42, 0x42),
+                border_width: 1.0,
+                border_radius: 2.0,
+            },
+            modes: ModeStyle {
+                bypass_color: rgb8(0xE8, 0x8C, 0x3C),
+                solo_color: rgb8(0xD6, 0xB4, 0x3C),
+                border_width: 2.0,
+                silenced: rgba8(0x5C, 0x5E, 0x66, 0x80),
+            },
+            title_bar: TitleBarStyles {
+                focused: TitleBarStyle {
+                    background: rgb8(0x72, 0x89, 0xDA),
+                    text_color: Color::WHITE,
+                    border_color: rgb8(0x42, 0x42, 0x42),
+                    border_width: 1.0,
+                    border_radius: 2.0,
+                },
+                unfocused: TitleBarStyle {
+                    background: rgb8(0xC8, 0xCC, 0xD4),
+                    text_color: rgb8(0x20, 0x20, 0x20),
+                    border_color: rgb8(0x42, 0x42, 0x42),
+                    border_width: 1.0,
+                    border_radius: 2.0,
+                },
+            },
+            ports: PortStyle {
+                background: rgb8(0xF0, 0xF3, 0xA5),
+                border_color: rgb8(0xF2, 0xF3, 0xF5),
+                socket_border: Color::BLACK,
+                types: vec![
+                    rgb8(0x72, 0x89, 0xDA),
+                    rgb8(0xE0, 0x6C, 0x4C),
+                    rgb8(0x4C, 0xB0, 0x6C),
+                    rgb8(0xD6, 0xB4, 0x3C),
+                    rgb8(0xA0, 0x5C, 0xC8),
+                    rgb8(0x4C, 0xB4, 0xC8),
+                ],
+                unknown: rgb8(0x42, 0x42, 0x42),
+                label_color: rgb8(0x20, 0x20, 0x20),
+            },
+            edges: EdgeStyles {
+                normal: EdgeStyle { color: Color::BLACK, width: 4.0 },
+                hovered: EdgeStyle { color: rgb8(0x42, 0x42, 0x42), width: 6.0 },
+                selected: EdgeStyle { color: rgb8(0xFF, 0x47, 0x47), width: 5.0 },
+                cable: EdgeStyle { color: Color::BLACK, width: 2.0 },
+                color_by_type: true,
+                flow: EdgeStyle { color: Color::WHITE, width: 3.0 },
+                feedback_dash: 10.0,
+            },
+            selection: SelectionStyle {
+                border_color: rgb8(0xFF, 0x47, 0x47),
+                border_width: 2.0,
+                offset: 3.0,
+            },
+            tooltip: TooltipStyle {
+                background: rgb8(0xFF, 0xFF, 0xF0),
+                text_color: rgb8(0x20, 0x20, 0x20),
+                border_color: rgb8(0x42, 0x42, 0x42),
+                border_width: 1.0,
+                border_radius: 3.0,
+                text_size: 12.0,
+                padding: 6.0,
+            },
+            minimap: MinimapStyle {
+                background: rgba8(0xF2, 0xF3, 0xF5, 0xD8),
+                border_color: rgb8(0x42, 0x42, 0x42),
+                border_width: 1.0,
+                node_color: rgb8(0x72, 0x89, 0xDA),
+                selected_color: rgb8(0xFF, 0x47, 0x47),
+                viewport_color: rgba8(0x00, 0x00, 0x00, 0x1A),
+                viewport_border_color: rgb8(0x20, 0x20, 0x20),
+            },
+            frame: FrameStyle {
+                background: rgba8(0x00, 0x00, 0x00, 0x14),
+                border_color: rgba8(0x00, 0x00, 0x00, 0x40),
+                border_width: 1.0,
+                border_radius: 4.0,
+                title_color: rgb8(0x20, 0x20, 0x20),
+                title_size: 14.0,
+                tint: 0.15,
+            },
+        }
+    }
+
+    /// A dark theme.
+    pub fn dark() -> Self {
+        let light = Self::light();
+
+        Self {
+            canvas: CanvasStyle {
+                background: rgb8(0x1E, 0x1F, 0x24),
+                border_color: rgb8(0x36, 0x39, 0x3F),
+                border_width: 1.0,
+                grid: Some(GridStyle {
+                    color: rgba8(0xFF, 0xFF, 0xFF, 0x10),
+                    spacing: 40.0,
+                    width: 1.0,
+                }),
+            },
+            node: NodeStyle {
+                background: rgb8(0x2F, 0x31, 0x36),
+                border_color: rgb8(0x20, 0x22, 0x25),
+                ..light.node
+            },
+            modes: ModeStyle {
+                silenced: rgba8(0x1E, 0x1F, 0x24, 0x99),
+                ..light.modes
+            },
+            title_bar: TitleBarStyles {
+                focused: TitleBarStyle {
+                    border_color: rgb8(0x20, 0x22, 0x25),
+                    ..light.title_bar.focused
+                },
+                unfocused: TitleBarStyle {
+                    background: rgb8(0x40, 0x44, 0x4B),
+                    text_color: rgb8(0xDC, 0xDD, 0xDE),
+                    border_color: rgb8(0x20, 0x22, 0x25),
+                    ..light.title_bar.unfocused
+                },
+            },
+            ports: PortStyle {
+                background: rgb8(0x36, 0x39, 0x3F),
+                border_color: rgb8(0x20, 0x22, 0x25),
+                socket_border: rgb8(0xDC, 0xDD, 0xDE),
+                label_color: rgb8(0xDC, 0xDD, 0xDE),
+                ..light.ports
+            },
+            edges: EdgeStyles {
+                normal: EdgeStyle { color: rgb8(0xB9, 0xBB, 0xBE), width: 4.0 },
+                hovered: EdgeStyle { color: Color::WHITE, width: 6.0 },
+                cable: EdgeStyle { color: rgb8(0xB9, 0xBB, 0xBE), width: 2.0 },
+                flow: EdgeStyle { color: rgb8(0x1E, 0x1F, 0x24), width: 3.0 },
+                ..light.edges
+            },
+            selection: light.selection,
+            tooltip: TooltipStyle {
+                background: rgb8(0x20, 0x22, 0x25),
+                text_color: rgb8(0xDC, 0xDD, 0xDE),
+                border_color: rgb8(0x72, 0x89, 0xDA),
+                ..light.tooltip
+            },
+            minimap: MinimapStyle {
+                background: rgba8(0x20, 0x22, 0x25, 0xD8),
+                border_color: rgb8(0x36, 0x39, 0x3F),
+                viewport_color: rgba8(0xFF, 0xFF, 0xFF, 0x1A),
+                viewport_border_color: rgb8(0xDC, 0xDD, 0xDE),
+                ..light.minimap
+            },
+            frame: FrameStyle {
+                background: rgba8(0xFF, 0xFF, 0xFF, 0x0C),
+                border_color: rgba8(0xFF, 0xFF, 0xFF, 0x30),
+                title_color: rgb8(0xDC, 0xDD, 0xDE),
+                ..light.frame
+            },
         }
-        _ => {
-            None
+    }
+
+    /// Reads a theme from JSON.
+    pub fn from_json(text: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(text)
+    }
+
+    /// Writes the theme as JSON, e.g. as a starting point for a theme file.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Reads a theme from TOML.
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Reads a theme from a file, as TOML if its extension is `toml` and as
+    /// JSON otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ThemeError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Ok(Self::from_toml(&text)?),
+            _ => Ok(Self::from_json(&text)?),
         }
-    } 
+    }
 }
 
-// A set of rules that dictate the style of an [`AudioGraph`].
-///
-/// [`AudioGraph`]: 
-pub trait StyleSheet {
-    /// Produces the style of an active [`HSlider`].
-    ///
-    /// [`AudioGraph`]: ../../native/h_slider/struct.HSlider.html
-    fn active(&self) -> Style;
-
-    /// Produces the style of a hovered [`HSlider`].
-    ///
-    /// [`AudioGraph`]: ../../native/h_slider/struct.HSlider.html
-    fn hovered(&self) -> Style;
-
-    /// Produces the style of an [`HSlider`] that is being dragged.
-    ///
-    /// [`AudioGraph`]: ../../native/h_slider/struct.HSlider.html
-    fn dragging(&self) -> Style;
-
-    fn style(&self) -> Style;
-}
-
-struct Default;
-impl Default {
-    const ACTIVE_STYLE: MusesStyle = MusesStyle {
-        fill: Color {
-            r: 0.26,
-            g: 0.26,
-            b: 0.26,
-            a: 0.75,
-        },
-        background: Color {
-            r: 0.26,
-            g: 0.26,
-            b: 0.26,
-            a: 0.75,
-        },
-        border_radius: 0.5,
-        border_width: 1.0,
-        border_color: Color {
-            r: 0.26,
-            g: 0.26,
-            b: 0.26,
-            a: 0.75,
-        },
-    };
-}
-
-impl StyleSheet for Default {
-    fn active(&self) -> Style {
-        Style::Muses(Self::ACTIVE_STYLE)
-    }
-
-    fn hovered(&self) -> Style {
-        Style::Muses(MusesStyle {
-            ..Self::ACTIVE_STYLE
-        })
-    }
-
-    fn dragging(&self) -> Style {
-        Style::Muses(MusesStyle {
-            ..Self::ACTIVE_STYLE
-        })
-    }
-
-    fn style(&self) -> Style {
-        Style::Muses(MusesStyle {
-            ..Self::ACTIVE_STYLE
-        })
-    }
-}
-
-impl std::default::Default for Box<dyn StyleSheet> {
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl Default for CanvasStyle {
     fn default() -> Self {
-        Box::new(Default)
+        Theme::light().canvas
     }
 }
 
-impl<T> From<T> for Box<dyn StyleSheet>
-where
-    T: 'static + StyleSheet,
-{
-    fn from(style: T) -> Self {
-        Box::new(style)
+impl Default for NodeStyle {
+    fn default() -> Self {
+        Theme::light().node
+    }
+}
+
+impl Default for TitleBarStyles {
+    fn default() -> Self {
+        Theme::light().title_bar
     }
-}
\ No newline at end of file
+}
+
+impl Default for PortStyle {
+    fn default() -> Self {
+        Theme::light().ports
+    }
+}
+
+impl Default for EdgeStyles {
+    fn default() -> Self {
+        Theme::light().edges
+    }
+}
+
+impl Default for SelectionStyle {
+    fn default() -> Self {
+        Theme::light().selection
+    }
+}
+
+impl Default for TooltipStyle {
+    fn default() -> Self {
+        Theme::light().tooltip
+    }
+}
+
+impl Default for ModeStyle {
+    fn default() -> Self {
+        Theme::light().modes
+    }
+}
+
+impl Default for FrameStyle {
+    fn default() -> Self {
+        Theme::light().frame
+    }
+}
+
+impl Default for MinimapStyle {
+    fn default() -> Self {
+        Theme::light().minimap
+    }
+}
+
+/// A filled rectangle with a border.
+pub fn quad(
+    bounds: Rectangle,
+    background: Color,
+    border_color: Color,
+    border_width: f32,
+    border_radius: f32) -> Primitive {
+    Primitive::Quad {
+        bounds,
+        background: Background::Color(background),
+        border_radius,
+        border_width,
+        border_color,
+    }
+}
+
+/// Colours written as `#rrggbb`, or `#rrggbbaa` when not opaque.
+mod hex {
+    use iced_native::Color;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        from_hex(&text).ok_or_else(|| de::Error::custom(format!("invalid colour {:?}", text)))
+    }
+
+    pub fn to_hex(color: Color) -> String {
+        let [r, g, b, a] = super::to_rgba8(color);
+        if a == 0xFF {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    pub fn from_hex(text: &str) -> Option<Color> {
+        let digits = text.strip_prefix('#')?;
+        let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+
+        match digits.len() {
+            6 => Some(super::rgb8(channel(0)?, channel(2)?, channel(4)?)),
+            8 => Some(super::rgba8(channel(0)?, channel(2)?, channel(4)?, channel(6)?)),
+            _ => None,
+        }
+    }
+}
+
+mod hex_list {
+    use iced_native::Color;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(colors: &[Color], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(colors.iter().map(|color| super::hex::to_hex(*color)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Color>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|text| {
+                super::hex::from_hex(text)
+                    .ok_or_else(|| de::Error::custom(format!("invalid colour {:?}", text)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let theme = Theme::dark();
+        assert_eq!(Theme::from_json(&theme.to_json()).unwrap(), theme);
+    }
+
+    #[test]
+    fn test_partial_theme_keeps_light_defaults() {
+        let theme = Theme::from_json(r##"{ "canvas": { "background": "#102030" } }"##).unwrap();
+        assert_eq!(theme.canvas.background, rgb8(0x10, 0x20, 0x30));
+        assert_eq!(theme.canvas.grid, Theme::light().canvas.grid);
+        assert_eq!(theme.node, Theme::light().node);
+    }
+
+    #[test]
+    fn test_toml_theme() {
+        let theme = Theme::from_toml(r##"
+            [canvas]
+            background = "#102030"
+
+            [edges]
+            color_by_type = false
+
+            [edges.hovered]
+            color = "#FFFFFF"
+            width = 4.0
+        "##).unwrap();
+        assert_eq!(theme.canvas.background, rgb8(0x10, 0x20, 0x30));
+        assert_eq!(theme.canvas.grid, Theme::light().canvas.grid);
+        assert!(!theme.edges.color_by_type);
+        assert_eq!(theme.edges.hovered.width, 4.0);
+        assert_eq!(theme.edges.normal, Theme::light().edges.normal);
+        assert!(Theme::from_toml("[canvas]\nbackground = 3").is_err());
+    }
+
+    #[test]
+    fn test_load_by_extension() {
+        let dir = std::env::temp_dir();
+        let json = dir.join("ag_test_theme.json");
+        let toml = dir.join("ag_test_theme.toml");
+        let misnamed = dir.join("ag_test_theme_json.toml");
+        std::fs::write(&json, r##"{ "node": { "border_width": 3.0 } }"##).unwrap();
+        std::fs::write(&toml, "[node]\nborder_width = 3.0\n").unwrap();
+        std::fs::copy(&json, &misnamed).unwrap();
+
+        assert_eq!(Theme::load(&json).unwrap().node.border_width, 3.0);
+        assert_eq!(Theme::load(&toml).unwrap().node.border_width, 3.0);
+        assert!(matches!(Theme::load(&misnamed), Err(ThemeError::Toml(_))));
+        assert!(matches!(Theme::load(dir.join("ag_test_missing.json")), Err(ThemeError::Io(_))));
+
+        for path in &[json, toml, misnamed] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn test_port_colours_as_rgb8() {
+        let ports = Theme::light().ports;
+        assert_eq!(ports.rgb8().len(), ports.types.len());
+        assert_eq!(ports.rgb8()[PortType::AUDIO.id()], [0xE0, 0x6C, 0x4C]);
+    }
+}